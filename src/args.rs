@@ -0,0 +1,1818 @@
+// Copyright © 2021-2025
+// Author: Antonio Caggiano <info@antoniocaggiano.eu>
+// SPDX-License-Identifier: MIT
+
+use std::path::PathBuf;
+
+use quote::ToTokens;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::*;
+
+use crate::model::{Blend, Cull, PipelineOptions, PolygonMode, Topology};
+use crate::parse::{Optimization, Vulkan};
+
+/// Named arguments accepted after the positional ones of `pipewriter!`
+const OPTIONS: &[&str] = &[
+    "name",
+    "vert",
+    "frag",
+    "optional",
+    "max_gap",
+    "deny_gaps",
+    "precompiled",
+    "export",
+    "embed",
+    "blend",
+    "debug_wireframe",
+    "search_path",
+    "vert_entry",
+    "frag_entry",
+    "defines",
+    "profile",
+    "spirv",
+    "debug",
+    "optimize",
+    "vulkan",
+    "subpass",
+    "rasterizer_discard",
+    "static_viewport",
+    "struct_name",
+    "vertex",
+    "bindless_count",
+    "immutable_samplers",
+    "dynamic",
+    "buffer_offsets",
+    "push_set",
+    "frames_in_flight",
+    "set_traits",
+    "image_layouts",
+    "external_sets",
+];
+
+/// Fixed-function state accepted in the trailing `{ key: value, .. }` block
+const BLOCK_OPTIONS: &[&str] = &[
+    "blend",
+    "cull",
+    "topology",
+    "depth_write",
+    "samples",
+    "alpha_to_coverage",
+    "sample_shading",
+    "polygon_mode",
+    "line_width",
+];
+
+/// Arguments of the `pipewriter!` macro:
+/// - the name of the pipeline
+/// - a path to a vertex shader, optionally followed by `:entry`
+/// - a path to a fragment shader, optionally followed by `:entry`, or none when the vertex shader path
+///   points to a module with both entry points, or an array of paths given as `frag = [..]`
+/// - a list of `key = value` options
+/// - a block of fixed-function state as `{ key: value, .. }`
+///
+/// Any of them can also be given by name as `key: value`, in any order
+pub struct Args {
+    pub name: String,
+    /// Shader paths are absolute, resolved relative to the invoking crate
+    pub vert: PathBuf,
+    pub frag: Option<PathBuf>,
+    /// Fragment shaders each generating a pipeline with the same vertex shader
+    pub variants: Vec<Variant>,
+    /// Vertex and fragment sources given inline, compiled instead of the shader paths
+    pub sources: Option<(String, String)>,
+    /// Name of the vertex entry point, instead of `main`
+    pub vert_entry: Option<String>,
+    /// Name of the fragment entry point, instead of `main`
+    pub frag_entry: Option<String>,
+    /// Names of texture uniforms which can be left unbound
+    pub optional: Vec<String>,
+    /// Unused set or binding indices tolerated before reporting a numbering gap
+    pub max_gap: u32,
+    /// Numbering gaps are reported as errors instead of warnings
+    pub deny_gaps: bool,
+    /// Shader paths point to SPIR-V files with a reflection sidecar
+    pub precompiled: bool,
+    /// Directory where SPIR-V files and reflection sidecars are written
+    pub export: Option<PathBuf>,
+    /// Embeds the SPIR-V compiled at expansion time instead of compiling the shaders at runtime
+    pub embed: bool,
+    /// Also creates a line-mode pipeline which can be toggled at runtime
+    pub debug_wireframe: bool,
+    pub options: PipelineOptions,
+    /// Subpass index, instead of the one of the vertex input
+    pub subpass: Option<u32>,
+    /// Discards primitives after the vertex stage, so that there is no fragment shader
+    pub rasterizer_discard: bool,
+    /// Viewport and scissor baked from an extent given to the constructors, instead of dynamic
+    pub static_viewport: bool,
+    /// Name of the generated struct, instead of `Pipeline{name}`
+    pub struct_name: Option<String>,
+    /// Path of the vertex type of the constructors, instead of a generic parameter
+    pub vertex: Option<String>,
+    /// Number of descriptors of unbounded arrays
+    pub bindless_count: Option<u32>,
+    /// Names of texture uniforms whose samplers are baked in the set layouts
+    pub immutable_samplers: Vec<String>,
+    /// Names of uniform buffers bound with a dynamic offset
+    pub dynamic: Vec<String>,
+    /// Names of buffers bound from an offset given to their bind methods
+    pub buffer_offsets: Vec<String>,
+    /// Set written with push descriptors instead of allocated sets
+    pub push_set: Option<u32>,
+    /// Number of frames in flight, each binding descriptor sets of its own
+    pub frames_in_flight: Option<u32>,
+    /// Traits binding a set, as paths and set indices, shared by pipelines with the same set layout
+    pub set_traits: Vec<(String, u32)>,
+    /// Layouts of image uniforms, as names and `vk::ImageLayout` constants, instead of the layout
+    /// given to their bind methods
+    pub image_layouts: Vec<(String, String)>,
+    /// Sets bound by the engine, which get no bind methods
+    pub external_sets: Vec<u32>,
+    /// Directories where Slang looks for imported modules, relative to the crate
+    pub search_paths: Vec<PathBuf>,
+    /// Preprocessor macros as `(name, value)`, where the value defaults to `1`
+    pub defines: Vec<(String, String)>,
+    /// Slang profile of the target, instead of `sm_6_5`
+    pub profile: Option<String>,
+    /// SPIR-V version of the target as `1.N`, instead of the default of the profile
+    pub spirv: Option<String>,
+    /// Optimization level of the compiled shaders, instead of `high`
+    pub optimize: Option<Optimization>,
+    /// Generates debug information in the compiled shaders
+    pub debug: bool,
+    /// Vulkan version of the target, selecting its profile and SPIR-V version
+    pub vulkan: Option<Vulkan>,
+}
+
+/// A fragment shader of `frag = [..]`, whose pipeline is named after the pipeline
+/// and the file name of the shader, e.g. `MeshLit` for `lit.frag.slang`
+pub struct Variant {
+    pub name: String,
+    pub path: PathBuf,
+    pub entry: Option<String>,
+}
+
+/// A single argument, either positional, named by `key: value` or `key = value`,
+/// or a block of named arguments
+#[derive(Clone)]
+enum Arg {
+    Positional(Expr),
+    Named(Ident, Expr),
+    Block(Vec<(Ident, Expr)>),
+}
+
+impl Parse for Arg {
+    fn parse(input: ParseStream) -> Result<Self> {
+        if input.peek(token::Brace) {
+            let content;
+            braced!(content in input);
+            let mut options = Vec::new();
+            for arg in Punctuated::<Arg, Token![,]>::parse_terminated(&content)? {
+                match arg {
+                    Arg::Named(key, value) => options.push((key, value)),
+                    Arg::Positional(expr) => {
+                        return Err(Error::new_spanned(expr, "Expected `key: value`"));
+                    }
+                    Arg::Block(_) => {
+                        return Err(Error::new(content.span(), "Unexpected nested block"));
+                    }
+                }
+            }
+            return Ok(Arg::Block(options));
+        }
+
+        if input.peek(Ident) && input.peek2(Token![:]) && !input.peek2(Token![::]) {
+            let key = input.parse()?;
+            input.parse::<Token![:]>()?;
+            return Ok(Arg::Named(key, input.parse()?));
+        }
+
+        match input.parse()? {
+            Expr::Assign(assign) => Ok(Arg::Named(get_key(&assign.left)?, *assign.right)),
+            expr => Ok(Arg::Positional(expr)),
+        }
+    }
+}
+
+impl Parse for Args {
+    fn parse(input: ParseStream) -> Result<Self> {
+        Args::parse_with(input, false)
+    }
+}
+
+/// Arguments of the `pipewriter_inline!` macro, where `vert` and `frag`
+/// are Slang sources instead of paths
+pub struct InlineArgs(pub Args);
+
+impl Parse for InlineArgs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        Ok(InlineArgs(Args::parse_with(input, true)?))
+    }
+}
+
+/// Arguments of the `pipewriter_compute!` macro, where the shader path
+/// points to a module with a compute entry point and there is no fragment shader
+pub struct ComputeArgs(pub Args);
+
+impl Parse for ComputeArgs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let args = Args::parse_with(input, false)?;
+        args.check_raw("Compute")?;
+        Ok(ComputeArgs(args))
+    }
+}
+
+/// Arguments of the `pipewriter_rt!` macro, where `raygen` takes the place of
+/// the vertex shader path, and `miss` and `hit` are arrays of shader paths
+pub struct RayTracingArgs {
+    pub args: Args,
+    /// Miss shaders as paths and entry points
+    pub miss: Vec<(PathBuf, Option<String>)>,
+    /// Closest hit shaders as paths and entry points
+    pub hit: Vec<(PathBuf, Option<String>)>,
+}
+
+impl Parse for RayTracingArgs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut args = Vec::new();
+        let mut miss = Vec::new();
+        let mut hit = Vec::new();
+        for arg in Punctuated::<Arg, Token![,]>::parse_terminated(input)? {
+            match arg {
+                Arg::Named(key, value) if key == "raygen" => {
+                    args.push(Arg::Named(Ident::new("vert", key.span()), value))
+                }
+                Arg::Named(key, value) if key == "miss" => miss = get_shader_paths(&value)?,
+                Arg::Named(key, value) if key == "hit" => hit = get_shader_paths(&value)?,
+                arg => args.push(arg),
+            }
+        }
+
+        let args = Args::from_args(args, false)?;
+        args.check_raw("Ray tracing")?;
+        Ok(RayTracingArgs { args, miss, hit })
+    }
+}
+
+/// Arguments of the `pipewriter_dir!` macro, a directory of `<name>.vert.slang`
+/// and `<name>.frag.slang` pairs followed by options shared by their pipelines
+pub struct DirArgs(pub Vec<Args>);
+
+impl Parse for DirArgs {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut args = Punctuated::<Arg, Token![,]>::parse_terminated(input)?.into_iter();
+        let dir_expr = match args.next() {
+            Some(Arg::Positional(expr)) => expr,
+            _ => {
+                return Err(Error::new(
+                    input.span(),
+                    "Expected a directory of shaders as first argument",
+                ));
+            }
+        };
+        let dir = get_string(&dir_expr)?;
+
+        let options = args.collect::<Vec<Arg>>();
+        for arg in &options {
+            match arg {
+                Arg::Positional(expr) => {
+                    return Err(Error::new_spanned(
+                        expr,
+                        "Expected options after the directory",
+                    ));
+                }
+                Arg::Named(key, _)
+                    if ["name", "vert", "frag", "struct_name"]
+                        .contains(&key.to_string().as_str()) =>
+                {
+                    return Err(Error::new_spanned(
+                        key,
+                        format!("`{}` is given by the shaders of the directory", key),
+                    ));
+                }
+                _ => (),
+            }
+        }
+
+        // Pairs are sorted by name, so that the generated code is reproducible
+        let mut pairs = std::collections::BTreeMap::<String, (bool, bool)>::new();
+        for file_name in read_dir(&dir_expr, &dir)? {
+            if let Some(stem) = file_name.strip_suffix(".vert.slang") {
+                pairs.entry(stem.to_string()).or_default().0 = true;
+            } else if let Some(stem) = file_name.strip_suffix(".frag.slang") {
+                pairs.entry(stem.to_string()).or_default().1 = true;
+            }
+        }
+
+        let orphans = pairs
+            .iter()
+            .filter_map(|(stem, pair)| match pair {
+                (true, false) => Some(format!("`{}.vert.slang`", stem)),
+                (false, true) => Some(format!("`{}.frag.slang`", stem)),
+                _ => None,
+            })
+            .collect::<Vec<String>>();
+        if !orphans.is_empty() {
+            return Err(Error::new_spanned(
+                &dir_expr,
+                format!(
+                    "Shaders without a counterpart in `{}`: {}",
+                    dir,
+                    orphans.join(", ")
+                ),
+            ));
+        }
+        if pairs.is_empty() {
+            return Err(Error::new_spanned(
+                &dir_expr,
+                format!(
+                    "Failed to find `<name>.vert.slang` and `<name>.frag.slang` pairs in `{}`",
+                    dir
+                ),
+            ));
+        }
+
+        let mut pipelines: Vec<Args> = Vec::new();
+        for stem in pairs.keys() {
+            let name = get_pipeline_name(&dir_expr, stem)?;
+            if pipelines.iter().any(|args| args.name == name) {
+                return Err(Error::new_spanned(
+                    &dir_expr,
+                    format!("Shaders of `{}` share the name `{}`", dir, name),
+                ));
+            }
+
+            let span = dir_expr.span();
+            let name = LitStr::new(&name, span);
+            let vert = LitStr::new(&format!("{}/{}.vert.slang", dir, stem), span);
+            let frag = LitStr::new(&format!("{}/{}.frag.slang", dir, stem), span);
+            let shaders = [
+                Arg::Named(Ident::new("name", span), parse_quote!(#name)),
+                Arg::Named(Ident::new("vert", span), parse_quote!(#vert)),
+                Arg::Named(Ident::new("frag", span), parse_quote!(#frag)),
+            ];
+            pipelines.push(Args::from_args(
+                shaders.into_iter().chain(options.iter().cloned()),
+                false,
+            )?);
+        }
+        Ok(DirArgs(pipelines))
+    }
+}
+
+impl Args {
+    fn parse_with(input: ParseStream, inline: bool) -> Result<Self> {
+        let args = Punctuated::<Arg, Token![,]>::parse_terminated(input)?;
+        Args::from_args(args, inline)
+    }
+
+    /// Rejects what only applies to graphics pipelines, for `kind` pipelines
+    fn check_raw(&self, kind: &str) -> Result<()> {
+        let call_site = proc_macro2::Span::call_site();
+        if self.frag.is_some() || self.frag_entry.is_some() || !self.variants.is_empty() {
+            return Err(Error::new(
+                call_site,
+                format!("{} pipelines have no fragment shader", kind),
+            ));
+        }
+        if self.precompiled || self.export.is_some() || self.debug_wireframe {
+            return Err(Error::new(
+                call_site,
+                format!(
+                    "{} shaders can not be precompiled, exported, or drawn in wireframe",
+                    kind
+                ),
+            ));
+        }
+        if self.options != PipelineOptions::default()
+            || self.subpass.is_some()
+            || self.rasterizer_discard
+            || self.static_viewport
+            || self.vertex.is_some()
+        {
+            return Err(Error::new(
+                call_site,
+                format!("{} pipelines have no fixed-function state", kind),
+            ));
+        }
+        Ok(())
+    }
+
+    fn from_args<I: IntoIterator<Item = Arg>>(args: I, inline: bool) -> Result<Self> {
+        let mut positional = Vec::new();
+        let mut has_named = false;
+        let mut name = None;
+        let mut vert = None;
+        let mut frag = None;
+        let mut variants = None;
+        let mut optional = Vec::new();
+        let mut max_gap = 4;
+        let mut deny_gaps = false;
+        let mut precompiled = false;
+        let mut export = None;
+        let mut embed = false;
+        let mut debug_wireframe = false;
+        let mut options = PipelineOptions::default();
+        let mut subpass = None;
+        let mut rasterizer_discard = false;
+        let mut static_viewport = false;
+        let mut struct_name = None;
+        let mut vertex = None;
+        let mut bindless_count = None;
+        let mut immutable_samplers = Vec::new();
+        let mut dynamic = Vec::new();
+        let mut buffer_offsets = Vec::new();
+        let mut push_set = None;
+        let mut frames_in_flight = None;
+        let mut set_traits = Vec::new();
+        let mut image_layouts = Vec::new();
+        let mut external_sets = Vec::new();
+        let mut search_paths = Vec::new();
+        let mut vert_entry = None;
+        let mut frag_entry = None;
+        let mut defines = Vec::new();
+        let mut profile = None;
+        let mut spirv = None;
+        let mut optimize = None;
+        let mut debug = false;
+        let mut vulkan = None;
+        // Keys given as named arguments and in options blocks, each accepted once
+        let mut keys = Vec::new();
+        let mut block_keys = Vec::new();
+
+        for arg in args {
+            match arg {
+                Arg::Named(key, value) => {
+                    has_named = true;
+                    if keys.contains(&key) {
+                        return Err(Error::new_spanned(
+                            &key,
+                            format!("Option `{}` is given more than once", key),
+                        ));
+                    }
+                    check_blend_once(&key, &block_keys)?;
+                    keys.push(key.clone());
+                    match key.to_string().as_str() {
+                        "name" => name = Some(get_string(&value)?),
+                        "vert" => vert = Some(get_string(&value)?),
+                        "frag" => match &value {
+                            Expr::Array(_) => {
+                                variants = Some((get_strings(&value)?, value.clone()))
+                            }
+                            _ => frag = Some(get_string(&value)?),
+                        },
+                        "vert_entry" => vert_entry = Some(get_string(&value)?),
+                        "frag_entry" => frag_entry = Some(get_string(&value)?),
+                        "defines" => {
+                            defines = get_strings(&value)?
+                                .into_iter()
+                                .map(|define| match define.split_once('=') {
+                                    Some((name, value)) => (name.to_string(), value.to_string()),
+                                    None => (define, String::from("1")),
+                                })
+                                .collect()
+                        }
+                        "profile" => profile = Some(get_string(&value)?),
+                        "spirv" => spirv = Some(get_spirv_version(&value)?),
+                        "optimize" => {
+                            optimize = Some(get_named(
+                                &value,
+                                Optimization::from_name,
+                                "optimization level",
+                            )?)
+                        }
+                        "debug" => debug = get_bool(&value)?,
+                        "vulkan" => vulkan = Some(get_vulkan_version(&value)?),
+                        "optional" => optional = get_strings(&value)?,
+                        "max_gap" => max_gap = get_int(&value)?,
+                        "subpass" => subpass = Some(get_int(&value)?),
+                        "deny_gaps" => deny_gaps = get_bool(&value)?,
+                        "precompiled" => precompiled = get_bool(&value)?,
+                        "export" => export = get_export_dir(&value)?,
+                        "embed" => embed = get_bool(&value)?,
+                        "debug_wireframe" => debug_wireframe = get_bool(&value)?,
+                        "rasterizer_discard" => rasterizer_discard = get_bool(&value)?,
+                        "static_viewport" => static_viewport = get_bool(&value)?,
+                        "struct_name" => struct_name = Some(get_struct_name(&value)?),
+                        "vertex" => vertex = Some(get_type_path(&value)?),
+                        "bindless_count" => bindless_count = Some(get_bindless_count(&value)?),
+                        "immutable_samplers" => immutable_samplers = get_strings(&value)?,
+                        "dynamic" => dynamic = get_strings(&value)?,
+                        "buffer_offsets" => buffer_offsets = get_strings(&value)?,
+                        "push_set" => push_set = Some(get_int(&value)?),
+                        "frames_in_flight" => {
+                            frames_in_flight = Some(get_frames_in_flight(&value)?)
+                        }
+                        "set_traits" => set_traits = get_set_traits(&value)?,
+                        "image_layouts" => image_layouts = get_image_layouts(&value)?,
+                        "external_sets" => external_sets = get_ints(&value)?,
+                        "search_path" => {
+                            search_paths = get_strings(&value)?
+                                .iter()
+                                .flat_map(|paths| paths.split(';'))
+                                .filter(|path| !path.is_empty())
+                                .map(|path| get_manifest_path(value.span(), path))
+                                .collect::<Result<_>>()?
+                        }
+                        "blend" => {
+                            options.blend = get_named(&value, Blend::from_name, "blend preset")?
+                        }
+                        _ => {
+                            return Err(Error::new_spanned(
+                                &key,
+                                format!("Unknown option `{}`, expected one of {:?}", key, OPTIONS),
+                            ));
+                        }
+                    }
+                }
+                Arg::Block(block) => {
+                    has_named = true;
+                    for (key, value) in block {
+                        if block_keys.contains(&key) {
+                            return Err(Error::new_spanned(
+                                &key,
+                                format!("Pipeline option `{}` is given more than once", key),
+                            ));
+                        }
+                        check_blend_once(&key, &keys)?;
+                        block_keys.push(key.clone());
+                        match key.to_string().as_str() {
+                            "blend" => {
+                                options.blend = get_named(&value, Blend::from_name, "blend preset")?
+                            }
+                            "cull" => {
+                                options.cull = get_named(&value, Cull::from_name, "cull mode")?
+                            }
+                            "topology" => {
+                                options.topology =
+                                    Some(get_named(&value, Topology::from_name, "topology")?)
+                            }
+                            "depth_write" => options.depth_write = Some(get_bool(&value)?),
+                            "samples" => options.samples = Some(get_samples(&value)?),
+                            "alpha_to_coverage" => options.alpha_to_coverage = get_bool(&value)?,
+                            "sample_shading" => {
+                                options.sample_shading = Some(get_fraction(&value)?)
+                            }
+                            "polygon_mode" => {
+                                options.polygon_mode =
+                                    get_named(&value, PolygonMode::from_name, "polygon mode")?
+                            }
+                            "line_width" => options.line_width = Some(get_line_width(&value)?),
+                            _ => {
+                                return Err(Error::new_spanned(
+                                    &key,
+                                    format!(
+                                        "Unknown pipeline option `{}`, expected one of {:?}",
+                                        key, BLOCK_OPTIONS
+                                    ),
+                                ));
+                            }
+                        }
+                    }
+                }
+                Arg::Positional(expr) => {
+                    if has_named {
+                        return Err(Error::new_spanned(
+                            expr,
+                            "Positional arguments must come before named arguments",
+                        ));
+                    }
+                    positional.push((get_string(&expr)?, expr))
+                }
+            }
+        }
+
+        if positional.len() > 3 {
+            return Err(Error::new_spanned(
+                &positional[3].1,
+                "Expected at most a name, a vertex shader path, and a fragment shader path",
+            ));
+        }
+
+        // Positional arguments fill the name, the vertex path, and the fragment path in order
+        let mut positional = positional.into_iter();
+        for (key, value) in [
+            ("name", &mut name),
+            ("vert", &mut vert),
+            ("frag", &mut frag),
+        ] {
+            let Some((arg, expr)) = positional.next() else {
+                break;
+            };
+            if value.is_some() {
+                return Err(Error::new_spanned(
+                    expr,
+                    format!("`{}` is given both by position and by name", key),
+                ));
+            }
+            *value = Some(arg);
+        }
+
+        let missing = [("name", &name), ("vert", &vert)]
+            .iter()
+            .filter(|(_, value)| value.is_none())
+            .map(|(key, _)| format!("`{}`", key))
+            .collect::<Vec<String>>();
+        if !missing.is_empty() {
+            return Err(Error::new(
+                proc_macro2::Span::call_site(),
+                format!(
+                    "Expected a name and a shader path, missing {}",
+                    missing.join(", ")
+                ),
+            ));
+        }
+
+        if let Some((paths, expr)) = &variants {
+            if frag.is_some() || inline {
+                return Err(Error::new_spanned(
+                    expr,
+                    "Fragment variants need shader paths and no other fragment shader",
+                ));
+            }
+            if struct_name.is_some() {
+                return Err(Error::new_spanned(
+                    expr,
+                    "Fragment variants generate several pipelines, which can not share a `struct_name`",
+                ));
+            }
+            if paths.is_empty() {
+                return Err(Error::new_spanned(
+                    expr,
+                    "Expected at least a fragment shader path",
+                ));
+            }
+        }
+
+        if rasterizer_discard {
+            if frag.is_some() || frag_entry.is_some() || variants.is_some() {
+                return Err(Error::new(
+                    proc_macro2::Span::call_site(),
+                    "Rasterizer discard pipelines have no fragment shader",
+                ));
+            }
+            if options.blend != Blend::default() || debug_wireframe {
+                return Err(Error::new(
+                    proc_macro2::Span::call_site(),
+                    "Rasterizer discard pipelines have no color blending nor wireframe variant",
+                ));
+            }
+        }
+
+        if frag.is_none()
+            && variants.is_none()
+            && !rasterizer_discard
+            && (precompiled || export.is_some())
+        {
+            return Err(Error::new(
+                proc_macro2::Span::call_site(),
+                "Precompiled and exported shaders need separate vertex and fragment paths",
+            ));
+        }
+
+        if precompiled && (debug || optimize.is_some()) {
+            return Err(Error::new(
+                proc_macro2::Span::call_site(),
+                "Precompiled shaders are not compiled with `debug` or `optimize`",
+            ));
+        }
+
+        if vulkan.is_some() && (profile.is_some() || spirv.is_some()) {
+            return Err(Error::new(
+                proc_macro2::Span::call_site(),
+                "`vulkan` selects the profile and the SPIR-V version, remove `profile` and `spirv`",
+            ));
+        }
+        if vulkan.is_some() && precompiled {
+            return Err(Error::new(
+                proc_macro2::Span::call_site(),
+                "Precompiled shaders are not compiled for a `vulkan` version",
+            ));
+        }
+
+        if inline {
+            if frag.is_none() {
+                return Err(Error::new(
+                    proc_macro2::Span::call_site(),
+                    "Expected a vertex and a fragment source, missing `frag`",
+                ));
+            }
+            if precompiled || export.is_some() {
+                return Err(Error::new(
+                    proc_macro2::Span::call_site(),
+                    "Inline shaders can not be precompiled or exported",
+                ));
+            }
+
+            // Sources are named after the pipeline for Slang diagnostics
+            let name = name.unwrap();
+            return Ok(Self {
+                vert: PathBuf::from(format!("{}.vert.slang", name)),
+                frag: Some(PathBuf::from(format!("{}.frag.slang", name))),
+                variants: Vec::new(),
+                sources: Some((vert.unwrap(), frag.unwrap())),
+                name,
+                vert_entry,
+                frag_entry,
+                optional,
+                max_gap,
+                deny_gaps,
+                precompiled,
+                export,
+                embed,
+                debug_wireframe,
+                options,
+                subpass,
+                rasterizer_discard,
+                static_viewport,
+                struct_name,
+                vertex,
+                bindless_count,
+                immutable_samplers,
+                dynamic,
+                buffer_offsets,
+                push_set,
+                frames_in_flight,
+                set_traits,
+                image_layouts,
+                external_sets,
+                search_paths,
+                defines,
+                profile,
+                spirv,
+                optimize,
+                debug,
+                vulkan,
+            });
+        }
+
+        // Entry points given by name take precedence over a `path:entry` suffix
+        let (vert, vert_suffix) = split_entry(vert.unwrap());
+        let vert_entry = vert_entry.or(vert_suffix);
+        let (frag, frag_entry) = match frag.map(split_entry) {
+            Some((frag, frag_suffix)) => (Some(frag), frag_entry.or(frag_suffix)),
+            None => (None, frag_entry),
+        };
+
+        let call_site = proc_macro2::Span::call_site();
+        let vert = get_manifest_path(call_site, &vert)?;
+        let frag = frag
+            .map(|frag| get_manifest_path(call_site, &frag))
+            .transpose()?;
+
+        let variants = match variants {
+            Some((paths, expr)) => get_variants(&expr, paths, &frag_entry)?,
+            None => Vec::new(),
+        };
+
+        Ok(Self {
+            name: name.unwrap(),
+            vert,
+            frag,
+            variants,
+            sources: None,
+            vert_entry,
+            frag_entry,
+            optional,
+            max_gap,
+            deny_gaps,
+            precompiled,
+            export,
+            embed,
+            debug_wireframe,
+            options,
+            subpass,
+            rasterizer_discard,
+            static_viewport,
+            struct_name,
+            vertex,
+            bindless_count,
+            immutable_samplers,
+            dynamic,
+            buffer_offsets,
+            push_set,
+            frames_in_flight,
+            set_traits,
+            image_layouts,
+            external_sets,
+            search_paths,
+            defines,
+            profile,
+            spirv,
+            optimize,
+            debug,
+            vulkan,
+        })
+    }
+}
+
+/// Accepts a shader path or an array of them, each optionally followed by `:entry`
+fn get_shader_paths(expr: &Expr) -> Result<Vec<(PathBuf, Option<String>)>> {
+    get_strings(expr)?
+        .into_iter()
+        .map(|path| {
+            let (path, entry) = split_entry(path);
+            Ok((get_manifest_path(expr.span(), &path)?, entry))
+        })
+        .collect()
+}
+
+/// Names each fragment variant after the first part of its file name in camel case
+fn get_variants(expr: &Expr, paths: Vec<String>, entry: &Option<String>) -> Result<Vec<Variant>> {
+    let mut variants: Vec<Variant> = Vec::new();
+    for path in paths {
+        let (path, suffix) = split_entry(path);
+        let path = get_manifest_path(expr.span(), &path)?;
+        let file_name = path
+            .file_name()
+            .map(|name| name.to_string_lossy())
+            .unwrap_or_default();
+        let stem = file_name.split('.').next().unwrap_or_default();
+        let name = get_pipeline_name(expr, stem)?;
+        if variants.iter().any(|variant| variant.name == name) {
+            return Err(Error::new_spanned(
+                expr,
+                format!("Fragment variants share the name `{}`", name),
+            ));
+        }
+
+        variants.push(Variant {
+            name,
+            path,
+            entry: suffix.or_else(|| entry.clone()),
+        });
+    }
+    Ok(variants)
+}
+
+/// Names a pipeline after a file stem in camel case, e.g. `UnlitDebug` for `unlit_debug`
+fn get_pipeline_name(expr: &Expr, stem: &str) -> Result<String> {
+    let name = stem
+        .split(['_', '-'])
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            let first = chars.next().unwrap().to_ascii_uppercase();
+            std::iter::once(first).chain(chars).collect::<String>()
+        })
+        .collect::<String>();
+
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(Error::new_spanned(
+            expr,
+            format!("Can not name a pipeline after `{}`", stem),
+        ));
+    }
+    Ok(name)
+}
+
+/// Lists the names of the files in a directory relative to the crate
+fn read_dir(expr: &Expr, dir: &str) -> Result<Vec<String>> {
+    let path = get_manifest_path(expr.span(), dir)?;
+    let entries = std::fs::read_dir(&path).map_err(|err| {
+        Error::new_spanned(
+            expr,
+            format!("Failed to read directory `{}`: {}", path.display(), err),
+        )
+    })?;
+
+    let mut file_names = Vec::new();
+    for entry in entries {
+        let entry = entry.map_err(|err| {
+            Error::new_spanned(
+                expr,
+                format!("Failed to read directory `{}`: {}", path.display(), err),
+            )
+        })?;
+        if entry.path().is_file() {
+            file_names.push(entry.file_name().to_string_lossy().to_string());
+        }
+    }
+    Ok(file_names)
+}
+
+/// Splits a `path:entry` argument, leaving alone paths whose colon
+/// is part of a drive letter or is not followed by an identifier
+fn split_entry(arg: String) -> (String, Option<String>) {
+    if let Some((path, entry)) = arg.rsplit_once(':')
+        && path.len() > 1
+        && !entry.is_empty()
+        && entry.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return (path.to_string(), Some(entry.to_string()));
+    }
+    (arg, None)
+}
+
+/// Replaces each `${VAR}` of a path with the value of the environment variable
+/// `VAR` at expansion time, e.g. `${CARGO_MANIFEST_DIR}` or `${OUT_DIR}`
+fn expand_vars(span: proc_macro2::Span, path: &str) -> Result<String> {
+    let mut ret = String::new();
+    let mut rest = path;
+    while let Some(start) = rest.find("${") {
+        let Some(len) = rest[start..].find('}') else {
+            return Err(Error::new(
+                span,
+                format!("Unterminated `${{` in `{}`", path),
+            ));
+        };
+        let var = &rest[start + 2..start + len];
+        let value = std::env::var(var).map_err(|_| {
+            Error::new(
+                span,
+                format!(
+                    "Failed to find environment variable `{}` in `{}`",
+                    var, path
+                ),
+            )
+        })?;
+        ret.push_str(&rest[..start]);
+        ret.push_str(&value);
+        rest = &rest[start + len + 1..];
+    }
+    ret.push_str(rest);
+    Ok(ret)
+}
+
+/// Resolves a path relative to the manifest directory of the invoking crate,
+/// so that it does not depend on the working directory of the build
+fn get_manifest_path(span: proc_macro2::Span, path: &str) -> Result<PathBuf> {
+    let path = PathBuf::from(expand_vars(span, path)?);
+    match std::env::var_os("CARGO_MANIFEST_DIR") {
+        Some(manifest_dir) if path.is_relative() => Ok(PathBuf::from(manifest_dir).join(path)),
+        _ => Ok(path),
+    }
+}
+
+/// Accepts `true` for exporting to the `OUT_DIR` of the build script of the invoking crate,
+/// `false`, or a directory relative to the crate
+fn get_export_dir(expr: &Expr) -> Result<Option<PathBuf>> {
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Bool(lit),
+            ..
+        }) => match lit.value {
+            true => match std::env::var_os("OUT_DIR") {
+                Some(out_dir) => Ok(Some(PathBuf::from(out_dir))),
+                None => Err(Error::new_spanned(
+                    expr,
+                    "`export = true` writes to `OUT_DIR`, which needs a build script, \
+                    otherwise give a directory like `export = \"shaders/spv\"`",
+                )),
+            },
+            false => Ok(None),
+        },
+        _ => Ok(Some(get_manifest_path(expr.span(), &get_string(expr)?)?)),
+    }
+}
+
+fn get_key(expr: &Expr) -> Result<Ident> {
+    match expr {
+        Expr::Path(path) => match path.path.get_ident() {
+            Some(ident) => Ok(ident.clone()),
+            None => Err(Error::new_spanned(expr, "Expected an option name")),
+        },
+        _ => Err(Error::new_spanned(expr, "Expected an option name")),
+    }
+}
+
+/// Accepts either an identifier or a string literal
+fn get_string(expr: &Expr) -> Result<String> {
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(lit), ..
+        }) => Ok(lit.value()),
+        Expr::Path(path) if path.path.get_ident().is_some() => {
+            Ok(path.path.get_ident().unwrap().to_string())
+        }
+        _ => Err(Error::new_spanned(
+            expr,
+            "Expected an identifier or a string literal",
+        )),
+    }
+}
+
+/// Rejects `key` when it is `blend` and `others` already has it, as the blend preset
+/// is given either as a named argument or in the options block, but not both
+fn check_blend_once(key: &Ident, others: &[Ident]) -> Result<()> {
+    if key == "blend" && others.contains(key) {
+        return Err(Error::new_spanned(
+            key,
+            "`blend` is given both as a named argument and in the options block",
+        ));
+    }
+    Ok(())
+}
+
+/// Accepts the name of a preset, e.g. `additive` for a blend preset
+fn get_named<T>(expr: &Expr, from_name: fn(&str) -> Option<T>, what: &str) -> Result<T> {
+    let name = get_string(expr)?;
+    from_name(&name).ok_or_else(|| Error::new_spanned(expr, format!("Unknown {} `{}`", what, name)))
+}
+
+/// Accepts a SPIR-V version as `"1.3"` or `1.3`
+fn get_spirv_version(expr: &Expr) -> Result<String> {
+    let version = match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(lit), ..
+        }) => lit.value(),
+        Expr::Lit(ExprLit {
+            lit: Lit::Float(lit),
+            ..
+        }) => lit.to_string(),
+        _ => String::new(),
+    };
+    match version.split_once('.') {
+        Some(("1", "0" | "1" | "2" | "3" | "4" | "5" | "6")) => Ok(version),
+        _ => Err(Error::new_spanned(
+            expr,
+            "Expected a SPIR-V version from `1.0` to `1.6`",
+        )),
+    }
+}
+
+/// Accepts a Vulkan version as `"1.0"` or `1.0`
+fn get_vulkan_version(expr: &Expr) -> Result<Vulkan> {
+    let version = match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Str(lit), ..
+        }) => lit.value(),
+        Expr::Lit(ExprLit {
+            lit: Lit::Float(lit),
+            ..
+        }) => lit.to_string(),
+        _ => String::new(),
+    };
+    Vulkan::from_name(&version)
+        .ok_or_else(|| Error::new_spanned(expr, "Expected a Vulkan version from `1.0` to `1.3`"))
+}
+
+/// Accepts a struct name as `"MeshPso"` or `MeshPso`, which must be an identifier
+fn get_struct_name(expr: &Expr) -> Result<String> {
+    let name = get_string(expr)?;
+    match parse_str::<Ident>(&name) {
+        Ok(_) => Ok(name),
+        Err(_) => Err(Error::new_spanned(
+            expr,
+            format!("Expected an identifier for `struct_name`, found `{}`", name),
+        )),
+    }
+}
+
+/// Accepts the path of a type, e.g. `crate::gfx::SkinnedVertex`
+fn get_type_path(expr: &Expr) -> Result<String> {
+    match expr {
+        Expr::Path(path) if path.qself.is_none() => Ok(path.path.to_token_stream().to_string()),
+        _ => Err(Error::new_spanned(
+            expr,
+            "Expected the path of a vertex type",
+        )),
+    }
+}
+
+/// Accepts either a single value or an array of values
+fn get_strings(expr: &Expr) -> Result<Vec<String>> {
+    match expr {
+        Expr::Array(array) => array.elems.iter().map(get_string).collect(),
+        _ => Ok(vec![get_string(expr)?]),
+    }
+}
+
+fn get_int<N>(expr: &Expr) -> Result<N>
+where
+    N: std::str::FromStr,
+    N::Err: std::fmt::Display,
+{
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(lit), ..
+        }) => lit.base10_parse(),
+        _ => Err(Error::new_spanned(expr, "Expected an integer literal")),
+    }
+}
+
+fn get_ints<N>(expr: &Expr) -> Result<Vec<N>>
+where
+    N: std::str::FromStr,
+    N::Err: std::fmt::Display,
+{
+    match expr {
+        Expr::Array(array) => array.elems.iter().map(get_int).collect(),
+        _ => Ok(vec![get_int(expr)?]),
+    }
+}
+
+/// Accepts a sample count, a power of two up to 64
+fn get_samples(expr: &Expr) -> Result<u32> {
+    let samples = get_int::<u32>(expr)?;
+    if !samples.is_power_of_two() || samples > 64 {
+        return Err(Error::new_spanned(
+            expr,
+            "Expected a sample count of 1, 2, 4, 8, 16, 32, or 64",
+        ));
+    }
+    Ok(samples)
+}
+
+/// Accepts a number of frames in flight, which can not be zero
+fn get_frames_in_flight(expr: &Expr) -> Result<u32> {
+    let frames = get_int::<u32>(expr)?;
+    if frames == 0 {
+        return Err(Error::new_spanned(
+            expr,
+            "Expected a `frames_in_flight` greater than zero",
+        ));
+    }
+    Ok(frames)
+}
+
+/// Accepts traits binding a set as `[Trait = set, ..]`, where a trait is a path
+fn get_set_traits(expr: &Expr) -> Result<Vec<(String, u32)>> {
+    let get_set_trait = |expr: &Expr| match expr {
+        Expr::Assign(assign) => match &*assign.left {
+            Expr::Path(path) if path.qself.is_none() => Ok((
+                path.path.to_token_stream().to_string(),
+                get_int(&assign.right)?,
+            )),
+            left => Err(Error::new_spanned(left, "Expected the path of a trait")),
+        },
+        _ => Err(Error::new_spanned(expr, "Expected `Trait = set`")),
+    };
+    match expr {
+        Expr::Array(array) => array.elems.iter().map(get_set_trait).collect(),
+        _ => Ok(vec![get_set_trait(expr)?]),
+    }
+}
+
+/// Image layouts in which a descriptor can be read, named after their `vk::ImageLayout` constants
+const IMAGE_LAYOUTS: &[&str] = &[
+    "GENERAL",
+    "SHADER_READ_ONLY_OPTIMAL",
+    "READ_ONLY_OPTIMAL",
+    "DEPTH_STENCIL_READ_ONLY_OPTIMAL",
+    "DEPTH_READ_ONLY_OPTIMAL",
+    "STENCIL_READ_ONLY_OPTIMAL",
+    "DEPTH_READ_ONLY_STENCIL_ATTACHMENT_OPTIMAL",
+    "DEPTH_ATTACHMENT_STENCIL_READ_ONLY_OPTIMAL",
+    "ATTACHMENT_FEEDBACK_LOOP_OPTIMAL_EXT",
+    "RENDERING_LOCAL_READ_KHR",
+    "SHARED_PRESENT_KHR",
+];
+
+/// Accepts `name = LAYOUT` or an array of them, where the name of the uniform can also be a string
+fn get_image_layouts(expr: &Expr) -> Result<Vec<(String, String)>> {
+    let get_image_layout = |expr: &Expr| match expr {
+        Expr::Assign(assign) => {
+            let layout = get_string(&assign.right)?;
+            if !IMAGE_LAYOUTS.contains(&layout.as_str()) {
+                return Err(Error::new_spanned(
+                    &assign.right,
+                    format!(
+                        "Unknown image layout `{}`, expected one of {}",
+                        layout,
+                        IMAGE_LAYOUTS.join(", ")
+                    ),
+                ));
+            }
+            Ok((get_string(&assign.left)?, layout))
+        }
+        _ => Err(Error::new_spanned(expr, "Expected `name = LAYOUT`")),
+    };
+    match expr {
+        Expr::Array(array) => array.elems.iter().map(get_image_layout).collect(),
+        _ => Ok(vec![get_image_layout(expr)?]),
+    }
+}
+
+/// Accepts a descriptor count for unbounded arrays, which can not be zero
+fn get_bindless_count(expr: &Expr) -> Result<u32> {
+    let count = get_int::<u32>(expr)?;
+    if count == 0 {
+        return Err(Error::new_spanned(
+            expr,
+            "Expected a `bindless_count` greater than zero",
+        ));
+    }
+    Ok(count)
+}
+
+/// Accepts a fraction from `0.0` to `1.0`
+fn get_fraction(expr: &Expr) -> Result<f32> {
+    let fraction = match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Float(lit),
+            ..
+        }) => lit.base10_parse::<f32>()?,
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(lit), ..
+        }) => lit.base10_parse::<f32>()?,
+        _ => return Err(Error::new_spanned(expr, "Expected a number literal")),
+    };
+    if !(0.0..=1.0).contains(&fraction) {
+        return Err(Error::new_spanned(
+            expr,
+            "Expected a fraction from `0.0` to `1.0`",
+        ));
+    }
+    Ok(fraction)
+}
+
+/// Accepts a line width, which must be positive
+fn get_line_width(expr: &Expr) -> Result<f32> {
+    let width = match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Float(lit),
+            ..
+        }) => lit.base10_parse::<f32>()?,
+        Expr::Lit(ExprLit {
+            lit: Lit::Int(lit), ..
+        }) => lit.base10_parse::<f32>()?,
+        _ => return Err(Error::new_spanned(expr, "Expected a number literal")),
+    };
+    if width <= 0.0 {
+        return Err(Error::new_spanned(expr, "Expected a positive `line_width`"));
+    }
+    Ok(width)
+}
+
+fn get_bool(expr: &Expr) -> Result<bool> {
+    match expr {
+        Expr::Lit(ExprLit {
+            lit: Lit::Bool(lit),
+            ..
+        }) => Ok(lit.value),
+        _ => Err(Error::new_spanned(expr, "Expected `true` or `false`")),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn positional_and_named() {
+        let positional: Args = parse_str(r#"Main, "main.vert.slang", "main.frag.slang""#).unwrap();
+        let named: Args = parse_str(
+            r#"frag: "main.frag.slang", name: Main, vert = "path with spaces/main.vert.slang""#,
+        )
+        .unwrap();
+        assert_eq!(positional.name, named.name);
+        assert_eq!(positional.frag, named.frag);
+        assert!(named.vert.is_absolute());
+        assert!(named.vert.ends_with("path with spaces/main.vert.slang"));
+
+        let single: Args = parse_str(r#"Main, "main.slang""#).unwrap();
+        assert!(single.vert.ends_with("main.slang"));
+        assert_eq!(single.frag, None);
+
+        let mixed: Args =
+            parse_str(r#"Main, frag: "main.frag.slang", vert: "main.vert.slang""#).unwrap();
+        assert_eq!(mixed.vert, positional.vert);
+    }
+
+    #[test]
+    fn entries() {
+        let args: Args =
+            parse_str(r#"Main, "a.vert.slang:vs_main", "C:\\a.frag.slang", frag_entry: fs_main"#)
+                .unwrap();
+        assert!(args.vert.ends_with("a.vert.slang"));
+        assert_eq!(args.vert_entry.as_deref(), Some("vs_main"));
+        assert!(args.frag.unwrap().ends_with("C:\\a.frag.slang"));
+        assert_eq!(args.frag_entry.as_deref(), Some("fs_main"));
+    }
+
+    #[test]
+    fn defines() {
+        let args: Args = parse_str(
+            r#"MainSkinned, "main.vert.slang", "main.frag.slang", defines = ["SKINNED", "MAX_BONES=64"]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            args.defines,
+            vec![
+                (String::from("SKINNED"), String::from("1")),
+                (String::from("MAX_BONES"), String::from("64"))
+            ]
+        );
+    }
+
+    #[test]
+    fn target() {
+        let args: Args =
+            parse_str(r#"Main, "main.slang", profile = "sm_6_0", spirv = 1.3"#).unwrap();
+        assert_eq!(args.profile.as_deref(), Some("sm_6_0"));
+        assert_eq!(args.spirv.as_deref(), Some("1.3"));
+        assert_eq!(args.optimize, None);
+        assert!(!args.debug);
+
+        let args: Args =
+            parse_str(r#"Main, "main.slang", debug = true, optimize = "none""#).unwrap();
+        assert_eq!(args.optimize, Some(Optimization::None));
+        assert!(args.debug);
+        assert_eq!(args.vulkan, None);
+
+        let args: Args = parse_str(r#"Main, "main.slang", vulkan = 1.0"#).unwrap();
+        assert_eq!(args.vulkan, Some(Vulkan::V1_0));
+        assert_eq!(Vulkan::V1_0.get_profile(), "glsl_450");
+        assert_eq!(Vulkan::V1_0.get_spirv(), "1.0");
+        let args: Args = parse_str(r#"Main, "main.slang", vulkan = "1.2""#).unwrap();
+        assert_eq!(args.vulkan, Some(Vulkan::V1_2));
+    }
+
+    #[test]
+    fn struct_name() {
+        let args: Args = parse_str(r#"Mesh, "mesh.slang""#).unwrap();
+        assert_eq!(args.struct_name, None);
+
+        let args: Args = parse_str(r#"Mesh, "mesh.slang", struct_name = MeshPso"#).unwrap();
+        assert_eq!(args.struct_name.as_deref(), Some("MeshPso"));
+    }
+
+    #[test]
+    fn vertex() {
+        let args: Args =
+            parse_str(r#"Mesh, "mesh.slang", vertex = crate::gfx::SkinnedVertex"#).unwrap();
+        assert_eq!(
+            args.vertex.as_deref(),
+            Some("crate :: gfx :: SkinnedVertex")
+        );
+    }
+
+    #[test]
+    fn immutable_samplers() {
+        let args: Args =
+            parse_str(r#"Mesh, "mesh.slang", immutable_samplers = [albedo, "normal"]"#).unwrap();
+        assert_eq!(args.immutable_samplers, vec!["albedo", "normal"]);
+    }
+
+    #[test]
+    fn dynamic() {
+        let args: Args = parse_str(r#"Mesh, "mesh.slang", dynamic = ["model"]"#).unwrap();
+        assert_eq!(args.dynamic, vec!["model"]);
+    }
+
+    #[test]
+    fn buffer_offsets() {
+        let args: Args =
+            parse_str(r#"Mesh, "mesh.slang", buffer_offsets = [model, "bones"]"#).unwrap();
+        assert_eq!(args.buffer_offsets, vec!["model", "bones"]);
+    }
+
+    #[test]
+    fn push_set() {
+        let args: Args = parse_str(r#"Mesh, "mesh.slang", push_set = 2"#).unwrap();
+        assert_eq!(args.push_set, Some(2));
+    }
+
+    #[test]
+    fn set_traits() {
+        let args: Args = parse_str(
+            r#"Mesh, "mesh.slang", set_traits = [BindsCameraSet = 0, crate::BindsLightSet = 1]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            args.set_traits,
+            vec![
+                ("BindsCameraSet".to_string(), 0),
+                ("crate :: BindsLightSet".to_string(), 1)
+            ]
+        );
+        assert!(parse_str::<Args>(r#"Mesh, "mesh.slang", set_traits = [BindsCameraSet]"#).is_err());
+    }
+
+    #[test]
+    fn image_layouts() {
+        let args: Args = parse_str(
+            r#"Mesh, "mesh.slang", image_layouts = [depth = DEPTH_STENCIL_READ_ONLY_OPTIMAL, "feedback" = GENERAL]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            args.image_layouts,
+            vec![
+                (
+                    "depth".to_string(),
+                    "DEPTH_STENCIL_READ_ONLY_OPTIMAL".to_string()
+                ),
+                ("feedback".to_string(), "GENERAL".to_string())
+            ]
+        );
+        assert!(
+            parse_str::<Args>(r#"Mesh, "mesh.slang", image_layouts = [depth = DEPTH]"#).is_err()
+        );
+    }
+
+    #[test]
+    fn external_sets() {
+        let args: Args = parse_str(r#"Mesh, "mesh.slang", external_sets = [0, 2]"#).unwrap();
+        assert_eq!(args.external_sets, vec![0, 2]);
+        let args: Args = parse_str(r#"Mesh, "mesh.slang", external_sets = 0"#).unwrap();
+        assert_eq!(args.external_sets, vec![0]);
+    }
+
+    #[test]
+    fn frames_in_flight() {
+        let args: Args = parse_str(r#"Mesh, "mesh.slang", frames_in_flight = 3"#).unwrap();
+        assert_eq!(args.frames_in_flight, Some(3));
+        assert!(parse_str::<Args>(r#"Mesh, "mesh.slang", frames_in_flight = 0"#).is_err());
+    }
+
+    #[test]
+    fn path_characters() {
+        for (vert, frag) in [
+            (
+                "my materials/main.vert.slang",
+                "my materials/main.frag.slang",
+            ),
+            ("a,b/main.vert.slang", "main, lit.frag.slang"),
+            ("matériaux/メイン.vert.slang", "matériaux/メイン.frag.slang"),
+        ] {
+            let args: Args =
+                parse_str(&format!(r#"Main, "{}", "{}:fs_main""#, vert, frag)).unwrap();
+            assert_eq!(args.name, "Main");
+            assert!(args.vert.ends_with(vert));
+            assert!(args.frag.unwrap().ends_with(frag));
+            assert_eq!(args.frag_entry.as_deref(), Some("fs_main"));
+        }
+
+        let args: Args = parse_str(
+            r#"Main, frag = ["a, b/lit.frag.slang", "ü/unlit.frag.slang"], vert = "v, w.slang""#,
+        )
+        .unwrap();
+        assert!(args.vert.ends_with("v, w.slang"));
+        assert!(args.variants[0].path.ends_with("a, b/lit.frag.slang"));
+        assert!(args.variants[1].path.ends_with("ü/unlit.frag.slang"));
+    }
+
+    #[test]
+    fn expand_path_vars() {
+        let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let args: Args = parse_str(
+            r#"Main, "${CARGO_MANIFEST_DIR}/shaders/main.vert.slang", "shaders/main.frag.slang""#,
+        )
+        .unwrap();
+        assert_eq!(
+            args.vert,
+            PathBuf::from(&manifest_dir).join("shaders/main.vert.slang")
+        );
+        assert_eq!(
+            args.frag.unwrap(),
+            PathBuf::from(&manifest_dir).join("shaders/main.frag.slang")
+        );
+
+        let error = parse_str::<Args>(r#"Main, "${RAYCA_PIPE_MISSING}/main.slang""#)
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("`RAYCA_PIPE_MISSING`"));
+    }
+
+    #[test]
+    fn search_paths() {
+        let manifest_dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").unwrap());
+        let args: Args = parse_str(
+            r#"Main, "main.vert.slang", "main.frag.slang", search_path = ["shaders/include;shaders/common", "/abs/include"]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            args.search_paths,
+            vec![
+                manifest_dir.join("shaders/include"),
+                manifest_dir.join("shaders/common"),
+                PathBuf::from("/abs/include"),
+            ]
+        );
+
+        let args: Args =
+            parse_str(r#"Main, "main.slang", search_path = "shaders/include""#).unwrap();
+        assert_eq!(
+            args.search_paths,
+            vec![manifest_dir.join("shaders/include")]
+        );
+    }
+
+    #[test]
+    fn export_dir() {
+        let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
+        let args: Args =
+            parse_str(r#"Main, "main.vert.slang", "main.frag.slang", export = "shaders/spv""#)
+                .unwrap();
+        assert_eq!(
+            args.export,
+            Some(PathBuf::from(&manifest_dir).join("shaders/spv"))
+        );
+
+        let args: Args =
+            parse_str(r#"Main, "main.vert.slang", "main.frag.slang", export = false"#).unwrap();
+        assert_eq!(args.export, None);
+
+        // The crate of this test has no build script
+        let error =
+            parse_str::<Args>(r#"Main, "main.vert.slang", "main.frag.slang", export = true"#)
+                .err()
+                .unwrap();
+        assert!(error.to_string().contains("`OUT_DIR`"));
+    }
+
+    #[test]
+    fn inline_sources() {
+        let InlineArgs(args) = parse_str(
+            r#"Blit, vert = "float4 main() : SV_Position", frag: "float4 main() : SV_Target""#,
+        )
+        .unwrap();
+        assert_eq!(args.vert, PathBuf::from("Blit.vert.slang"));
+        let (vert, frag) = args.sources.unwrap();
+        assert_eq!(vert, "float4 main() : SV_Position");
+        assert_eq!(frag, "float4 main() : SV_Target");
+
+        let error = parse_str::<InlineArgs>(r#"Blit, "float4 main() : SV_Position""#)
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("missing `frag`"));
+    }
+
+    #[test]
+    fn options_block() {
+        let args: Args = parse_str(
+            r#"Particles, "p.vert.slang", "p.frag.slang", { blend: additive, cull: none, topology: point_list, depth_write: false }"#,
+        )
+        .unwrap();
+        assert_eq!(args.options.blend, Blend::Additive);
+        assert_eq!(args.options.cull, Cull::None);
+        assert_eq!(args.options.topology, Some(Topology::PointList));
+        assert_eq!(args.options.depth_write, Some(false));
+        assert_eq!(args.options.samples, None);
+
+        let args: Args = parse_str(
+            r#"Foliage, "f.vert.slang", "f.frag.slang", { samples: 4, alpha_to_coverage: true, sample_shading: 0.5 }"#,
+        )
+        .unwrap();
+        assert_eq!(args.options.samples, Some(4));
+        assert!(args.options.alpha_to_coverage);
+        assert_eq!(args.options.sample_shading, Some(0.5));
+        assert!(!args.static_viewport);
+
+        let args: Args = parse_str(
+            r#"DebugWire, "w.vert.slang", "w.frag.slang", { polygon_mode: line, line_width: 2 }"#,
+        )
+        .unwrap();
+        assert_eq!(args.options.polygon_mode, PolygonMode::Line);
+        assert_eq!(args.options.line_width, Some(2.0));
+
+        let error =
+            parse_str::<Args>(r#"DebugWire, "w.vert.slang", "w.frag.slang", { line_width: 0.0 }"#)
+                .err()
+                .unwrap();
+        assert!(
+            error
+                .to_string()
+                .contains("Expected a positive `line_width`")
+        );
+
+        let args: Args =
+            parse_str(r#"Capture, "c.vert.slang", "c.frag.slang", static_viewport = true"#)
+                .unwrap();
+        assert!(args.static_viewport);
+
+        let error = parse_str::<Args>(r#"Foliage, "f.vert.slang", "f.frag.slang", { samples: 3 }"#)
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("Expected a sample count"));
+
+        let error =
+            parse_str::<Args>(r#"Particles, "p.vert.slang", "p.frag.slang", { culling: back }"#)
+                .err()
+                .unwrap();
+        assert!(
+            error
+                .to_string()
+                .contains("Unknown pipeline option `culling`")
+        );
+
+        let error =
+            parse_str::<Args>(r#"Particles, "p.vert.slang", "p.frag.slang", { cull: sideways }"#)
+                .err()
+                .unwrap();
+        assert!(error.to_string().contains("Unknown cull mode `sideways`"));
+
+        let error = parse_str::<Args>(
+            r#"Particles, "p.vert.slang", "p.frag.slang", { cull: back, blend: additive, cull: none }"#,
+        )
+        .err()
+        .unwrap();
+        assert!(
+            error
+                .to_string()
+                .contains("Pipeline option `cull` is given more than once")
+        );
+
+        // The blend preset is also accepted as a named argument
+        let args: Args =
+            parse_str(r#"Particles, "p.vert.slang", "p.frag.slang", blend = additive"#).unwrap();
+        assert_eq!(args.options.blend, Blend::Additive);
+    }
+
+    #[test]
+    fn duplicate_options() {
+        let error = parse_str::<Args>(r#"Mesh, vert = "a.vert.slang", vert = "b.vert.slang""#)
+            .err()
+            .unwrap();
+        assert!(
+            error
+                .to_string()
+                .contains("Option `vert` is given more than once")
+        );
+
+        // A fragment path and fragment variants are the same option
+        let error = parse_str::<Args>(
+            r#"Mesh, "mesh.vert.slang", frag = "lit.frag.slang", frag = ["unlit.frag.slang"]"#,
+        )
+        .err()
+        .unwrap();
+        assert!(
+            error
+                .to_string()
+                .contains("Option `frag` is given more than once")
+        );
+
+        for args in [
+            r#"Particles, "p.vert.slang", "p.frag.slang", blend = additive, { blend: vertex }"#,
+            r#"Particles, "p.vert.slang", "p.frag.slang", { blend: vertex }, blend = additive"#,
+        ] {
+            let error = parse_str::<Args>(args).err().unwrap();
+            assert!(
+                error
+                    .to_string()
+                    .contains("`blend` is given both as a named argument and in the options block")
+            );
+        }
+    }
+
+    #[test]
+    fn frag_variants() {
+        let args: Args = parse_str(
+            r#"Mesh, "mesh.vert.slang", frag = ["lit.frag.slang", "unlit_debug.frag.slang:fs_main"]"#,
+        )
+        .unwrap();
+        assert_eq!(args.frag, None);
+        let names = args
+            .variants
+            .iter()
+            .map(|variant| variant.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["Lit", "UnlitDebug"]);
+        assert!(args.variants[0].path.ends_with("lit.frag.slang"));
+        assert_eq!(args.variants[0].entry, None);
+        assert_eq!(args.variants[1].entry.as_deref(), Some("fs_main"));
+
+        let error = parse_str::<Args>(
+            r#"Mesh, "mesh.vert.slang", frag = ["a/lit.frag.slang", "b/lit.frag.slang"]"#,
+        )
+        .err()
+        .unwrap();
+        assert!(error.to_string().contains("share the name `Lit`"));
+    }
+
+    #[test]
+    fn ray_tracing_args() {
+        let RayTracingArgs { args, miss, hit } = parse_str(
+            r#"PathTrace, raygen = "rt.rgen.slang", miss = ["sky.rmiss.slang", "shadow.rmiss.slang:main"], hit = ["rt.rchit.slang"]"#,
+        )
+        .unwrap();
+        assert_eq!(args.name, "PathTrace");
+        assert!(args.vert.ends_with("rt.rgen.slang"));
+        assert_eq!(miss.len(), 2);
+        assert!(miss[0].0.ends_with("sky.rmiss.slang"));
+        assert_eq!(miss[1].1.as_deref(), Some("main"));
+        assert_eq!(hit.len(), 1);
+
+        let error = parse_str::<RayTracingArgs>(
+            r#"PathTrace, raygen = "rt.rgen.slang", hit = ["rt.rchit.slang"], blend = additive"#,
+        )
+        .err()
+        .unwrap();
+        assert!(
+            error
+                .to_string()
+                .contains("Ray tracing pipelines have no fixed-function state")
+        );
+    }
+
+    #[test]
+    fn dir_pairs() {
+        let dir = std::env::temp_dir().join(format!("rayca-pipe-dir-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        for file_name in [
+            "unlit.frag.slang",
+            "toon_shaded.vert.slang",
+            "unlit.vert.slang",
+            "toon_shaded.frag.slang",
+            "common.slang",
+        ] {
+            std::fs::write(dir.join(file_name), "").unwrap();
+        }
+
+        let input = format!(r#""{}", embed = true"#, dir.display());
+        let DirArgs(pipelines) = parse_str(&input).unwrap();
+        let names = pipelines
+            .iter()
+            .map(|args| args.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["ToonShaded", "Unlit"]);
+        assert!(pipelines[1].vert.ends_with("unlit.vert.slang"));
+        assert!(
+            pipelines[1]
+                .frag
+                .as_ref()
+                .unwrap()
+                .ends_with("unlit.frag.slang")
+        );
+        assert!(pipelines.iter().all(|args| args.embed));
+
+        let input = format!(r#""{}", name = Main"#, dir.display());
+        let error = parse_str::<DirArgs>(&input).err().unwrap();
+        assert!(error.to_string().contains("`name` is given by the shaders"));
+
+        std::fs::write(dir.join("lit.vert.slang"), "").unwrap();
+        let error = parse_str::<DirArgs>(&format!(r#""{}""#, dir.display()))
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("without a counterpart"));
+        assert!(error.to_string().contains("`lit.vert.slang`"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn invalid_args() {
+        let error = parse_str::<Args>(r#"Main, "main.slang", optimize = fast"#)
+            .err()
+            .unwrap();
+        assert!(
+            error
+                .to_string()
+                .contains("Unknown optimization level `fast`")
+        );
+
+        let error = parse_str::<Args>(r#"Mesh, "mesh.slang", struct_name = "Mesh Pso""#)
+            .err()
+            .unwrap();
+        assert!(
+            error
+                .to_string()
+                .contains("Expected an identifier for `struct_name`")
+        );
+
+        let error = parse_str::<Args>(
+            r#"Cull, "cull.vert.slang", "cull.frag.slang", rasterizer_discard = true"#,
+        )
+        .err()
+        .unwrap();
+        assert!(error.to_string().contains("have no fragment shader"));
+
+        let error = parse_str::<Args>(r#"name: Main, vret: "main.vert.slang""#)
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("Unknown option `vret`"));
+
+        let error = parse_str::<Args>(r#"Main, name: Other, vert: "a", frag: "b""#)
+            .err()
+            .unwrap();
+        assert!(
+            error
+                .to_string()
+                .contains("`name` is given both by position and by name")
+        );
+
+        let error = parse_str::<Args>(r#"name: Main, frag: "main.frag.slang""#)
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("missing `vert`"));
+
+        let error = parse_str::<Args>(r#"Main, "main.slang", spirv = "2.0""#)
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("Expected a SPIR-V version"));
+
+        let error = parse_str::<Args>(r#"Main, "main.slang", vulkan = "1.4""#)
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("Expected a Vulkan version"));
+
+        let error = parse_str::<Args>(r#"Main, "main.slang", vulkan = "1.1", spirv = "1.3""#)
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("remove `profile` and `spirv`"));
+
+        let error = parse_str::<Args>(
+            r#"Main, "main.vert.spv", "main.frag.spv", precompiled = true, vulkan = "1.0""#,
+        )
+        .err()
+        .unwrap();
+        assert!(
+            error
+                .to_string()
+                .contains("not compiled for a `vulkan` version")
+        );
+    }
+}
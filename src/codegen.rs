@@ -9,71 +9,646 @@ use crate::model::*;
 
 impl ToTokens for Pipeline {
     fn to_tokens(&self, tokens: &mut TokenStream) {
-        let pipeline_name = format!("Pipeline{}", self.name);
-        let pipeline_ident = Ident::new(&pipeline_name, Span::call_site());
+        self.check_name_collisions();
+        tokens.extend(self.get_dependency_items());
+        tokens.extend(self.get_set_trait_items());
+
+        if self.is_compute() || self.is_ray_tracing() {
+            tokens.extend(self.get_raw_items());
+            return;
+        }
+
+        let pipeline_name = self.get_pipeline_name();
+        let pipeline_ident = Ident::new(&self.get_struct_name(), Span::call_site());
+        let handle_ident = format_ident!("{}Handle", pipeline_ident);
+        let error_ident = format_ident!("{}Error", pipeline_ident);
+        let cache_ident = format_ident!("{}LayoutCache", pipeline_ident);
 
         let vert = &self.shaders[0];
-        let frag = &self.shaders[1];
+        let vert_entry = &vert.entry;
 
-        let vert_path = vert.path.to_string_lossy();
-        if !vert.path.exists() {
-            panic!(
-                "{}:{}: Failed to find `{}`",
-                file!(),
-                line!(),
-                vert.path.display()
-            );
-        }
+        let create_shaders = self.get_create_shaders();
 
-        let frag_path = frag.path.to_string_lossy();
-        if !frag.path.exists() {
-            panic!(
-                "{}:{}: Failed to find `{}`",
-                file!(),
-                line!(),
-                frag.path.display()
-            );
+        let (vertex_generics, vertex_ty) = self.get_vertex_tokens();
+        let vertex_turbofish = match self.vertex {
+            Some(_) => quote! {},
+            None => quote! { ::<V> },
+        };
+
+        let spec_ident = format_ident!("{}SpecConstants", pipeline_ident);
+        let (vert_spec, vert_stage) = get_stage(vert, &spec_ident);
+
+        // Without a fragment stage there is neither a fragment module nor color blending
+        let (frag_param, frag_arg, frag_entry, frag_stage) = match self.shaders.get(1) {
+            Some(frag) => {
+                let frag_entry = &frag.entry;
+                let (frag_spec, frag_stage) = get_stage(frag, &spec_ident);
+                (
+                    quote! { frag_module: &ShaderModule, },
+                    quote! { &fragment, },
+                    quote! {
+                        let frag_entry = std::ffi::CString::new(#frag_entry).expect("Failed to create entry point");
+                        #frag_spec
+                    },
+                    quote! { #frag_stage, },
+                )
+            }
+            None => (quote! {}, quote! {}, quote! {}, quote! {}),
+        };
+
+        // Immutable samplers are provided to the constructors, which bake them in the set layouts
+        let (samplers_items, samplers_param, samplers_arg, set_layout_samplers) =
+            self.get_immutable_sampler_tokens(&pipeline_ident);
+
+        // The extent of a static viewport is threaded down to the pipeline creation
+        let (viewport_param, viewport_arg, viewport_extent, dynamic_states, viewport_method) =
+            self.get_viewport_tokens();
+        let (extent_field, extent_init) = if self.static_viewport {
+            (quote! { extent: vk::Extent2D, }, quote! { extent, })
+        } else {
+            (quote! {}, quote! {})
+        };
+
+        // Specialization constants are threaded down to the stages, where `None` keeps their defaults
+        let (spec_items, spec_param, spec_arg, spec_default) =
+            if self.get_spec_constants().is_empty() {
+                (quote! {}, quote! {}, quote! {}, quote! {})
+            } else {
+                (
+                    self.get_spec_items(&spec_ident),
+                    quote! { constants: Option<&#spec_ident>, },
+                    quote! { constants, },
+                    quote! { None, },
+                )
+            };
+
+        // Mock pipelines are created by constructors with the same signatures
+        let ignore = |args: TokenStream| {
+            if args.is_empty() {
+                quote! {}
+            } else {
+                quote! { let _ = (#args); }
+            }
+        };
+        let mock_new = quote! {
+            Self::from_mock::<#vertex_ty>(Some(pass.device.clone()), #viewport_arg)
+        };
+        let mut constructors = vec![Constructor {
+            docs: quote! {},
+            ident: format_ident!("new"),
+            generics: vertex_generics.clone(),
+            params: quote! {
+                pass: &Pass,
+                #samplers_param
+                #viewport_param
+            },
+            ret: quote! { Self },
+            body: quote! {
+                let set_layouts = Self::new_set_layouts(&pass.device, #samplers_arg);
+                let layout = Self::new_layout(&pass.device, &set_layouts);
+
+                Self::from_layout::<#vertex_ty>(
+                    #[cfg(target_os = "android")]
+                    android_app,
+                    pass,
+                    set_layouts,
+                    layout,
+                    true,
+                    #viewport_arg
+                    #spec_default
+                )
+            },
+            mock_body: {
+                let ignored = ignore(samplers_arg.clone());
+                quote! {
+                    #ignored
+                    #mock_new
+                }
+            },
+        }];
+        if !self.get_spec_constants().is_empty() {
+            let ignored = ignore(quote! { #samplers_arg constants, });
+            constructors.push(Constructor {
+                docs: quote! {
+                    /// Creates the pipeline with specialization constants replacing
+                    /// the default values of the shaders
+                },
+                ident: format_ident!("new_with_constants"),
+                generics: vertex_generics.clone(),
+                params: quote! {
+                    pass: &Pass,
+                    #samplers_param
+                    #viewport_param
+                    constants: &#spec_ident,
+                },
+                ret: quote! { Self },
+                body: quote! {
+                    let set_layouts = Self::new_set_layouts(&pass.device, #samplers_arg);
+                    let layout = Self::new_layout(&pass.device, &set_layouts);
+
+                    Self::from_layout::<#vertex_ty>(
+                        #[cfg(target_os = "android")]
+                        android_app,
+                        pass,
+                        set_layouts,
+                        layout,
+                        true,
+                        #viewport_arg
+                        Some(constants),
+                    )
+                },
+                mock_body: quote! {
+                    #ignored
+                    #mock_new
+                },
+            });
         }
+        let ignored = ignore(quote! { cache, #samplers_arg });
+        constructors.push(Constructor {
+            docs: quote! {
+                /// Creates the pipeline with set layouts fetched from `cache`, shared with
+                /// the other pipelines having structurally equal set layouts
+            },
+            ident: format_ident!("new_cached"),
+            generics: vertex_generics.clone(),
+            params: quote! {
+                pass: &Pass,
+                cache: &#cache_ident,
+                #samplers_param
+                #viewport_param
+            },
+            ret: quote! { Self },
+            body: quote! {
+                let set_layouts = Self::new_cached_set_layouts(&pass.device, cache, #samplers_arg);
+                let layout = Self::new_layout(&pass.device, &set_layouts);
+
+                let mut pipeline = Self::from_layout::<#vertex_ty>(
+                    #[cfg(target_os = "android")]
+                    android_app,
+                    pass,
+                    set_layouts,
+                    layout,
+                    true,
+                    #viewport_arg
+                    #spec_default
+                );
+                pipeline.layout_cache = Some(cache.clone());
+                pipeline
+            },
+            mock_body: quote! {
+                #ignored
+                #mock_new
+            },
+        });
+        constructors.push(Constructor {
+            docs: quote! {
+                /// Creates the pipeline with a layout owned by the caller, which is not
+                /// destroyed on drop. The layout must have been created from `set_layouts`,
+                /// whose bindings are described by `set_bindings`, and from `push_ranges`:
+                /// in debug builds these descriptions are checked against the shaders.
+            },
+            ident: format_ident!("new_with_layout"),
+            generics: vertex_generics.clone(),
+            params: quote! {
+                pass: &Pass,
+                layout: vk::PipelineLayout,
+                set_layouts: &[vk::DescriptorSetLayout],
+                set_bindings: &[&[vk::DescriptorSetLayoutBinding]],
+                push_ranges: &[vk::PushConstantRange],
+                #viewport_param
+            },
+            ret: quote! { Self },
+            body: quote! {
+                if cfg!(debug_assertions) {
+                    Self::check_layout(set_bindings, push_ranges);
+                }
+
+                Self::from_layout::<#vertex_ty>(
+                    #[cfg(target_os = "android")]
+                    android_app,
+                    pass,
+                    set_layouts.to_vec(),
+                    layout,
+                    false,
+                    #viewport_arg
+                    #spec_default
+                )
+            },
+            mock_body: quote! {
+                let _ = (layout, set_layouts, set_bindings, push_ranges);
+                #mock_new
+            },
+        });
+        let new_checked_body = quote! {
+            Self::check_features(enabled)?;
+            Ok(Self::new #vertex_turbofish(
+                #[cfg(target_os = "android")]
+                android_app,
+                pass,
+                #samplers_arg
+                #viewport_arg
+            ))
+        };
+        constructors.push(Constructor {
+            docs: quote! {
+                /// Creates the pipeline after checking that the enabled device features
+                /// include the ones required by this pipeline
+            },
+            ident: format_ident!("new_checked"),
+            generics: vertex_generics.clone(),
+            params: quote! {
+                pass: &Pass,
+                #samplers_param
+                #viewport_param
+                enabled: &vk::PhysicalDeviceFeatures,
+            },
+            ret: quote! { Result<Self, #error_ident> },
+            body: new_checked_body.clone(),
+            mock_body: new_checked_body,
+        });
 
-        let push_ranges = self.get_push_ranges();
         let set_layouts = self.get_set_layouts();
-        let bind_methods = self.get_bind_methods();
+        let bind_methods = self.get_checked_bind_methods();
+        let shaders_doc = self.get_shaders_doc();
+        let bind_all_method = self.get_bind_all_method();
+        let batch_methods = self.get_batch_methods();
         let push_methods = self.get_push_methods();
 
+        let (
+            default_texture_field,
+            default_texture_init,
+            default_texture_methods,
+            default_texture_setter,
+        ) = self.get_default_texture_tokens();
+
+        let (wireframe_field, wireframe_init, wireframe_setter, wireframe_drop, get_pipeline) =
+            if self.debug_wireframe {
+                (
+                    quote! {
+                        wireframe_pipeline: vk::Pipeline,
+                        wireframe: bool,
+                    },
+                    quote! {
+                        wireframe_pipeline: Self::new_impl::<V>(
+                            layout,
+                            &vertex,
+                            #frag_arg
+                            pass.render,
+                            vk::PolygonMode::LINE,
+                            #viewport_arg
+                            #spec_arg
+                        ),
+                        wireframe: false,
+                    },
+                    quote! {
+                        /// Switches between the fill pipeline and its line-mode variant.
+                        /// Handles obtained before this call keep their previous mode.
+                        pub fn set_wireframe(&mut self, wireframe: bool) {
+                            self.handle.wireframe = wireframe;
+                        }
+                    },
+                    quote! {
+                        self.device.destroy_pipeline(self.wireframe_pipeline, None);
+                    },
+                    quote! {
+                        if self.wireframe {
+                            self.wireframe_pipeline
+                        } else {
+                            self.pipeline
+                        }
+                    },
+                )
+            } else {
+                (
+                    quote! {},
+                    quote! {},
+                    quote! {},
+                    quote! {},
+                    quote! { self.pipeline },
+                )
+            };
+
+        let derive_key_method = get_derive_key_method(&bind_methods);
+
+        let blend_attachments = match self.options.blend {
+            Blend::Vertex => quote! { V::get_color_blend() },
+            Blend::DualSourceCoverage => quote! {
+                [vk::PipelineColorBlendAttachmentState::default()
+                    .blend_enable(true)
+                    .src_color_blend_factor(vk::BlendFactor::SRC1_COLOR)
+                    .dst_color_blend_factor(vk::BlendFactor::ONE_MINUS_SRC1_COLOR)
+                    .color_blend_op(vk::BlendOp::ADD)
+                    .src_alpha_blend_factor(vk::BlendFactor::SRC1_ALPHA)
+                    .dst_alpha_blend_factor(vk::BlendFactor::ONE_MINUS_SRC1_ALPHA)
+                    .alpha_blend_op(vk::BlendOp::ADD)
+                    .color_write_mask(vk::ColorComponentFlags::RGBA)]
+            },
+            Blend::Additive => quote! {
+                [vk::PipelineColorBlendAttachmentState::default()
+                    .blend_enable(true)
+                    .src_color_blend_factor(vk::BlendFactor::ONE)
+                    .dst_color_blend_factor(vk::BlendFactor::ONE)
+                    .color_blend_op(vk::BlendOp::ADD)
+                    .src_alpha_blend_factor(vk::BlendFactor::ONE)
+                    .dst_alpha_blend_factor(vk::BlendFactor::ONE)
+                    .alpha_blend_op(vk::BlendOp::ADD)
+                    .color_write_mask(vk::ColorComponentFlags::RGBA)]
+            },
+        };
+
+        let rasterizer_discard = self.rasterizer_discard;
+        let (blend_state, color_blend_state) = if rasterizer_discard {
+            (quote! {}, quote! {})
+        } else {
+            (
+                quote! {
+                    let blend_attachments = #blend_attachments;
+
+                    let blend = vk::PipelineColorBlendStateCreateInfo::default()
+                        .logic_op_enable(false)
+                        .attachments(&blend_attachments);
+                },
+                quote! { .color_blend_state(&blend) },
+            )
+        };
+
+        let subpass = match self.subpass {
+            Some(subpass) => quote! { #subpass },
+            None => quote! { V::get_subpass() },
+        };
+
+        let cull_mode = self.options.cull;
+        let polygon_mode = self.options.polygon_mode;
+        let line_width = self.options.line_width.unwrap_or(1.0);
+        let topology = match self.options.topology {
+            Some(topology) => quote! { #topology },
+            None => quote! { V::get_topology() },
+        };
+        let samples = format_ident!("TYPE_{}", self.options.samples.unwrap_or(1));
+        let samples = quote! { vk::SampleCountFlags::#samples };
+        let sample_shading = match self.options.sample_shading {
+            Some(min_sample_shading) => quote! {
+                .sample_shading_enable(true)
+                .min_sample_shading(#min_sample_shading)
+            },
+            None => quote! { .sample_shading_enable(false) },
+        };
+        let alpha_to_coverage = self.options.alpha_to_coverage;
+
+        let depth_state = match self.options.depth_write {
+            Some(depth_write) => quote! {
+                V::get_depth_state().depth_write_enable(#depth_write)
+            },
+            None => quote! { V::get_depth_state() },
+        };
+
+        let required_features = self.get_required_features();
+        let feature_names = required_features.iter().map(|feature| feature.get_name());
+        let feature_checks = required_features.iter().map(|feature| {
+            let name = feature.get_name();
+            let field = format_ident!("{}", feature.get_field());
+            quote! {
+                if enabled.#field == vk::FALSE {
+                    return Err(#error_ident::MissingFeature(#name));
+                }
+            }
+        });
+
+        let writes_depth = self.writes_depth();
+
+        let (desc_items, set_layout_bindings_fn) =
+            self.get_set_layout_binding_items(&pipeline_ident);
+        let binding_flags = get_binding_flags(&set_layouts);
+        let set_layout_flags = get_set_layout_flags(&set_layouts);
+        let set_layout_hashes = set_layouts
+            .iter()
+            .map(|set_layout| match set_layout.get_hash() {
+                Some(hash) => quote! { Some(#hash) },
+                None => quote! { None },
+            });
+
+        // Reflection is the same for real and mock pipelines
+        let reflection_impl = quote! {
+            /// Structural hash of each set layout, under which structurally equal set layouts
+            /// of any pipeline share a handle in a layout cache, or `None` for set layouts
+            /// baking immutable samplers, which are never shared
+            pub const SET_LAYOUT_HASHES: &'static [Option<u64>] = &[ #( #set_layout_hashes, )* ];
+
+            /// Whether the fragment shader writes `SV_Depth`, which disables early
+            /// depth testing and needs depth writes enabled by the vertex input
+            pub const WRITES_DEPTH: bool = #writes_depth;
+
+            /// Device features which must be enabled to create this pipeline
+            pub const REQUIRED_FEATURES: &'static [&'static str] = &[ #( #feature_names, )* ];
+
+            /// Checks that the device features enabled at device creation
+            /// include the ones required by this pipeline
+            pub fn check_features(enabled: &vk::PhysicalDeviceFeatures) -> Result<(), #error_ident> {
+                let _ = enabled;
+                #( #feature_checks )*
+                Ok(())
+            }
+
+            #set_layout_bindings_fn
+        };
+
+        let reflected_bindings_fn = quote! {
+            /// Bindings of each descriptor set as reflected from the shaders
+            fn get_reflected_bindings() -> Vec<Vec<vk::DescriptorSetLayoutBinding<'static>>> {
+                vec![
+                    #( #set_layouts, )*
+                ]
+            }
+        };
+
+        // Both the owner and its handles implement `Pipeline` through the handle fields
+        let pipeline_impl = get_pipeline_impl(get_pipeline, quote! { self.vertex_size });
+
+        let uniform_items = self.get_uniform_items();
+        let push_structs = self.get_push_structs();
+        let mock_items =
+            self.get_mock_items(&reflection_impl, &reflected_bindings_fn, &constructors);
+
         tokens.extend(quote! {
-            pub struct #pipeline_ident {
+            /// Cloneable handle to the Vulkan objects of a pipeline, which can bind
+            /// descriptors and push constants but does not destroy anything on drop
+            #[derive(Clone)]
+            #[cfg(not(feature = "rayca-pipe-mock"))]
+            pub struct #handle_ident {
                 vertex_size: usize,
+                subpass: u32,
                 set_layouts: Vec<vk::DescriptorSetLayout>,
                 layout: vk::PipelineLayout,
                 pipeline: vk::Pipeline,
                 device: std::sync::Arc<ash::Device>,
                 name: String,
+                #extent_field
+                #default_texture_field
+                #wireframe_field
+            }
+
+            #[cfg(not(feature = "rayca-pipe-mock"))]
+            impl #handle_ident {
+                #default_texture_methods
+
+                #derive_key_method
+
+                /// Index of the subpass of the render pass where the pipeline is used
+                pub fn get_subpass(&self) -> u32 {
+                    self.subpass
+                }
+
+                #( #bind_methods )*
+
+                #bind_all_method
+
+                #batch_methods
+
+                #( #push_methods )*
+
+                #viewport_method
+            }
+
+            #[cfg(not(feature = "rayca-pipe-mock"))]
+            impl Pipeline for #handle_ident {
+                #pipeline_impl
+            }
+
+            #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+            pub enum #error_ident {
+                /// A device feature needed by this pipeline is not enabled
+                MissingFeature(&'static str),
+            }
+
+            impl std::fmt::Display for #error_ident {
+                fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                    match self {
+                        Self::MissingFeature(feature) => write!(
+                            f,
+                            "{} requires the `{}` device feature",
+                            #pipeline_name,
+                            feature
+                        ),
+                    }
+                }
+            }
+
+            impl std::error::Error for #error_ident {}
+
+            #desc_items
+
+            /// Set layouts shared by pipelines, by structural hash, with their reference count.
+            /// The layout caches of all pipelines are the same type, so one can be shared by all
+            pub type #cache_ident = std::sync::Arc<
+                std::sync::Mutex<std::collections::HashMap<u64, (vk::DescriptorSetLayout, usize)>>,
+            >;
+
+            /// Owns the Vulkan objects of the pipeline, while binding and pushing
+            /// are provided by its handle through `Deref`
+            #shaders_doc
+            #[cfg(not(feature = "rayca-pipe-mock"))]
+            pub struct #pipeline_ident {
+                handle: #handle_ident,
+                /// An external layout and its set layouts are destroyed by their owner
+                owns_layout: bool,
+                /// Cached set layouts are destroyed when their last pipeline is dropped
+                layout_cache: Option<#cache_ident>,
+            }
+
+            #[cfg(not(feature = "rayca-pipe-mock"))]
+            impl std::ops::Deref for #pipeline_ident {
+                type Target = #handle_ident;
+
+                fn deref(&self) -> &Self::Target {
+                    &self.handle
+                }
             }
 
+            #[cfg(not(feature = "rayca-pipe-mock"))]
             impl #pipeline_ident {
+                #reflection_impl
+
                 fn create_set_layout(
                     device: &ash::Device,
                     bindings: &[vk::DescriptorSetLayoutBinding],
+                    binding_flags: &[vk::DescriptorBindingFlags],
+                    flags: vk::DescriptorSetLayoutCreateFlags,
                 ) -> vk::DescriptorSetLayout {
-                    let set_layout_info = vk::DescriptorSetLayoutCreateInfo::default()
-                        .bindings(bindings);
+                    let mut flags_info = vk::DescriptorSetLayoutBindingFlagsCreateInfo::default()
+                        .binding_flags(binding_flags);
+                    let mut set_layout_info = vk::DescriptorSetLayoutCreateInfo::default()
+                        .bindings(bindings)
+                        .flags(flags);
+                    // Unbounded arrays are allocated from pools allowing updates after binding
+                    if binding_flags.iter().any(|flags| !flags.is_empty()) {
+                        set_layout_info = set_layout_info
+                            .flags(flags | vk::DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND_POOL)
+                            .push_next(&mut flags_info);
+                    }
                     unsafe { device.create_descriptor_set_layout(&set_layout_info, None) }
                         .expect("Failed to create Vulkan descriptor set layout")
                 }
 
-                fn new_set_layouts(device: &ash::Device) -> Vec<vk::DescriptorSetLayout> {
-                    vec![
-                        #( #set_layouts, )*
-                    ]
+                #reflected_bindings_fn
+
+                /// Flags of the bindings of each descriptor set, where unbounded
+                /// arrays are partially bound and updated after binding
+                fn get_reflected_binding_flags() -> Vec<Vec<vk::DescriptorBindingFlags>> {
+                    #binding_flags
+                }
+
+                /// Flags of each descriptor set layout, where a push set is
+                /// created for push descriptors
+                fn get_reflected_set_layout_flags() -> Vec<vk::DescriptorSetLayoutCreateFlags> {
+                    #set_layout_flags
+                }
+
+                fn new_set_layouts(device: &ash::Device, #samplers_param) -> Vec<vk::DescriptorSetLayout> {
+                    let bindings = Self::get_reflected_bindings();
+                    #set_layout_samplers
+                    bindings
+                        .iter()
+                        .zip(Self::get_reflected_binding_flags())
+                        .zip(Self::get_reflected_set_layout_flags())
+                        .map(|((bindings, binding_flags), flags)| {
+                            Self::create_set_layout(device, bindings, &binding_flags, flags)
+                        })
+                        .collect()
+                }
+
+                /// Fetches the set layouts from the cache, creating the missing ones,
+                /// and counts a reference to each of them
+                fn new_cached_set_layouts(
+                    device: &ash::Device,
+                    cache: &#cache_ident,
+                    #samplers_param
+                ) -> Vec<vk::DescriptorSetLayout> {
+                    let bindings = Self::get_reflected_bindings();
+                    #set_layout_samplers
+                    let mut cache = cache.lock().expect("Failed to lock layout cache");
+                    bindings
+                        .iter()
+                        .zip(Self::get_reflected_binding_flags())
+                        .zip(Self::get_reflected_set_layout_flags())
+                        .zip(Self::SET_LAYOUT_HASHES)
+                        .map(|(((bindings, binding_flags), flags), hash)| match hash {
+                            Some(hash) => {
+                                let entry = cache.entry(*hash).or_insert_with(|| {
+                                    (Self::create_set_layout(device, bindings, &binding_flags, flags), 0)
+                                });
+                                entry.1 += 1;
+                                entry.0
+                            }
+                            None => Self::create_set_layout(device, bindings, &binding_flags, flags),
+                        })
+                        .collect()
                 }
 
                 fn new_layout(device: &ash::Device, set_layouts: &[vk::DescriptorSetLayout]) -> vk::PipelineLayout {
                     let mut create_info = vk::PipelineLayoutCreateInfo::default()
                         .set_layouts(set_layouts);
 
-                    let push_ranges = [
-                        #( #push_ranges, )*
-                    ];
+                    let push_ranges = Self::get_push_ranges();
                     if !push_ranges.is_empty() {
                         create_info = create_info.push_constant_ranges(&push_ranges);
                     }
@@ -85,14 +660,19 @@ impl ToTokens for Pipeline {
                 fn new_impl<V: VertexInput>(
                     layout: vk::PipelineLayout,
                     vert_module: &ShaderModule,
-                    frag_module: &ShaderModule,
+                    #frag_param
                     pass: vk::RenderPass,
+                    polygon_mode: vk::PolygonMode,
+                    #viewport_param
+                    #spec_param
                 ) -> vk::Pipeline {
-                    let entry = std::ffi::CString::new("main").expect("Failed to create entry point");
+                    let vert_entry = std::ffi::CString::new(#vert_entry).expect("Failed to create entry point");
+                    #vert_spec
+                    #frag_entry
 
                     let stages = [
-                        vert_module.get_stage(&entry, vk::ShaderStageFlags::VERTEX),
-                        frag_module.get_stage(&entry, vk::ShaderStageFlags::FRAGMENT),
+                        #vert_stage,
+                        #frag_stage
                     ];
 
                     let vertex_attributes = V::get_attributes();
@@ -103,74 +683,68 @@ impl ToTokens for Pipeline {
                         .vertex_binding_descriptions(&vertex_bindings);
 
                     let input_assembly = vk::PipelineInputAssemblyStateCreateInfo::default()
-                        .topology(V::get_topology())
+                        .topology(#topology)
                         .primitive_restart_enable(false);
 
                     let rasterization = vk::PipelineRasterizationStateCreateInfo::default()
-                        .line_width(1.0)
+                        .line_width(#line_width)
                         .depth_clamp_enable(false)
-                        .rasterizer_discard_enable(false)
-                        .polygon_mode(vk::PolygonMode::FILL)
-                        .cull_mode(vk::CullModeFlags::NONE)
+                        .rasterizer_discard_enable(#rasterizer_discard)
+                        .polygon_mode(polygon_mode)
+                        .cull_mode(#cull_mode)
                         .front_face(vk::FrontFace::COUNTER_CLOCKWISE)
                         .depth_bias_enable(false);
 
-                    // Pass as input? Or just use a default value.
-                    let width = 960;
-                    let height = 960;
+                    #viewport_extent
 
                     let viewports = [vk::Viewport::default()
                         .x(0.0)
-                        .y(height as f32)
-                        .width(width as f32)
-                        .height(-(height as f32))
+                        .y(extent.height as f32)
+                        .width(extent.width as f32)
+                        .height(-(extent.height as f32))
                         // Reversed Z-buffering
                         .min_depth(1.0)
                         .max_depth(0.0)];
-
-                    let scissors = [
-                        vk::Rect2D::default()
-                            .offset(vk::Offset2D::default().x(0).y(0))
-                            .extent(vk::Extent2D::default().width(width).height(height))
-                    ];
+                    let scissors = [vk::Rect2D::default().extent(extent)];
 
                     let view = vk::PipelineViewportStateCreateInfo::default()
                         .viewports(&viewports)
                         .scissors(&scissors);
 
                     let multisample = vk::PipelineMultisampleStateCreateInfo::default()
-                        .rasterization_samples(vk::SampleCountFlags::TYPE_1)
-                        .sample_shading_enable(false)
-                        .alpha_to_coverage_enable(false)
+                        .rasterization_samples(#samples)
+                        #sample_shading
+                        .alpha_to_coverage_enable(#alpha_to_coverage)
                         .alpha_to_one_enable(false);
 
-                    let blend_attachments = V::get_color_blend();
-
-                    let blend = vk::PipelineColorBlendStateCreateInfo::default()
-                        .logic_op_enable(false)
-                        .attachments(&blend_attachments);
+                    #blend_state
 
-                    let states = [
-                        vk::DynamicState::VIEWPORT,
-                        vk::DynamicState::SCISSOR
-                    ];
+                    #dynamic_states
                     let dynamic_states = vk::PipelineDynamicStateCreateInfo::default()
                         .dynamic_states(&states);
 
-                    let depth_state = V::get_depth_state();
+                    let depth_state = #depth_state;
+                    if cfg!(debug_assertions) && Self::WRITES_DEPTH {
+                        assert!(
+                            depth_state.depth_write_enable == vk::TRUE,
+                            "{}: the fragment shader writes SV_Depth, but depth writes are disabled by `{}::get_depth_state()`",
+                            #pipeline_name,
+                            std::any::type_name::<V>(),
+                        );
+                    }
 
                     let create_info = vk::GraphicsPipelineCreateInfo::default()
                         .stages(&stages)
                         .layout(layout)
                         .render_pass(pass)
-                        .subpass(V::get_subpass())
+                        .subpass(#subpass)
                         .vertex_input_state(&vertex_input)
                         .input_assembly_state(&input_assembly)
                         .depth_stencil_state(&depth_state)
                         .rasterization_state(&rasterization)
                         .viewport_state(&view)
                         .multisample_state(&multisample)
-                        .color_blend_state(&blend)
+                        #color_blend_state
                         .dynamic_state(&dynamic_states);
 
                     let pipelines = unsafe { vert_module.device.create_graphics_pipelines(vk::PipelineCache::null(), &[create_info], None) };
@@ -180,319 +754,8239 @@ impl ToTokens for Pipeline {
                     pipeline
                 }
 
-                pub fn new<V: VertexInput>(
+                #( #constructors )*
+
+                /// Panics if the description of an external layout is not compatible
+                /// with the descriptor sets and push constants of the shaders
+                fn check_layout(
+                    set_bindings: &[&[vk::DescriptorSetLayoutBinding]],
+                    push_ranges: &[vk::PushConstantRange],
+                ) {
+                    for (set, reflected_bindings) in Self::get_reflected_bindings().iter().enumerate() {
+                        for reflected in reflected_bindings {
+                            let binding = set_bindings
+                                .get(set)
+                                .and_then(|bindings| {
+                                    bindings.iter().find(|b| b.binding == reflected.binding)
+                                })
+                                .unwrap_or_else(|| panic!(
+                                    "{}: external layout is missing set {} binding {}",
+                                    #pipeline_name,
+                                    set,
+                                    reflected.binding
+                                ));
+                            assert!(
+                                binding.descriptor_type == reflected.descriptor_type
+                                    && binding.descriptor_count >= reflected.descriptor_count
+                                    && binding.stage_flags.contains(reflected.stage_flags),
+                                "{}: external layout set {} binding {} is {:?}[{}] for {:?}, expected {:?}[{}] for {:?}",
+                                #pipeline_name,
+                                set,
+                                reflected.binding,
+                                binding.descriptor_type,
+                                binding.descriptor_count,
+                                binding.stage_flags,
+                                reflected.descriptor_type,
+                                reflected.descriptor_count,
+                                reflected.stage_flags,
+                            );
+                        }
+                    }
+
+                    for reflected in Self::get_push_ranges() {
+                        assert!(
+                            push_ranges.iter().any(|range| {
+                                range.stage_flags.contains(reflected.stage_flags)
+                                    && range.offset <= reflected.offset
+                                    && range.offset + range.size >= reflected.offset + reflected.size
+                            }),
+                            "{}: external layout has no push constant range covering {:?} [{}, {})",
+                            #pipeline_name,
+                            reflected.stage_flags,
+                            reflected.offset,
+                            reflected.offset + reflected.size,
+                        );
+                    }
+                }
+
+                fn from_layout<V: VertexInput>(
                     #[cfg(target_os = "android")]
                     android_app: &AndroidApp,
                     pass: &Pass,
+                    set_layouts: Vec<vk::DescriptorSetLayout>,
+                    layout: vk::PipelineLayout,
+                    owns_layout: bool,
+                    #viewport_param
+                    #spec_param
                 ) -> Self {
                     let name = String::from(#pipeline_name);
 
                     let device = pass.device.clone();
 
-                    let set_layouts = Self::new_set_layouts(&device);
-                    let layout = Self::new_layout(&device, &set_layouts);
-
-                    #[cfg(target_os = "android")]
-                    let (vertex, fragment) = ShaderModule::create_shaders(android_app, &device, #vert_path, #frag_path);
-                    #[cfg(not(target_os = "android"))]
-                    let (vertex, fragment) = ShaderModule::create_shaders(&device, #vert_path, #frag_path);
+                    #create_shaders
 
-                    let pipeline = Self::new_impl::<V>(layout, &vertex, &fragment, pass.render);
+                    let pipeline = Self::new_impl::<V>(
+                        layout,
+                        &vertex,
+                        #frag_arg
+                        pass.render,
+                        #polygon_mode,
+                        #viewport_arg
+                        #spec_arg
+                    );
 
-                    Self {
+                    let handle = #handle_ident {
                         vertex_size: std::mem::size_of::<V>(),
+                        subpass: #subpass,
                         set_layouts,
                         layout,
                         pipeline,
                         device,
                         name,
-                    }
-                }
-
-                #( #bind_methods )*
-
-                #( #push_methods )*
-            }
-
-            impl Pipeline for #pipeline_ident {
-                fn as_any(&self) -> &dyn std::any::Any {
-                    self
-                }
-
-                fn get_name(&self) -> &String {
-                    &self.name
-                }
+                        #extent_init
+                        #default_texture_init
+                        #wireframe_init
+                    };
 
-                fn get_set_layouts(&self) -> &[vk::DescriptorSetLayout] {
-                    &self.set_layouts
+                    Self { handle, owns_layout, layout_cache: None }
                 }
 
-                fn get_layout(&self) -> vk::PipelineLayout {
-                    self.layout
+                /// Returns a cloneable handle for binding and pushing with this pipeline,
+                /// which must not outlive it
+                pub fn handle(&self) -> #handle_ident {
+                    self.handle.clone()
                 }
 
-                fn get_pipeline(&self) -> vk::Pipeline {
-                    self.pipeline
-                }
+                #default_texture_setter
 
-                fn get_device(&self) -> &ash::Device {
-                    &self.device
-                }
+                #wireframe_setter
+            }
 
-                fn get_vertex_size(&self) -> usize {
-                    self.vertex_size
-                }
+            #[cfg(not(feature = "rayca-pipe-mock"))]
+            impl Pipeline for #pipeline_ident {
+                #pipeline_impl
             }
 
+            #[cfg(not(feature = "rayca-pipe-mock"))]
             impl Drop for #pipeline_ident {
                 fn drop(&mut self) {
                     unsafe {
-                        for set_layout in &self.set_layouts {
-                            self.device.destroy_descriptor_set_layout(*set_layout, None);
+                        if self.owns_layout {
+                            let mut cache = self
+                                .layout_cache
+                                .as_ref()
+                                .map(|cache| cache.lock().expect("Failed to lock layout cache"));
+                            for (set_layout, hash) in self.set_layouts.iter().zip(Self::SET_LAYOUT_HASHES) {
+                                // A cached set layout is destroyed with its last reference
+                                if let (Some(cache), Some(hash)) = (cache.as_mut(), hash) {
+                                    let entry = cache.get_mut(hash).expect("Failed to find cached set layout");
+                                    entry.1 -= 1;
+                                    if entry.1 > 0 {
+                                        continue;
+                                    }
+                                    cache.remove(hash);
+                                }
+                                self.device.destroy_descriptor_set_layout(*set_layout, None);
+                            }
+                            self.device.destroy_pipeline_layout(self.layout, None);
                         }
-                        self.device.destroy_pipeline_layout(self.layout, None);
                         self.device.destroy_pipeline(self.pipeline, None);
+                        #wireframe_drop
                     }
                 }
             }
-        })
-    }
-}
 
-pub fn codegen(pipeline: Pipeline) -> TokenStream {
-    pipeline.to_token_stream()
-}
+            #uniform_items
 
-impl ToTokens for SetLayout {
-    fn to_tokens(&self, tokens: &mut TokenStream) {
-        let bindings = &self.bindings;
-        tokens.extend(quote! {
-            Self::create_set_layout(
-                device,
-                &[
-                    #( #bindings, )*
-                ]
-            )
-        })
-    }
-}
+            #push_structs
 
-impl ToTokens for SetLayoutBinding {
-    fn to_tokens(&self, tokens: &mut TokenStream) {
-        let binding = self.binding;
-        let descriptor_type = self.descriptor_type;
-        let stage = self.stage;
+            #spec_items
 
-        tokens.extend(quote! {
-            vk::DescriptorSetLayoutBinding::default()
-                .binding(#binding)
-                .descriptor_type(#descriptor_type)
-                .descriptor_count(1)
-                .stage_flags(#stage)
+            #samplers_items
+
+            #mock_items
         })
     }
 }
 
-impl ToTokens for DescriptorType {
-    fn to_tokens(&self, tokens: &mut TokenStream) {
-        let new_tokens = match self {
-            DescriptorType::Uniform => quote! { vk::DescriptorType::UNIFORM_BUFFER },
-            DescriptorType::CombinedSampler => {
-                quote! { vk::DescriptorType::COMBINED_IMAGE_SAMPLER }
+impl Pipeline {
+    /// Panics if different shader variables end up with the same name in the
+    /// generated code, once they are made valid Rust identifiers
+    fn check_name_collisions(&self) {
+        // Different uniforms with the same name would share their variant and methods
+        let all_uniforms = self
+            .shaders
+            .iter()
+            .flat_map(|shader| &shader.uniforms)
+            .collect::<Vec<_>>();
+        for (i, uniform) in all_uniforms.iter().enumerate() {
+            if let Some(other) = all_uniforms[i + 1..].iter().find(|other| {
+                other.param.name == uniform.param.name
+                    && (other.set, other.binding) != (uniform.set, uniform.binding)
+            }) {
+                panic!(
+                    "{}:{}: Uniforms at set {} binding {} and at set {} binding {} are both named `{}`",
+                    file!(),
+                    line!(),
+                    uniform.set,
+                    uniform.binding,
+                    other.set,
+                    other.binding,
+                    uniform.param.name
+                );
             }
-            DescriptorType::InputAttachment => {
-                quote! { vk::DescriptorType::INPUT_ATTACHMENT }
+        }
+        let uniforms = self
+            .get_uniforms()
+            .into_iter()
+            .map(|uniform| uniform.param.name.as_str())
+            .collect::<Vec<_>>();
+        let push_constants = self
+            .shaders
+            .iter()
+            .flat_map(|shader| shader.constants.iter().map(|param| param.name.as_str()))
+            .collect::<Vec<_>>();
+        // Uniforms also name the variants of the uniform enum
+        for (kind, names, variants) in [
+            ("Uniforms", uniforms, true),
+            ("Push constants", push_constants, false),
+        ] {
+            for (i, name) in names.iter().enumerate() {
+                let ident = get_param_ident(name);
+                let variant = get_variant_ident(name);
+                for other in names[i + 1..].iter().filter(|other| *other != name) {
+                    let collision = if get_param_ident(other) == ident {
+                        ident.clone()
+                    } else if variants && get_variant_ident(other) == variant {
+                        variant.clone()
+                    } else {
+                        continue;
+                    };
+                    panic!(
+                        "{}:{}: {} `{}` and `{}` are both named `{}` in the generated code",
+                        file!(),
+                        line!(),
+                        kind,
+                        name,
+                        other,
+                        collision
+                    );
+                }
             }
-        };
-        tokens.extend(new_tokens)
+        }
     }
-}
 
-impl ToTokens for ShaderType {
-    fn to_tokens(&self, tokens: &mut TokenStream) {
-        let new_tokens = match self {
-            ShaderType::Vertex => quote! {vk::ShaderStageFlags::VERTEX},
-            ShaderType::Fragment => quote! {vk::ShaderStageFlags::FRAGMENT},
-        };
-        tokens.extend(new_tokens)
-    }
-}
+    /// Returns the bind methods, after checking that no two generated methods share
+    /// a name. Deprecated former names colliding with other methods are left out,
+    /// as the methods can still be called by the names of their sets
+    fn get_checked_bind_methods(&self) -> Vec<BindMethod> {
+        let mut bind_methods = self.get_bind_methods();
 
-impl ToTokens for BindMethod {
-    fn to_tokens(&self, tokens: &mut TokenStream) {
-        // Build the signature of the function
-        let joined_param_names = self
-            .uniforms
-            .iter()
-            .map(|uniform| uniform.param.name.clone())
-            .collect::<Vec<String>>()
-            .join("_and_");
+        // Names of the generated methods, with what generates them
+        let mut names: Vec<(Ident, String)> = Vec::new();
+        for method in &bind_methods {
+            let set = format!("set {}", method.uniforms[0].set);
+            let mut methods = vec![(get_bind_ident(method), set.clone())];
+            if method.has_collect_method() {
+                methods.push((get_collect_ident(method), set.clone()));
+            }
+            if !method.push {
+                methods.push((get_update_ident(method), set.clone()));
+                if !method.uniforms.iter().any(|uniform| uniform.buffer_offset) {
+                    for uniform in &method.uniforms {
+                        let source = format!("uniform `{}`", uniform.param.name);
+                        methods.push((get_write_ident(uniform), source));
+                    }
+                }
+            }
+            // Texture sets also get a variant of each method overriding the layout
+            if method.has_textures() {
+                let with_layouts = methods
+                    .iter()
+                    .map(|(ident, source)| (format_ident!("{}_with_layout", ident), source.clone()))
+                    .collect::<Vec<_>>();
+                methods.extend(with_layouts);
+            }
+            methods.push((get_raw_bind_ident(method), set.clone()));
+            names.push((method.get_set_method_ident(), set));
+            names.extend(methods);
+        }
+        if self.get_bind_all_method().is_some() {
+            names.push((format_ident!("bind_all"), "all the sets".into()));
+        }
+        if self.has_batched_writes() {
+            names.push((format_ident!("bind_sets"), "batched sets".into()));
+        }
+        for method in self.get_push_methods() {
+            let ident = format_ident!("push_{}", get_rust_name(&method.name));
+            names.push((ident, format!("push constant `{}`", method.name)));
+            for (ident, field, _, _) in method.get_pushed_fields() {
+                let source = format!("field `{}` of push constant `{}`", field.name, method.name);
+                names.push((ident, source));
+            }
+        }
 
-        let bind_signature = format_ident!("bind_{}", joined_param_names);
+        for (i, (ident, source)) in names.iter().enumerate() {
+            if let Some((_, other)) = names[i + 1..].iter().find(|(other, _)| other == ident) {
+                panic!(
+                    "{}:{}: Methods generated for {} and {} are both named `{}`",
+                    file!(),
+                    line!(),
+                    source,
+                    other,
+                    ident
+                );
+            }
+        }
 
-        // Build the string for the parameters of the function
-        let method_params = self.get_method_params();
+        // Former names are dropped when they collide with any other method
+        let former_names = bind_methods
+            .iter()
+            .map(|method| method.get_former_idents())
+            .collect::<Vec<_>>();
+        for (i, method) in bind_methods.iter_mut().enumerate() {
+            method.former_names_collide = former_names[i].iter().any(|former| {
+                names.iter().any(|(ident, _)| ident == former)
+                    || former_names
+                        .iter()
+                        .enumerate()
+                        .any(|(j, others)| j != i && others.contains(former))
+            });
+        }
+        bind_methods
+    }
 
-        let write_sets = self.get_write_sets();
+    /// Implements the traits binding a set on the pipeline and its handle, defining
+    /// the ones named by a single identifier. Implementations assert at compile time
+    /// that the structural hash of their set layout matches the one of the trait
+    fn get_set_trait_items(&self) -> TokenStream {
+        if self.set_traits.is_empty() {
+            return quote! {};
+        }
+        let pipeline_ident = Ident::new(&self.get_struct_name(), Span::call_site());
+        let handle_ident = format_ident!("{}Handle", pipeline_ident);
+        let bind_methods = self.get_bind_methods();
+        let set_layouts = self.get_set_layouts();
 
-        if self.uniforms.is_empty() {
-            panic!(
-                "Failed to find uniforms for bind method `{}`",
-                bind_signature
+        let mut tokens = TokenStream::new();
+        for (name, set) in &self.set_traits {
+            let trait_path = name.parse::<TokenStream>().unwrap_or_else(|err| {
+                panic!(
+                    "{}:{}: Failed to parse set trait `{}`: {}",
+                    file!(),
+                    line!(),
+                    name,
+                    err
+                )
+            });
+            let Some(method) = bind_methods
+                .iter()
+                .find(|method| method.uniforms[0].set == *set)
+            else {
+                panic!(
+                    "{}:{}: Set {} of `{}` has no uniforms to bind with `{}`",
+                    file!(),
+                    line!(),
+                    set,
+                    pipeline_ident,
+                    name
+                );
+            };
+            if method.push {
+                panic!(
+                    "{}:{}: Set {} of `{}` is pushed, so it can not be bound with `{}`",
+                    file!(),
+                    line!(),
+                    set,
+                    pipeline_ident,
+                    name
+                );
+            }
+            let Some(hash) = set_layouts[*set as usize].get_hash() else {
+                panic!(
+                    "{}:{}: Set {} of `{}` bakes immutable samplers, so it can not be shared with `{}`",
+                    file!(),
+                    line!(),
+                    set,
+                    pipeline_ident,
+                    name
+                );
+            };
+
+            let bind_signature = get_bind_ident(method);
+            let command_buffer_ty = method.get_command_buffer_ty();
+            let (leading_params, leading_args) = method.get_leading_params();
+            let method_params = method.get_method_params();
+            let param_names = method.get_param_idents();
+            let signature = quote! {
+                fn bind_set(
+                    &self,
+                    command_buffer: #command_buffer_ty,
+                    #leading_params
+                    #( #method_params, )*
+                ) -> vk::DescriptorSet
+            };
+
+            if !name.contains("::") {
+                let doc = format!(
+                    "Binds a set laid out as set {} of `{}`, implemented by the pipelines sharing it",
+                    set, pipeline_ident
+                );
+                tokens.extend(quote! {
+                    #[doc = #doc]
+                    pub trait #trait_path {
+                        /// Structural hash of the set layout, which implementations must match
+                        const SET_LAYOUT_HASH: u64 = #hash;
+
+                        /// Binds the set for `key` on `command_buffer`, at the index of the set
+                        /// in the pipeline, returning it
+                        #signature;
+                    }
+                });
+            }
+
+            let message = format!(
+                "Set {} of `{}` is laid out differently from the set of `{}`",
+                set, pipeline_ident, name
             );
+            tokens.extend(quote! {
+                impl #trait_path for #handle_ident {
+                    #signature {
+                        self.#bind_signature(command_buffer, #leading_args #( #param_names, )*)
+                    }
+                }
+
+                impl #trait_path for #pipeline_ident {
+                    #signature {
+                        self.handle.#bind_signature(command_buffer, #leading_args #( #param_names, )*)
+                    }
+                }
+
+                const _: () = assert!(
+                    <#handle_ident as #trait_path>::SET_LAYOUT_HASH == #hash,
+                    #message
+                );
+            });
         }
-        let set = self.uniforms[0].set;
+        tokens
+    }
 
-        tokens.extend(quote! {
-            pub fn #bind_signature(
-                &self,
-                command_buffer: &CommandBuffer,
-                descriptors: &mut Descriptors,
-                key: DescriptorKey,
-                #( #method_params, )*
-            ) {
-                let set_layouts = &[self.get_set_layouts()[#set as usize]];
-                let sets = match descriptors.get_or_create(key, set_layouts) {
-                    DescriptorEntry::Created(sets) => {
-                            unsafe {
-                                self.device.update_descriptor_sets(
-                                    &[
-                                        #( #write_sets, )*
-                                    ],
-                                    &[]
-                                );
+    /// Whether any set can have its writes collected, to be flushed with other sets
+    fn has_batched_writes(&self) -> bool {
+        self.get_bind_methods()
+            .iter()
+            .any(BindMethod::has_collect_method)
+    }
+
+    /// Generates the struct gathering the writes of several sets with the infos
+    /// they refer to, so that they are performed with a single call
+    fn get_writes_items(&self) -> TokenStream {
+        if !self.has_batched_writes() {
+            return quote! {};
+        }
+        let writes_ident = format_ident!("{}Writes", self.get_struct_name());
+        let info_ident = format_ident!("{}WriteInfo", self.get_struct_name());
+        let doc = format!(
+            "Descriptor writes gathered by the `collect_writes_set*` methods of [`{}`], owning \
+            their infos until `flush_writes` performs all of them with a single call",
+            self.get_struct_name()
+        );
+        quote! {
+            /// Infos of a gathered descriptor write, as a range of the infos of its kind
+            enum #info_ident {
+                Buffers(std::ops::Range<usize>),
+                Images(std::ops::Range<usize>),
+                TexelBufferViews(std::ops::Range<usize>),
+            }
+
+            #[doc = #doc]
+            #[derive(Default)]
+            pub struct #writes_ident {
+                buffer_infos: Vec<vk::DescriptorBufferInfo>,
+                image_infos: Vec<vk::DescriptorImageInfo>,
+                texel_buffer_views: Vec<vk::BufferView>,
+                /// Destination set, binding, first array element, and descriptor type of each write
+                writes: Vec<(vk::DescriptorSet, u32, u32, vk::DescriptorType, #info_ident)>,
+            }
+
+            impl #writes_ident {
+                /// Number of descriptor writes waiting to be flushed
+                pub fn len(&self) -> usize {
+                    self.writes.len()
+                }
+
+                /// Whether no descriptor writes are waiting to be flushed
+                pub fn is_empty(&self) -> bool {
+                    self.writes.is_empty()
+                }
+
+                /// Drops the gathered writes, keeping the allocations for the next ones
+                pub fn clear(&mut self) {
+                    self.buffer_infos.clear();
+                    self.image_infos.clear();
+                    self.texel_buffer_views.clear();
+                    self.writes.clear();
+                }
+
+                fn push_buffer_infos<I: IntoIterator<Item = vk::DescriptorBufferInfo>>(
+                    &mut self,
+                    set: vk::DescriptorSet,
+                    binding: u32,
+                    array_element: u32,
+                    descriptor_type: vk::DescriptorType,
+                    infos: I,
+                ) {
+                    let start = self.buffer_infos.len();
+                    self.buffer_infos.extend(infos);
+                    let range = start..self.buffer_infos.len();
+                    self.writes.push((set, binding, array_element, descriptor_type, #info_ident::Buffers(range)));
+                }
+
+                fn push_image_infos<I: IntoIterator<Item = vk::DescriptorImageInfo>>(
+                    &mut self,
+                    set: vk::DescriptorSet,
+                    binding: u32,
+                    array_element: u32,
+                    descriptor_type: vk::DescriptorType,
+                    infos: I,
+                ) {
+                    let start = self.image_infos.len();
+                    self.image_infos.extend(infos);
+                    let range = start..self.image_infos.len();
+                    self.writes.push((set, binding, array_element, descriptor_type, #info_ident::Images(range)));
+                }
+
+                fn push_texel_buffer_views<I: IntoIterator<Item = vk::BufferView>>(
+                    &mut self,
+                    set: vk::DescriptorSet,
+                    binding: u32,
+                    array_element: u32,
+                    descriptor_type: vk::DescriptorType,
+                    views: I,
+                ) {
+                    let start = self.texel_buffer_views.len();
+                    self.texel_buffer_views.extend(views);
+                    let range = start..self.texel_buffer_views.len();
+                    self.writes.push((set, binding, array_element, descriptor_type, #info_ident::TexelBufferViews(range)));
+                }
+            }
+        }
+    }
+
+    /// Generates the methods performing gathered writes and binding a range of sets,
+    /// each with a single call
+    fn get_batch_methods(&self) -> TokenStream {
+        if !self.has_batched_writes() {
+            return quote! {};
+        }
+        let writes_ident = format_ident!("{}Writes", self.get_struct_name());
+        let info_ident = format_ident!("{}WriteInfo", self.get_struct_name());
+        let bind_sets = self.get_bind_sets_method();
+        quote! {
+            /// Performs the writes gathered by the `collect_writes_set*` methods with a single
+            /// `update_descriptor_sets`, leaving `writes` empty for the next ones
+            pub fn flush_writes(&self, writes: &mut #writes_ident) {
+                if !writes.is_empty() {
+                    let descriptor_writes = writes
+                        .writes
+                        .iter()
+                        .map(|(set, binding, array_element, descriptor_type, info)| {
+                            let write = vk::WriteDescriptorSet::default()
+                                .dst_set(*set)
+                                .dst_binding(*binding)
+                                .dst_array_element(*array_element)
+                                .descriptor_type(*descriptor_type);
+                            match info {
+                                #info_ident::Buffers(range) => {
+                                    write.buffer_info(&writes.buffer_infos[range.clone()])
+                                }
+                                #info_ident::Images(range) => {
+                                    write.image_info(&writes.image_infos[range.clone()])
+                                }
+                                #info_ident::TexelBufferViews(range) => {
+                                    write.texel_buffer_view(&writes.texel_buffer_views[range.clone()])
+                                }
                             }
-                            sets
+                        })
+                        .collect::<Vec<_>>();
+                    unsafe {
+                        self.device.update_descriptor_sets(&descriptor_writes, &[]);
                     }
-                    DescriptorEntry::Get(sets) => sets,
-                };
-                command_buffer.bind_descriptor_sets(self.get_layout(), sets, #set);
+                }
+                writes.clear();
             }
-        })
+
+            #bind_sets
+        }
     }
-}
 
-impl ToTokens for MethodParam {
-    fn to_tokens(&self, tokens: &mut TokenStream) {
-        let name = Ident::new(&self.name, Span::call_site());
-        let ty: VkrType = self.ty.into();
-        tokens.extend(quote! { #name: &#ty })
+    /// Sets with dynamic uniform buffers, and sets of other pipelines than graphics
+    /// ones, are bound on the raw handle of a command buffer with their dynamic offsets
+    fn has_raw_bind_sets(&self) -> bool {
+        self.get_bind_point() != BindPoint::Graphics
+            || self
+                .get_bind_methods()
+                .iter()
+                .any(|method| method.has_dynamic())
     }
-}
 
-impl ToTokens for ParamType {
-    fn to_tokens(&self, tokens: &mut TokenStream) {
-        let new_tokens = match self {
-            ParamType::Vec2 => quote! { Vec2 },
-            ParamType::Vec3 => quote! { Vec3 },
-            ParamType::Vec4 => quote! { Vec4 },
-            ParamType::Mat3 => quote! { Mat3 },
-            ParamType::Mat4 => quote! { Mat4 },
-            ParamType::Struct(size) => {
-                let size = *size;
-                quote! { [u8;#size] }
-            }
-            _ => panic!(
-                "{}:{}: Can not use param type on CPU: `{:?}`",
-                file!(),
-                line!(),
-                self
-            ),
+    fn get_bind_sets_method(&self) -> TokenStream {
+        let doc = quote! {
+            /// Binds the contiguous `sets` from `first_set` with a single call, like the
+            /// sets returned by the `collect_writes_set*` methods once their writes are flushed
         };
-        tokens.extend(new_tokens);
+        if self.has_raw_bind_sets() {
+            let bind_point = self.get_bind_point();
+            let (command_buffer_ty, raw_command_buffer) =
+                get_command_buffer_tokens(bind_point, true);
+            quote! {
+                #doc
+                pub fn bind_sets(
+                    &self,
+                    command_buffer: #command_buffer_ty,
+                    first_set: u32,
+                    sets: &[vk::DescriptorSet],
+                    dynamic_offsets: &[u32],
+                ) {
+                    unsafe {
+                        self.device.cmd_bind_descriptor_sets(
+                            #raw_command_buffer,
+                            #bind_point,
+                            self.get_layout(),
+                            first_set,
+                            sets,
+                            dynamic_offsets,
+                        );
+                    }
+                }
+            }
+        } else {
+            quote! {
+                #doc
+                pub fn bind_sets(
+                    &self,
+                    command_buffer: &CommandBuffer,
+                    first_set: u32,
+                    sets: &[vk::DescriptorSet],
+                ) {
+                    command_buffer.bind_descriptor_sets(self.get_layout(), sets, first_set);
+                }
+            }
+        }
     }
-}
 
-impl ToTokens for VkrType {
-    fn to_tokens(&self, tokens: &mut TokenStream) {
-        let new_tokens = match self {
-            VkrType::Buffer => quote! { RenderBuffer },
-            VkrType::Texture => quote! { RenderTexture },
+    /// Generates the batch methods of a mock pipeline, recording a bind per set
+    fn get_mock_batch_methods(&self) -> TokenStream {
+        if !self.has_batched_writes() {
+            return quote! {};
+        }
+        let writes_ident = format_ident!("{}Writes", self.get_struct_name());
+        let (command_buffer_ty, dynamic_offsets, dynamic_offsets_arg) = if self.has_raw_bind_sets()
+        {
+            (
+                get_command_buffer_tokens(self.get_bind_point(), true).0,
+                quote! { dynamic_offsets: &[u32], },
+                quote! { dynamic_offsets, },
+            )
+        } else {
+            (quote! { &CommandBuffer }, quote! {}, quote! {})
         };
-        tokens.extend(new_tokens);
+        quote! {
+            pub fn flush_writes(&self, writes: &mut #writes_ident) {
+                writes.clear();
+            }
+
+            pub fn bind_sets(
+                &self,
+                command_buffer: #command_buffer_ty,
+                first_set: u32,
+                sets: &[vk::DescriptorSet],
+                #dynamic_offsets
+            ) {
+                let _ = (command_buffer, #dynamic_offsets_arg);
+                for index in 0..sets.len() as u32 {
+                    self.record_bind("bind_sets", first_set + index);
+                }
+            }
+        }
     }
-}
 
-impl ToTokens for WriteSet {
-    fn to_tokens(&self, tokens: &mut TokenStream) {
-        let binding = self.binding;
-        let descriptor_type = self.descriptor_type;
-        let info = &self.info;
-        tokens.extend(quote! {
-            vk::WriteDescriptorSet::default()
-                .dst_set(sets[0])
-                .dst_binding(#binding)
-                .dst_array_element(0)
-                .descriptor_type(#descriptor_type)
+    /// Lists the stages and paths of the shaders in the docs of the pipeline
+    fn get_shaders_doc(&self) -> TokenStream {
+        let lines = self
+            .shaders
+            .iter()
+            .map(|shader| format!("- {:?}: `{}`", shader.ty, shader.path.display()));
+        quote! {
+            #[doc = ""]
+            #[doc = "Built from the shaders:"]
+            #( #[doc = #lines] )*
+        }
+    }
+
+    /// Includes the files the shaders depend on, so that cargo rebuilds
+    /// the invoking crate when an imported module changes
+    fn get_dependency_items(&self) -> TokenStream {
+        let paths = self
+            .shaders
+            .iter()
+            .flat_map(|shader| &shader.dependencies)
+            .map(|path| get_absolute_path(path))
+            .collect::<std::collections::BTreeSet<String>>();
+        if paths.is_empty() {
+            return quote! {};
+        }
+
+        let paths = paths.iter();
+        quote! {
+            const _: &[&[u8]] = &[ #( include_bytes!(#paths), )* ];
+        }
+    }
+
+    /// Struct of the specialization constants, whose fields are laid out
+    /// as the data of `vk::SpecializationInfo`
+    fn get_spec_items(&self, spec_ident: &Ident) -> TokenStream {
+        let fields = self.get_spec_constants().into_iter().map(|constant| {
+            let ident = get_field_ident(&constant.name);
+            let ty = constant.ty;
+            quote! { pub #ident: #ty }
         });
 
-        match self.info.ty {
-            ParamType::Image | ParamType::SampledImage => {
-                tokens.extend(quote! { .image_info(&#info) })
+        quote! {
+            #[repr(C)]
+            #[derive(Clone, Copy, Debug, Default, PartialEq)]
+            pub struct #spec_ident {
+                #( #fields, )*
+            }
+
+            #[cfg(not(feature = "rayca-pipe-mock"))]
+            impl #spec_ident {
+                fn get_bytes(&self) -> &[u8] {
+                    unsafe {
+                        std::slice::from_raw_parts(
+                            self as *const Self as *const u8,
+                            std::mem::size_of::<Self>(),
+                        )
+                    }
+                }
             }
-            _ => tokens.extend(quote! { .buffer_info(&#info) }),
         }
-        tokens.extend(quote! {});
     }
-}
 
-impl ToTokens for WriteSetInfo {
-    fn to_tokens(&self, tokens: &mut TokenStream) {
-        let name = Ident::new(&self.name, Span::call_site());
-        let ty = self.ty;
+    /// Returns the parameter and argument of the constructors taking the extent of a static
+    /// viewport, the statement defining the extent the viewport state is created with,
+    /// the dynamic states, and the method setting a dynamic viewport or getting a static extent
+    fn get_viewport_tokens(
+        &self,
+    ) -> (
+        TokenStream,
+        TokenStream,
+        TokenStream,
+        TokenStream,
+        TokenStream,
+    ) {
+        if self.static_viewport {
+            return (
+                quote! { extent: vk::Extent2D, },
+                quote! { extent, },
+                quote! {},
+                quote! { let states: [vk::DynamicState; 0] = []; },
+                quote! {
+                    /// Extent of the viewport and scissor baked in the pipeline
+                    pub fn get_extent(&self) -> vk::Extent2D {
+                        self.extent
+                    }
+                },
+            );
+        }
 
-        match ty {
-            ParamType::Image | ParamType::SampledImage => tokens.extend(quote! {
-                [
-                    vk::DescriptorImageInfo::default()
-                        .image_layout(vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL)
-                        .image_view(#name.view)
-                        .sampler(#name.sampler)
-                ]
-            }),
-            _ => {
-                let size = ty.get_size();
-                tokens.extend(quote! {
-                    [
-                        vk::DescriptorBufferInfo::default()
-                            .range(#size as vk::DeviceSize)
-                            .buffer(#name.buffer)
-                    ]
+        (
+            quote! {},
+            quote! {},
+            // Only the count of the dynamic viewports and scissors is used
+            quote! { let extent = vk::Extent2D::default().width(1).height(1); },
+            quote! {
+                let states = [
+                    vk::DynamicState::VIEWPORT,
+                    vk::DynamicState::SCISSOR
+                ];
+            },
+            quote! {
+                /// Sets the dynamic viewport and scissor of the pipeline to cover `extent`, with
+                /// the viewport flipped so that Y points up and the depth range reversed
+                pub fn set_viewport(&self, command_buffer: &CommandBuffer, extent: vk::Extent2D) {
+                    let viewport = vk::Viewport::default()
+                        .x(0.0)
+                        .y(extent.height as f32)
+                        .width(extent.width as f32)
+                        .height(-(extent.height as f32))
+                        .min_depth(1.0)
+                        .max_depth(0.0);
+                    let scissor = vk::Rect2D::default().extent(extent);
+                    unsafe {
+                        let command_buffer = command_buffer.command_buffer;
+                        self.device.cmd_set_viewport(command_buffer, 0, &[viewport]);
+                        self.device.cmd_set_scissor(command_buffer, 0, &[scissor]);
+                    }
+                }
+            },
+        )
+    }
+
+    /// Generic parameter of the constructors and the vertex type they use,
+    /// which is the one given to the macro when there is no generic parameter
+    fn get_vertex_tokens(&self) -> (TokenStream, TokenStream) {
+        match &self.vertex {
+            Some(vertex) => {
+                let vertex = vertex.parse::<TokenStream>().unwrap_or_else(|err| {
+                    panic!(
+                        "{}:{}: Failed to parse vertex type `{}`: {}",
+                        file!(),
+                        line!(),
+                        vertex,
+                        err
+                    )
                 });
+                (quote! {}, vertex)
             }
+            None => (quote! { <V: VertexInput> }, quote! { V }),
         }
     }
-}
 
-impl ToTokens for PushRange {
-    fn to_tokens(&self, tokens: &mut TokenStream) {
-        let stage = self.stage;
-        let range = self.ty.get_size();
-        tokens.extend(quote! {
-            vk::PushConstantRange::default()
-                .offset(0)
-                .stage_flags(#stage)
-                .size(#range as u32)
-        })
+    /// Field, initializer, and methods of the handle storing the default texture,
+    /// with the setter of the owner, which are empty without optional textures
+    fn get_default_texture_tokens(&self) -> (TokenStream, TokenStream, TokenStream, TokenStream) {
+        if self.has_optional() {
+            (
+                quote! { default_texture: Option<(vk::ImageView, vk::Sampler)>, },
+                quote! { default_texture: None, },
+                quote! {
+                    /// Registers the texture written in place of optional textures bound to `None`
+                    pub fn set_default_texture(&mut self, texture: &RenderTexture) {
+                        self.default_texture = Some((texture.view, texture.sampler));
+                    }
+
+                    fn get_default_texture(&self) -> (vk::ImageView, vk::Sampler) {
+                        self.default_texture
+                            .expect("Failed to find default texture: call `set_default_texture` first")
+                    }
+                },
+                quote! {
+                    /// Registers the texture written in place of optional textures bound to `None`.
+                    /// Handles obtained before this call keep their previous default texture.
+                    pub fn set_default_texture(&mut self, texture: &RenderTexture) {
+                        self.handle.set_default_texture(texture);
+                    }
+                },
+            )
+        } else {
+            (quote! {}, quote! {}, quote! {}, quote! {})
+        }
     }
-}
 
-impl ToTokens for PushMethod {
-    fn to_tokens(&self, tokens: &mut TokenStream) {
-        let push_signature = format_ident!("push_{}", self.name);
-        let arg_name = Ident::new(&self.name, Span::call_site());
-        let stage = self.stage;
-        let size = self.ty.get_size();
-        tokens.extend(quote! {
-            pub fn #push_signature<B: AsBytes>(&self, command_buffer: &CommandBuffer, #arg_name: &B) {
-                let bytes = #arg_name.as_bytes();
-                command_buffer.push_constants(
-                    self,
-                    #stage,
-                    0, //offset,
-                    &bytes[0..#size]
-                );
+    /// Generates the items of a compute or ray tracing pipeline, which has no vertex
+    /// input nor render pass and is bound and recorded on a raw command buffer
+    fn get_raw_items(&self) -> TokenStream {
+        let pipeline_name = self.get_pipeline_name();
+        let pipeline_ident = Ident::new(&self.get_struct_name(), Span::call_site());
+        let handle_ident = format_ident!("{}Handle", pipeline_ident);
+
+        let RawPipeline {
+            kind,
+            consts,
+            methods,
+            mock_methods,
+            new_params,
+            mock_ignored,
+            create_pipeline,
+        } = if self.is_compute() {
+            self.get_compute_pipeline(&pipeline_ident)
+        } else {
+            self.get_ray_tracing_pipeline(&pipeline_ident)
+        };
+        let handle_doc = format!(
+            "Cloneable handle to the Vulkan objects of a {} pipeline, which can bind\n\
+             descriptors, push constants, and record commands but does not destroy anything on drop",
+            kind
+        );
+        let pipeline_doc = format!(
+            "Owns the Vulkan objects of the {} pipeline, while binding, pushing,\n\
+             and recording are provided by its handle through `Deref`",
+            kind
+        );
+
+        let set_layouts = self.get_set_layouts();
+        let binding_flags = get_binding_flags(&set_layouts);
+        let set_layout_flags = get_set_layout_flags(&set_layouts);
+        let bind_methods = self.get_checked_bind_methods();
+        let shaders_doc = self.get_shaders_doc();
+        let bind_all_method = self.get_bind_all_method();
+        let batch_methods = self.get_batch_methods();
+        let push_methods = self.get_push_methods();
+
+        let (
+            default_texture_field,
+            default_texture_init,
+            default_texture_methods,
+            default_texture_setter,
+        ) = self.get_default_texture_tokens();
+
+        let derive_key_method = get_derive_key_method(&bind_methods);
+
+        let (desc_items, set_layout_bindings_fn) =
+            self.get_set_layout_binding_items(&pipeline_ident);
+        let uniform_items = self.get_uniform_items();
+        let push_structs = self.get_push_structs();
+
+        let mock_items = self.get_raw_mock_items(
+            &consts,
+            &set_layout_bindings_fn,
+            mock_methods,
+            new_params.clone(),
+            mock_ignored,
+        );
+
+        // Both the owner and its handles implement `Pipeline` through the handle fields,
+        // so that they push constants through `CommandBuffer` like graphics pipelines.
+        // There is no vertex input
+        let pipeline_impl = get_pipeline_impl(quote! { self.pipeline }, quote! { 0 });
+
+        quote! {
+            #[doc = #handle_doc]
+            #[derive(Clone)]
+            #[cfg(not(feature = "rayca-pipe-mock"))]
+            pub struct #handle_ident {
+                set_layouts: Vec<vk::DescriptorSetLayout>,
+                layout: vk::PipelineLayout,
+                pipeline: vk::Pipeline,
+                device: std::sync::Arc<ash::Device>,
+                name: String,
+                #default_texture_field
             }
-        })
+
+            #[cfg(not(feature = "rayca-pipe-mock"))]
+            impl #handle_ident {
+                #default_texture_methods
+
+                #derive_key_method
+
+                #methods
+
+                #( #bind_methods )*
+
+                #bind_all_method
+
+                #batch_methods
+
+                #( #push_methods )*
+            }
+
+            #[cfg(not(feature = "rayca-pipe-mock"))]
+            impl Pipeline for #handle_ident {
+                #pipeline_impl
+            }
+
+            #desc_items
+
+            #[doc = #pipeline_doc]
+            #shaders_doc
+            #[cfg(not(feature = "rayca-pipe-mock"))]
+            pub struct #pipeline_ident {
+                handle: #handle_ident,
+            }
+
+            #[cfg(not(feature = "rayca-pipe-mock"))]
+            impl std::ops::Deref for #pipeline_ident {
+                type Target = #handle_ident;
+
+                fn deref(&self) -> &Self::Target {
+                    &self.handle
+                }
+            }
+
+            #[cfg(not(feature = "rayca-pipe-mock"))]
+            impl #pipeline_ident {
+                #consts
+
+                #set_layout_bindings_fn
+
+                pub fn new(#new_params) -> Self {
+                    let binding_flags: Vec<Vec<vk::DescriptorBindingFlags>> = #binding_flags;
+                    let set_layout_flags: Vec<vk::DescriptorSetLayoutCreateFlags> = #set_layout_flags;
+                    let set_layouts: Vec<vk::DescriptorSetLayout> = [ #( #set_layouts, )* ]
+                        .iter()
+                        .zip(binding_flags)
+                        .zip(set_layout_flags)
+                        .map(|((bindings, flags), create_flags): ((&Vec<vk::DescriptorSetLayoutBinding>, _), _)| {
+                            let mut flags_info = vk::DescriptorSetLayoutBindingFlagsCreateInfo::default()
+                                .binding_flags(&flags);
+                            let mut set_layout_info = vk::DescriptorSetLayoutCreateInfo::default()
+                                .bindings(bindings)
+                                .flags(create_flags);
+                            // Unbounded arrays are allocated from pools allowing updates after binding
+                            if flags.iter().any(|flags| !flags.is_empty()) {
+                                set_layout_info = set_layout_info
+                                    .flags(create_flags | vk::DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND_POOL)
+                                    .push_next(&mut flags_info);
+                            }
+                            unsafe { device.create_descriptor_set_layout(&set_layout_info, None) }
+                                .expect("Failed to create Vulkan descriptor set layout")
+                        })
+                        .collect();
+
+                    let mut create_info = vk::PipelineLayoutCreateInfo::default()
+                        .set_layouts(&set_layouts);
+
+                    let push_ranges = Self::get_push_ranges();
+                    if !push_ranges.is_empty() {
+                        create_info = create_info.push_constant_ranges(&push_ranges);
+                    }
+
+                    let layout = unsafe { device.create_pipeline_layout(&create_info, None) }
+                        .expect("Failed to create Vulkan pipeline layout");
+
+                    #create_pipeline
+
+                    let handle = #handle_ident {
+                        set_layouts,
+                        layout,
+                        pipeline,
+                        device: device.clone(),
+                        name: String::from(#pipeline_name),
+                        #default_texture_init
+                    };
+
+                    Self { handle }
+                }
+
+                /// Returns a cloneable handle for binding, pushing, and recording
+                /// with this pipeline, which must not outlive it
+                pub fn handle(&self) -> #handle_ident {
+                    self.handle.clone()
+                }
+
+                #default_texture_setter
+            }
+
+            #[cfg(not(feature = "rayca-pipe-mock"))]
+            impl Pipeline for #pipeline_ident {
+                #pipeline_impl
+            }
+
+            #[cfg(not(feature = "rayca-pipe-mock"))]
+            impl Drop for #pipeline_ident {
+                fn drop(&mut self) {
+                    unsafe {
+                        for set_layout in &self.set_layouts {
+                            self.device.destroy_descriptor_set_layout(*set_layout, None);
+                        }
+                        self.device.destroy_pipeline_layout(self.layout, None);
+                        self.device.destroy_pipeline(self.pipeline, None);
+                    }
+                }
+            }
+
+            #uniform_items
+
+            #push_structs
+
+            #mock_items
+        }
+    }
+
+    /// Generates the mock of a compute or ray tracing pipeline, with null handles,
+    /// recording its calls, and created by `new` with the same parameters
+    fn get_raw_mock_items(
+        &self,
+        consts: &TokenStream,
+        set_layout_bindings_fn: &TokenStream,
+        mock_methods: TokenStream,
+        new_params: TokenStream,
+        mock_ignored: TokenStream,
+    ) -> TokenStream {
+        let pipeline_ident = Ident::new(&self.get_struct_name(), Span::call_site());
+        let pipeline_name = self.get_pipeline_name();
+        let handle_ident = format_ident!("{}Handle", pipeline_ident);
+        let call_ident = format_ident!("{}Call", pipeline_ident);
+        let (call_enum, call_methods) = self.get_mock_call_items(&call_ident);
+
+        let set_count = self.get_set_layouts().len();
+        let shaders_doc = self.get_shaders_doc();
+        let bind_methods = self.get_checked_bind_methods();
+        let mock_bind_methods = bind_methods.iter().map(Mock);
+        let bind_all_method = self.get_bind_all_method();
+        let mock_bind_all_method = bind_all_method.as_ref().map(Mock);
+        let mock_batch_methods = self.get_mock_batch_methods();
+        let push_methods = self.get_push_methods();
+        let mock_push_methods = push_methods.iter().map(Mock);
+
+        let (default_texture_methods, default_texture_setter) = if self.has_optional() {
+            (
+                quote! {
+                    /// Ignores the texture, as the mock writes no descriptors
+                    pub fn set_default_texture(&mut self, texture: &RenderTexture) {
+                        let _ = texture;
+                    }
+                },
+                quote! {
+                    /// Forwards the texture to the handle of the mock, which ignores it
+                    pub fn set_default_texture(&mut self, texture: &RenderTexture) {
+                        self.handle.set_default_texture(texture);
+                    }
+                },
+            )
+        } else {
+            (quote! {}, quote! {})
+        };
+
+        let pipeline_impl = get_mock_pipeline_impl(quote! { 0 });
+
+        quote! {
+            #call_enum
+
+            /// Cloneable handle to a mock pipeline, sharing its call log
+            #[cfg(feature = "rayca-pipe-mock")]
+            #[derive(Clone)]
+            pub struct #handle_ident {
+                set_layouts: Vec<vk::DescriptorSetLayout>,
+                device: Option<std::sync::Arc<ash::Device>>,
+                name: String,
+                calls: std::sync::Arc<std::sync::Mutex<Vec<#call_ident>>>,
+            }
+
+            #[cfg(feature = "rayca-pipe-mock")]
+            impl #handle_ident {
+                #call_methods
+
+                #default_texture_methods
+
+                #mock_methods
+
+                #( #mock_bind_methods )*
+
+                #mock_bind_all_method
+
+                #mock_batch_methods
+
+                #( #mock_push_methods )*
+            }
+
+            #[cfg(feature = "rayca-pipe-mock")]
+            impl Pipeline for #handle_ident {
+                #pipeline_impl
+            }
+
+            /// Mock pipeline with null handles, recording its calls instead of touching Vulkan
+            #shaders_doc
+            #[cfg(feature = "rayca-pipe-mock")]
+            pub struct #pipeline_ident {
+                handle: #handle_ident,
+            }
+
+            #[cfg(feature = "rayca-pipe-mock")]
+            impl std::ops::Deref for #pipeline_ident {
+                type Target = #handle_ident;
+
+                fn deref(&self) -> &Self::Target {
+                    &self.handle
+                }
+            }
+
+            #[cfg(feature = "rayca-pipe-mock")]
+            impl #pipeline_ident {
+                #consts
+
+                #set_layout_bindings_fn
+
+                /// Creates the mock pipeline with null set layouts, keeping the device if any
+                fn from_mock(device: Option<std::sync::Arc<ash::Device>>) -> Self {
+                    let handle = #handle_ident {
+                        set_layouts: vec![vk::DescriptorSetLayout::null(); #set_count],
+                        device,
+                        name: String::from(#pipeline_name),
+                        calls: Default::default(),
+                    };
+
+                    Self { handle }
+                }
+
+                /// Creates the mock pipeline without a device, so that `get_device` panics
+                pub fn new_mock() -> Self {
+                    Self::from_mock(None)
+                }
+
+                /// Creates the mock pipeline without touching Vulkan
+                pub fn new(#new_params) -> Self {
+                    #mock_ignored
+                    Self::from_mock(Some(device.clone()))
+                }
+
+                /// Returns a cloneable handle sharing the call log of the mock
+                pub fn handle(&self) -> #handle_ident {
+                    self.handle.clone()
+                }
+
+                #default_texture_setter
+            }
+
+            #[cfg(feature = "rayca-pipe-mock")]
+            impl Pipeline for #pipeline_ident {
+                #pipeline_impl
+            }
+        }
+    }
+
+    /// Compute pipelines are created from a single shader and dispatched
+    fn get_compute_pipeline(&self, pipeline_ident: &Ident) -> RawPipeline {
+        let compute = &self.shaders[0];
+        let entry = &compute.entry;
+        let [x, y, z] = compute.local_size;
+        let code = get_shader_code(compute);
+
+        RawPipeline {
+            kind: "compute",
+            consts: quote! {
+                /// Workgroup size of the compute shader, as declared by `numthreads`
+                pub const LOCAL_SIZE: [u32; 3] = [#x, #y, #z];
+            },
+            methods: quote! {
+                /// Workgroup size of the compute shader, as declared by `numthreads`
+                pub fn get_local_size(&self) -> [u32; 3] {
+                    #pipeline_ident::LOCAL_SIZE
+                }
+
+                /// Binds the pipeline and dispatches `x * y * z` workgroups
+                pub fn dispatch(&self, command_buffer: &CommandBuffer, x: u32, y: u32, z: u32) {
+                    unsafe {
+                        self.device.cmd_bind_pipeline(
+                            command_buffer.command_buffer,
+                            vk::PipelineBindPoint::COMPUTE,
+                            self.pipeline,
+                        );
+                        self.device.cmd_dispatch(command_buffer.command_buffer, x, y, z);
+                    }
+                }
+            },
+            mock_methods: quote! {
+                /// Workgroup size of the compute shader, as declared by `numthreads`
+                pub fn get_local_size(&self) -> [u32; 3] {
+                    #pipeline_ident::LOCAL_SIZE
+                }
+
+                /// Records the dispatch of `x * y * z` workgroups
+                pub fn dispatch(&self, command_buffer: &CommandBuffer, x: u32, y: u32, z: u32) {
+                    let _ = command_buffer;
+                    self.record_dispatch(x, y, z);
+                }
+            },
+            new_params: quote! { device: &std::sync::Arc<ash::Device> },
+            mock_ignored: quote! {},
+            create_pipeline: quote! {
+                let module = ShaderModule::new(device, #code);
+                let entry = std::ffi::CString::new(#entry).expect("Failed to create entry point");
+                let stage = module.get_stage(&entry, vk::ShaderStageFlags::COMPUTE);
+
+                let create_info = vk::ComputePipelineCreateInfo::default()
+                    .stage(stage)
+                    .layout(layout);
+
+                let pipelines = unsafe { device.create_compute_pipelines(vk::PipelineCache::null(), &[create_info], None) };
+                let mut pipelines = pipelines.expect("Failed to create Vulkan compute pipeline");
+                let pipeline = pipelines.pop().expect("Failed to pop Vulkan pipeline");
+            },
+        }
+    }
+
+    /// Ray tracing pipelines have a general group for the ray generation shader
+    /// and for each miss shader, followed by a triangles hit group for each
+    /// closest hit shader, in the order of the shader binding table
+    fn get_ray_tracing_pipeline(&self, pipeline_ident: &Ident) -> RawPipeline {
+        let codes = self.shaders.iter().map(get_shader_code);
+        let entries = self.shaders.iter().map(|shader| &shader.entry);
+        let stages = self.shaders.iter().map(|shader| shader.ty);
+
+        let count = |ty| self.shaders.iter().filter(|shader| shader.ty == ty).count() as u32;
+        let miss_count = count(ShaderType::Miss);
+        let hit_count = count(ShaderType::ClosestHit);
+
+        let groups = self.shaders.iter().enumerate().map(|(index, shader)| {
+            let index = index as u32;
+            match shader.ty {
+                ShaderType::ClosestHit => quote! {
+                    vk::RayTracingShaderGroupCreateInfoKHR::default()
+                        .ty(vk::RayTracingShaderGroupTypeKHR::TRIANGLES_HIT_GROUP)
+                        .general_shader(vk::SHADER_UNUSED_KHR)
+                        .closest_hit_shader(#index)
+                        .any_hit_shader(vk::SHADER_UNUSED_KHR)
+                        .intersection_shader(vk::SHADER_UNUSED_KHR)
+                },
+                _ => quote! {
+                    vk::RayTracingShaderGroupCreateInfoKHR::default()
+                        .ty(vk::RayTracingShaderGroupTypeKHR::GENERAL)
+                        .general_shader(#index)
+                        .closest_hit_shader(vk::SHADER_UNUSED_KHR)
+                        .any_hit_shader(vk::SHADER_UNUSED_KHR)
+                        .intersection_shader(vk::SHADER_UNUSED_KHR)
+                },
+            }
+        });
+
+        RawPipeline {
+            kind: "ray tracing",
+            consts: quote! {
+                /// Number of miss groups, following the ray generation group
+                pub const MISS_GROUP_COUNT: u32 = #miss_count;
+
+                /// Number of hit groups, following the miss groups
+                pub const HIT_GROUP_COUNT: u32 = #hit_count;
+
+                /// Number of shader groups, starting with the ray generation group
+                pub const GROUP_COUNT: u32 = 1 + Self::MISS_GROUP_COUNT + Self::HIT_GROUP_COUNT;
+            },
+            methods: quote! {
+                /// Returns the handles of all the shader groups for building the shader
+                /// binding table, each `handle_size` bytes as reported by
+                /// `vk::PhysicalDeviceRayTracingPipelinePropertiesKHR::shader_group_handle_size`
+                pub fn get_group_handles(
+                    &self,
+                    ray_tracing: &ash::khr::ray_tracing_pipeline::Device,
+                    handle_size: u32,
+                ) -> Vec<u8> {
+                    let group_count = #pipeline_ident::GROUP_COUNT;
+                    let data_size = (group_count * handle_size) as usize;
+                    unsafe {
+                        ray_tracing.get_ray_tracing_shader_group_handles(
+                            self.pipeline,
+                            0,
+                            group_count,
+                            data_size,
+                        )
+                    }
+                    .expect("Failed to get Vulkan shader group handles")
+                }
+            },
+            mock_methods: quote! {
+                /// Returns zeroed handles of all the shader groups, each `handle_size` bytes
+                pub fn get_group_handles(
+                    &self,
+                    ray_tracing: &ash::khr::ray_tracing_pipeline::Device,
+                    handle_size: u32,
+                ) -> Vec<u8> {
+                    let _ = ray_tracing;
+                    vec![0; (#pipeline_ident::GROUP_COUNT * handle_size) as usize]
+                }
+            },
+            new_params: quote! {
+                device: &std::sync::Arc<ash::Device>,
+                ray_tracing: &ash::khr::ray_tracing_pipeline::Device,
+            },
+            mock_ignored: quote! { let _ = ray_tracing; },
+            create_pipeline: quote! {
+                let modules = [ #( ShaderModule::new(device, #codes), )* ];
+                let entries = [
+                    #( std::ffi::CString::new(#entries).expect("Failed to create entry point"), )*
+                ];
+                let stages = modules
+                    .iter()
+                    .zip(&entries)
+                    .zip([ #( #stages, )* ])
+                    .map(|((module, entry), stage)| module.get_stage(entry, stage))
+                    .collect::<Vec<_>>();
+
+                let groups = [ #( #groups, )* ];
+
+                let create_info = vk::RayTracingPipelineCreateInfoKHR::default()
+                    .stages(&stages)
+                    .groups(&groups)
+                    .max_pipeline_ray_recursion_depth(1)
+                    .layout(layout);
+
+                let pipelines = unsafe {
+                    ray_tracing.create_ray_tracing_pipelines(
+                        vk::DeferredOperationKHR::null(),
+                        vk::PipelineCache::null(),
+                        &[create_info],
+                        None,
+                    )
+                };
+                let mut pipelines = pipelines.expect("Failed to create Vulkan ray tracing pipeline");
+                let pipeline = pipelines.pop().expect("Failed to pop Vulkan pipeline");
+            },
+        }
+    }
+
+    /// Generates the description of reflected set layout bindings, uniforms, and push
+    /// constants, and the `set_layout_bindings`, `get_uniform_infos`, and `get_reflection`
+    /// functions returning them
+    fn get_set_layout_binding_items(&self, pipeline_ident: &Ident) -> (TokenStream, TokenStream) {
+        let desc_ident = format_ident!("{}SetLayoutBindingDesc", pipeline_ident);
+        let info_ident = format_ident!("{}UniformInfo", pipeline_ident);
+        let push_info_ident = format_ident!("{}PushInfo", pipeline_ident);
+        let reflection_ident = format_ident!("{}Reflection", pipeline_ident);
+        let infos = self
+            .get_uniforms()
+            .into_iter()
+            .map(|uniform| {
+                let name = &uniform.param.name;
+                let set = uniform.set;
+                let binding = uniform.binding;
+                let count = uniform.get_array_count();
+                let view_type = match uniform.shape {
+                    Some(shape) => quote! { Some(#shape) },
+                    None => quote! { None },
+                };
+                let descriptor_type = uniform.get_descriptor_type();
+                let size = match uniform.get_buffer_size() {
+                    Some(size) => quote! { Some(#size) },
+                    None => quote! { None },
+                };
+                quote! {
+                    #info_ident {
+                        name: #name,
+                        set: #set,
+                        binding: #binding,
+                        count: #count,
+                        view_type: #view_type,
+                        descriptor_type: #descriptor_type,
+                        size: #size,
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+        let push_infos = self.get_push_methods().into_iter().map(|method| {
+            let name = &method.name;
+            let offset = method.offset;
+            let size = method.get_size();
+            let stage = get_stage_flags(&method.stages);
+            quote! {
+                #push_info_ident {
+                    name: #name,
+                    offset: #offset,
+                    size: #size,
+                    stage_flags: #stage,
+                }
+            }
+        });
+        let set_layouts = self.get_set_layouts();
+        let set_descs = set_layouts.iter().map(|set_layout| {
+            let descs = set_layout.bindings.iter().map(|binding| {
+                let index = binding.binding;
+                let descriptor_type = binding.descriptor_type;
+                let count = binding.count;
+                let stage = binding.get_stage_flags();
+                quote! {
+                    #desc_ident {
+                        binding: #index,
+                        descriptor_type: #descriptor_type,
+                        count: #count,
+                        stage_flags: #stage,
+                    }
+                }
+            });
+            quote! { &[ #( #descs, )* ] }
+        });
+        let uniform_consts = self.get_uniforms().into_iter().map(|uniform| {
+            let name = &uniform.param.name;
+            let set_ident = get_const_ident("SET", name);
+            let binding_ident = get_const_ident("BINDING", name);
+            let set = uniform.set;
+            let binding = uniform.binding;
+            let set_doc = format!("Descriptor set of `{}`", name);
+            let binding_doc = format!("Binding of `{}` in its descriptor set", name);
+            // Render passes refer to input attachments by this index
+            let input_attachment = if uniform.param.ty == ParamType::Image {
+                let index_ident = get_const_ident("INPUT_ATTACHMENT_INDEX", name);
+                let index = uniform.get_input_attachment_index();
+                let index_doc = format!(
+                    "Index of `{}` in the input attachments of its subpass",
+                    name
+                );
+                quote! {
+                    #[doc = #index_doc]
+                    pub const #index_ident: u32 = #index;
+                }
+            } else {
+                quote! {}
+            };
+            quote! {
+                #[doc = #set_doc]
+                pub const #set_ident: u32 = #set;
+                #[doc = #binding_doc]
+                pub const #binding_ident: u32 = #binding;
+                #input_attachment
+            }
+        });
+        // The block ends with the range ending last
+        let push_ranges = self.get_push_ranges();
+        let push_constant_size = push_ranges
+            .iter()
+            .map(|range| range.offset + range.get_size())
+            .max()
+            .unwrap_or_default();
+        let pool_sizes = self.get_pool_sizes().into_iter().map(|(ty, count)| {
+            quote! {
+                vk::DescriptorPoolSize::default()
+                    .ty(#ty)
+                    .descriptor_count(#count * max_sets)
+            }
+        });
+
+        let desc_items = quote! {
+            /// Descriptor set layout binding as reflected from the shaders
+            #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+            pub struct #desc_ident {
+                pub binding: u32,
+                pub descriptor_type: vk::DescriptorType,
+                pub count: u32,
+                pub stage_flags: vk::ShaderStageFlags,
+            }
+
+            /// Uniform as reflected from the shaders, with the view type
+            /// expected for a texture
+            #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+            pub struct #info_ident {
+                pub name: &'static str,
+                pub set: u32,
+                pub binding: u32,
+                pub count: u32,
+                pub view_type: Option<vk::ImageViewType>,
+                pub descriptor_type: vk::DescriptorType,
+                /// Size in bytes of a buffer, `None` for other resources or unknown sizes
+                pub size: Option<usize>,
+            }
+
+            /// Push constant as reflected from the shaders
+            #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+            pub struct #push_info_ident {
+                pub name: &'static str,
+                pub offset: usize,
+                pub size: usize,
+                pub stage_flags: vk::ShaderStageFlags,
+            }
+
+            /// Resources of the pipeline, for tools enumerating them at runtime
+            #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+            pub struct #reflection_ident {
+                /// Bindings of each descriptor set, indexed by set
+                pub set_layout_bindings: &'static [&'static [#desc_ident]],
+                pub uniforms: &'static [#info_ident],
+                pub push_constants: &'static [#push_info_ident],
+            }
+        };
+
+        let set_layout_bindings_fn = quote! {
+            #( #uniform_consts )*
+
+            /// Size in bytes of the push constants, zero when there are none
+            pub const PUSH_CONSTANT_SIZE: usize = #push_constant_size;
+
+            /// Push constant ranges of the pipeline layout, for creating layouts of other
+            /// pipelines compatible with it, so that they can share pushed constants
+            pub fn get_push_ranges() -> Vec<vk::PushConstantRange> {
+                vec![
+                    #( #push_ranges, )*
+                ]
+            }
+
+            /// Whether the push constants fit in the `max_push_constants_size` of a device,
+            /// which is only guaranteed to be 128 bytes
+            pub fn fits_push_constants(limits: &vk::PhysicalDeviceLimits) -> bool {
+                Self::PUSH_CONSTANT_SIZE <= limits.max_push_constants_size as usize
+            }
+
+            /// Bindings of each descriptor set, shared by `set_layout_bindings` and the reflection
+            const SET_LAYOUT_BINDINGS: &'static [&'static [#desc_ident]] = &[ #( #set_descs, )* ];
+
+            /// Uniforms of all shaders, shared by `get_uniform_infos` and the reflection
+            const UNIFORM_INFOS: &'static [#info_ident] = &[ #( #infos, )* ];
+
+            /// Uniforms of all shaders, once per name
+            pub fn get_uniform_infos() -> &'static [#info_ident] {
+                Self::UNIFORM_INFOS
+            }
+
+            /// Set layout bindings, uniforms, and push constants of all shaders
+            pub fn get_reflection() -> &'static #reflection_ident {
+                static REFLECTION: #reflection_ident = #reflection_ident {
+                    set_layout_bindings: #pipeline_ident::SET_LAYOUT_BINDINGS,
+                    uniforms: #pipeline_ident::UNIFORM_INFOS,
+                    push_constants: &[ #( #push_infos, )* ],
+                };
+                &REFLECTION
+            }
+
+            /// Bindings of a descriptor set, for creating descriptor pools and
+            /// allocating sets outside of the pipeline
+            pub fn set_layout_bindings(set: u32) -> &'static [#desc_ident] {
+                Self::SET_LAYOUT_BINDINGS
+                    .get(set as usize)
+                    .copied()
+                    .unwrap_or(&[])
+            }
+
+            /// Sizes of a descriptor pool for allocating `max_sets` sets
+            /// of each descriptor set layout of the pipeline
+            pub fn get_pool_sizes(max_sets: u32) -> Vec<vk::DescriptorPoolSize> {
+                let _ = max_sets;
+                vec![ #( #pool_sizes, )* ]
+            }
+        };
+
+        (desc_items, set_layout_bindings_fn)
+    }
+
+    /// Creates the shader modules from embedded SPIR-V, compiled at expansion time
+    /// or precompiled, or from their source paths
+    fn get_create_shaders(&self) -> TokenStream {
+        let vert = &self.shaders[0];
+        let Some(frag) = self.shaders.get(1) else {
+            // A lone vertex shader can only be created from SPIR-V bytes
+            let Some(vert_code) = &vert.code else {
+                panic!(
+                    "{}:{}: A pipeline without fragment shader needs embedded SPIR-V",
+                    file!(),
+                    line!()
+                );
+            };
+            let vert_code = get_embedded_spirv(vert_code);
+            return quote! {
+                #[cfg(target_os = "android")]
+                let _ = android_app;
+                let vertex = ShaderModule::new(&device, #vert_code);
+            };
+        };
+
+        if let (Some(vert_code), Some(frag_code)) = (&vert.code, &frag.code) {
+            let vert_code = get_embedded_spirv(vert_code);
+            let frag_code = get_embedded_spirv(frag_code);
+            return quote! {
+                #[cfg(target_os = "android")]
+                let _ = android_app;
+                let vertex = ShaderModule::new(&device, #vert_code);
+                let fragment = ShaderModule::new(&device, #frag_code);
+            };
+        }
+
+        let vert_path = vert.path.to_string_lossy();
+        if !vert.path.exists() {
+            panic!(
+                "{}:{}: Failed to find `{}`",
+                file!(),
+                line!(),
+                vert.path.display()
+            );
+        }
+
+        let frag_path = frag.path.to_string_lossy();
+        if !frag.path.exists() {
+            panic!(
+                "{}:{}: Failed to find `{}`",
+                file!(),
+                line!(),
+                frag.path.display()
+            );
+        }
+
+        quote! {
+            #[cfg(target_os = "android")]
+            let (vertex, fragment) = ShaderModule::create_shaders(android_app, &device, #vert_path, #frag_path);
+            #[cfg(not(target_os = "android"))]
+            let (vertex, fragment) = ShaderModule::create_shaders(&device, #vert_path, #frag_path);
+        }
+    }
+
+    /// Generates an enum of the uniforms of the pipeline, so that their reflection
+    /// is reachable without strings and descriptor keys can refer to them
+    fn get_uniform_items(&self) -> TokenStream {
+        let uniforms = self.get_uniforms();
+        if uniforms.is_empty() {
+            return quote! {};
+        }
+
+        let uniform_ident = format_ident!("{}Uniform", self.get_struct_name());
+        let structs = uniforms
+            .iter()
+            .map(|uniform| self.get_uniform_struct(uniform))
+            .collect::<Vec<TokenStream>>();
+        let variants = uniforms
+            .iter()
+            .map(|uniform| get_variant_ident(&uniform.param.name))
+            .collect::<Vec<Ident>>();
+        let names = uniforms.iter().map(|uniform| &uniform.param.name);
+        let sets = uniforms.iter().map(|uniform| uniform.set);
+        let bindings = uniforms.iter().map(|uniform| uniform.binding);
+        let descriptor_types = uniforms.iter().map(|uniform| uniform.get_descriptor_type());
+        let set_structs = self
+            .get_bind_methods()
+            .iter()
+            .map(BindMethod::get_set_struct)
+            .collect::<Vec<TokenStream>>();
+        let writes_items = self.get_writes_items();
+        let docs = uniforms.iter().map(|uniform| {
+            let mut doc = format!(
+                "`{}` at set {}, binding {}",
+                uniform.param.name, uniform.set, uniform.binding
+            );
+            if let Some(shape) = uniform.shape {
+                doc += &format!(", a {} texture", shape.get_name());
+            }
+            if uniform.get_array_count() > 1 {
+                doc += &format!(", with {} descriptors", uniform.get_array_count());
+            }
+            doc
+        });
+
+        quote! {
+            /// Uniforms of the pipeline as reflected from its shaders
+            #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+            pub enum #uniform_ident {
+                #(
+                    #[doc = #docs]
+                    #variants,
+                )*
+            }
+
+            impl #uniform_ident {
+                pub const ALL: &'static [Self] = &[ #( Self::#variants, )* ];
+
+                /// Name of the uniform in the shader
+                pub fn get_name(&self) -> &'static str {
+                    match self {
+                        #( Self::#variants => #names, )*
+                    }
+                }
+
+                pub fn get_set(&self) -> u32 {
+                    match self {
+                        #( Self::#variants => #sets, )*
+                    }
+                }
+
+                pub fn get_binding(&self) -> u32 {
+                    match self {
+                        #( Self::#variants => #bindings, )*
+                    }
+                }
+
+                pub fn get_descriptor_type(&self) -> vk::DescriptorType {
+                    match self {
+                        #( Self::#variants => #descriptor_types, )*
+                    }
+                }
+            }
+
+            #( #structs )*
+
+            #( #set_structs )*
+
+            #writes_items
+        }
+    }
+
+    /// Generates a `#[repr(C)]` struct mirroring the std140 layout of a constant
+    /// buffer, with explicit padding and a compile-time check of its size
+    fn get_uniform_struct(&self, uniform: &Uniform) -> TokenStream {
+        let Some(size) = uniform.get_struct_size() else {
+            return quote! {};
+        };
+
+        let struct_ident = get_uniform_struct_ident(&self.get_struct_name(), uniform);
+        let doc = format!(
+            "Contents of the buffer bound to `{}`, laid out as std140",
+            uniform.param.name
+        );
+
+        let fields =
+            get_padded_fields(&uniform.fields, size)
+                .into_iter()
+                .map(|(ident, ty, padding)| {
+                    if padding {
+                        quote! { #ident: #ty }
+                    } else {
+                        quote! { pub #ident: #ty }
+                    }
+                });
+
+        quote! {
+            #[doc = #doc]
+            #[repr(C)]
+            #[derive(Clone, Copy, Debug, PartialEq)]
+            pub struct #struct_ident {
+                #( #fields, )*
+            }
+
+            const _: () = assert!(std::mem::size_of::<#struct_ident>() == #size);
+        }
+    }
+
+    /// Generates the structs mirroring push constant structs, whose padding is zeroed
+    /// by their constructors, as it is pushed along with the fields
+    fn get_push_structs(&self) -> TokenStream {
+        let structs = self.get_push_methods().into_iter().filter_map(|method| {
+            let struct_ident = method.get_struct_ident()?;
+            let size = method.get_size();
+            let doc = format!(
+                "Contents of the push constant `{}`, laid out as in the shaders",
+                method.name
+            );
+            let fields = get_padded_fields(&method.fields, size);
+            let field_tokens = fields.iter().map(|(ident, ty, padding)| {
+                if *padding {
+                    quote! { #ident: #ty }
+                } else {
+                    quote! { pub #ident: #ty }
+                }
+            });
+            let params = fields
+                .iter()
+                .filter(|(_, _, padding)| !padding)
+                .map(|(ident, ty, _)| quote! { #ident: #ty });
+            let inits = fields.iter().map(|(ident, ty, padding)| {
+                if *padding {
+                    quote! { #ident: [0; std::mem::size_of::<#ty>()] }
+                } else {
+                    quote! { #ident }
+                }
+            });
+            Some(quote! {
+                #[doc = #doc]
+                #[repr(C)]
+                #[derive(Clone, Copy, Debug, PartialEq)]
+                pub struct #struct_ident {
+                    #( #field_tokens, )*
+                }
+
+                impl #struct_ident {
+                    #[allow(clippy::too_many_arguments)]
+                    pub fn new(#( #params ),*) -> Self {
+                        Self {
+                            #( #inits, )*
+                        }
+                    }
+                }
+
+                const _: () = assert!(std::mem::size_of::<#struct_ident>() == #size);
+            })
+        });
+        quote! { #( #structs )* }
+    }
+
+    /// Generates the struct of the immutable samplers, the constructor parameter and
+    /// argument providing it, and the statements baking its samplers in the bindings
+    fn get_immutable_sampler_tokens(
+        &self,
+        pipeline_ident: &Ident,
+    ) -> (TokenStream, TokenStream, TokenStream, TokenStream) {
+        let immutable_samplers = self.get_immutable_samplers();
+        if immutable_samplers.is_empty() {
+            return (quote! {}, quote! {}, quote! {}, quote! {});
+        }
+
+        let samplers_ident = format_ident!("{}Samplers", pipeline_ident);
+        let fields = immutable_samplers
+            .iter()
+            .map(|uniform| get_param_ident(&uniform.param.name))
+            .collect::<Vec<Ident>>();
+        let sets = immutable_samplers
+            .iter()
+            .map(|uniform| uniform.set as usize);
+        let bindings = immutable_samplers.iter().map(|uniform| uniform.binding);
+
+        let items = quote! {
+            /// Samplers baked in the set layouts of the pipeline, one per texture
+            /// bound by its image view only
+            #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+            pub struct #samplers_ident {
+                #( pub #fields: vk::Sampler, )*
+            }
+        };
+        let set_layout_samplers = quote! {
+            let mut bindings: Vec<Vec<vk::DescriptorSetLayoutBinding>> = bindings;
+            #(
+                for binding in bindings[#sets]
+                    .iter_mut()
+                    .filter(|binding| binding.binding == #bindings)
+                {
+                    *binding = binding.immutable_samplers(std::slice::from_ref(&samplers.#fields));
+                }
+            )*
+        };
+        (
+            items,
+            quote! { samplers: &#samplers_ident, },
+            quote! { samplers, },
+            set_layout_samplers,
+        )
+    }
+
+    /// Generates the enum of the calls recorded by a mock pipeline, and the methods of
+    /// its handle recording and returning them, where compute pipelines also record dispatches
+    fn get_mock_call_items(&self, call_ident: &Ident) -> (TokenStream, TokenStream) {
+        let (dispatch_variant, record_dispatch) = if self.is_compute() {
+            (
+                quote! {
+                    Dispatch {
+                        x: u32,
+                        y: u32,
+                        z: u32,
+                    },
+                },
+                quote! {
+                    fn record_dispatch(&self, x: u32, y: u32, z: u32) {
+                        self.calls.lock().unwrap().push(#call_ident::Dispatch { x, y, z });
+                    }
+                },
+            )
+        } else {
+            (quote! {}, quote! {})
+        };
+
+        let call_enum = quote! {
+            /// Call recorded by a mock pipeline
+            #[cfg(feature = "rayca-pipe-mock")]
+            #[derive(Clone, Debug, PartialEq, Eq)]
+            pub enum #call_ident {
+                Bind {
+                    method: &'static str,
+                    set: u32,
+                },
+                Update {
+                    method: &'static str,
+                    set: u32,
+                },
+                Push {
+                    method: &'static str,
+                    stage: vk::ShaderStageFlags,
+                    offset: u32,
+                    bytes: Vec<u8>,
+                },
+                #dispatch_variant
+            }
+        };
+        let call_methods = quote! {
+            fn record_bind(&self, method: &'static str, set: u32) {
+                self.calls.lock().unwrap().push(#call_ident::Bind { method, set });
+            }
+
+            fn record_update(&self, method: &'static str, set: u32) {
+                self.calls.lock().unwrap().push(#call_ident::Update { method, set });
+            }
+
+            fn record_push(
+                &self,
+                method: &'static str,
+                stage: vk::ShaderStageFlags,
+                offset: u32,
+                bytes: &[u8],
+            ) {
+                self.calls.lock().unwrap().push(#call_ident::Push {
+                    method,
+                    stage,
+                    offset,
+                    bytes: bytes.to_vec(),
+                });
+            }
+
+            #record_dispatch
+
+            /// Returns the calls recorded by the pipeline and its handles, in order
+            pub fn get_calls(&self) -> Vec<#call_ident> {
+                self.calls.lock().unwrap().clone()
+            }
+
+            /// Forgets the calls recorded so far
+            pub fn clear_calls(&self) {
+                self.calls.lock().unwrap().clear();
+            }
+        };
+        (call_enum, call_methods)
+    }
+
+    /// Generates a pipeline with null handles which records the calls of its bind
+    /// and push methods, so that code using it can be tested without a device.
+    /// Its constructors have the same signatures as the ones of the pipeline
+    fn get_mock_items(
+        &self,
+        reflection_impl: &TokenStream,
+        reflected_bindings_fn: &TokenStream,
+        constructors: &[Constructor],
+    ) -> TokenStream {
+        let pipeline_ident = Ident::new(&self.get_struct_name(), Span::call_site());
+        let pipeline_name = self.get_pipeline_name();
+        let handle_ident = format_ident!("{}Handle", pipeline_ident);
+        let call_ident = format_ident!("{}Call", pipeline_ident);
+        let (call_enum, call_methods) = self.get_mock_call_items(&call_ident);
+
+        let (vertex_generics, vertex_ty) = self.get_vertex_tokens();
+        let subpass = match self.subpass {
+            Some(subpass) => quote! { #subpass },
+            None => quote! { V::get_subpass() },
+        };
+
+        let bind_methods = self.get_checked_bind_methods();
+        let shaders_doc = self.get_shaders_doc();
+        let mock_bind_methods = bind_methods.iter().map(Mock);
+        let bind_all_method = self.get_bind_all_method();
+        let mock_bind_all_method = bind_all_method.as_ref().map(Mock);
+        let mock_batch_methods = self.get_mock_batch_methods();
+        let push_methods = self.get_push_methods();
+        let mock_push_methods = push_methods.iter().map(Mock);
+        let mock_constructors = constructors.iter().map(Mock);
+
+        // The extent of a static viewport is kept as by the pipeline
+        let (viewport_param, viewport_arg, _, _, viewport_method) = self.get_viewport_tokens();
+        let (extent_field, extent_init, mock_viewport_method) = if self.static_viewport {
+            (
+                quote! { extent: vk::Extent2D, },
+                quote! { extent, },
+                viewport_method,
+            )
+        } else {
+            (
+                quote! {},
+                quote! {},
+                quote! {
+                    /// Ignores the viewport, as the mock records no commands
+                    pub fn set_viewport(&self, command_buffer: &CommandBuffer, extent: vk::Extent2D) {
+                        let _ = (command_buffer, extent);
+                    }
+                },
+            )
+        };
+
+        let (default_texture_methods, default_texture_setter) = if self.has_optional() {
+            (
+                quote! {
+                    /// Ignores the texture, as the mock writes no descriptors
+                    pub fn set_default_texture(&mut self, texture: &RenderTexture) {
+                        let _ = texture;
+                    }
+                },
+                quote! {
+                    /// Forwards the texture to the handle of the mock, which ignores it
+                    pub fn set_default_texture(&mut self, texture: &RenderTexture) {
+                        self.handle.set_default_texture(texture);
+                    }
+                },
+            )
+        } else {
+            (quote! {}, quote! {})
+        };
+
+        let wireframe_setter = if self.debug_wireframe {
+            quote! {
+                /// Ignores the mode, as the mock has no line-mode variant
+                pub fn set_wireframe(&mut self, wireframe: bool) {
+                    let _ = wireframe;
+                }
+            }
+        } else {
+            quote! {}
+        };
+
+        let pipeline_impl = get_mock_pipeline_impl(quote! { self.vertex_size });
+
+        quote! {
+            #call_enum
+
+            /// Cloneable handle to a mock pipeline, sharing its call log
+            #[cfg(feature = "rayca-pipe-mock")]
+            #[derive(Clone)]
+            pub struct #handle_ident {
+                vertex_size: usize,
+                subpass: u32,
+                set_layouts: Vec<vk::DescriptorSetLayout>,
+                device: Option<std::sync::Arc<ash::Device>>,
+                name: String,
+                calls: std::sync::Arc<std::sync::Mutex<Vec<#call_ident>>>,
+                #extent_field
+            }
+
+            #[cfg(feature = "rayca-pipe-mock")]
+            impl #handle_ident {
+                #call_methods
+
+                /// Index of the subpass the mock pipeline was created for
+                pub fn get_subpass(&self) -> u32 {
+                    self.subpass
+                }
+
+                #default_texture_methods
+
+                #( #mock_bind_methods )*
+
+                #mock_bind_all_method
+
+                #mock_batch_methods
+
+                #( #mock_push_methods )*
+
+                #mock_viewport_method
+            }
+
+            #[cfg(feature = "rayca-pipe-mock")]
+            impl Pipeline for #handle_ident {
+                #pipeline_impl
+            }
+
+            /// Mock pipeline with null handles, recording its calls instead of touching Vulkan
+            #shaders_doc
+            #[cfg(feature = "rayca-pipe-mock")]
+            pub struct #pipeline_ident {
+                handle: #handle_ident,
+            }
+
+            #[cfg(feature = "rayca-pipe-mock")]
+            impl std::ops::Deref for #pipeline_ident {
+                type Target = #handle_ident;
+
+                fn deref(&self) -> &Self::Target {
+                    &self.handle
+                }
+            }
+
+            #[cfg(feature = "rayca-pipe-mock")]
+            impl #pipeline_ident {
+                #reflection_impl
+
+                #reflected_bindings_fn
+
+                /// Creates the mock pipeline with null set layouts, keeping the device of a pass if any
+                fn from_mock<V: VertexInput>(
+                    device: Option<std::sync::Arc<ash::Device>>,
+                    #viewport_param
+                ) -> Self {
+                    let set_layouts = Self::get_reflected_bindings()
+                        .iter()
+                        .map(|_| vk::DescriptorSetLayout::null())
+                        .collect();
+
+                    let handle = #handle_ident {
+                        vertex_size: std::mem::size_of::<V>(),
+                        subpass: #subpass,
+                        set_layouts,
+                        device,
+                        name: String::from(#pipeline_name),
+                        calls: Default::default(),
+                        #extent_init
+                    };
+
+                    Self { handle }
+                }
+
+                /// Creates the mock pipeline without a device, so that `get_device` panics
+                pub fn new_mock #vertex_generics(#viewport_param) -> Self {
+                    Self::from_mock::<#vertex_ty>(None, #viewport_arg)
+                }
+
+                #( #mock_constructors )*
+
+                /// Returns a cloneable handle sharing the call log of the mock
+                pub fn handle(&self) -> #handle_ident {
+                    self.handle.clone()
+                }
+
+                #default_texture_setter
+
+                #wireframe_setter
+            }
+
+            #[cfg(feature = "rayca-pipe-mock")]
+            impl Pipeline for #pipeline_ident {
+                #pipeline_impl
+            }
+        }
+    }
+}
+
+/// Identifiers used by the generated methods and fields, which must not be
+/// shadowed by parameters named after shader variables
+const RESERVED: &[&str] = &[
+    "command_buffer",
+    "descriptors",
+    "key",
+    "layout",
+    "set_layouts",
+    "sets",
+    "view",
+    "sampler",
+    "texture",
+    "bytes",
+    "device",
+    "pipeline",
+    "name",
+];
+
+/// Rust keywords, which are valid names of shader variables
+const KEYWORDS: &[&str] = &[
+    "_", "abstract", "as", "async", "await", "become", "box", "break", "const", "continue",
+    "crate", "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if",
+    "impl", "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+/// Returns the name of a shader variable as a valid Rust identifier, replacing
+/// invalid characters with `_` and prefixing a leading digit with `_`
+fn get_rust_name(name: &str) -> String {
+    let mut ret = name
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c,
+            false => '_',
+        })
+        .collect::<String>();
+    if ret.is_empty() || ret.starts_with(|c: char| c.is_ascii_digit()) {
+        ret.insert(0, '_');
+    }
+    ret
+}
+
+/// Returns the identifier of a struct field named after a shader variable, as a raw
+/// identifier when it is a keyword, or with a `_u` suffix for the ones which can not be raw
+fn get_field_ident(name: &str) -> Ident {
+    let name = get_rust_name(name);
+    match name.as_str() {
+        "_" | "crate" | "self" | "Self" | "super" => format_ident!("{}_u", name),
+        name if KEYWORDS.contains(&name) => Ident::new_raw(name, Span::call_site()),
+        name => Ident::new(name, Span::call_site()),
+    }
+}
+
+/// Returns the identifier of a parameter named after a shader variable,
+/// with a `_u` suffix when it collides with a reserved name or a keyword
+fn get_param_ident(name: &str) -> Ident {
+    let name = get_rust_name(name);
+    if RESERVED.contains(&name.as_str()) || KEYWORDS.contains(&name.as_str()) {
+        format_ident!("{}_u", name)
+    } else {
+        Ident::new(&name, Span::call_site())
+    }
+}
+
+/// Returns the flags of the bindings of each set, as `vk::DescriptorBindingFlags`
+fn get_binding_flags(set_layouts: &[SetLayout]) -> TokenStream {
+    let sets = set_layouts.iter().map(|set_layout| {
+        let flags = set_layout
+            .bindings
+            .iter()
+            .map(|binding| match binding.bindless {
+                true => quote! {
+                    vk::DescriptorBindingFlags::PARTIALLY_BOUND
+                        | vk::DescriptorBindingFlags::UPDATE_AFTER_BIND
+                },
+                false => quote! { vk::DescriptorBindingFlags::empty() },
+            });
+        quote! { vec![ #( #flags, )* ] }
+    });
+    quote! { vec![ #( #sets, )* ] }
+}
+
+/// Returns the create flags of each set layout, as `vk::DescriptorSetLayoutCreateFlags`
+fn get_set_layout_flags(set_layouts: &[SetLayout]) -> TokenStream {
+    let flags = set_layouts.iter().map(|set_layout| match set_layout.push {
+        true => quote! { vk::DescriptorSetLayoutCreateFlags::PUSH_DESCRIPTOR_KHR },
+        false => quote! { vk::DescriptorSetLayoutCreateFlags::empty() },
+    });
+    quote! { vec![ #( #flags, )* ] }
+}
+
+/// Returns the name of the index of the first element written to an unbounded array,
+/// or of the offset of a dynamic uniform buffer
+fn get_offset_ident(name: &str) -> Ident {
+    format_ident!("{}_offset", get_rust_name(name))
+}
+
+/// Parts of the items of a compute or ray tracing pipeline specific to its kind
+struct RawPipeline {
+    kind: &'static str,
+    /// Associated constants of the pipeline
+    consts: TokenStream,
+    /// Methods of the handle recording the pipeline
+    methods: TokenStream,
+    /// The same methods of the handle of the mock, recording their calls
+    mock_methods: TokenStream,
+    /// Parameters of `new()`
+    new_params: TokenStream,
+    /// Statements of the mock `new()` ignoring its parameters, besides the device it keeps
+    mock_ignored: TokenStream,
+    /// Creates `pipeline` from `device` and `layout`
+    create_pipeline: TokenStream,
+}
+
+/// Public constructor of a graphics pipeline, generated with the same signature
+/// for the pipeline and for its mock, so that the same code creates either of them
+struct Constructor {
+    docs: TokenStream,
+    ident: Ident,
+    generics: TokenStream,
+    /// Parameters following the `android_app` one of Android builds
+    params: TokenStream,
+    ret: TokenStream,
+    body: TokenStream,
+    /// Body of the mock constructor, which ignores the Vulkan objects
+    mock_body: TokenStream,
+}
+
+impl ToTokens for Constructor {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let Constructor {
+            docs,
+            ident,
+            generics,
+            params,
+            ret,
+            body,
+            ..
+        } = self;
+        tokens.extend(quote! {
+            #docs
+            pub fn #ident #generics(
+                #[cfg(target_os = "android")]
+                android_app: &AndroidApp,
+                #params
+            ) -> #ret {
+                #body
+            }
+        })
+    }
+}
+
+impl ToTokens for Mock<'_, Constructor> {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let Constructor {
+            docs,
+            ident,
+            generics,
+            params,
+            ret,
+            mock_body,
+            ..
+        } = self.0;
+        let separator = if docs.is_empty() {
+            quote! {}
+        } else {
+            quote! { #[doc = ""] }
+        };
+        tokens.extend(quote! {
+            #docs
+            #separator
+            #[doc = "The mock pipeline is created without touching Vulkan"]
+            pub fn #ident #generics(
+                #[cfg(target_os = "android")]
+                android_app: &AndroidApp,
+                #params
+            ) -> #ret {
+                #[cfg(target_os = "android")]
+                let _ = android_app;
+                #mock_body
+            }
+        })
+    }
+}
+
+/// Implements `Pipeline` for a mock pipeline or its handle, with null Vulkan handles and
+/// the device it was created with, where `vertex_size` is the size of its vertex type
+fn get_mock_pipeline_impl(vertex_size: TokenStream) -> TokenStream {
+    quote! {
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+
+        fn get_name(&self) -> &String {
+            &self.name
+        }
+
+        fn get_set_layouts(&self) -> &[vk::DescriptorSetLayout] {
+            &self.set_layouts
+        }
+
+        fn get_layout(&self) -> vk::PipelineLayout {
+            vk::PipelineLayout::null()
+        }
+
+        fn get_pipeline(&self) -> vk::Pipeline {
+            vk::Pipeline::null()
+        }
+
+        fn get_device(&self) -> &ash::Device {
+            self.device
+                .as_deref()
+                .expect("Failed to get device: the mock pipeline was created by `new_mock`")
+        }
+
+        fn get_vertex_size(&self) -> usize {
+            #vertex_size
+        }
+    }
+}
+
+/// Returns the statements preparing the specialization of a graphics shader
+/// and the expression of its stage, specialized when constants are given
+fn get_stage(shader: &Shader, spec_ident: &Ident) -> (TokenStream, TokenStream) {
+    let ty = shader.ty;
+    let prefix = match ty {
+        ShaderType::Vertex => "vert",
+        _ => "frag",
+    };
+    let module = format_ident!("{}_module", prefix);
+    let entry = format_ident!("{}_entry", prefix);
+    if shader.spec_constants.is_empty() {
+        return (quote! {}, quote! { #module.get_stage(&#entry, #ty) });
+    }
+
+    let info = format_ident!("{}_spec_info", prefix);
+    let entries = format_ident!("{}_spec_entries", prefix);
+    let map_entries = shader.spec_constants.iter().map(|constant| {
+        let id = constant.id;
+        let field = get_field_ident(&constant.name);
+        quote! {
+            vk::SpecializationMapEntry::default()
+                .constant_id(#id)
+                .offset(std::mem::offset_of!(#spec_ident, #field) as u32)
+                .size(4)
+        }
+    });
+
+    (
+        quote! {
+            let #entries = [ #( #map_entries, )* ];
+            let #info = constants.map(|constants| {
+                vk::SpecializationInfo::default()
+                    .map_entries(&#entries)
+                    .data(constants.get_bytes())
+            });
+        },
+        quote! {
+            match &#info {
+                Some(info) => #module.get_stage(&#entry, #ty).specialization_info(info),
+                None => #module.get_stage(&#entry, #ty),
+            }
+        },
+    )
+}
+
+/// Returns the SPIR-V of a shader compiled at expansion time, embedded in the generated code
+fn get_shader_code(shader: &Shader) -> TokenStream {
+    let code = shader.code.as_ref().unwrap_or_else(|| {
+        panic!(
+            "{}:{}: Shader `{}` has not been compiled",
+            file!(),
+            line!(),
+            shader.path.display()
+        )
+    });
+    get_embedded_spirv(code)
+}
+
+/// Returns a reference to SPIR-V embedded in a static aligned to 4 bytes,
+/// since SPIR-V is read as words
+fn get_embedded_spirv(code: &[u8]) -> TokenStream {
+    if code.is_empty() || code.len() % 4 != 0 {
+        panic!(
+            "{}:{}: Invalid SPIR-V of {} bytes",
+            file!(),
+            line!(),
+            code.len()
+        );
+    }
+
+    let len = code.len();
+    let bytes = Literal::byte_string(code);
+    quote! {
+        {
+            #[repr(C, align(4))]
+            struct AlignedSpirv([u8; #len]);
+            static SPIRV: AlignedSpirv = AlignedSpirv(*#bytes);
+            &SPIRV.0
+        }
+    }
+}
+
+/// Paths embedded with `include_bytes!` are relative to the invoking file
+/// unless absolute, while the macro receives them relative to the crate
+fn get_absolute_path(path: &std::path::Path) -> String {
+    match std::fs::canonicalize(path) {
+        Ok(path) => path.to_string_lossy().to_string(),
+        Err(err) => panic!(
+            "{}:{}: Failed to find `{}`: {}",
+            file!(),
+            line!(),
+            path.display(),
+            err
+        ),
+    }
+}
+
+pub fn codegen(pipeline: Pipeline) -> TokenStream {
+    pipeline.to_token_stream()
+}
+
+/// Proc macros can not emit warnings on stable, so each message
+/// is reported through the use of a deprecated item
+pub fn codegen_warnings(messages: &[String]) -> TokenStream {
+    let mut tokens = TokenStream::new();
+    for message in messages {
+        tokens.extend(quote! {
+            const _: () = {
+                #[deprecated(note = #message)]
+                struct PipewriterWarning;
+                let _ = PipewriterWarning;
+            };
+        });
+    }
+    tokens
+}
+
+impl ToTokens for SetLayout {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let bindings = &self.bindings;
+        tokens.extend(quote! {
+            vec![
+                #( #bindings, )*
+            ]
+        })
+    }
+}
+
+impl SetLayoutBinding {
+    /// Stage flags of the binding, combined with raw values so that the
+    /// expression can be promoted to a constant
+    fn get_stage_flags(&self) -> TokenStream {
+        get_stage_flags(&self.stages)
+    }
+}
+
+/// Type of the command buffer taken by the methods recording at `bind_point`, and the
+/// expression of its raw handle. `CommandBuffer` binds at the graphics bind point without
+/// dynamic offsets nor push descriptors, so graphics methods needing them take a raw one
+/// when `raw`, while the methods of other pipelines record on the handle of a `CommandBuffer`
+fn get_command_buffer_tokens(bind_point: BindPoint, raw: bool) -> (TokenStream, TokenStream) {
+    if bind_point != BindPoint::Graphics {
+        (
+            quote! { &CommandBuffer },
+            quote! { command_buffer.command_buffer },
+        )
+    } else if raw {
+        (quote! { vk::CommandBuffer }, quote! { command_buffer })
+    } else {
+        (quote! { &CommandBuffer }, quote! { command_buffer })
+    }
+}
+
+/// Stage flags of several stages, combined with raw values so that the
+/// expression can be promoted to a constant
+fn get_stage_flags(stages: &[ShaderType]) -> TokenStream {
+    if let [stage] = stages {
+        quote! { #stage }
+    } else {
+        quote! { vk::ShaderStageFlags::from_raw(#( #stages.as_raw() )|*) }
+    }
+}
+
+impl ToTokens for SetLayoutBinding {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let binding = self.binding;
+        let descriptor_type = self.descriptor_type;
+        let count = self.count;
+        let stage = self.get_stage_flags();
+
+        tokens.extend(quote! {
+            vk::DescriptorSetLayoutBinding::default()
+                .binding(#binding)
+                .descriptor_type(#descriptor_type)
+                .descriptor_count(#count)
+                .stage_flags(#stage)
+        })
+    }
+}
+
+impl ToTokens for DescriptorType {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let new_tokens = match self {
+            DescriptorType::Uniform => quote! { vk::DescriptorType::UNIFORM_BUFFER },
+            DescriptorType::UniformDynamic => {
+                quote! { vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC }
+            }
+            DescriptorType::CombinedSampler => {
+                quote! { vk::DescriptorType::COMBINED_IMAGE_SAMPLER }
+            }
+            DescriptorType::InputAttachment => {
+                quote! { vk::DescriptorType::INPUT_ATTACHMENT }
+            }
+            DescriptorType::AccelerationStructure => {
+                quote! { vk::DescriptorType::ACCELERATION_STRUCTURE_KHR }
+            }
+            DescriptorType::StorageBuffer => quote! { vk::DescriptorType::STORAGE_BUFFER },
+            DescriptorType::StorageImage => quote! { vk::DescriptorType::STORAGE_IMAGE },
+            DescriptorType::Sampler => quote! { vk::DescriptorType::SAMPLER },
+            DescriptorType::UniformTexelBuffer => {
+                quote! { vk::DescriptorType::UNIFORM_TEXEL_BUFFER }
+            }
+            DescriptorType::StorageTexelBuffer => {
+                quote! { vk::DescriptorType::STORAGE_TEXEL_BUFFER }
+            }
+        };
+        tokens.extend(new_tokens)
+    }
+}
+
+impl ToTokens for Cull {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let new_tokens = match self {
+            Cull::None => quote! { vk::CullModeFlags::NONE },
+            Cull::Front => quote! { vk::CullModeFlags::FRONT },
+            Cull::Back => quote! { vk::CullModeFlags::BACK },
+            Cull::FrontAndBack => quote! { vk::CullModeFlags::FRONT_AND_BACK },
+        };
+        tokens.extend(new_tokens)
+    }
+}
+
+impl ToTokens for PolygonMode {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let new_tokens = match self {
+            PolygonMode::Fill => quote! { vk::PolygonMode::FILL },
+            PolygonMode::Line => quote! { vk::PolygonMode::LINE },
+            PolygonMode::Point => quote! { vk::PolygonMode::POINT },
+        };
+        tokens.extend(new_tokens)
+    }
+}
+
+impl ToTokens for Topology {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let new_tokens = match self {
+            Topology::PointList => quote! { vk::PrimitiveTopology::POINT_LIST },
+            Topology::LineList => quote! { vk::PrimitiveTopology::LINE_LIST },
+            Topology::LineStrip => quote! { vk::PrimitiveTopology::LINE_STRIP },
+            Topology::TriangleList => quote! { vk::PrimitiveTopology::TRIANGLE_LIST },
+            Topology::TriangleStrip => quote! { vk::PrimitiveTopology::TRIANGLE_STRIP },
+            Topology::TriangleFan => quote! { vk::PrimitiveTopology::TRIANGLE_FAN },
+        };
+        tokens.extend(new_tokens)
+    }
+}
+
+impl ToTokens for BindPoint {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let new_tokens = match self {
+            BindPoint::Graphics => quote! { vk::PipelineBindPoint::GRAPHICS },
+            BindPoint::Compute => quote! { vk::PipelineBindPoint::COMPUTE },
+            BindPoint::RayTracing => quote! { vk::PipelineBindPoint::RAY_TRACING_KHR },
+        };
+        tokens.extend(new_tokens)
+    }
+}
+
+impl ToTokens for ShaderType {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let new_tokens = match self {
+            ShaderType::Vertex => quote! {vk::ShaderStageFlags::VERTEX},
+            ShaderType::Fragment => quote! {vk::ShaderStageFlags::FRAGMENT},
+            ShaderType::Compute => quote! {vk::ShaderStageFlags::COMPUTE},
+            ShaderType::RayGeneration => quote! {vk::ShaderStageFlags::RAYGEN_KHR},
+            ShaderType::Miss => quote! {vk::ShaderStageFlags::MISS_KHR},
+            ShaderType::ClosestHit => quote! {vk::ShaderStageFlags::CLOSEST_HIT_KHR},
+        };
+        tokens.extend(new_tokens)
+    }
+}
+
+impl ToTokens for TextureShape {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let new_tokens = match self {
+            TextureShape::Texture1D => quote! {vk::ImageViewType::TYPE_1D},
+            TextureShape::Texture1DArray => quote! {vk::ImageViewType::TYPE_1D_ARRAY},
+            TextureShape::Texture2D => quote! {vk::ImageViewType::TYPE_2D},
+            TextureShape::Texture2DArray => quote! {vk::ImageViewType::TYPE_2D_ARRAY},
+            TextureShape::Texture3D => quote! {vk::ImageViewType::TYPE_3D},
+            TextureShape::Cube => quote! {vk::ImageViewType::CUBE},
+            TextureShape::CubeArray => quote! {vk::ImageViewType::CUBE_ARRAY},
+        };
+        tokens.extend(new_tokens)
+    }
+}
+
+impl ToTokens for ScalarType {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let new_tokens = match self {
+            ScalarType::Bool => quote! {vk::Bool32},
+            ScalarType::Int => quote! {i32},
+            ScalarType::Uint => quote! {u32},
+            ScalarType::Float => quote! {f32},
+        };
+        tokens.extend(new_tokens)
+    }
+}
+
+impl ToTokens for BindMethod {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let bind_signature = get_bind_ident(self);
+
+        // Build the string for the parameters of the function
+        let method_params = self.get_method_params();
+
+        let write_sets = self.get_write_sets();
+        let raw_write_sets = self.get_raw_write_sets();
+
+        if self.uniforms.is_empty() {
+            panic!(
+                "Failed to find uniforms for bind method `{}`",
+                bind_signature
+            );
+        }
+        let set = self.uniforms[0].set;
+        tokens.extend(self.get_set_method());
+        tokens.extend(self.get_deprecated_methods());
+
+        let uniform_ident = format_ident!("{}Uniform", self.pipeline);
+        let variants = self
+            .uniforms
+            .iter()
+            .map(|uniform| get_variant_ident(&uniform.param.name))
+            .collect::<Vec<_>>();
+
+        let bind_point = self.bind_point;
+        let (command_buffer_ty, raw_command_buffer) =
+            get_command_buffer_tokens(bind_point, self.has_dynamic() || self.push);
+        let bind_sets = if bind_point != BindPoint::Graphics || self.has_dynamic() {
+            // Dynamic offsets follow the order of the bindings
+            let mut dynamic_uniforms = self
+                .uniforms
+                .iter()
+                .filter(|uniform| uniform.dynamic)
+                .collect::<Vec<_>>();
+            dynamic_uniforms.sort_by_key(|uniform| uniform.binding);
+            let offsets = dynamic_uniforms
+                .iter()
+                .map(|uniform| get_offset_ident(&uniform.param.name));
+            quote! {
+                unsafe {
+                    self.device.cmd_bind_descriptor_sets(
+                        #raw_command_buffer,
+                        #bind_point,
+                        self.get_layout(),
+                        #set,
+                        sets,
+                        &[ #( #offsets, )* ],
+                    );
+                }
+            }
+        } else {
+            quote! { command_buffer.bind_descriptor_sets(self.get_layout(), sets, #set); }
+        };
+
+        let view_type_checks = self.get_view_type_checks();
+        let bindless_checks = self.get_bindless_checks();
+
+        // Unbounded arrays of a cached set are written again, as they are updated after binding
+        let bindless_write_sets = write_sets
+            .iter()
+            .filter(|write_set| write_set.info.bindless)
+            .collect::<Vec<&WriteSet>>();
+        let raw_bindless_write_sets = raw_write_sets
+            .iter()
+            .filter(|write_set| write_set.info.bindless)
+            .collect::<Vec<&WriteSet>>();
+        let get_sets = if raw_bindless_write_sets.is_empty() {
+            quote! { DescriptorEntry::Get(sets) => sets, }
+        } else {
+            quote! {
+                DescriptorEntry::Get(sets) => {
+                    unsafe {
+                        self.device.update_descriptor_sets(
+                            &[
+                                #( #raw_bindless_write_sets, )*
+                            ],
+                            &[]
+                        );
+                    }
+                    sets
+                }
+            }
+        };
+
+        let frame_key = self.get_frame_key();
+
+        // Sets written from different offsets must not share a cache entry
+        let buffer_offsets = self
+            .uniforms
+            .iter()
+            .filter(|uniform| uniform.buffer_offset)
+            .map(|uniform| get_offset_ident(&uniform.param.name))
+            .collect::<Vec<_>>();
+        let offset_key = if buffer_offsets.is_empty() {
+            quote! {}
+        } else {
+            quote! { let key = Self::derive_key(key, [ #( #buffer_offsets, )* ]); }
+        };
+
+        // The views of raw handles do not carry their type, so they are checked beforehand
+        let raw_body = if self.push {
+            quote! {
+                unsafe {
+                    push_descriptor.cmd_push_descriptor_set(
+                        #raw_command_buffer,
+                        #bind_point,
+                        self.get_layout(),
+                        #set,
+                        &[
+                            #( #raw_write_sets, )*
+                        ],
+                    );
+                }
+            }
+        } else {
+            quote! {
+                #bindless_checks
+                #frame_key
+                #offset_key
+
+                // Sets of different uniforms must not share a cache entry
+                let key = Self::derive_key(key, [ #( #uniform_ident::#variants, )* ]);
+                let set_layouts = &[self.get_set_layouts()[#set as usize]];
+                let sets = match descriptors.get_or_create(key, set_layouts) {
+                    DescriptorEntry::Created(sets) => {
+                            unsafe {
+                                self.device.update_descriptor_sets(
+                                    &[
+                                        #( #raw_write_sets, )*
+                                    ],
+                                    &[]
+                                );
+                            }
+                            sets
+                    }
+                    #get_sets
+                };
+                #bind_sets
+                sets[0]
+            }
+        };
+
+        // Resources are bound through their raw handles
+        let (leading_params, leading_args) = self.get_leading_params();
+        let raw_signature = get_raw_bind_ident(self);
+        let raw_params = self.get_raw_method_params();
+        let raw_args = self
+            .get_method_params()
+            .iter()
+            .map(MethodParam::get_raw_args)
+            .collect::<Vec<_>>();
+        let (layout_param, layout_arg) = if self.has_textures() {
+            (quote! { layout: vk::ImageLayout, }, quote! { layout, })
+        } else {
+            (quote! {}, quote! {})
+        };
+        let body = quote! {
+            #view_type_checks
+            self.#raw_signature(
+                command_buffer,
+                #leading_args
+                #( #raw_args, )*
+                #layout_arg
+            )
+        };
+
+        // Sets of push descriptors are written at every bind, so they need no update
+        let update_body = quote! {
+            #view_type_checks
+            #bindless_checks
+            #frame_key
+            #offset_key
+
+            let key = Self::derive_key(key, [ #( #uniform_ident::#variants, )* ]);
+            let set_layouts = &[self.get_set_layouts()[#set as usize]];
+            let sets = match descriptors.get_or_create(key, set_layouts) {
+                DescriptorEntry::Created(sets) | DescriptorEntry::Get(sets) => sets,
+            };
+            unsafe {
+                self.device.update_descriptor_sets(
+                    &[
+                        #( #write_sets, )*
+                    ],
+                    &[]
+                );
+            }
+        };
+        let update_signature = get_update_ident(self);
+        let update_doc = format!(
+            "Writes the descriptors of the set of `key` again, even when it is cached, \
+            for example after a buffer is reallocated, so that `{}` binds the new ones. \
+            The set must not be in use by a command buffer still executing",
+            bind_signature
+        );
+        // Offsets of dynamic uniform buffers are only given when binding
+        let dynamic_offsets = self
+            .uniforms
+            .iter()
+            .filter(|uniform| uniform.dynamic)
+            .map(|uniform| get_offset_ident(&uniform.param.name))
+            .collect::<Vec<_>>();
+        let ignore_offsets = if dynamic_offsets.is_empty() {
+            quote! {}
+        } else {
+            quote! { let _ = ( #( #dynamic_offsets, )* ); }
+        };
+
+        if !self.push && buffer_offsets.is_empty() {
+            for write in self.get_write_methods() {
+                let write_signature = get_write_ident(&write.uniforms[0]);
+                let method_params = write.get_method_params();
+                let param_names = write.get_param_idents();
+                let write_set = write.get_write_sets();
+                let view_type_checks = write.get_view_type_checks();
+                let bindless_checks = write.get_bindless_checks();
+                let (leading_params, leading_args) = write.get_leading_params();
+                let message = format!(
+                    "`{}` is written before binding its set with `{}`",
+                    write.uniforms[0].param.name, bind_signature
+                );
+                let doc = format!(
+                    "Writes only `{}` in the set of `key`, which must have been bound with `{}`, \
+                    so that the other uniforms of the set are not written again",
+                    write.uniforms[0].param.name, bind_signature
+                );
+                // The offset of a dynamic uniform buffer is only given when binding
+                let ignore_offset = if write.has_dynamic() {
+                    let offset = get_offset_ident(&write.uniforms[0].param.name);
+                    quote! { let _ = #offset; }
+                } else {
+                    quote! {}
+                };
+                let body = quote! {
+                    #ignore_offset
+                    #view_type_checks
+                    #bindless_checks
+                    #frame_key
+
+                    let key = Self::derive_key(key, [ #( #uniform_ident::#variants, )* ]);
+                    let set_layouts = &[self.get_set_layouts()[#set as usize]];
+                    let sets = match descriptors.get_or_create(key, set_layouts) {
+                        DescriptorEntry::Get(sets) => sets,
+                        DescriptorEntry::Created(_) => panic!(#message),
+                    };
+                    unsafe {
+                        self.device.update_descriptor_sets(&[ #( #write_set, )* ], &[]);
+                    }
+                };
+                // Sets bound with a different layout are cached under another key
+                if self.has_textures() {
+                    let write_with_layout_signature =
+                        format_ident!("{}_with_layout", write_signature);
+                    let write_layout_doc = get_layout_doc(&write_signature);
+                    tokens.extend(quote! {
+                        #[doc = #doc]
+                        pub fn #write_signature(
+                            &self,
+                            #leading_params
+                            #( #method_params, )*
+                        ) {
+                            self.#write_with_layout_signature(
+                                #leading_args
+                                #( #param_names, )*
+                                vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                            )
+                        }
+
+                        #[doc = #write_layout_doc]
+                        pub fn #write_with_layout_signature(
+                            &self,
+                            #leading_params
+                            #( #method_params, )*
+                            layout: vk::ImageLayout,
+                        ) {
+                            let key = if layout == vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL {
+                                key
+                            } else {
+                                Self::derive_key(key, layout.as_raw())
+                            };
+                            #body
+                        }
+                    });
+                } else {
+                    tokens.extend(quote! {
+                        #[doc = #doc]
+                        pub fn #write_signature(
+                            &self,
+                            #leading_params
+                            #( #method_params, )*
+                        ) {
+                            #body
+                        }
+                    });
+                }
+            }
+        }
+
+        if self.has_collect_method() {
+            let collect_signature = get_collect_ident(self);
+            let writes_ident = format_ident!("{}Writes", self.pipeline);
+            let collect_writes = write_sets
+                .iter()
+                .map(get_collect_tokens)
+                .collect::<Vec<_>>();
+            let collect_bindless_writes = bindless_write_sets
+                .iter()
+                .map(|write_set| get_collect_tokens(write_set));
+            let layout = if self.has_textures() {
+                quote! { let layout = vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL; }
+            } else {
+                quote! {}
+            };
+            let (leading_params, _) = self.get_leading_params();
+            let doc = format!(
+                "Same as `{}`, except that the writes of a created set are gathered in `writes` \
+                instead of being performed, and the set is returned instead of being bound. \
+                Sets of several calls are written by `flush_writes` and bound by `bind_sets`",
+                bind_signature
+            );
+            tokens.extend(quote! {
+                #[doc = #doc]
+                pub fn #collect_signature(
+                    &self,
+                    writes: &mut #writes_ident,
+                    #leading_params
+                    #( #method_params, )*
+                ) -> vk::DescriptorSet {
+                    #ignore_offsets
+                    #view_type_checks
+                    #bindless_checks
+                    #frame_key
+                    #offset_key
+                    #layout
+
+                    let key = Self::derive_key(key, [ #( #uniform_ident::#variants, )* ]);
+                    let set_layouts = &[self.get_set_layouts()[#set as usize]];
+                    match descriptors.get_or_create(key, set_layouts) {
+                        DescriptorEntry::Created(sets) => {
+                            #( #collect_writes )*
+                            sets[0]
+                        }
+                        DescriptorEntry::Get(sets) => {
+                            #( #collect_bindless_writes )*
+                            sets[0]
+                        }
+                    }
+                }
+            });
+        }
+
+        let docs = self.get_docs_with_alias();
+        let return_ty = self.get_return_ty();
+        let raw_doc = format!(
+            "Same as `{}`, taking the raw Vulkan handles of the resources instead of \
+            their wrappers, where a sampled image is a pair of its view and its sampler. \
+            The types of the views are not checked",
+            bind_signature
+        );
+
+        if !self.has_textures() {
+            tokens.extend(quote! {
+                #docs
+                pub fn #bind_signature(
+                    &self,
+                    command_buffer: #command_buffer_ty,
+                    #leading_params
+                    #( #method_params, )*
+                ) #return_ty {
+                    #body
+                }
+
+                #[doc = #raw_doc]
+                pub fn #raw_signature(
+                    &self,
+                    command_buffer: #command_buffer_ty,
+                    #leading_params
+                    #( #raw_params, )*
+                ) #return_ty {
+                    #raw_body
+                }
+            });
+            if !self.push {
+                tokens.extend(quote! {
+                    #[doc = #update_doc]
+                    pub fn #update_signature(
+                        &self,
+                        #leading_params
+                        #( #method_params, )*
+                    ) {
+                        #ignore_offsets
+                        #update_body
+                    }
+                });
+            }
+            return;
+        }
+
+        // Texture bindings get a variant overriding the layout of their image infos
+        let bind_with_layout_signature = format_ident!("{}_with_layout", bind_signature);
+        let bind_layout_doc = get_layout_doc(&bind_signature);
+        let param_names = self.get_param_idents();
+        // Sets written with a different layout must not be reused
+        let layout_key = if self.push {
+            quote! {}
+        } else {
+            quote! {
+                let key = if layout == vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL {
+                    key
+                } else {
+                    Self::derive_key(key, layout.as_raw())
+                };
+            }
+        };
+
+        if !self.push {
+            let update_with_layout_signature = format_ident!("{}_with_layout", update_signature);
+            let update_layout_doc = get_layout_doc(&update_signature);
+            tokens.extend(quote! {
+                #[doc = #update_doc]
+                pub fn #update_signature(
+                    &self,
+                    #leading_params
+                    #( #method_params, )*
+                ) {
+                    self.#update_with_layout_signature(
+                        #leading_args
+                        #( #param_names, )*
+                        vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                    )
+                }
+
+                #[doc = #update_layout_doc]
+                pub fn #update_with_layout_signature(
+                    &self,
+                    #leading_params
+                    #( #method_params, )*
+                    layout: vk::ImageLayout,
+                ) {
+                    #ignore_offsets
+                    #layout_key
+                    #update_body
+                }
+            });
+        }
+
+        tokens.extend(quote! {
+            #docs
+            pub fn #bind_signature(
+                &self,
+                command_buffer: #command_buffer_ty,
+                #leading_params
+                #( #method_params, )*
+            ) #return_ty {
+                self.#bind_with_layout_signature(
+                    command_buffer,
+                    #leading_args
+                    #( #param_names, )*
+                    vk::ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                )
+            }
+
+            #[doc = #bind_layout_doc]
+            pub fn #bind_with_layout_signature(
+                &self,
+                command_buffer: #command_buffer_ty,
+                #leading_params
+                #( #method_params, )*
+                layout: vk::ImageLayout,
+            ) #return_ty {
+                #body
+            }
+
+            #[doc = #raw_doc]
+            pub fn #raw_signature(
+                &self,
+                command_buffer: #command_buffer_ty,
+                #leading_params
+                #( #raw_params, )*
+                #layout_param
+            ) #return_ty {
+                #layout_key
+                #raw_body
+            }
+        })
+    }
+}
+
+impl BindMethod {
+    /// Whether the writes of the set can be gathered with the ones of other sets,
+    /// which excludes pushed sets and acceleration structures chained to their writes
+    fn has_collect_method(&self) -> bool {
+        !self.push
+            && !self
+                .uniforms
+                .iter()
+                .any(|uniform| uniform.param.ty == ParamType::AccelerationStructure)
+    }
+
+    /// Each frame in flight binds a set of its own, so that a set is not
+    /// written while a previous frame still reads it
+    fn get_frame_key(&self) -> TokenStream {
+        match self.frames_in_flight {
+            Some(frames_in_flight) => {
+                let frames_in_flight = frames_in_flight as usize;
+                let message = format!(
+                    "Frame index {{}} is out of the {} frames in flight",
+                    frames_in_flight
+                );
+                quote! {
+                    assert!(frame_index < #frames_in_flight, #message, frame_index);
+                    let key = Self::derive_key(key, frame_index);
+                }
+            }
+            None => quote! {},
+        }
+    }
+
+    /// Returns the name of the struct of the arguments of the set
+    fn get_set_struct_ident(&self) -> Ident {
+        format_ident!("{}Set{}", self.pipeline, self.uniforms[0].set)
+    }
+
+    /// Returns a method for each uniform of the set, writing only that uniform
+    fn get_write_methods(&self) -> Vec<BindMethod> {
+        self.uniforms
+            .iter()
+            .map(|uniform| BindMethod {
+                uniforms: vec![uniform.clone()],
+                ..self.clone()
+            })
+            .collect()
+    }
+
+    /// Returns the name of the method binding the set from its struct of arguments
+    fn get_set_method_ident(&self) -> Ident {
+        format_ident!("{}_args", get_bind_ident(self))
+    }
+
+    /// Returns the docs of the bind method, searchable by its former name, or by
+    /// the name of each of its uniforms when that name is too long to be read
+    fn get_docs_with_alias(&self) -> TokenStream {
+        let docs = self.get_docs();
+        let aliases = if self.has_long_former_names() {
+            self.uniforms
+                .iter()
+                .map(|uniform| uniform.param.name.clone())
+                .collect()
+        } else {
+            vec![get_method_ident(get_bind_prefix(self), self).to_string()]
+        };
+        quote! {
+            #docs
+            #( #[doc(alias = #aliases)] )*
+        }
+    }
+
+    /// Whether the former name of the bind method, joining the names of the uniforms,
+    /// is longer than `MAX_FORMER_NAME_LEN`
+    fn has_long_former_names(&self) -> bool {
+        get_method_ident(get_bind_prefix(self), self)
+            .to_string()
+            .len()
+            > MAX_FORMER_NAME_LEN
+    }
+
+    /// Returns the former names of the methods of the set, joining the names of its uniforms
+    fn get_former_idents(&self) -> Vec<Ident> {
+        let mut ret = vec![get_method_ident(get_bind_prefix(self), self)];
+        if !self.push {
+            ret.push(get_method_ident("update", self));
+        }
+        if self.has_textures() {
+            let with_layouts = ret
+                .iter()
+                .map(|ident| format_ident!("{}_with_layout", ident))
+                .collect::<Vec<_>>();
+            ret.extend(with_layouts);
+        }
+        ret
+    }
+
+    /// Generates the methods named after the uniforms of the set, as bind methods were
+    /// before being named after their set, forwarding to the methods replacing them
+    fn get_deprecated_methods(&self) -> TokenStream {
+        if self.former_names_collide {
+            return TokenStream::new();
+        }
+        let (leading_params, leading_args) = self.get_leading_params();
+        let method_params = self.get_method_params();
+        let param_names = self.get_param_idents();
+        let command_buffer_ty = self.get_command_buffer_ty();
+        let bind_prefix = get_bind_prefix(self);
+
+        // Former name, new name, and whether they take a command buffer
+        let mut methods = vec![(
+            get_method_ident(bind_prefix, self),
+            get_bind_ident(self),
+            true,
+        )];
+        if !self.push {
+            methods.push((
+                get_method_ident("update", self),
+                get_update_ident(self),
+                false,
+            ));
+        }
+
+        let mut tokens = TokenStream::new();
+        for (former, current, records) in methods {
+            let (command_buffer_param, command_buffer_arg, return_ty) = if records {
+                (
+                    quote! { command_buffer: #command_buffer_ty, },
+                    quote! { command_buffer, },
+                    self.get_return_ty(),
+                )
+            } else {
+                (quote! {}, quote! {}, quote! {})
+            };
+            // Texture bindings also have a variant overriding the layout of their image infos
+            let with_layout = (
+                format_ident!("{}_with_layout", former),
+                format_ident!("{}_with_layout", current),
+                quote! { layout: vk::ImageLayout, },
+                quote! { layout, },
+            );
+            let mut variants = vec![(former, current, quote! {}, quote! {})];
+            if self.has_textures() {
+                variants.push(with_layout);
+            }
+            for (former, current, layout_param, layout_arg) in variants {
+                let note = format!(
+                    "Use `{}`, named after the set, which does not change with its uniforms",
+                    current
+                );
+                tokens.extend(quote! {
+                    #[deprecated(note = #note)]
+                    pub fn #former(
+                        &self,
+                        #command_buffer_param
+                        #leading_params
+                        #( #method_params, )*
+                        #layout_param
+                    ) #return_ty {
+                        self.#current(
+                            #command_buffer_arg
+                            #leading_args
+                            #( #param_names, )*
+                            #layout_arg
+                        )
+                    }
+                });
+            }
+        }
+        tokens
+    }
+
+    /// Generates the struct of the arguments of the set, with a field per parameter
+    /// of the bind method, so that arguments of the same type can not be swapped
+    fn get_set_struct(&self) -> TokenStream {
+        let struct_ident = self.get_set_struct_ident();
+        let fields = self
+            .get_method_params()
+            .iter()
+            .map(MethodParam::get_field_tokens)
+            .collect::<Vec<TokenStream>>();
+        let doc = format!(
+            "Arguments of `{}`, named after the uniforms of set {}",
+            self.get_set_method_ident(),
+            self.uniforms[0].set
+        );
+        quote! {
+            #[doc = #doc]
+            #[derive(Clone, Copy)]
+            pub struct #struct_ident<'a> {
+                #( #fields, )*
+            }
+        }
+    }
+
+    /// Generates the method binding the set from its struct of arguments,
+    /// which forwards them to the positional bind method
+    fn get_set_method(&self) -> TokenStream {
+        let set_signature = self.get_set_method_ident();
+        let bind_signature = get_bind_ident(self);
+        let struct_ident = self.get_set_struct_ident();
+        let command_buffer_ty = self.get_command_buffer_ty();
+        let (leading_params, leading_args) = self.get_leading_params();
+        let param_names = self.get_param_idents();
+        let return_ty = self.get_return_ty();
+        let doc = format!("Same as `{}`, with named arguments", bind_signature);
+        quote! {
+            #[doc = #doc]
+            pub fn #set_signature(
+                &self,
+                command_buffer: #command_buffer_ty,
+                #leading_params
+                args: #struct_ident<'_>,
+            ) #return_ty {
+                self.#bind_signature(command_buffer, #leading_args #( args.#param_names, )*)
+            }
+        }
+    }
+
+    /// Bind methods return the set they bind, while a pushed set has no handle
+    fn get_return_ty(&self) -> TokenStream {
+        if self.push {
+            quote! {}
+        } else {
+            quote! { -> vk::DescriptorSet }
+        }
+    }
+
+    /// Type of the command buffer of the bind methods, where graphics sets with dynamic
+    /// uniform buffers and push sets are bound on a raw command buffer
+    fn get_command_buffer_ty(&self) -> TokenStream {
+        get_command_buffer_tokens(self.bind_point, self.has_dynamic() || self.push).0
+    }
+
+    /// Parameters of the bind method before the uniforms, and their names,
+    /// which are the push descriptor functions for a push set
+    fn get_leading_params(&self) -> (TokenStream, TokenStream) {
+        if self.push {
+            (
+                quote! { push_descriptor: &ash::khr::push_descriptor::Device, },
+                quote! { push_descriptor, },
+            )
+        } else if self.frames_in_flight.is_some() {
+            (
+                quote! { descriptors: &mut Descriptors, key: DescriptorKey, frame_index: usize, },
+                quote! { descriptors, key, frame_index, },
+            )
+        } else {
+            (
+                quote! { descriptors: &mut Descriptors, key: DescriptorKey, },
+                quote! { descriptors, key, },
+            )
+        }
+    }
+
+    /// Names of the parameters of the bind method, where an unbounded array
+    /// is followed by the index of its first element, and a dynamic uniform
+    /// buffer by its offset
+    fn get_param_idents(&self) -> Vec<Ident> {
+        let mut ret = Vec::new();
+        for uniform in &self.uniforms {
+            ret.push(get_param_ident(&uniform.param.name));
+            if uniform.bindless || uniform.dynamic || uniform.buffer_offset {
+                ret.push(get_offset_ident(&uniform.param.name));
+            }
+        }
+        ret
+    }
+
+    /// Lists the bindings of the set with their descriptor types and arguments, refers
+    /// to the structs mirroring the constant buffers to bind, explains the
+    /// representation of booleans, and the offsets of dynamic uniform buffers
+    fn get_docs(&self) -> TokenStream {
+        let set = self.uniforms[0].set;
+        let summary = if self.push {
+            format!("Pushes the descriptors of set {} on `command_buffer`", set)
+        } else {
+            format!(
+                "Binds set {} for `key` on `command_buffer`, writing it when it is created",
+                set
+            )
+        };
+        let bindings =
+            self.uniforms
+                .iter()
+                .zip(self.get_method_params())
+                .map(|(uniform, param)| {
+                    let descriptor_type = uniform.get_descriptor_type().to_token_stream();
+                    format!(
+                        "- Binding {}, `{}`: `{}` from `{}`",
+                        uniform.binding,
+                        uniform.param.name,
+                        descriptor_type.to_string().replace(' ', ""),
+                        param.to_token_stream().to_string().replace(' ', "")
+                    )
+                });
+        let docs = self.uniforms.iter().filter_map(|uniform| {
+            if uniform.param.ty == ParamType::Bool {
+                return Some(format!(
+                    "The buffer of `{}` holds a `bool` as a `u32` of `0` for false or `1` for true",
+                    uniform.param.name
+                ));
+            }
+            uniform.get_struct_size()?;
+            let struct_ident = get_uniform_struct_ident(&self.pipeline, uniform);
+            Some(format!(
+                "The buffer of `{}` holds a [`{}`]",
+                uniform.param.name, struct_ident
+            ))
+        });
+        let dynamic_docs = self
+            .uniforms
+            .iter()
+            .filter(|uniform| uniform.dynamic)
+            .map(|uniform| {
+                format!(
+                    "`{}` is read `{}` bytes into its buffer, a multiple of `minUniformBufferOffsetAlignment`",
+                    uniform.param.name,
+                    get_offset_ident(&uniform.param.name)
+                )
+            });
+        let offset_docs = self
+            .uniforms
+            .iter()
+            .filter(|uniform| uniform.buffer_offset)
+            .map(|uniform| {
+                let limit = match uniform.param.ty {
+                    ParamType::StorageBuffer(_) => "minStorageBufferOffsetAlignment",
+                    _ => "minUniformBufferOffsetAlignment",
+                };
+                format!(
+                    "`{}` is written `{}` bytes into its buffer, a multiple of `{}`",
+                    uniform.param.name,
+                    get_offset_ident(&uniform.param.name),
+                    limit
+                )
+            });
+        let layout_docs = self.uniforms.iter().filter_map(|uniform| {
+            Some(format!(
+                "`{}` is written in the `{}` layout, whatever the layout given to this method",
+                uniform.param.name,
+                uniform.image_layout.as_ref()?
+            ))
+        });
+        quote! {
+            #[doc = #summary]
+            #[doc = ""]
+            #( #[doc = #bindings] )*
+            #[doc = ""]
+            #( #[doc = #docs] )*
+            #( #[doc = #dynamic_docs] )*
+            #( #[doc = #offset_docs] )*
+            #( #[doc = #layout_docs] )*
+        }
+    }
+
+    /// Asserts in debug builds that the elements written to unbounded arrays
+    /// fit in the descriptors of their bindings
+    fn get_bindless_checks(&self) -> TokenStream {
+        let checks = self
+            .uniforms
+            .iter()
+            .filter(|uniform| uniform.bindless)
+            .map(|uniform| {
+                let name = get_param_ident(&uniform.param.name);
+                let offset = get_offset_ident(&uniform.param.name);
+                let count = uniform.get_array_count() as usize;
+                let message = format!(
+                    "`{}` writes {{}} elements from {{}}, past its {} descriptors",
+                    uniform.param.name, count
+                );
+                quote! {
+                    debug_assert!(
+                        !#name.is_empty() && #offset as usize + #name.len() <= #count,
+                        #message,
+                        #name.len(),
+                        #offset,
+                    );
+                }
+            });
+        quote! { #( #checks )* }
+    }
+
+    /// Asserts in debug builds that the views of the bound textures
+    /// have the type of the textures declared by the shaders
+    fn get_view_type_checks(&self) -> TokenStream {
+        let checks = self.uniforms.iter().filter_map(|uniform| {
+            // Image views bound with an immutable sampler do not carry their type
+            if uniform.immutable {
+                return None;
+            }
+            let shape = uniform.shape?;
+            let name = get_param_ident(&uniform.param.name);
+            let message = format!(
+                "`{}` is bound to a {{:?}} view, expected {{:?}}",
+                uniform.param.name
+            );
+            let check = quote! {
+                debug_assert!(
+                    texture.view_type == #shape,
+                    #message,
+                    texture.view_type,
+                    #shape,
+                );
+            };
+            Some(if uniform.get_array_count() > 1 || uniform.bindless {
+                quote! { for texture in #name.iter() { #check } }
+            } else if uniform.optional {
+                quote! { if let Some(texture) = #name { #check } }
+            } else {
+                quote! { { let texture = #name; #check } }
+            })
+        });
+        quote! { #( #checks )* }
+    }
+}
+
+impl ToTokens for BindAllMethod {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let first = &self.methods[0];
+        let uniform_ident = format_ident!("{}Uniform", first.pipeline);
+        let method_params = self.get_method_params();
+        let write_sets = self.get_write_sets();
+        let (leading_params, _) = first.get_leading_params();
+        let frame_key = first.get_frame_key();
+
+        let view_type_checks = self
+            .methods
+            .iter()
+            .map(|method| method.get_view_type_checks());
+        let bindless_checks = self
+            .methods
+            .iter()
+            .map(|method| method.get_bindless_checks());
+
+        // Each set is fetched with the same key its own bind method derives
+        let sets = self.methods.iter().map(|method| {
+            let set = method.uniforms[0].set;
+            let variants = method
+                .uniforms
+                .iter()
+                .map(|uniform| get_variant_ident(&uniform.param.name));
+            let buffer_offsets = method
+                .uniforms
+                .iter()
+                .filter(|uniform| uniform.buffer_offset)
+                .map(|uniform| get_offset_ident(&uniform.param.name))
+                .collect::<Vec<_>>();
+            let offset_key = if buffer_offsets.is_empty() {
+                quote! {}
+            } else {
+                quote! { let key = Self::derive_key(key, [ #( #buffer_offsets, )* ]); }
+            };
+            quote! {
+                {
+                    #offset_key
+                    let key = Self::derive_key(key, [ #( #uniform_ident::#variants, )* ]);
+                    let set_layouts = &[self.get_set_layouts()[#set as usize]];
+                    match descriptors.get_or_create(key, set_layouts) {
+                        DescriptorEntry::Created(sets) | DescriptorEntry::Get(sets) => sets[0],
+                    }
+                }
+            }
+        });
+
+        let bind_point = first.bind_point;
+        let (command_buffer_ty, raw_command_buffer) =
+            get_command_buffer_tokens(bind_point, self.has_dynamic());
+        let bind_sets = if bind_point != BindPoint::Graphics || self.has_dynamic() {
+            // Dynamic offsets follow the order of the sets, then of their bindings
+            let offsets = self.methods.iter().flat_map(|method| {
+                let mut dynamic_uniforms = method
+                    .uniforms
+                    .iter()
+                    .filter(|uniform| uniform.dynamic)
+                    .collect::<Vec<_>>();
+                dynamic_uniforms.sort_by_key(|uniform| uniform.binding);
+                dynamic_uniforms
+                    .into_iter()
+                    .map(|uniform| get_offset_ident(&uniform.param.name))
+            });
+            quote! {
+                unsafe {
+                    self.device.cmd_bind_descriptor_sets(
+                        #raw_command_buffer,
+                        #bind_point,
+                        self.get_layout(),
+                        0,
+                        &sets,
+                        &[ #( #offsets, )* ],
+                    );
+                }
+            }
+        } else {
+            quote! { command_buffer.bind_descriptor_sets(self.get_layout(), &sets, 0); }
+        };
+
+        let doc = format!(
+            "Writes every set of `key` and binds them with a single call, taking the \
+            uniforms of `{}` in order. Unlike the bind methods of each set, the sets \
+            are written even when they are cached",
+            self.methods
+                .iter()
+                .map(|method| get_bind_ident(method).to_string())
+                .collect::<Vec<_>>()
+                .join("`, `")
+        );
+        let layout_doc = if self.has_textures() {
+            quote! { #[doc = "Textures are written in the `SHADER_READ_ONLY_OPTIMAL` layout"] }
+        } else {
+            quote! {}
+        };
+
+        tokens.extend(quote! {
+            #[doc = #doc]
+            #layout_doc
+            pub fn bind_all(
+                &self,
+                command_buffer: #command_buffer_ty,
+                #leading_params
+                #( #method_params, )*
+            ) {
+                #( #view_type_checks )*
+                #( #bindless_checks )*
+                #frame_key
+
+                let sets = [ #( #sets, )* ];
+                unsafe {
+                    self.device.update_descriptor_sets(
+                        &[
+                            #( #write_sets, )*
+                        ],
+                        &[]
+                    );
+                }
+                #bind_sets
+            }
+        });
+    }
+}
+
+/// Returns the enum variant of a uniform, converting its name to camel case
+fn get_variant_ident(name: &str) -> Ident {
+    let mut variant = get_rust_name(name)
+        .split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            let first = chars.next().unwrap().to_ascii_uppercase();
+            std::iter::once(first).chain(chars).collect::<String>()
+        })
+        .collect::<String>();
+    if variant.is_empty() || variant.starts_with(|c: char| c.is_ascii_digit()) {
+        variant.insert(0, '_');
+    }
+    // `Self` is the only keyword left once capitalized
+    if variant == "Self" {
+        variant.push('U');
+    }
+    Ident::new(&variant, Span::call_site())
+}
+
+/// Returns the name of a constant of a uniform, converting its name to
+/// upper snake case, e.g. `SET_VIEW_PROJ` for `viewProj` or `view_proj`
+fn get_const_ident(prefix: &str, name: &str) -> Ident {
+    let mut upper = String::new();
+    let mut after_lower = false;
+    for c in get_rust_name(name).chars() {
+        if c.is_ascii_uppercase() && after_lower {
+            upper.push('_');
+        }
+        after_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        upper.push(c.to_ascii_uppercase());
+    }
+    let parts = upper
+        .split('_')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>();
+    format_ident!("{}_{}", prefix, parts.join("_"))
+}
+
+/// Returns the name of the struct mirroring a constant buffer, e.g. `PipelineMainMaterial`
+fn get_uniform_struct_ident(pipeline: &str, uniform: &Uniform) -> Ident {
+    let variant = get_variant_ident(&uniform.param.name).to_string();
+    format_ident!("{}{}", pipeline, variant.trim_start_matches('_'))
+}
+
+/// Returns the ident and type of the fields of a struct of `size` bytes, with padding
+/// fields filling the gaps between them, which are flagged as such
+fn get_padded_fields(fields: &[StructField], size: usize) -> Vec<(Ident, TokenStream, bool)> {
+    let mut ret = Vec::new();
+    let mut end = 0;
+    for (i, field) in fields.iter().enumerate() {
+        if field.offset > end {
+            let pad = field.offset - end;
+            ret.push((format_ident!("_pad{}", i), quote! { [u8; #pad] }, true));
+        }
+        let ident = get_field_ident(&field.name);
+        let (ty, field_size) = get_field_tokens(field);
+        ret.push((ident, ty, false));
+        end = field.offset + field_size;
+    }
+    if size > end {
+        let pad = size - end;
+        ret.push((
+            format_ident!("_pad{}", fields.len()),
+            quote! { [u8; #pad] },
+            true,
+        ));
+    }
+    ret
+}
+
+/// Returns the type of a struct field and its size. Vectors and matrices are
+/// plain arrays, so that their size does not depend on the math library, and
+/// array elements are padded to their stride
+fn get_field_tokens(field: &StructField) -> (TokenStream, usize) {
+    let (scalar, scalar_size) = match field.ty {
+        ParamType::Int | ParamType::IVec2 | ParamType::IVec3 | ParamType::IVec4 => {
+            (quote! { i32 }, 4)
+        }
+        ParamType::Uint
+        | ParamType::Bool
+        | ParamType::UVec2
+        | ParamType::UVec3
+        | ParamType::UVec4 => (quote! { u32 }, 4),
+        ParamType::Half | ParamType::Half2 | ParamType::Half3 | ParamType::Half4 => {
+            (quote! { u16 }, 2)
+        }
+        ParamType::Double | ParamType::DVec2 | ParamType::DVec3 | ParamType::DVec4 => {
+            (quote! { f64 }, 8)
+        }
+        ParamType::Struct(_) => (quote! { u8 }, 1),
+        _ => (quote! { f32 }, 4),
+    };
+    let (ty, size) = match field.ty {
+        ParamType::Float
+        | ParamType::Int
+        | ParamType::Uint
+        | ParamType::Bool
+        | ParamType::Half
+        | ParamType::Double => (scalar.clone(), scalar_size),
+        ParamType::Vec2
+        | ParamType::IVec2
+        | ParamType::UVec2
+        | ParamType::Half2
+        | ParamType::DVec2 => (quote! { [#scalar; 2] }, scalar_size * 2),
+        ParamType::Vec3
+        | ParamType::IVec3
+        | ParamType::UVec3
+        | ParamType::Half3
+        | ParamType::DVec3 => (quote! { [#scalar; 3] }, scalar_size * 3),
+        ParamType::Vec4
+        | ParamType::IVec4
+        | ParamType::UVec4
+        | ParamType::Half4
+        | ParamType::DVec4 => (quote! { [#scalar; 4] }, scalar_size * 4),
+        ParamType::Mat3 => (quote! { [[f32; 4]; 3] }, 48),
+        ParamType::Mat4 => (quote! { [[f32; 4]; 4] }, 64),
+        ty => (quote! { #ty }, ty.get_size()),
+    };
+    if field.count == 1 {
+        return (ty, size);
+    }
+
+    let count = field.count as usize;
+    let stride = field.stride;
+    if stride == size {
+        (quote! { [#ty; #count] }, size * count)
+    } else {
+        let padded = stride / scalar_size;
+        (quote! { [[#scalar; #padded]; #count] }, stride * count)
+    }
+}
+
+/// Returns the name of a bind method after the index of its set, which does not
+/// change when uniforms are added, or the one of a push method for a set of push descriptors
+fn get_bind_ident(method: &BindMethod) -> Ident {
+    format_ident!("{}_set{}", get_bind_prefix(method), method.uniforms[0].set)
+}
+
+/// Returns the name of the variant of a bind method taking raw Vulkan handles
+fn get_raw_bind_ident(method: &BindMethod) -> Ident {
+    format_ident!("{}_raw", get_bind_ident(method))
+}
+
+/// Returns the doc of the variant of a method taking the layout of its textures
+fn get_layout_doc(signature: &Ident) -> String {
+    format!(
+        "Same as `{}`, writing the image infos of its textures with the given `layout`",
+        signature
+    )
+}
+
+/// Returns the name of the method writing only a uniform in its set
+fn get_write_ident(uniform: &Uniform) -> Ident {
+    format_ident!("write_{}", get_rust_name(&uniform.param.name))
+}
+
+/// Returns the name of the method gathering the writes of the set of a bind method
+fn get_collect_ident(method: &BindMethod) -> Ident {
+    format_ident!("collect_writes_set{}", method.uniforms[0].set)
+}
+
+/// Gathers a write of a set in `writes`, with the infos of its kind
+fn get_collect_tokens(write_set: &WriteSet) -> TokenStream {
+    let binding = write_set.binding;
+    let descriptor_type = write_set.descriptor_type;
+    let info = &write_set.info;
+    let array_element = match info.bindless {
+        true => get_offset_ident(&info.name).to_token_stream(),
+        false => quote! { 0 },
+    };
+    let push = match info.ty {
+        ParamType::Image
+        | ParamType::SampledImage
+        | ParamType::StorageImage
+        | ParamType::Sampler => {
+            quote! { push_image_infos }
+        }
+        ParamType::UniformTexelBuffer | ParamType::StorageTexelBuffer => {
+            quote! { push_texel_buffer_views }
+        }
+        _ => quote! { push_buffer_infos },
+    };
+    quote! {
+        writes.#push(sets[0], #binding, #array_element, #descriptor_type, #info);
+    }
+}
+
+/// Returns the name of the method writing the set of a bind method again
+fn get_update_ident(method: &BindMethod) -> Ident {
+    format_ident!("update_set{}", method.uniforms[0].set)
+}
+
+fn get_bind_prefix(method: &BindMethod) -> &'static str {
+    if method.push { "push" } else { "bind" }
+}
+
+/// Returns the method deriving the descriptor keys of bind methods, if there is any
+fn get_derive_key_method(bind_methods: &[BindMethod]) -> TokenStream {
+    if bind_methods.is_empty() {
+        return quote! {};
+    }
+    quote! {
+        /// Derives a descriptor key which also depends on `salt`,
+        /// so that sets written differently are cached separately
+        fn derive_key<S: std::hash::Hash>(key: DescriptorKey, salt: S) -> DescriptorKey {
+            use std::hash::{Hash, Hasher};
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            key.hash(&mut hasher);
+            salt.hash(&mut hasher);
+            DescriptorKey::from(hasher.finish())
+        }
+    }
+}
+
+/// Returns the implementation of `Pipeline` shared by pipelines and their handles,
+/// with the expressions of the bound pipeline and of the size of its vertices
+fn get_pipeline_impl(get_pipeline: TokenStream, vertex_size: TokenStream) -> TokenStream {
+    quote! {
+        fn as_any(&self) -> &dyn std::any::Any {
+            self
+        }
+
+        fn get_name(&self) -> &String {
+            &self.name
+        }
+
+        fn get_set_layouts(&self) -> &[vk::DescriptorSetLayout] {
+            &self.set_layouts
+        }
+
+        fn get_layout(&self) -> vk::PipelineLayout {
+            self.layout
+        }
+
+        fn get_pipeline(&self) -> vk::Pipeline {
+            #get_pipeline
+        }
+
+        fn get_device(&self) -> &ash::Device {
+            &self.device
+        }
+
+        fn get_vertex_size(&self) -> usize {
+            #vertex_size
+        }
+    }
+}
+
+/// Length past which a former name joining the uniforms of a set is no longer a doc alias
+const MAX_FORMER_NAME_LEN: usize = 40;
+
+/// Returns the former name of a method, joining the names of the uniforms of its set after `prefix`
+fn get_method_ident(prefix: &str, method: &BindMethod) -> Ident {
+    let joined_param_names = method
+        .uniforms
+        .iter()
+        .map(|uniform| get_rust_name(&uniform.param.name))
+        .collect::<Vec<String>>()
+        .join("_and_");
+    format_ident!("{}_{}", prefix, joined_param_names)
+}
+
+/// Generates the variant of an item which records its calls
+/// instead of touching Vulkan, for the `rayca-pipe-mock` feature
+struct Mock<'a, T>(&'a T);
+
+impl ToTokens for Mock<'_, BindMethod> {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let method = self.0;
+        let bind_signature = get_bind_ident(method);
+        let method_name = bind_signature.to_string();
+        let method_params = method.get_method_params();
+        let param_names = method.get_param_idents();
+        let set = method.uniforms[0].set;
+        let docs = method.get_docs_with_alias();
+        let command_buffer_ty = method.get_command_buffer_ty();
+        let (leading_params, leading_args) = method.get_leading_params();
+        let return_ty = method.get_return_ty();
+        // Mock pipelines bind null sets
+        let null_set = if method.push {
+            quote! {}
+        } else {
+            quote! { vk::DescriptorSet::null() }
+        };
+        tokens.extend(method.get_set_method());
+        tokens.extend(method.get_deprecated_methods());
+
+        tokens.extend(quote! {
+            #docs
+            pub fn #bind_signature(
+                &self,
+                command_buffer: #command_buffer_ty,
+                #leading_params
+                #( #method_params, )*
+            ) #return_ty {
+                let _ = (command_buffer, #leading_args #( #param_names, )*);
+                self.record_bind(#method_name, #set);
+                #null_set
+            }
+        });
+
+        if method.has_textures() {
+            let bind_with_layout_signature = format_ident!("{}_with_layout", bind_signature);
+            let bind_layout_doc = get_layout_doc(&bind_signature);
+            let method_name = bind_with_layout_signature.to_string();
+            tokens.extend(quote! {
+                #[doc = #bind_layout_doc]
+                pub fn #bind_with_layout_signature(
+                    &self,
+                    command_buffer: #command_buffer_ty,
+                    #leading_params
+                    #( #method_params, )*
+                    layout: vk::ImageLayout,
+                ) #return_ty {
+                    let _ = (command_buffer, #leading_args #( #param_names, )* layout);
+                    self.record_bind(#method_name, #set);
+                    #null_set
+                }
+            });
+        }
+
+        let raw_signature = get_raw_bind_ident(method);
+        let method_name = raw_signature.to_string();
+        let raw_params = method.get_raw_method_params();
+        let (layout_param, layout_arg) = if method.has_textures() {
+            (quote! { layout: vk::ImageLayout, }, quote! { layout, })
+        } else {
+            (quote! {}, quote! {})
+        };
+        tokens.extend(quote! {
+            pub fn #raw_signature(
+                &self,
+                command_buffer: #command_buffer_ty,
+                #leading_params
+                #( #raw_params, )*
+                #layout_param
+            ) #return_ty {
+                let _ = (command_buffer, #leading_args #( #param_names, )* #layout_arg);
+                self.record_bind(#method_name, #set);
+                #null_set
+            }
+        });
+
+        if method.push {
+            return;
+        }
+        if method.has_collect_method() {
+            let collect_signature = get_collect_ident(method);
+            let method_name = collect_signature.to_string();
+            let writes_ident = format_ident!("{}Writes", method.pipeline);
+            tokens.extend(quote! {
+                pub fn #collect_signature(
+                    &self,
+                    writes: &mut #writes_ident,
+                    #leading_params
+                    #( #method_params, )*
+                ) -> vk::DescriptorSet {
+                    let _ = (writes, #leading_args #( #param_names, )*);
+                    self.record_update(#method_name, #set);
+                    vk::DescriptorSet::null()
+                }
+            });
+        }
+        let update_signature = get_update_ident(method);
+        let method_name = update_signature.to_string();
+        tokens.extend(quote! {
+            pub fn #update_signature(
+                &self,
+                #leading_params
+                #( #method_params, )*
+            ) {
+                let _ = (#leading_args #( #param_names, )*);
+                self.record_update(#method_name, #set);
+            }
+        });
+        if method.has_textures() {
+            let update_with_layout_signature = format_ident!("{}_with_layout", update_signature);
+            let update_layout_doc = get_layout_doc(&update_signature);
+            let method_name = update_with_layout_signature.to_string();
+            tokens.extend(quote! {
+                #[doc = #update_layout_doc]
+                pub fn #update_with_layout_signature(
+                    &self,
+                    #leading_params
+                    #( #method_params, )*
+                    layout: vk::ImageLayout,
+                ) {
+                    let _ = (#leading_args #( #param_names, )* layout);
+                    self.record_update(#method_name, #set);
+                }
+            });
+        }
+
+        if method.uniforms.iter().any(|uniform| uniform.buffer_offset) {
+            return;
+        }
+        for write in method.get_write_methods() {
+            let write_signature = get_write_ident(&write.uniforms[0]);
+            let method_name = write_signature.to_string();
+            let method_params = write.get_method_params();
+            let param_names = write.get_param_idents();
+            tokens.extend(quote! {
+                pub fn #write_signature(
+                    &self,
+                    #leading_params
+                    #( #method_params, )*
+                ) {
+                    let _ = (#leading_args #( #param_names, )*);
+                    self.record_update(#method_name, #set);
+                }
+            });
+            if method.has_textures() {
+                let write_with_layout_signature = format_ident!("{}_with_layout", write_signature);
+                let write_layout_doc = get_layout_doc(&write_signature);
+                let method_name = write_with_layout_signature.to_string();
+                tokens.extend(quote! {
+                    #[doc = #write_layout_doc]
+                    pub fn #write_with_layout_signature(
+                        &self,
+                        #leading_params
+                        #( #method_params, )*
+                        layout: vk::ImageLayout,
+                    ) {
+                        let _ = (#leading_args #( #param_names, )* layout);
+                        self.record_update(#method_name, #set);
+                    }
+                });
+            }
+        }
+    }
+}
+
+impl ToTokens for Mock<'_, BindAllMethod> {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let method = self.0;
+        let first = &method.methods[0];
+        let method_params = method.get_method_params();
+        let param_names = method
+            .methods
+            .iter()
+            .flat_map(|method| method.get_param_idents());
+        let (command_buffer_ty, _) =
+            get_command_buffer_tokens(first.bind_point, method.has_dynamic());
+        let (leading_params, leading_args) = first.get_leading_params();
+        let sets = method.methods.iter().map(|method| method.uniforms[0].set);
+
+        tokens.extend(quote! {
+            pub fn bind_all(
+                &self,
+                command_buffer: #command_buffer_ty,
+                #leading_params
+                #( #method_params, )*
+            ) {
+                let _ = (command_buffer, #leading_args #( #param_names, )*);
+                #( self.record_bind("bind_all", #sets); )*
+            }
+        });
+    }
+}
+
+impl ToTokens for Mock<'_, PushMethod> {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let method = self.0;
+        let push_signature = format_ident!("push_{}", get_rust_name(&method.name));
+        let method_name = push_signature.to_string();
+        let (generics, arg, get_bytes) = method.get_arg_tokens();
+        let docs = method.get_docs();
+        let stage = method.get_push_stage_flags();
+        let offset = method.offset as u32;
+        let size = method.get_size();
+        tokens.extend(quote! {
+            #docs
+            pub fn #push_signature #generics(&self, command_buffer: &CommandBuffer, #arg) {
+                let _ = command_buffer;
+                #get_bytes
+                self.record_push(#method_name, #stage, #offset, &bytes[0..#size]);
+            }
+        });
+
+        let field_methods = method.get_field_methods(|ident, offset| {
+            let method_name = ident.to_string();
+            quote! {
+                let _ = command_buffer;
+                self.record_push(#method_name, #stage, #offset, bytes);
+            }
+        });
+        tokens.extend(field_methods);
+    }
+}
+
+impl MethodParam {
+    /// Generates the public fields of the struct of arguments of a set, which borrow
+    /// the resources for the lifetime of the struct
+    fn get_field_tokens(&self) -> TokenStream {
+        let name = get_param_ident(&self.name);
+        let ty: VkrType = self.ty.into();
+        let offset = get_offset_ident(&self.name);
+        if self.bindless {
+            quote! { pub #name: &'a [&'a #ty], pub #offset: u32 }
+        } else if self.immutable {
+            quote! { pub #name: &'a vk::ImageView }
+        } else if self.dynamic {
+            quote! { pub #name: &'a #ty, pub #offset: u32 }
+        } else if self.buffer_offset {
+            quote! { pub #name: &'a #ty, pub #offset: vk::DeviceSize }
+        } else if self.count > 1 {
+            let count = self.count as usize;
+            quote! { pub #name: &'a [&'a #ty; #count] }
+        } else if self.optional {
+            quote! { pub #name: Option<&'a #ty> }
+        } else {
+            quote! { pub #name: &'a #ty }
+        }
+    }
+
+    /// Returns the raw Vulkan handles wrapped by an element of the parameter,
+    /// where a sampled image is taken as its view and its sampler
+    fn get_raw_ty(&self) -> TokenStream {
+        if self.immutable && !self.bindless {
+            return quote! { vk::ImageView };
+        }
+        match self.ty {
+            ParamType::SampledImage => quote! { (vk::ImageView, vk::Sampler) },
+            ParamType::Image | ParamType::StorageImage => quote! { vk::ImageView },
+            _ => match VkrType::from(self.ty) {
+                VkrType::Buffer => quote! { vk::Buffer },
+                ty => ty.to_token_stream(),
+            },
+        }
+    }
+
+    /// Converts a reference to an element of the parameter into its raw Vulkan handles
+    fn get_raw_element(&self, element: TokenStream) -> TokenStream {
+        if self.immutable && !self.bindless {
+            return quote! { *#element };
+        }
+        match self.ty {
+            ParamType::SampledImage => quote! { (#element.view, #element.sampler) },
+            ParamType::Image | ParamType::StorageImage => quote! { #element.view },
+            _ => match VkrType::from(self.ty) {
+                VkrType::Buffer => quote! { #element.buffer },
+                _ => quote! { *#element },
+            },
+        }
+    }
+
+    /// Returns the arguments passing the parameter to the variant of its bind method
+    /// taking raw Vulkan handles, followed by its offset if any
+    fn get_raw_args(&self) -> TokenStream {
+        let name = get_param_ident(&self.name);
+        let element = self.get_raw_element(quote! { element });
+        let arg = if self.bindless {
+            quote! { &#name.iter().map(|&element| #element).collect::<Vec<_>>() }
+        } else if (self.count > 1 || self.optional)
+            && !(self.immutable || self.dynamic || self.buffer_offset)
+        {
+            quote! { #name.map(|element| #element) }
+        } else {
+            self.get_raw_element(name.to_token_stream())
+        };
+        if self.bindless || self.dynamic || self.buffer_offset {
+            let offset = get_offset_ident(&self.name);
+            quote! { #arg, #offset }
+        } else {
+            arg
+        }
+    }
+
+    /// Generates the parameter of the variant of its bind method taking raw Vulkan handles
+    fn get_raw_tokens(&self) -> TokenStream {
+        let name = get_param_ident(&self.name);
+        let ty = self.get_raw_ty();
+        if self.bindless {
+            let offset = get_offset_ident(&self.name);
+            quote! { #name: &[#ty], #offset: u32 }
+        } else if self.immutable {
+            quote! { #name: #ty }
+        } else if self.dynamic {
+            let offset = get_offset_ident(&self.name);
+            quote! { #name: #ty, #offset: u32 }
+        } else if self.buffer_offset {
+            let offset = get_offset_ident(&self.name);
+            quote! { #name: #ty, #offset: vk::DeviceSize }
+        } else if self.count > 1 {
+            let count = self.count as usize;
+            quote! { #name: [#ty; #count] }
+        } else if self.optional {
+            quote! { #name: Option<#ty> }
+        } else {
+            quote! { #name: #ty }
+        }
+    }
+}
+
+impl ToTokens for MethodParam {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let name = get_param_ident(&self.name);
+        let ty: VkrType = self.ty.into();
+        if self.raw {
+            tokens.extend(self.get_raw_tokens())
+        } else if self.bindless {
+            let offset = get_offset_ident(&self.name);
+            tokens.extend(quote! { #name: &[&#ty], #offset: u32 })
+        } else if self.immutable {
+            tokens.extend(quote! { #name: &vk::ImageView })
+        } else if self.dynamic {
+            let offset = get_offset_ident(&self.name);
+            tokens.extend(quote! { #name: &#ty, #offset: u32 })
+        } else if self.buffer_offset {
+            let offset = get_offset_ident(&self.name);
+            tokens.extend(quote! { #name: &#ty, #offset: vk::DeviceSize })
+        } else if self.count > 1 {
+            let count = self.count as usize;
+            tokens.extend(quote! { #name: &[&#ty; #count] })
+        } else if self.optional {
+            tokens.extend(quote! { #name: Option<&#ty> })
+        } else {
+            tokens.extend(quote! { #name: &#ty })
+        }
+    }
+}
+
+impl ToTokens for ParamType {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let new_tokens = match self {
+            ParamType::Vec2 => quote! { Vec2 },
+            ParamType::Vec3 => quote! { Vec3 },
+            ParamType::Vec4 => quote! { Vec4 },
+            ParamType::IVec2 => quote! { IVec2 },
+            ParamType::IVec3 => quote! { IVec3 },
+            ParamType::IVec4 => quote! { IVec4 },
+            ParamType::UVec2 => quote! { UVec2 },
+            ParamType::UVec3 => quote! { UVec3 },
+            ParamType::UVec4 => quote! { UVec4 },
+            ParamType::Mat3 => quote! { Mat3 },
+            // Rows are padded as on the GPU, so that the value covers its size
+            ParamType::Mat2 => quote! { [[f32; 4]; 2] },
+            ParamType::Mat3x4 => quote! { [[f32; 4]; 3] },
+            ParamType::Mat4x3 => quote! { [[f32; 4]; 4] },
+            ParamType::Mat4 => quote! { Mat4 },
+            ParamType::Float => quote! { f32 },
+            ParamType::Int => quote! { i32 },
+            ParamType::Uint | ParamType::Bool => quote! { u32 },
+            // Half-precision floats are passed as their bits
+            ParamType::Half => quote! { u16 },
+            ParamType::Half2 => quote! { [u16; 2] },
+            ParamType::Half3 | ParamType::Half4 => quote! { [u16; 4] },
+            ParamType::Double => quote! { f64 },
+            ParamType::DVec2 => quote! { [f64; 2] },
+            ParamType::DVec3 | ParamType::DVec4 => quote! { [f64; 4] },
+            ParamType::Struct(size) => {
+                let size = *size;
+                quote! { [u8;#size] }
+            }
+            _ => panic!(
+                "{}:{}: Can not use param type on CPU: `{:?}`",
+                file!(),
+                line!(),
+                self
+            ),
+        };
+        tokens.extend(new_tokens);
+    }
+}
+
+impl ToTokens for VkrType {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let new_tokens = match self {
+            VkrType::Buffer => quote! { RenderBuffer },
+            VkrType::Texture => quote! { RenderTexture },
+            VkrType::AccelerationStructure => quote! { vk::AccelerationStructureKHR },
+            VkrType::Sampler => quote! { vk::Sampler },
+            VkrType::BufferView => quote! { vk::BufferView },
+        };
+        tokens.extend(new_tokens);
+    }
+}
+
+impl ToTokens for WriteSet {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let binding = self.binding;
+        let descriptor_type = self.descriptor_type;
+        let info = &self.info;
+        let array_element = match self.info.bindless {
+            true => get_offset_ident(&self.info.name).to_token_stream(),
+            false => quote! { 0 },
+        };
+        // Pushed descriptors have no destination set
+        let index = Literal::usize_unsuffixed(self.index);
+        let dst_set = match self.push {
+            true => quote! {},
+            false => quote! { .dst_set(sets[#index]) },
+        };
+        tokens.extend(quote! {
+            vk::WriteDescriptorSet::default()
+                #dst_set
+                .dst_binding(#binding)
+                .dst_array_element(#array_element)
+                .descriptor_type(#descriptor_type)
+        });
+
+        match self.info.ty {
+            ParamType::Image
+            | ParamType::SampledImage
+            | ParamType::StorageImage
+            | ParamType::Sampler => tokens.extend(quote! { .image_info(&#info) }),
+            // Acceleration structures are written through the chained structure
+            ParamType::AccelerationStructure => {
+                let count = self.info.count;
+                tokens.extend(quote! { .descriptor_count(#count).push_next(&mut #info) })
+            }
+            // Texel buffers are written with their views instead of infos
+            ParamType::UniformTexelBuffer | ParamType::StorageTexelBuffer => {
+                tokens.extend(quote! { .texel_buffer_view(&#info) })
+            }
+            _ => tokens.extend(quote! { .buffer_info(&#info) }),
+        }
+        tokens.extend(quote! {});
+    }
+}
+
+impl ToTokens for WriteSetInfo {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let name = get_param_ident(&self.name);
+        let ty = self.ty;
+        // Images are written in the layout configured for them, if any
+        let image_layout = self.get_image_layout(quote! { layout });
+        let storage_layout = self.get_image_layout(quote! { vk::ImageLayout::GENERAL });
+        let element = quote! { element };
+        let element_buffer = self.get_buffer(&element);
+        let element_view = self.get_view(&element);
+        let element_sampler = self.get_sampler(&element);
+        // Raw handles are copied out of the slice, resources are borrowed from it
+        let (element_pattern, element_handle) = match self.raw {
+            true => (quote! { &element }, quote! { element }),
+            false => (quote! { element }, quote! { **element }),
+        };
+        let name_tokens = name.to_token_stream();
+        let name_buffer = self.get_buffer(&name_tokens);
+        let name_view = self.get_view(&name_tokens);
+        let name_sampler = self.get_sampler(&name_tokens);
+        let texture = quote! { texture };
+        let texture_view = self.get_view(&texture);
+        let texture_sampler = self.get_sampler(&texture);
+        // Elements of an array, or the only resource of the binding
+        let elements = match self.count {
+            1 => vec![name_tokens.clone()],
+            count => (0..count as usize).map(|i| quote! { #name[#i] }).collect(),
+        };
+
+        match ty {
+            // Unbounded arrays are written from a slice of any length
+            _ if self.bindless => {
+                let info = match ty {
+                    ParamType::Image => quote! {
+                        vk::DescriptorImageInfo::default()
+                            .image_layout(#image_layout)
+                            .image_view(#element_view)
+                    },
+                    ParamType::SampledImage => quote! {
+                        vk::DescriptorImageInfo::default()
+                            .image_layout(#image_layout)
+                            .image_view(#element_view)
+                            .sampler(#element_sampler)
+                    },
+                    ParamType::StorageImage => quote! {
+                        vk::DescriptorImageInfo::default()
+                            .image_layout(#storage_layout)
+                            .image_view(#element_view)
+                    },
+                    ParamType::Sampler => quote! {
+                        vk::DescriptorImageInfo::default().sampler(#element_handle)
+                    },
+                    ParamType::UniformTexelBuffer | ParamType::StorageTexelBuffer => quote! {
+                        #element_handle
+                    },
+                    ParamType::StorageBuffer(_) => quote! {
+                        vk::DescriptorBufferInfo::default()
+                            .range(vk::WHOLE_SIZE)
+                            .buffer(#element_buffer)
+                    },
+                    ParamType::AccelerationStructure => panic!(
+                        "{}:{}: unsupported unbounded array of acceleration structures `{}`",
+                        file!(),
+                        line!(),
+                        self.name
+                    ),
+                    _ => {
+                        let size = ty.get_size();
+                        quote! {
+                            vk::DescriptorBufferInfo::default()
+                                .range(#size as vk::DeviceSize)
+                                .buffer(#element_buffer)
+                        }
+                    }
+                };
+                tokens.extend(quote! {
+                    #name.iter().map(|#element_pattern| #info).collect::<Vec<_>>()
+                });
+            }
+            // The sampler of the binding is baked in the set layout
+            _ if self.immutable => {
+                let view = self.get_handle(&name_tokens);
+                tokens.extend(quote! {
+                    [
+                        vk::DescriptorImageInfo::default()
+                            .image_layout(#image_layout)
+                            .image_view(#view)
+                    ]
+                })
+            }
+            // Storage images are accessed in the general layout, without a sampler
+            ParamType::StorageImage if self.optional && self.count == 1 => tokens.extend(quote! {
+                [
+                    {
+                        let view = match #name {
+                            Some(texture) => #texture_view,
+                            None => self.get_default_texture().0,
+                        };
+                        vk::DescriptorImageInfo::default()
+                            .image_layout(#storage_layout)
+                            .image_view(view)
+                    }
+                ]
+            }),
+            ParamType::StorageImage => {
+                let views = elements.iter().map(|texture| self.get_view(texture));
+                tokens.extend(quote! {
+                    [
+                        #(
+                            vk::DescriptorImageInfo::default()
+                                .image_layout(#storage_layout)
+                                .image_view(#views),
+                        )*
+                    ]
+                });
+            }
+            // Elements of an array are written from the first one of the binding
+            ParamType::Image | ParamType::SampledImage if self.count > 1 => {
+                let views = elements.iter().map(|texture| self.get_view(texture));
+                let samplers = elements.iter().map(|texture| match ty {
+                    ParamType::SampledImage => {
+                        let sampler = self.get_sampler(texture);
+                        quote! { .sampler(#sampler) }
+                    }
+                    _ => quote! {},
+                });
+                tokens.extend(quote! {
+                    [
+                        #(
+                            vk::DescriptorImageInfo::default()
+                                .image_layout(#image_layout)
+                                .image_view(#views)
+                                #samplers,
+                        )*
+                    ]
+                });
+            }
+            ParamType::SampledImage if self.optional => tokens.extend(quote! {
+                [
+                    {
+                        let (view, sampler) = match #name {
+                            Some(texture) => (#texture_view, #texture_sampler),
+                            None => self.get_default_texture(),
+                        };
+                        vk::DescriptorImageInfo::default()
+                            .image_layout(#image_layout)
+                            .image_view(view)
+                            .sampler(sampler)
+                    }
+                ]
+            }),
+            // Input attachments are read without a sampler
+            ParamType::Image if self.optional => tokens.extend(quote! {
+                [
+                    {
+                        let view = match #name {
+                            Some(texture) => #texture_view,
+                            None => self.get_default_texture().0,
+                        };
+                        vk::DescriptorImageInfo::default()
+                            .image_layout(#image_layout)
+                            .image_view(view)
+                    }
+                ]
+            }),
+            ParamType::Image => tokens.extend(quote! {
+                [
+                    vk::DescriptorImageInfo::default()
+                        .image_layout(#image_layout)
+                        .image_view(#name_view)
+                ]
+            }),
+            ParamType::SampledImage => tokens.extend(quote! {
+                [
+                    vk::DescriptorImageInfo::default()
+                        .image_layout(#image_layout)
+                        .image_view(#name_view)
+                        .sampler(#name_sampler)
+                ]
+            }),
+            // Standalone samplers are written without an image
+            ParamType::Sampler => {
+                let samplers = elements.iter().map(|sampler| self.get_handle(sampler));
+                tokens.extend(quote! {
+                    [
+                        #(
+                            vk::DescriptorImageInfo::default().sampler(#samplers),
+                        )*
+                    ]
+                });
+            }
+            // Texel buffers are written as their buffer views
+            ParamType::UniformTexelBuffer | ParamType::StorageTexelBuffer => {
+                let views = elements.iter().map(|view| self.get_handle(view));
+                tokens.extend(quote! {
+                    [
+                        #( #views, )*
+                    ]
+                });
+            }
+            ParamType::AccelerationStructure if self.count > 1 => {
+                let structures = match self.raw {
+                    true => quote! { &#name },
+                    false => quote! { &#name.map(|structure| *structure) },
+                };
+                tokens.extend(quote! {
+                    vk::WriteDescriptorSetAccelerationStructureKHR::default()
+                        .acceleration_structures(#structures)
+                })
+            }
+            ParamType::AccelerationStructure => {
+                let structure = match self.raw {
+                    true => quote! { &#name },
+                    false => quote! { #name },
+                };
+                tokens.extend(quote! {
+                    vk::WriteDescriptorSetAccelerationStructureKHR::default()
+                        .acceleration_structures(std::slice::from_ref(#structure))
+                })
+            }
+            // Storage buffers are bound whole, as their length is only known at runtime,
+            // or up to their end from an offset
+            ParamType::StorageBuffer(_) if self.buffer_offset => {
+                let offset = get_offset_ident(&self.name);
+                tokens.extend(quote! {
+                    [
+                        vk::DescriptorBufferInfo::default()
+                            .offset(#offset)
+                            .range(vk::WHOLE_SIZE)
+                            .buffer(#name_buffer)
+                    ]
+                });
+            }
+            ParamType::StorageBuffer(_) => {
+                let buffers = elements.iter().map(|buffer| self.get_buffer(buffer));
+                tokens.extend(quote! {
+                    [
+                        #(
+                            vk::DescriptorBufferInfo::default()
+                                .range(vk::WHOLE_SIZE)
+                                .buffer(#buffers),
+                        )*
+                    ]
+                });
+            }
+            _ if self.count > 1 => {
+                let size = ty.get_size();
+                let buffers = elements.iter().map(|buffer| self.get_buffer(buffer));
+                tokens.extend(quote! {
+                    [
+                        #(
+                            vk::DescriptorBufferInfo::default()
+                                .range(#size as vk::DeviceSize)
+                                .buffer(#buffers),
+                        )*
+                    ]
+                });
+            }
+            _ if self.buffer_offset => {
+                let size = ty.get_size();
+                let offset = get_offset_ident(&self.name);
+                tokens.extend(quote! {
+                    [
+                        vk::DescriptorBufferInfo::default()
+                            .offset(#offset)
+                            .range(#size as vk::DeviceSize)
+                            .buffer(#name_buffer)
+                    ]
+                });
+            }
+            _ => {
+                let size = ty.get_size();
+                tokens.extend(quote! {
+                    [
+                        vk::DescriptorBufferInfo::default()
+                            .range(#size as vk::DeviceSize)
+                            .buffer(#name_buffer)
+                    ]
+                });
+            }
+        }
+    }
+}
+
+impl WriteSetInfo {
+    /// Returns the layout configured for the image, or the default one
+    fn get_image_layout(&self, default: TokenStream) -> TokenStream {
+        match &self.image_layout {
+            Some(layout) => {
+                let layout = format_ident!("{}", layout);
+                quote! { vk::ImageLayout::#layout }
+            }
+            None => default,
+        }
+    }
+
+    /// Returns the buffer of a resource, which is the resource itself when raw
+    fn get_buffer(&self, resource: &TokenStream) -> TokenStream {
+        match self.raw {
+            true => resource.clone(),
+            false => quote! { #resource.buffer },
+        }
+    }
+
+    /// Returns the image view of a texture, the first of the raw handles of a sampled image
+    fn get_view(&self, texture: &TokenStream) -> TokenStream {
+        match (self.raw, self.ty) {
+            (true, ParamType::SampledImage) if !self.immutable || self.bindless => {
+                quote! { #texture.0 }
+            }
+            (true, _) => texture.clone(),
+            (false, _) => quote! { #texture.view },
+        }
+    }
+
+    /// Returns the sampler of a texture, the second of the raw handles of a sampled image
+    fn get_sampler(&self, texture: &TokenStream) -> TokenStream {
+        match self.raw {
+            true => quote! { #texture.1 },
+            false => quote! { #texture.sampler },
+        }
+    }
+
+    /// Returns a handle taken by reference, or by value when raw
+    fn get_handle(&self, handle: &TokenStream) -> TokenStream {
+        match self.raw {
+            true => handle.clone(),
+            false => quote! { *#handle },
+        }
+    }
+}
+
+impl ToTokens for PushRange {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let stage = get_stage_flags(&self.stages);
+        let offset = self.offset;
+        let range = self.get_size();
+        tokens.extend(quote! {
+            vk::PushConstantRange::default()
+                .offset(#offset as u32)
+                .stage_flags(#stage)
+                .size(#range as u32)
+        })
+    }
+}
+
+impl PushMethod {
+    /// Describes the stages, offset, and size of the push, and how booleans are pushed
+    fn get_docs(&self) -> TokenStream {
+        let summary = format!(
+            "Pushes `{}` to the {} {}, {} bytes at offset {} of the push constant block",
+            self.name,
+            get_stage_names(&self.stages),
+            if self.stages.len() > 1 {
+                "stages"
+            } else {
+                "stage"
+            },
+            self.get_size(),
+            self.offset
+        );
+        let struct_doc = match self.get_struct_ident() {
+            Some(struct_ident) => {
+                let doc = format!("`{}` is pushed as a [`{}`]", self.name, struct_ident);
+                quote! { #[doc = #doc] }
+            }
+            None => quote! {},
+        };
+        let bool_doc = if self.count == 1 && self.ty == ParamType::Bool {
+            let doc = format!(
+                "`{}` is a `bool` of 4 bytes on the GPU, pushed as `0` for false or `1` for true",
+                self.name
+            );
+            quote! { #[doc = #doc] }
+        } else {
+            quote! {}
+        };
+        quote! {
+            #[doc = #summary]
+            #struct_doc
+            #bool_doc
+        }
+    }
+
+    /// Name of the struct mirroring a struct constant, prefixed by the pipeline
+    /// and by `Push` so that it does not clash with the uniform structs
+    fn get_struct_ident(&self) -> Option<Ident> {
+        if self.fields.is_empty() || self.count > 1 {
+            return None;
+        }
+        let variant = get_variant_ident(&self.name).to_string();
+        Some(format_ident!(
+            "{}Push{}",
+            self.pipeline,
+            variant.trim_start_matches('_')
+        ))
+    }
+
+    /// Fields of a struct constant which can be pushed on their own, at their offset in
+    /// the struct, with their type and size, where array elements keep their std430
+    /// stride so that a push does not reach the next field. Vulkan needs pushes aligned
+    /// to 4 bytes
+    fn get_pushed_fields(&self) -> Vec<(Ident, &StructField, TokenStream, usize)> {
+        if self.get_struct_ident().is_none() {
+            return Vec::new();
+        }
+        self.fields
+            .iter()
+            .filter_map(|field| {
+                let (ty, size) = get_field_tokens(field);
+                if (self.offset + field.offset) % 4 != 0 || size % 4 != 0 {
+                    return None;
+                }
+                let ident = format_ident!(
+                    "push_{}_{}",
+                    get_rust_name(&self.name),
+                    get_rust_name(&field.name)
+                );
+                Some((ident, field, ty, size))
+            })
+            .collect()
+    }
+
+    /// Generates the methods pushing a single field of a struct constant, where
+    /// `push` returns the statements pushing its `bytes` at `offset` in the block
+    fn get_field_methods(&self, push: impl Fn(&Ident, u32) -> TokenStream) -> TokenStream {
+        let methods = self
+            .get_pushed_fields()
+            .into_iter()
+            .map(|(ident, field, ty, size)| {
+                let offset = self.offset + field.offset;
+                let doc = format!(
+                    "Pushes the `{}` field of `{}` alone, {} bytes at offset {} of the push constant block",
+                    field.name, self.name, size, offset
+                );
+                let arg_name = get_param_ident(&field.name);
+                let body = push(&ident, offset as u32);
+                quote! {
+                    #[doc = #doc]
+                    pub fn #ident(&self, command_buffer: &CommandBuffer, #arg_name: &#ty) {
+                        let bytes = unsafe {
+                            std::slice::from_raw_parts(#arg_name as *const #ty as *const u8, #size)
+                        };
+                        #body
+                    }
+                }
+            });
+        quote! { #( #methods )* }
+    }
+
+    /// Stage flags of the push, the ones of its range, which no other range overlaps
+    fn get_push_stage_flags(&self) -> TokenStream {
+        get_stage_flags(&self.stages)
+    }
+
+    /// Returns the generics and argument of a push method, and the statement getting
+    /// its bytes. Booleans take 4 bytes on the GPU, so they are pushed as `u32`
+    fn get_arg_tokens(&self) -> (TokenStream, TokenStream, TokenStream) {
+        let arg_name = get_param_ident(&self.name);
+        // Structs are taken as their mirror, whose size is asserted at compile time
+        if let Some(struct_ident) = self.get_struct_ident() {
+            return (
+                quote! {},
+                quote! { #arg_name: &#struct_ident },
+                quote! {
+                    let bytes = unsafe {
+                        std::slice::from_raw_parts(
+                            #arg_name as *const #struct_ident as *const u8,
+                            std::mem::size_of::<#struct_ident>(),
+                        )
+                    };
+                },
+            );
+        }
+        if self.count == 1 && self.ty == ParamType::Bool {
+            return (
+                quote! {},
+                quote! { #arg_name: u32 },
+                quote! { let bytes = #arg_name.to_ne_bytes(); },
+            );
+        }
+        // Math crates pack a 3x3 matrix in 36 bytes or pad its rows to 48 bytes
+        // as on the GPU, so packed rows are spread to 16 bytes each
+        if self.count == 1 && self.ty == ParamType::Mat3 {
+            let message = format!(
+                "`{}` is a 3x3 matrix of 36 or 48 bytes, but the argument has {{}} bytes",
+                self.name
+            );
+            return (
+                quote! { <B: AsBytes> },
+                quote! { #arg_name: &B },
+                quote! {
+                    let packed = #arg_name.as_bytes();
+                    debug_assert!(
+                        packed.len() == 36 || packed.len() == 48,
+                        #message,
+                        packed.len()
+                    );
+                    let mut bytes = [0u8; 48];
+                    if packed.len() == 48 {
+                        bytes.copy_from_slice(packed);
+                    } else {
+                        for row in 0..3 {
+                            bytes[row * 16..row * 16 + 12]
+                                .copy_from_slice(&packed[row * 12..row * 12 + 12]);
+                        }
+                    }
+                },
+            );
+        }
+        // Other types, arrays included, provide their bytes, which in debug
+        // builds must be as many as the reflected size of the constant
+        let size = self.get_size();
+        let message = format!(
+            "`{}` is pushed as {} bytes, but the argument has {{}} bytes",
+            self.name, size
+        );
+        (
+            quote! { <B: AsBytes> },
+            quote! { #arg_name: &B },
+            quote! {
+                let bytes = #arg_name.as_bytes();
+                debug_assert_eq!(bytes.len(), #size, #message, bytes.len());
+            },
+        )
+    }
+}
+
+/// Names the stages in prose, like `Vertex and Fragment`
+fn get_stage_names(stages: &[ShaderType]) -> String {
+    stages
+        .iter()
+        .map(|stage| format!("{:?}", stage))
+        .collect::<Vec<_>>()
+        .join(" and ")
+}
+
+impl ToTokens for PushMethod {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let push_signature = format_ident!("push_{}", get_rust_name(&self.name));
+        let (generics, arg, get_bytes) = self.get_arg_tokens();
+        let docs = self.get_docs();
+        let stage = self.get_push_stage_flags();
+        let offset = self.offset as u32;
+        let size = self.get_size();
+        let field_methods = self.get_field_methods(|_, offset| {
+            quote! {
+                command_buffer.push_constants(self, #stage, #offset, bytes);
+            }
+        });
+
+        tokens.extend(quote! {
+            #docs
+            pub fn #push_signature #generics(&self, command_buffer: &CommandBuffer, #arg) {
+                #get_bytes
+                command_buffer.push_constants(self, #stage, #offset, &bytes[0..#size]);
+            }
+
+            #field_methods
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parse::Slang;
+
+    #[test]
+    fn constant_buffer_array() {
+        let code = r#"
+            struct CascadeData {
+                float4x4 view_proj;
+                float4 split;
+            };
+
+            [vk::binding(0, 0)]
+            ConstantBuffer<CascadeData> cascades[4];
+
+            [shader("fragment")]
+            float4 main() : SV_Target {
+                return cascades[3].split;
+            }
+        "#;
+
+        let slang = Slang::new();
+        let frag = slang.from_source("test", code);
+        let shader = Shader::from(frag);
+
+        let bindings = shader.get_set_layout_bindings(0);
+        assert_eq!(bindings.len(), 1);
+        let layout_tokens = bindings[0].to_token_stream().to_string().replace(' ', "");
+        assert!(layout_tokens.contains("descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)"));
+        assert!(layout_tokens.contains("descriptor_count(4u32)"));
+
+        let mut methods = vec![BindMethod::default()];
+        shader.get_bind_methods(&mut methods);
+        let method_tokens = methods[0].to_token_stream().to_string().replace(' ', "");
+        assert!(method_tokens.contains("cascades:&[&RenderBuffer;4usize]"));
+        for i in 0..4 {
+            assert!(method_tokens.contains(&format!(".buffer(cascades[{}usize].buffer)", i)));
+        }
+    }
+
+    #[test]
+    fn texture_array() {
+        let code = r#"
+            [vk::binding(0, 0)]
+            Sampler2D shadow_maps[4];
+
+            [shader("fragment")]
+            float4 main(float2 uv) : SV_Target {
+                return shadow_maps[3].Sample(uv);
+            }
+        "#;
+
+        let slang = Slang::new();
+        let frag = slang.from_source("test", code);
+        let shader = Shader::from(frag);
+
+        let bindings = shader.get_set_layout_bindings(0);
+        assert_eq!(bindings.len(), 1);
+        let layout_tokens = bindings[0].to_token_stream().to_string().replace(' ', "");
+        assert!(
+            layout_tokens.contains("descriptor_type(vk::DescriptorType::COMBINED_IMAGE_SAMPLER)")
+        );
+        assert!(layout_tokens.contains("descriptor_count(4u32)"));
+
+        let mut methods = vec![BindMethod::default()];
+        shader.get_bind_methods(&mut methods);
+        let method_tokens = methods[0].to_token_stream().to_string().replace(' ', "");
+        assert!(method_tokens.contains("shadow_maps:&[&RenderTexture;4usize]"));
+        assert!(method_tokens.contains(".dst_array_element(0)"));
+        for i in 0..4 {
+            assert!(method_tokens.contains(&format!(".image_view(shadow_maps[{}usize].view)", i)));
+        }
+    }
+
+    #[test]
+    fn texture_array_infos() {
+        let info = WriteSetInfo {
+            name: "shadow_maps".into(),
+            ty: ParamType::SampledImage,
+            optional: false,
+            count: 2,
+            bindless: false,
+            immutable: false,
+            buffer_offset: false,
+            raw: false,
+            image_layout: None,
+        };
+        let tokens = info.to_token_stream().to_string().replace(' ', "");
+        assert_eq!(
+            tokens.matches("vk::DescriptorImageInfo::default()").count(),
+            2
+        );
+        assert!(tokens.contains(".sampler(shadow_maps[1usize].sampler)"));
+    }
+
+    #[test]
+    fn storage_buffers() {
+        let code = r#"
+            struct InstanceData {
+                float4x4 model;
+                float4 color;
+            };
+
+            [vk::binding(0, 0)]
+            StructuredBuffer<InstanceData> instances;
+
+            [vk::binding(1, 0)]
+            RWStructuredBuffer<uint> counters;
+
+            [vk::binding(2, 0)]
+            ByteAddressBuffer words;
+
+            [shader("vertex")]
+            float4 main(float3 pos, uint id : SV_InstanceID) : SV_Position {
+                InterlockedAdd(counters[0], words.Load(0));
+                return mul(instances[id].model, float4(pos, 1.0));
+            }
+        "#;
+
+        let slang = Slang::new();
+        let vert = slang.from_source("test", code);
+        let shader = Shader::from(vert);
+
+        for binding in shader.get_set_layout_bindings(0) {
+            let layout_tokens = binding.to_token_stream().to_string().replace(' ', "");
+            assert!(layout_tokens.contains("descriptor_type(vk::DescriptorType::STORAGE_BUFFER)"));
+        }
+
+        let mut methods = vec![BindMethod::default()];
+        shader.get_bind_methods(&mut methods);
+        let method_tokens = methods[0].to_token_stream().to_string().replace(' ', "");
+        assert!(method_tokens.contains("instances:&RenderBuffer"));
+        assert!(method_tokens.contains(".range(vk::WHOLE_SIZE).buffer(instances.buffer)"));
+    }
+
+    #[test]
+    fn storage_buffer_infos() {
+        let mut info = WriteSetInfo {
+            name: "instances".into(),
+            ty: ParamType::StorageBuffer(80),
+            optional: false,
+            count: 1,
+            bindless: false,
+            immutable: false,
+            buffer_offset: false,
+            raw: false,
+            image_layout: None,
+        };
+        let tokens = info.to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(".range(vk::WHOLE_SIZE).buffer(instances.buffer)"));
+        assert_eq!(
+            DescriptorType::from(info.ty).to_token_stream().to_string(),
+            "vk :: DescriptorType :: STORAGE_BUFFER"
+        );
+
+        info.count = 2;
+        let tokens = info.to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(".buffer(instances[1usize].buffer)"));
+    }
+
+    #[test]
+    fn storage_image() {
+        let code = r#"
+            [vk::binding(0, 0)]
+            RWTexture2D<float4> output;
+
+            [shader("fragment")]
+            float4 main(float4 pos : SV_Position) : SV_Target {
+                output[uint2(pos.xy)] = float4(1.0);
+                return float4(0.0);
+            }
+        "#;
+
+        let slang = Slang::new();
+        let frag = slang.from_source("test", code);
+        let shader = Shader::from(frag);
+
+        let bindings = shader.get_set_layout_bindings(0);
+        assert_eq!(bindings.len(), 1);
+        let layout_tokens = bindings[0].to_token_stream().to_string().replace(' ', "");
+        assert!(layout_tokens.contains("descriptor_type(vk::DescriptorType::STORAGE_IMAGE)"));
+
+        let mut methods = vec![BindMethod::default()];
+        shader.get_bind_methods(&mut methods);
+        let method_tokens = methods[0].to_token_stream().to_string().replace(' ', "");
+        assert!(method_tokens.contains("output:&RenderTexture"));
+        assert!(
+            method_tokens
+                .contains(".image_layout(vk::ImageLayout::GENERAL).image_view(output.view)")
+        );
+    }
+
+    #[test]
+    fn storage_image_infos() {
+        let mut info = WriteSetInfo {
+            name: "output".into(),
+            ty: ParamType::StorageImage,
+            optional: false,
+            count: 1,
+            bindless: false,
+            immutable: false,
+            buffer_offset: false,
+            raw: false,
+            image_layout: None,
+        };
+        let tokens = info.to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(".image_layout(vk::ImageLayout::GENERAL).image_view(output.view)"));
+        assert!(!tokens.contains("sampler"));
+
+        info.optional = true;
+        let tokens = info.to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains("self.get_default_texture().0"));
+    }
+
+    #[test]
+    fn comparison_sampler() {
+        let code = r#"
+            [vk::binding(0, 0)]
+            Texture2D shadow_map;
+
+            [vk::binding(1, 0)]
+            SamplerComparisonState shadow_sampler;
+
+            [shader("fragment")]
+            float4 main(float3 coord) : SV_Target {
+                return float4(shadow_map.SampleCmp(shadow_sampler, coord.xy, coord.z));
+            }
+        "#;
+
+        let slang = Slang::new();
+        let frag = slang.from_source("test", code);
+        let shader = Shader::from(frag);
+
+        // Both bindings declared by the SPIR-V are in the set layout
+        let bindings = shader.get_set_layout_bindings(0);
+        assert_eq!(bindings.len(), 2);
+        let layout_tokens = bindings[1].to_token_stream().to_string().replace(' ', "");
+        assert!(layout_tokens.contains("descriptor_type(vk::DescriptorType::SAMPLER)"));
+
+        let mut methods = vec![BindMethod::default()];
+        shader.get_bind_methods(&mut methods);
+        let method_tokens = methods[0].to_token_stream().to_string().replace(' ', "");
+        assert!(method_tokens.contains("shadow_sampler:&vk::Sampler"));
+    }
+
+    #[test]
+    fn comparison_sampler_infos() {
+        let mut info = WriteSetInfo {
+            name: "shadow_sampler".into(),
+            ty: ParamType::Sampler,
+            optional: false,
+            count: 1,
+            bindless: false,
+            immutable: false,
+            buffer_offset: false,
+            raw: false,
+            image_layout: None,
+        };
+        let tokens = info.to_token_stream().to_string().replace(' ', "");
+        assert_eq!(
+            tokens,
+            "[vk::DescriptorImageInfo::default().sampler(*shadow_sampler),]"
+        );
+
+        info.count = 2;
+        let tokens = info.to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(".sampler(*shadow_sampler[0usize]),"));
+        assert!(tokens.contains(".sampler(*shadow_sampler[1usize]),"));
+
+        let param = MethodParam {
+            name: "shadow_sampler".into(),
+            ty: ParamType::Sampler,
+            optional: false,
+            count: 1,
+            bindless: false,
+            immutable: false,
+            dynamic: false,
+            buffer_offset: false,
+            raw: false,
+        };
+        let tokens = param.to_token_stream().to_string().replace(' ', "");
+        assert_eq!(tokens, "shadow_sampler:&vk::Sampler");
+    }
+
+    #[test]
+    fn ray_query_acceleration_structures() {
+        let code = r#"
+            [vk::binding(0, 0)]
+            RaytracingAccelerationStructure tlas;
+
+            [shader("fragment")]
+            float4 main(float3 position) : SV_Target {
+                RayDesc ray;
+                ray.Origin = position;
+                ray.Direction = float3(0.0, 1.0, 0.0);
+                ray.TMin = 0.001;
+                ray.TMax = 1000.0;
+                RayQuery<RAY_FLAG_ACCEPT_FIRST_HIT_AND_END_SEARCH> query;
+                query.TraceRayInline(tlas, 0, 0xFF, ray);
+                query.Proceed();
+                float shadow = query.CommittedStatus() == COMMITTED_NOTHING ? 1.0 : 0.0;
+                return float4(shadow, shadow, shadow, 1.0);
+            }
+        "#;
+
+        let slang = Slang::new();
+        let shader = Shader::from(slang.from_source("test", code));
+        assert_eq!(
+            shader.uniforms[0].param.ty,
+            ParamType::AccelerationStructure
+        );
+
+        let bindings = shader.get_set_layout_bindings(0);
+        let layout_tokens = bindings[0].to_token_stream().to_string().replace(' ', "");
+        assert!(layout_tokens.contains("vk::DescriptorType::ACCELERATION_STRUCTURE_KHR"));
+        assert!(layout_tokens.contains("vk::ShaderStageFlags::FRAGMENT"));
+
+        let mut methods = vec![BindMethod::default()];
+        shader.get_bind_methods(&mut methods);
+        let method_tokens = methods[0].to_token_stream().to_string().replace(' ', "");
+        assert!(method_tokens.contains("command_buffer:&CommandBuffer"));
+        assert!(method_tokens.contains("tlas:&vk::AccelerationStructureKHR"));
+        assert!(method_tokens.contains(
+            ".descriptor_count(1u32).push_next(&mutvk::WriteDescriptorSetAccelerationStructureKHR::default()"
+        ));
+        assert!(method_tokens.contains(".acceleration_structures(std::slice::from_ref(tlas))"));
+        assert!(!method_tokens.contains("bind_tlas_with_layout"));
+    }
+
+    #[test]
+    fn texel_buffers() {
+        let code = r#"
+            [vk::binding(0, 0)]
+            Buffer<float4> palette;
+
+            [vk::binding(1, 0)]
+            RWBuffer<uint> visible_count;
+
+            [shader("fragment")]
+            float4 main(uint index) : SV_Target {
+                InterlockedAdd(visible_count[0], 1);
+                return palette[index];
+            }
+        "#;
+
+        let slang = Slang::new();
+        let shader = Shader::from(slang.from_source("test", code));
+        assert_eq!(shader.uniforms[0].param.ty, ParamType::UniformTexelBuffer);
+        assert_eq!(shader.uniforms[1].param.ty, ParamType::StorageTexelBuffer);
+
+        let bindings = shader.get_set_layout_bindings(0);
+        let layout_tokens = bindings[0].to_token_stream().to_string().replace(' ', "");
+        assert!(
+            layout_tokens.contains("descriptor_type(vk::DescriptorType::UNIFORM_TEXEL_BUFFER)")
+        );
+        let layout_tokens = bindings[1].to_token_stream().to_string().replace(' ', "");
+        assert!(
+            layout_tokens.contains("descriptor_type(vk::DescriptorType::STORAGE_TEXEL_BUFFER)")
+        );
+
+        let mut methods = vec![BindMethod::default()];
+        shader.get_bind_methods(&mut methods);
+        let method_tokens = methods[0].to_token_stream().to_string().replace(' ', "");
+        assert!(method_tokens.contains("palette:&vk::BufferView"));
+        assert!(method_tokens.contains("visible_count:&vk::BufferView"));
+        assert!(method_tokens.contains(".texel_buffer_view(&[*palette,])"));
+        assert!(method_tokens.contains(".texel_buffer_view(&[*visible_count,])"));
+    }
+
+    #[test]
+    fn cube_textures() {
+        let code = r#"
+            [vk::binding(0, 0)]
+            SamplerCube environment;
+
+            [vk::binding(1, 0)]
+            SamplerCube probes[4];
+
+            [vk::binding(2, 0)]
+            SamplerCubeArray irradiance;
+
+            [shader("fragment")]
+            float4 main(float3 dir) : SV_Target {
+                return environment.Sample(dir) + probes[3].Sample(dir)
+                    + irradiance.Sample(float4(dir, 0.0));
+            }
+        "#;
+
+        let slang = Slang::new();
+        let frag = slang.from_source("test", code);
+        let shader = Shader::from(frag);
+        assert_eq!(shader.uniforms[0].shape, Some(TextureShape::Cube));
+        assert_eq!(shader.uniforms[2].shape, Some(TextureShape::CubeArray));
+
+        let bindings = shader.get_set_layout_bindings(0);
+        let layout_tokens = bindings[1].to_token_stream().to_string().replace(' ', "");
+        assert!(layout_tokens.contains("descriptor_count(4u32)"));
+
+        let mut methods = vec![BindMethod::default()];
+        shader.get_bind_methods(&mut methods);
+        let method_tokens = methods[0].to_token_stream().to_string().replace(' ', "");
+        assert!(method_tokens.contains("texture.view_type==vk::ImageViewType::CUBE,"));
+        assert!(method_tokens.contains("fortextureinprobes.iter()"));
+        assert!(method_tokens.contains("texture.view_type==vk::ImageViewType::CUBE_ARRAY,"));
+    }
+
+    #[test]
+    fn immutable_samplers() {
+        let code = r#"
+            [vk::binding(0, 0)]
+            Sampler2D albedo;
+
+            [vk::binding(1, 0)]
+            Sampler2D shadow;
+
+            [shader("fragment")]
+            float4 main(float2 uv) : SV_Target {
+                return albedo.Sample(uv) * shadow.Sample(uv);
+            }
+        "#;
+
+        let slang = Slang::new();
+        let pipeline = Pipeline::builder()
+            .name("Mesh")
+            .shader(Shader::from(slang.from_source("frag", code)))
+            .immutable_samplers(vec!["albedo".into()])
+            .build();
+        let set_layouts = pipeline.get_set_layouts();
+        assert!(set_layouts[0].bindings[0].immutable);
+        assert!(!set_layouts[0].bindings[1].immutable);
+
+        let tokens = pipeline.to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains("pubstructPipelineMeshSamplers{pubalbedo:vk::Sampler,}"));
+        assert!(tokens.contains("pass:&Pass,samplers:&PipelineMeshSamplers,)->Self"));
+        assert!(tokens.contains("Self::new_set_layouts(&pass.device,samplers,)"));
+        assert!(tokens.contains(".filter(|binding|binding.binding==0u32)"));
+        assert!(tokens.contains(".immutable_samplers(std::slice::from_ref(&samplers.albedo))"));
+        assert!(tokens.contains("albedo:&vk::ImageView,shadow:&RenderTexture"));
+        assert!(tokens.contains(".image_layout(layout).image_view(*albedo)]"));
+        assert!(tokens.contains(".image_view(shadow.view).sampler(shadow.sampler)"));
+
+        // Optional textures fall back to the sampler of the default texture
+        let frag = Shader::from(slang.from_source("frag", code));
+        let result = std::panic::catch_unwind(|| {
+            Pipeline::builder()
+                .name("Mesh")
+                .shader(frag)
+                .optional(vec!["albedo".into()])
+                .immutable_samplers(vec!["albedo".into()])
+                .build()
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn bindless_textures() {
+        let code = r#"
+            [vk::binding(0, 0)]
+            Sampler2D textures[];
+
+            [shader("fragment")]
+            float4 main(float2 uv, uint index) : SV_Target {
+                return textures[NonUniformResourceIndex(index)].Sample(uv);
+            }
+        "#;
+
+        let slang = Slang::new();
+        let pipeline = Pipeline::builder()
+            .name("Material")
+            .shader(Shader::from(slang.from_source("frag", code)))
+            .bindless_count(Some(4096))
+            .build();
+        let set_layouts = pipeline.get_set_layouts();
+        assert_eq!(set_layouts[0].bindings[0].count, 4096);
+        assert!(set_layouts[0].bindings[0].bindless);
+
+        let tokens = pipeline.to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains("descriptor_count(4096u32)"));
+        assert!(tokens.contains(
+            "vk::DescriptorBindingFlags::PARTIALLY_BOUND|vk::DescriptorBindingFlags::UPDATE_AFTER_BIND"
+        ));
+        assert!(tokens.contains("vk::DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND_POOL"));
+        assert!(tokens.contains("textures:&[&RenderTexture],textures_offset:u32"));
+        assert!(tokens.contains(".dst_array_element(textures_offset)"));
+    }
+
+    #[test]
+    #[should_panic(expected = "needs a `bindless_count`")]
+    fn bindless_textures_without_count() {
+        let code = r#"
+            [vk::binding(0, 0)]
+            Sampler2D textures[];
+
+            [shader("fragment")]
+            float4 main(float2 uv, uint index) : SV_Target {
+                return textures[NonUniformResourceIndex(index)].Sample(uv);
+            }
+        "#;
+
+        let slang = Slang::new();
+        Pipeline::builder()
+            .name("Material")
+            .shader(Shader::from(slang.from_source("frag", code)))
+            .build();
+    }
+
+    #[test]
+    fn texture_arrays_and_volumes() {
+        let code = r#"
+            [vk::binding(0, 0)]
+            Sampler3D lut;
+
+            [vk::binding(1, 0)]
+            Sampler2DArray cascades[2];
+
+            [shader("fragment")]
+            float4 main(float3 uvw) : SV_Target {
+                return lut.Sample(uvw) + cascades[1].Sample(uvw);
+            }
+        "#;
+
+        let slang = Slang::new();
+        let pipeline = Pipeline::builder()
+            .name("Lighting")
+            .shader(Shader::from(slang.from_source("frag", code)))
+            .build();
+        let shader = &pipeline.shaders[0];
+        assert_eq!(shader.uniforms[0].shape, Some(TextureShape::Texture3D));
+        assert_eq!(shader.uniforms[1].shape, Some(TextureShape::Texture2DArray));
+
+        let bindings = shader.get_set_layout_bindings(0);
+        let layout_tokens = bindings[1].to_token_stream().to_string().replace(' ', "");
+        assert!(layout_tokens.contains("descriptor_count(2u32)"));
+
+        let enum_tokens = pipeline.get_uniform_items().to_string();
+        assert!(enum_tokens.contains("`lut` at set 0, binding 0, a 3D texture"));
+        assert!(
+            enum_tokens
+                .contains("`cascades` at set 0, binding 1, a 2D array texture, with 2 descriptors")
+        );
+    }
+
+    #[test]
+    fn uniform_infos() {
+        let mut environment = Uniform::new(
+            Param::new("environment".into(), ParamType::SampledImage),
+            0,
+            1,
+            0,
+        );
+        environment.shape = Some(TextureShape::Cube);
+        environment.optional = true;
+        let mut cascades = Uniform::new(
+            Param::new("cascades".into(), ParamType::SampledImage),
+            0,
+            2,
+            0,
+        );
+        cascades.shape = Some(TextureShape::Texture2DArray);
+        let mut frag = Shader::new(
+            ShaderType::Fragment,
+            "frag".into(),
+            vec![],
+            vec![
+                Uniform::new(Param::new("color".into(), ParamType::Vec4), 0, 0, 0),
+                environment.clone(),
+                cascades,
+            ],
+            vec![],
+        );
+        frag.code = Some(vec![0x03, 0x02, 0x23, 0x07]);
+
+        let pipeline = Pipeline {
+            name: "Sky".into(),
+            shaders: vec![frag],
+            ..Default::default()
+        };
+        let ident = Ident::new("PipelineSky", Span::call_site());
+        let (desc_items, fns) = pipeline.get_set_layout_binding_items(&ident);
+        assert!(
+            desc_items
+                .to_string()
+                .contains("pub struct PipelineSkyUniformInfo")
+        );
+        let tokens = fns.to_string().replace(' ', "");
+        assert!(tokens.contains("pubfnget_uniform_infos()->&'static[PipelineSkyUniformInfo]"));
+        assert!(tokens.contains("name:\"color\",set:0u32,binding:0u32,count:1u32,view_type:None"));
+        assert!(tokens.contains("view_type:Some(vk::ImageViewType::CUBE)"));
+        assert!(tokens.contains("view_type:Some(vk::ImageViewType::TYPE_2D_ARRAY)"));
+
+        let enum_tokens = pipeline.get_uniform_items().to_string();
+        assert!(enum_tokens.contains("`cascades` at set 0, binding 2, a 2D array texture"));
+
+        let method = BindMethod {
+            uniforms: vec![environment],
+            ..Default::default()
+        };
+        let tokens = method.get_view_type_checks().to_string().replace(' ', "");
+        assert!(tokens.starts_with("ifletSome(texture)=environment{debug_assert!("));
+    }
+
+    #[test]
+    fn shared_uniforms() {
+        let view_proj = Uniform::new(Param::new("view_proj".into(), ParamType::Mat4), 1, 0, 0);
+        let code = vec![0x03, 0x02, 0x23, 0x07];
+        let mut vert = Shader::new(
+            ShaderType::Vertex,
+            "vert".into(),
+            vec![],
+            vec![view_proj.clone()],
+            vec![],
+        );
+        vert.code = Some(code.clone());
+        let mut frag = Shader::new(
+            ShaderType::Fragment,
+            "frag".into(),
+            vec![],
+            vec![view_proj],
+            vec![],
+        );
+        frag.code = Some(code);
+
+        let pipeline = Pipeline::builder()
+            .name("Main")
+            .shader(vert)
+            .shader(frag)
+            .build();
+        let bindings = pipeline.get_set_layout_bindings(1);
+        assert_eq!(bindings.len(), 1);
+        assert_eq!(
+            bindings[0].stages,
+            [ShaderType::Vertex, ShaderType::Fragment]
+        );
+        let tokens = bindings[0].to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(
+            ".stage_flags(vk::ShaderStageFlags::from_raw(vk::ShaderStageFlags::VERTEX.as_raw()|vk::ShaderStageFlags::FRAGMENT.as_raw()))"
+        ));
+
+        let methods = pipeline.get_bind_methods();
+        assert_eq!(methods.len(), 1);
+        assert_eq!(methods[0].uniforms.len(), 1);
+
+        let ident = Ident::new("PipelineMain", Span::call_site());
+        let (_, fns) = pipeline.get_set_layout_binding_items(&ident);
+        let tokens = fns.to_string().replace(' ', "");
+        assert_eq!(tokens.matches("binding:0u32").count(), 2);
+    }
+
+    #[test]
+    fn uniform_structs() {
+        let field = |name: &str, ty, count, offset, stride| StructField {
+            name: name.into(),
+            ty,
+            count,
+            offset,
+            stride,
+        };
+        let mut material = Uniform::new(
+            Param::new("material".into(), ParamType::Struct(112)),
+            0,
+            0,
+            0,
+        );
+        material.fields = vec![
+            field("base", ParamType::Vec3, 1, 0, 12),
+            field("roughness", ParamType::Float, 1, 12, 4),
+            field("tint", ParamType::Half2, 1, 16, 4),
+            field("weights", ParamType::Float, 2, 32, 16),
+            field("transform", ParamType::Mat3, 1, 64, 48),
+        ];
+        let mut frag = Shader::new(
+            ShaderType::Fragment,
+            "frag".into(),
+            vec![],
+            vec![material.clone()],
+            vec![],
+        );
+        frag.code = Some(vec![0x03, 0x02, 0x23, 0x07]);
+
+        let pipeline = Pipeline {
+            name: "Main".into(),
+            shaders: vec![frag],
+            ..Default::default()
+        };
+        let tokens = pipeline.get_uniform_items().to_string().replace(' ', "");
+        assert!(tokens.contains(
+            "pubstructPipelineMainMaterial{pubbase:[f32;3],pubroughness:f32,pubtint:[u16;2],"
+        ));
+        assert!(tokens.contains("_pad3:[u8;12usize],pubweights:[[f32;4usize];2usize],"));
+        assert!(tokens.contains("pubtransform:[[f32;4];3],}"));
+        assert!(
+            tokens.contains(
+                "const_:()=assert!(std::mem::size_of::<PipelineMainMaterial>()==112usize);"
+            )
+        );
+
+        let method = BindMethod {
+            pipeline: "PipelineMain".into(),
+            uniforms: vec![material],
+            ..Default::default()
+        };
+        let tokens = method.get_docs().to_string();
+        assert!(tokens.contains("Binds set 0 for `key` on `command_buffer`"));
+        assert!(tokens.contains(
+            "- Binding 0, `material`: `vk::DescriptorType::UNIFORM_BUFFER` from `material:&RenderBuffer`"
+        ));
+        assert!(tokens.contains("The buffer of `material` holds a [`PipelineMainMaterial`]"));
+    }
+
+    #[test]
+    fn generated_docs() {
+        let push = PushMethod::new("tint".into(), ParamType::Vec4, ShaderType::Fragment);
+        let tokens = push.to_token_stream().to_string();
+        assert!(tokens.contains(
+            "Pushes `tint` to the Fragment stage, 16 bytes at offset 0 of the push constant block"
+        ));
+
+        let frag = Shader::new(ShaderType::Fragment, "frag".into(), vec![], vec![], vec![]);
+        let pipeline = Pipeline::builder().name("Main").shader(frag).build();
+        let tokens = pipeline.get_shaders_doc().to_string();
+        assert!(tokens.contains("- Fragment: `frag`"));
+    }
+
+    #[test]
+    fn bind_with_layout() {
+        let code = r#"
+            [vk::binding(0)]
+            ConstantBuffer<float4> color;
+
+            [vk::binding(1)]
+            Sampler2D albedo;
+
+            [shader("fragment")]
+            float4 main(float2 uv) : SV_Target {
+                return color * albedo.Sample(uv);
+            }
+        "#;
+
+        let slang = Slang::new();
+        let frag = slang.from_source("test", code);
+        let shader = Shader::from(frag);
+
+        let mut methods = vec![BindMethod::default()];
+        shader.get_bind_methods(&mut methods);
+        let method_tokens = methods[0].to_token_stream().to_string().replace(' ', "");
+        assert!(method_tokens.contains("pubfnbind_color_and_albedo("));
+        assert!(method_tokens.contains("pubfnbind_color_and_albedo_with_layout("));
+        assert!(method_tokens.contains("layout:vk::ImageLayout,"));
+        assert!(method_tokens.contains("Self::derive_key(key,layout.as_raw())"));
+        assert!(method_tokens.contains(".image_layout(layout)"));
+    }
+
+    #[test]
+    fn image_layouts() {
+        let uniforms = vec![
+            Uniform::new(Param::new("depth".into(), ParamType::SampledImage), 0, 0, 0),
+            Uniform::new(
+                Param::new("albedo".into(), ParamType::SampledImage),
+                0,
+                1,
+                0,
+            ),
+            Uniform::new(
+                Param::new("history".into(), ParamType::StorageImage),
+                0,
+                2,
+                0,
+            ),
+        ];
+        let frag = Shader::new(
+            ShaderType::Fragment,
+            "frag".into(),
+            vec![],
+            uniforms,
+            vec![],
+        );
+        let pipeline = Pipeline::builder()
+            .name("Main")
+            .shader(frag)
+            .image_layouts(vec![
+                ("depth".into(), "DEPTH_STENCIL_READ_ONLY_OPTIMAL".into()),
+                ("history".into(), "SHARED_PRESENT_KHR".into()),
+            ])
+            .build();
+
+        // Configured images ignore the layout given to the bind method
+        let methods = pipeline.get_bind_methods();
+        let tokens = methods[0].to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(
+            ".image_layout(vk::ImageLayout::DEPTH_STENCIL_READ_ONLY_OPTIMAL).image_view(depth.view)"
+        ));
+        assert!(tokens.contains(".image_layout(layout).image_view(albedo.view)"));
+        assert!(tokens.contains(
+            ".image_layout(vk::ImageLayout::SHARED_PRESENT_KHR).image_view(history.view)"
+        ));
+        assert!(tokens.contains("`depth`iswritteninthe`DEPTH_STENCIL_READ_ONLY_OPTIMAL`layout"));
+
+        // Only images have a layout
+        let result = std::panic::catch_unwind(move || {
+            let frag = Shader::new(
+                ShaderType::Fragment,
+                "frag".into(),
+                vec![],
+                vec![Uniform::new(
+                    Param::new("model".into(), ParamType::Mat4),
+                    0,
+                    0,
+                    0,
+                )],
+                vec![],
+            );
+            Pipeline::builder()
+                .name("Main")
+                .shader(frag)
+                .image_layouts(vec![("model".into(), "GENERAL".into())])
+                .build()
+        });
+        let message = *result.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.ends_with("Image layout uniform `model` is not an image"));
+    }
+
+    #[test]
+    fn uniform_enum() {
+        let vert_code = r#"
+            [vk::binding(0, 0)]
+            ConstantBuffer<float4x4> view_proj;
+
+            [shader("vertex")]
+            float4 main(float3 pos) : SV_Position {
+                return mul(view_proj, float4(pos, 1.0));
+            }
+        "#;
+        let frag_code = r#"
+            [vk::binding(0, 1)]
+            Sampler2D albedo;
+
+            [shader("fragment")]
+            float4 main(float2 uv) : SV_Target {
+                return albedo.Sample(uv);
+            }
+        "#;
+
+        let slang = Slang::new();
+        let pipeline = Pipeline::builder()
+            .name("Main")
+            .shader(Shader::from(slang.from_source("vert", vert_code)))
+            .shader(Shader::from(slang.from_source("frag", frag_code)))
+            .build();
+
+        let enum_tokens = pipeline.get_uniform_items().to_string().replace(' ', "");
+        assert!(
+            enum_tokens.contains(
+                "pubenumPipelineMainUniform{#[doc=\"`view_proj`atset0,binding0\"]ViewProj,"
+            )
+        );
+        assert!(enum_tokens.contains("#[doc=\"`albedo`atset1,binding0,a2Dtexture\"]Albedo,}"));
+        assert!(enum_tokens.contains("Self::ViewProj=>\"view_proj\""));
+        assert!(enum_tokens.contains("Self::Albedo=>1u32"));
+        assert!(enum_tokens.contains("Self::Albedo=>vk::DescriptorType::COMBINED_IMAGE_SAMPLER"));
+
+        let methods = pipeline.get_bind_methods();
+        let method_tokens = methods[1].to_token_stream().to_string().replace(' ', "");
+        assert!(method_tokens.contains("Self::derive_key(key,[PipelineMainUniform::Albedo,])"));
+    }
+
+    #[test]
+    fn mock_methods() {
+        let code = r#"
+            [vk::binding(0, 2)]
+            Sampler2D albedo;
+
+            [shader("fragment")]
+            float4 main(float2 uv) : SV_Target {
+                return albedo.Sample(uv);
+            }
+        "#;
+
+        let slang = Slang::new();
+        let frag = slang.from_source("test", code);
+        let shader = Shader::from(frag);
+
+        let mut methods = vec![BindMethod::default(); 3];
+        shader.get_bind_methods(&mut methods);
+        let method_tokens = Mock(&methods[2])
+            .to_token_stream()
+            .to_string()
+            .replace(' ', "");
+        assert!(method_tokens.contains("self.record_bind(\"bind_set2\",2u32)"));
+        assert!(method_tokens.contains("self.record_bind(\"bind_set2_with_layout\",2u32)"));
+        assert!(!method_tokens.contains("update_descriptor_sets"));
+
+        let push = PushMethod::new("tint".into(), ParamType::Vec4, ShaderType::Fragment);
+        let push_tokens = Mock(&push).to_token_stream().to_string().replace(' ', "");
+        assert!(
+            push_tokens
+                .contains("self.record_push(\"push_tint\",vk::ShaderStageFlags::FRAGMENT,0u32,")
+        );
+        assert!(!push_tokens.contains("command_buffer.push_constants"));
+    }
+
+    #[test]
+    fn update_methods() {
+        let model = Uniform::new(Param::new("model".into(), ParamType::Mat4), 0, 0, 0);
+        let method = BindMethod {
+            pipeline: "PipelineMain".into(),
+            uniforms: vec![model],
+            ..Default::default()
+        };
+
+        // A reallocated buffer is written even when the set of the key is cached
+        let tokens = method.to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(
+            "pubfnupdate_set0(&self,descriptors:&mutDescriptors,key:DescriptorKey,model:&RenderBuffer,)"
+        ));
+        assert!(tokens.contains(
+            "letsets=matchdescriptors.get_or_create(key,set_layouts){\
+            DescriptorEntry::Created(sets)|DescriptorEntry::Get(sets)=>sets,};\
+            unsafe{self.device.update_descriptor_sets(&["
+        ));
+        assert!(tokens.contains(".buffer(model.buffer)"));
+        assert!(tokens.contains("sothat`bind_set0`bindsthenewones"));
+
+        let tokens = Mock(&method).to_token_stream().to_string().replace(' ', "");
+        assert!(
+            tokens.contains(
+                "let_=(descriptors,key,model,);self.record_update(\"update_set0\",0u32);"
+            )
+        );
+
+        // Push descriptors are written at every bind
+        let method = BindMethod {
+            push: true,
+            ..method
+        };
+        let tokens = method.to_token_stream().to_string().replace(' ', "");
+        assert!(!tokens.contains("update_"));
+    }
+
+    #[test]
+    fn bind_returns_set() {
+        let model = Uniform::new(Param::new("model".into(), ParamType::Mat4), 0, 0, 0);
+        let method = BindMethod {
+            pipeline: "PipelineMain".into(),
+            uniforms: vec![model],
+            ..Default::default()
+        };
+
+        // The bound set is returned to be named or cached by the caller
+        let tokens = method.to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(
+            "pubfnbind_set0(&self,command_buffer:&CommandBuffer,descriptors:&mutDescriptors,\
+            key:DescriptorKey,model:&RenderBuffer,)->vk::DescriptorSet{"
+        ));
+        assert!(
+            tokens.contains(
+                "command_buffer.bind_descriptor_sets(self.get_layout(),sets,0u32);sets[0]}"
+            )
+        );
+
+        let tokens = Mock(&method).to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains("self.record_bind(\"bind_set0\",0u32);vk::DescriptorSet::null()}"));
+
+        // Pushed sets have no handle to return
+        let method = BindMethod {
+            push: true,
+            ..method
+        };
+        let tokens = method.to_token_stream().to_string().replace(' ', "");
+        assert!(!tokens.contains("->vk::DescriptorSet"));
+    }
+
+    #[test]
+    fn raw_handles() {
+        let model = Uniform::new(Param::new("model".into(), ParamType::Mat4), 0, 0, 0);
+        let mut albedo = Uniform::new(
+            Param::new("albedo".into(), ParamType::SampledImage),
+            0,
+            1,
+            0,
+        );
+        albedo.shape = Some(TextureShape::Texture2D);
+        let mut shadows = Uniform::new(
+            Param::new("shadows".into(), ParamType::SampledImage),
+            0,
+            2,
+            0,
+        );
+        shadows.array_count = 2;
+        let method = BindMethod {
+            pipeline: "PipelineMain".into(),
+            uniforms: vec![model, albedo, shadows],
+            ..Default::default()
+        };
+
+        let tokens = method.to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(
+            "pubfnbind_set0_raw(&self,command_buffer:&CommandBuffer,descriptors:&mutDescriptors,\
+            key:DescriptorKey,model:vk::Buffer,albedo:(vk::ImageView,vk::Sampler),\
+            shadows:[(vk::ImageView,vk::Sampler);2usize],layout:vk::ImageLayout,)->vk::DescriptorSet{"
+        ));
+        // Wrappers are checked, then bound through their handles
+        assert!(tokens.contains(
+            "self.bind_set0_raw(command_buffer,descriptors,key,model.buffer,(albedo.view,albedo.sampler),\
+            shadows.map(|element|(element.view,element.sampler)),layout,)"
+        ));
+        let raw_tokens = tokens.split("pubfnbind_set0_raw(").nth(1).unwrap();
+        assert!(!raw_tokens.contains("view_type"));
+        assert!(raw_tokens.contains(".buffer(model)"));
+        assert!(raw_tokens.contains(".image_view(albedo.0).sampler(albedo.1)"));
+        assert!(raw_tokens.contains(".image_view(shadows[1usize].0).sampler(shadows[1usize].1)"));
+
+        let tokens = Mock(&method).to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(
+            "let_=(command_buffer,descriptors,key,model,albedo,shadows,layout,);\
+            self.record_bind(\"bind_set0_raw\",0u32);"
+        ));
+    }
+
+    #[test]
+    fn sparse_sets() {
+        let vert_code = r#"
+            [shader("vertex")]
+            float4 main(float3 pos) : SV_Position {
+                return float4(pos, 1.0);
+            }
+        "#;
+        let frag_code = r#"
+            [vk::binding(0, 2)]
+            Sampler2D albedo;
+
+            [shader("fragment")]
+            float4 main(float2 uv) : SV_Target {
+                return albedo.Sample(uv);
+            }
+        "#;
+
+        let slang = Slang::new();
+        let mut pipeline = Pipeline::builder()
+            .name("Main")
+            .shader(Shader::from(slang.from_source("vert", vert_code)))
+            .shader(Shader::from(slang.from_source("frag", frag_code)))
+            .build();
+        for shader in &mut pipeline.shaders {
+            shader.code = Some(vec![0x03, 0x02, 0x23, 0x07]);
+        }
+
+        // Sets 0 and 1 keep empty layouts so that `albedo` is still bound at set 2
+        let set_layouts = pipeline.get_set_layouts();
+        assert_eq!(set_layouts.len(), 3);
+        assert!(set_layouts[0].bindings.is_empty());
+        assert!(set_layouts[1].bindings.is_empty());
+        assert_eq!(set_layouts[2].bindings.len(), 1);
+
+        let methods = pipeline.get_bind_methods();
+        assert_eq!(methods.len(), 1);
+        assert_eq!(methods[0].uniforms[0].set, 2);
+
+        let tokens = pipeline.to_token_stream().to_string().replace(' ', "");
+        assert!(
+            tokens.contains("vec![vec![],vec![],vec![vk::DescriptorSetLayoutBinding::default()")
+        );
+        assert!(tokens.contains("=&[&[],&[],&[PipelineMainSetLayoutBindingDesc{binding:0u32,"));
+        assert!(tokens.contains("pubfnbind_albedo("));
+    }
+
+    #[test]
+    fn external_sets() {
+        let uniforms = vec![
+            Uniform::new(Param::new("frame".into(), ParamType::Mat4), 0, 0, 0),
+            Uniform::new(
+                Param::new("albedo".into(), ParamType::SampledImage),
+                1,
+                0,
+                0,
+            ),
+        ];
+        let mut frag = Shader::new(
+            ShaderType::Fragment,
+            "frag".into(),
+            vec![],
+            uniforms,
+            vec![],
+        );
+        frag.code = Some(vec![0x03, 0x02, 0x23, 0x07]);
+        let pipeline = Pipeline::builder()
+            .name("Main")
+            .shader(frag.clone())
+            .external_sets(vec![0])
+            .build();
+
+        // The layout of set 0 is kept for the engine to bind it
+        assert_eq!(pipeline.get_set_layouts().len(), 2);
+        assert_eq!(
+            pipeline.get_pool_sizes(),
+            [(DescriptorType::CombinedSampler, 1)]
+        );
+        let methods = pipeline.get_bind_methods();
+        assert_eq!(methods.len(), 1);
+        assert_eq!(methods[0].uniforms[0].set, 1);
+        assert!(pipeline.get_bind_all_method().is_none());
+
+        let tokens = pipeline.to_token_stream().to_string().replace(' ', "");
+        assert!(!tokens.contains("pubfnbind_set0("));
+        assert!(!tokens.contains("PipelineMainSet0"));
+        assert!(tokens.contains("pubfnbind_set1("));
+        assert!(tokens.contains("pubconstSET_FRAME:u32=0u32;"));
+
+        let result = std::panic::catch_unwind(move || {
+            Pipeline::builder()
+                .name("Main")
+                .shader(frag)
+                .external_sets(vec![2])
+                .build()
+        });
+        let message = *result.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.ends_with("External set 2 has no uniforms"));
+    }
+
+    #[test]
+    fn shared_set_layouts() {
+        let vert_code = r#"
+            [vk::binding(0, 0)]
+            ConstantBuffer<float4x4> view_proj;
+
+            [shader("vertex")]
+            float4 main(float3 pos) : SV_Position {
+                return mul(view_proj, float4(pos, 1.0));
+            }
+        "#;
+        let albedo_code = r#"
+            [vk::binding(0, 1)]
+            Sampler2D albedo;
+
+            [shader("fragment")]
+            float4 main(float2 uv) : SV_Target {
+                return albedo.Sample(uv);
+            }
+        "#;
+        let normal_code = r#"
+            [vk::binding(0, 1)]
+            Sampler2D albedo;
+            [vk::binding(1, 1)]
+            Sampler2D normal;
+
+            [shader("fragment")]
+            float4 main(float2 uv) : SV_Target {
+                return albedo.Sample(uv) * normal.Sample(uv);
+            }
+        "#;
+
+        let slang = Slang::new();
+        let mut albedo = Pipeline::builder()
+            .name("Albedo")
+            .shader(Shader::from(slang.from_source("vert", vert_code)))
+            .shader(Shader::from(slang.from_source("albedo", albedo_code)))
+            .build();
+        for shader in &mut albedo.shaders {
+            shader.code = Some(vec![0x03, 0x02, 0x23, 0x07]);
+        }
+        let normal = Pipeline::builder()
+            .name("Normal")
+            .shader(Shader::from(slang.from_source("vert", vert_code)))
+            .shader(Shader::from(slang.from_source("normal", normal_code)))
+            .build();
+
+        // The camera set is shared, while the material sets differ
+        let albedo_layouts = albedo.get_set_layouts();
+        let normal_layouts = normal.get_set_layouts();
+        let albedo_hash = albedo_layouts[0].get_hash().unwrap();
+        assert_eq!(Some(albedo_hash), normal_layouts[0].get_hash());
+        assert_ne!(albedo_layouts[1].get_hash(), normal_layouts[1].get_hash());
+
+        let tokens = albedo.to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(&format!(
+            "pubconstSET_LAYOUT_HASHES:&'static[Option<u64>]=&[Some({}u64),",
+            albedo_hash
+        )));
+        assert!(tokens.contains("pubtypePipelineAlbedoLayoutCache=std::sync::Arc<"));
+        assert!(tokens.contains("pubfnnew_cached<V:VertexInput>("));
+        assert!(tokens.contains("pipeline.layout_cache=Some(cache.clone());"));
+        assert!(tokens.contains("entry.1-=1;ifentry.1>0{continue;}cache.remove(hash);"));
+    }
+
+    #[test]
+    fn pool_sizes() {
+        let vert_code = r#"
+            [vk::binding(0, 0)]
+            ConstantBuffer<float4x4> view_proj;
+
+            [shader("vertex")]
+            float4 main(float3 pos) : SV_Position {
+                return mul(view_proj, float4(pos, 1.0));
+            }
+        "#;
+        let frag_code = r#"
+            [vk::binding(1, 0)]
+            ConstantBuffer<float4> tint;
+            [vk::binding(0, 1)]
+            Sampler2D albedo;
+            [vk::binding(1, 1)]
+            Sampler2D shadows[4];
+
+            [shader("fragment")]
+            float4 main(float2 uv) : SV_Target {
+                return tint * albedo.Sample(uv) * shadows[1].Sample(uv);
+            }
+        "#;
+
+        let slang = Slang::new();
+        let pipeline = Pipeline::builder()
+            .name("Main")
+            .shader(Shader::from(slang.from_source("vert", vert_code)))
+            .shader(Shader::from(slang.from_source("frag", frag_code)))
+            .build();
+        assert_eq!(
+            pipeline.get_pool_sizes(),
+            [
+                (DescriptorType::Uniform, 2),
+                (DescriptorType::CombinedSampler, 5)
+            ]
+        );
+
+        let ident = Ident::new("PipelineMain", Span::call_site());
+        let (_, fns) = pipeline.get_set_layout_binding_items(&ident);
+        let tokens = fns.to_string().replace(' ', "");
+        assert!(tokens.contains(
+            "pubfnget_pool_sizes(max_sets:u32)->Vec<vk::DescriptorPoolSize>{let_=max_sets;vec![\
+            vk::DescriptorPoolSize::default().ty(vk::DescriptorType::UNIFORM_BUFFER).descriptor_count(2u32*max_sets),\
+            vk::DescriptorPoolSize::default().ty(vk::DescriptorType::COMBINED_IMAGE_SAMPLER).descriptor_count(5u32*max_sets),]"
+        ));
+    }
+
+    #[test]
+    fn reflection() {
+        let uniforms = ["model", "view", "proj"]
+            .into_iter()
+            .enumerate()
+            .map(|(binding, name)| {
+                Uniform::new(
+                    Param::new(name.into(), ParamType::Mat4),
+                    0,
+                    binding as u32,
+                    0,
+                )
+            })
+            .collect();
+        let vert = Shader::new(
+            ShaderType::Vertex,
+            "vert".into(),
+            vec![],
+            uniforms,
+            vec![Param::new("time".into(), ParamType::Float)],
+        );
+        let pipeline = Pipeline::builder().name("Main").shader(vert).build();
+
+        let ident = Ident::new("PipelineMain", Span::call_site());
+        let (desc_items, fns) = pipeline.get_set_layout_binding_items(&ident);
+        let desc_tokens = desc_items.to_string().replace(' ', "");
+        assert!(desc_tokens.contains(
+            "pubstructPipelineMainReflection{\
+            #[doc=\"Bindingsofeachdescriptorset,indexedbyset\"]\
+            pubset_layout_bindings:&'static[&'static[PipelineMainSetLayoutBindingDesc]],\
+            pubuniforms:&'static[PipelineMainUniformInfo],\
+            pubpush_constants:&'static[PipelineMainPushInfo],}"
+        ));
+
+        let tokens = fns.to_string().replace(' ', "");
+        assert!(tokens.contains(
+            "pubfnget_reflection()->&'staticPipelineMainReflection{\
+            staticREFLECTION:PipelineMainReflection=PipelineMainReflection{\
+            set_layout_bindings:PipelineMain::SET_LAYOUT_BINDINGS,\
+            uniforms:PipelineMain::UNIFORM_INFOS,"
+        ));
+        // The uniforms are listed once, for both `get_uniform_infos` and the reflection
+        assert_eq!(
+            tokens
+                .matches("PipelineMainUniformInfo{name:\"model\"")
+                .count(),
+            1
+        );
+        assert!(tokens.contains("Self::UNIFORM_INFOS}"));
+        for (binding, name) in ["model", "view", "proj"].into_iter().enumerate() {
+            assert!(tokens.contains(&format!(
+                "PipelineMainUniformInfo{{name:\"{}\",set:0u32,binding:{}u32,count:1u32,\
+                view_type:None,descriptor_type:vk::DescriptorType::UNIFORM_BUFFER,size:Some(64usize),}}",
+                name, binding
+            )));
+        }
+        assert!(tokens.contains(
+            "push_constants:&[PipelineMainPushInfo{name:\"time\",offset:0usize,size:4usize,\
+            stage_flags:vk::ShaderStageFlags::VERTEX,},],};&REFLECTION}"
+        ));
+
+        // The bindings of the reflection are the ones of `set_layout_bindings`
+        let bindings = (0..3)
+            .map(|binding| {
+                format!(
+                    "PipelineMainSetLayoutBindingDesc{{binding:{}u32,\
+                    descriptor_type:vk::DescriptorType::UNIFORM_BUFFER,count:1u32,\
+                    stage_flags:vk::ShaderStageFlags::VERTEX,}},",
+                    binding
+                )
+            })
+            .collect::<String>();
+        assert!(tokens.contains(&format!(
+            "constSET_LAYOUT_BINDINGS:&'static[&'static[PipelineMainSetLayoutBindingDesc]]=&[&[{}],];",
+            bindings
+        )));
+        assert!(
+            tokens.contains("Self::SET_LAYOUT_BINDINGS.get(setasusize).copied().unwrap_or(&[])")
+        );
+    }
+
+    #[test]
+    fn index_consts() {
+        let mut vert = Shader::new(
+            ShaderType::Vertex,
+            "vert".into(),
+            vec![],
+            vec![Uniform::new(
+                Param::new("viewProj".into(), ParamType::Mat4),
+                0,
+                0,
+                0,
+            )],
+            vec![Param::new("model".into(), ParamType::Mat4)],
+        );
+        vert.code = Some(vec![0x03, 0x02, 0x23, 0x07]);
+        let mut tint = Param::new("tint".into(), ParamType::Vec4);
+        tint.offset = 64;
+        let mut frag = Shader::new(
+            ShaderType::Fragment,
+            "frag".into(),
+            vec![],
+            vec![
+                Uniform::new(
+                    Param::new("base_color".into(), ParamType::SampledImage),
+                    1,
+                    2,
+                    0,
+                ),
+                Uniform::new(Param::new("scene_depth".into(), ParamType::Image), 1, 3, 2),
+            ],
+            vec![tint],
+        );
+        frag.code = Some(vec![0x03, 0x02, 0x23, 0x07]);
+
+        let pipeline = Pipeline::builder()
+            .name("Main")
+            .shader(vert)
+            .shader(frag)
+            .build();
+        let ident = Ident::new("PipelineMain", Span::call_site());
+        let (_, fns) = pipeline.get_set_layout_binding_items(&ident);
+        let tokens = fns.to_string().replace(' ', "");
+        assert!(tokens.contains("pubconstSET_VIEW_PROJ:u32=0u32;"));
+        assert!(tokens.contains("pubconstBINDING_VIEW_PROJ:u32=0u32;"));
+        assert!(tokens.contains("pubconstSET_BASE_COLOR:u32=1u32;"));
+        assert!(tokens.contains("pubconstBINDING_BASE_COLOR:u32=2u32;"));
+        assert!(tokens.contains("pubconstINPUT_ATTACHMENT_INDEX_SCENE_DEPTH:u32=2u32;"));
+        assert!(!tokens.contains("INPUT_ATTACHMENT_INDEX_BASE_COLOR"));
+        // The block ends with the range of the fragment stage, following the vertex one
+        assert!(tokens.contains("pubconstPUSH_CONSTANT_SIZE:usize=80usize;"));
+        assert!(tokens.contains(
+            "pubfnget_push_ranges()->Vec<vk::PushConstantRange>{vec![\
+            vk::PushConstantRange::default().offset(0usizeasu32)\
+            .stage_flags(vk::ShaderStageFlags::VERTEX).size(64usizeasu32),\
+            vk::PushConstantRange::default().offset(64usizeasu32)\
+            .stage_flags(vk::ShaderStageFlags::FRAGMENT).size(16usizeasu32),]}"
+        ));
+        assert!(tokens.contains("Self::PUSH_CONSTANT_SIZE<=limits.max_push_constants_sizeasusize"));
+    }
+
+    #[test]
+    fn dynamic_uniforms() {
+        let mut vert = Shader::new(
+            ShaderType::Vertex,
+            "vert".into(),
+            vec![],
+            vec![
+                Uniform::new(Param::new("view_proj".into(), ParamType::Mat4), 0, 0, 0),
+                Uniform::new(Param::new("model".into(), ParamType::Mat4), 0, 1, 0),
+            ],
+            vec![],
+        );
+        vert.code = Some(vec![0x03, 0x02, 0x23, 0x07]);
+
+        let pipeline = Pipeline::builder()
+            .name("Main")
+            .shader(vert)
+            .dynamic(vec!["model".into()])
+            .build();
+        let set_layouts = pipeline.get_set_layouts();
+        let tokens = set_layouts[0]
+            .to_token_stream()
+            .to_string()
+            .replace(' ', "");
+        assert!(
+            tokens.contains(".binding(0u32).descriptor_type(vk::DescriptorType::UNIFORM_BUFFER)")
+        );
+        assert!(tokens.contains(
+            ".binding(1u32).descriptor_type(vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC)"
+        ));
+
+        let methods = pipeline.get_bind_methods();
+        let tokens = methods[0].to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(
+            "pubfnbind_view_proj_and_model(&self,command_buffer:vk::CommandBuffer,\
+            descriptors:&mutDescriptors,key:DescriptorKey,view_proj:&RenderBuffer,\
+            model:&RenderBuffer,model_offset:u32,)"
+        ));
+        assert!(tokens.contains(".descriptor_type(vk::DescriptorType::UNIFORM_BUFFER_DYNAMIC)"));
+        assert!(tokens.contains(".range(64usizeasvk::DeviceSize).buffer(model.buffer)"));
+        assert!(tokens.contains(
+            "vk::PipelineBindPoint::GRAPHICS,self.get_layout(),0u32,sets,&[model_offset,],"
+        ));
+
+        let tokens = Mock(&methods[0])
+            .to_token_stream()
+            .to_string()
+            .replace(' ', "");
+        assert!(tokens.contains("command_buffer:vk::CommandBuffer,"));
+        assert!(
+            tokens.contains("let_=(command_buffer,descriptors,key,view_proj,model,model_offset,);")
+        );
+
+        let mut vert = Shader::new(
+            ShaderType::Vertex,
+            "vert".into(),
+            vec![],
+            vec![Uniform::new(
+                Param::new("albedo".into(), ParamType::SampledImage),
+                0,
+                0,
+                0,
+            )],
+            vec![],
+        );
+        vert.code = Some(vec![0x03, 0x02, 0x23, 0x07]);
+        let result = std::panic::catch_unwind(|| {
+            Pipeline::builder()
+                .name("Main")
+                .shader(vert)
+                .dynamic(vec!["albedo".into()])
+                .build()
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn batched_writes() {
+        let vert = Shader::new(
+            ShaderType::Vertex,
+            "vert".into(),
+            vec![],
+            vec![Uniform::new(
+                Param::new("model".into(), ParamType::Mat4),
+                0,
+                0,
+                0,
+            )],
+            vec![],
+        );
+        let frag = Shader::new(
+            ShaderType::Fragment,
+            "frag".into(),
+            vec![],
+            vec![Uniform::new(
+                Param::new("albedo".into(), ParamType::SampledImage),
+                1,
+                0,
+                0,
+            )],
+            vec![],
+        );
+        let pipeline = Pipeline::builder()
+            .name("Main")
+            .shader(vert)
+            .shader(frag)
+            .build();
+
+        // Writes of created sets are gathered instead of performed
+        let methods = pipeline.get_bind_methods();
+        let tokens = methods[1].to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(
+            "pubfncollect_writes_set1(&self,writes:&mutPipelineMainWrites,descriptors:&mutDescriptors,\
+            key:DescriptorKey,albedo:&RenderTexture,)->vk::DescriptorSet{"
+        ));
+        assert!(tokens.contains(
+            "DescriptorEntry::Created(sets)=>{writes.push_image_infos(sets[0],0u32,0,\
+            vk::DescriptorType::COMBINED_IMAGE_SAMPLER,["
+        ));
+        let tokens = methods[0].to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains("writes.push_buffer_infos(sets[0],0u32,0,"));
+
+        let tokens = pipeline.get_uniform_items().to_string().replace(' ', "");
+        assert!(tokens.contains("pubstructPipelineMainWrites{"));
+
+        // All the writes are performed at once, and the sets bound at once
+        let tokens = pipeline.get_batch_methods().to_string().replace(' ', "");
+        assert!(tokens.contains("pubfnflush_writes(&self,writes:&mutPipelineMainWrites)"));
+        assert_eq!(tokens.matches("update_descriptor_sets").count(), 1);
+        assert!(tokens.contains(
+            "pubfnbind_sets(&self,command_buffer:&CommandBuffer,first_set:u32,sets:&[vk::DescriptorSet],)\
+            {command_buffer.bind_descriptor_sets(self.get_layout(),sets,first_set);}"
+        ));
+
+        let tokens = pipeline
+            .get_mock_batch_methods()
+            .to_string()
+            .replace(' ', "");
+        assert!(tokens.contains("self.record_bind(\"bind_sets\",first_set+index);"));
+        let tokens = Mock(&methods[1])
+            .to_token_stream()
+            .to_string()
+            .replace(' ', "");
+        assert!(tokens.contains("self.record_update(\"collect_writes_set1\",1u32);"));
+    }
+
+    #[test]
+    fn set_traits() {
+        let camera = || {
+            Shader::new(
+                ShaderType::Vertex,
+                "vert".into(),
+                vec![],
+                vec![Uniform::new(
+                    Param::new("view_proj".into(), ParamType::Mat4),
+                    0,
+                    0,
+                    0,
+                )],
+                vec![],
+            )
+        };
+        let pipeline = Pipeline::builder()
+            .name("Main")
+            .shader(camera())
+            .set_traits(vec![("BindsCameraSet".into(), 0)])
+            .build();
+        let hash = pipeline.get_set_layouts()[0].get_hash().unwrap();
+
+        // The trait is defined by the pipeline naming it by an identifier
+        let tokens = pipeline.get_set_trait_items().to_string().replace(' ', "");
+        assert!(tokens.contains(&format!(
+            "pubtraitBindsCameraSet{{\
+            #[doc=r\"Structuralhashofthesetlayout,whichimplementationsmustmatch\"]\
+            constSET_LAYOUT_HASH:u64={}u64;",
+            hash
+        )));
+        assert!(tokens.contains(
+            "fnbind_set(&self,command_buffer:&CommandBuffer,descriptors:&mutDescriptors,\
+            key:DescriptorKey,view_proj:&RenderBuffer,)->vk::DescriptorSet;"
+        ));
+        assert!(tokens.contains("implBindsCameraSetforPipelineMainHandle{fnbind_set(&self,"));
+        assert!(
+            tokens.contains("self.handle.bind_set0(command_buffer,descriptors,key,view_proj,)")
+        );
+
+        // Other pipelines implement it by its path
+        let pipeline = Pipeline::builder()
+            .name("Other")
+            .shader(camera())
+            .set_traits(vec![("crate :: BindsCameraSet".into(), 0)])
+            .build();
+        let tokens = pipeline.get_set_trait_items().to_string().replace(' ', "");
+        assert!(!tokens.contains("pubtrait"));
+        assert!(tokens.contains(&format!(
+            "const_:()=assert!(<PipelineOtherHandleascrate::BindsCameraSet>::SET_LAYOUT_HASH=={}u64,",
+            hash
+        )));
+
+        let pipeline = Pipeline::builder()
+            .name("Main")
+            .shader(camera())
+            .set_traits(vec![("BindsLightSet".into(), 1)])
+            .build();
+        let result = std::panic::catch_unwind(move || pipeline.get_set_trait_items());
+        let message = *result.unwrap_err().downcast::<String>().unwrap();
+        assert!(
+            message
+                .contains("Set 1 of `PipelineMain` has no uniforms to bind with `BindsLightSet`")
+        );
+    }
+
+    #[test]
+    fn buffer_offsets() {
+        let vert = Shader::new(
+            ShaderType::Vertex,
+            "vert".into(),
+            vec![],
+            vec![
+                Uniform::new(Param::new("model".into(), ParamType::Mat4), 0, 0, 0),
+                Uniform::new(
+                    Param::new("bones".into(), ParamType::StorageBuffer(64)),
+                    0,
+                    1,
+                    0,
+                ),
+            ],
+            vec![],
+        );
+        let pipeline = Pipeline::builder()
+            .name("Main")
+            .shader(vert)
+            .buffer_offsets(vec!["model".into(), "bones".into()])
+            .build();
+
+        let methods = pipeline.get_bind_methods();
+        let tokens = methods[0].to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(
+            "pubfnbind_model_and_bones(&self,command_buffer:&CommandBuffer,\
+            descriptors:&mutDescriptors,key:DescriptorKey,model:&RenderBuffer,\
+            model_offset:vk::DeviceSize,bones:&RenderBuffer,bones_offset:vk::DeviceSize,)"
+        ));
+        assert!(
+            tokens.contains(
+                ".offset(model_offset).range(64usizeasvk::DeviceSize).buffer(model.buffer)"
+            )
+        );
+        assert!(
+            tokens.contains(".offset(bones_offset).range(vk::WHOLE_SIZE).buffer(bones.buffer)")
+        );
+        // Each offset is written in a set of its own
+        assert!(tokens.contains("letkey=Self::derive_key(key,[model_offset,bones_offset,]);"));
+        assert!(tokens.contains("amultipleof`minStorageBufferOffsetAlignment`"));
+
+        let tokens = Mock(&methods[0])
+            .to_token_stream()
+            .to_string()
+            .replace(' ', "");
+        assert!(tokens.contains(
+            "let_=(command_buffer,descriptors,key,model,model_offset,bones,bones_offset,);"
+        ));
+
+        let vert = Shader::new(
+            ShaderType::Vertex,
+            "vert".into(),
+            vec![],
+            vec![Uniform::new(
+                Param::new("model".into(), ParamType::Mat4),
+                0,
+                0,
+                0,
+            )],
+            vec![],
+        );
+        let result = std::panic::catch_unwind(|| {
+            Pipeline::builder()
+                .name("Main")
+                .shader(vert)
+                .dynamic(vec!["model".into()])
+                .buffer_offsets(vec!["model".into()])
+                .build()
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn write_methods() {
+        let method = BindMethod {
+            pipeline: "PipelineMain".into(),
+            uniforms: vec![
+                Uniform::new(Param::new("color".into(), ParamType::Vec4), 2, 0, 0),
+                Uniform::new(
+                    Param::new("albedo".into(), ParamType::SampledImage),
+                    2,
+                    1,
+                    0,
+                ),
+            ],
+            ..Default::default()
+        };
+
+        // A single binding is written in the set of the key, keeping the others
+        let tokens = method.to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(
+            "pubfnwrite_albedo(&self,descriptors:&mutDescriptors,key:DescriptorKey,albedo:&RenderTexture,)"
+        ));
+        assert!(tokens.contains("pubfnwrite_albedo_with_layout("));
+        assert!(tokens.contains("pubfnwrite_color("));
+        assert!(tokens.contains(
+            "letkey=Self::derive_key(key,[PipelineMainUniform::Color,PipelineMainUniform::Albedo,]);"
+        ));
+        assert!(tokens.contains(
+            "DescriptorEntry::Get(sets)=>sets,DescriptorEntry::Created(_)=>panic!(\
+            \"`albedo`iswrittenbeforebindingitssetwith`bind_set2`\"),};\
+            unsafe{self.device.update_descriptor_sets(&[vk::WriteDescriptorSet::default().dst_set(sets[0]).dst_binding(1u32)"
+        ));
+
+        let tokens = Mock(&method).to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains("self.record_update(\"write_albedo\",2u32);"));
+
+        // The key of a set bound from offsets depends on all of them
+        let mut method = method;
+        method.uniforms[0].buffer_offset = true;
+        let tokens = method.to_token_stream().to_string().replace(' ', "");
+        assert!(!tokens.contains("write_"));
+    }
+
+    #[test]
+    fn bind_all() {
+        let vert = Shader::new(
+            ShaderType::Vertex,
+            "vert".into(),
+            vec![],
+            vec![Uniform::new(
+                Param::new("model".into(), ParamType::Mat4),
+                0,
+                0,
+                0,
+            )],
+            vec![],
+        );
+        let frag = Shader::new(
+            ShaderType::Fragment,
+            "frag".into(),
+            vec![],
+            vec![
+                Uniform::new(Param::new("color".into(), ParamType::Vec4), 1, 0, 0),
+                Uniform::new(
+                    Param::new("albedo".into(), ParamType::SampledImage),
+                    1,
+                    1,
+                    0,
+                ),
+            ],
+            vec![],
+        );
+        let pipeline = Pipeline::builder()
+            .name("Main")
+            .shader(vert)
+            .shader(frag)
+            .build();
+
+        // Uniforms are taken by set, then in the order of their bind methods
+        let method = pipeline.get_bind_all_method().unwrap();
+        let tokens = method.to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(
+            "pubfnbind_all(&self,command_buffer:&CommandBuffer,descriptors:&mutDescriptors,\
+            key:DescriptorKey,model:&RenderBuffer,color:&RenderBuffer,albedo:&RenderTexture,)"
+        ));
+        assert!(tokens.contains(
+            "letkey=Self::derive_key(key,[PipelineMainUniform::Color,PipelineMainUniform::Albedo,]);\
+            letset_layouts=&[self.get_set_layouts()[1u32asusize]];"
+        ));
+        assert!(tokens.contains(".dst_set(sets[1]).dst_binding(1u32)"));
+        assert_eq!(tokens.matches("update_descriptor_sets").count(), 1);
+        assert!(tokens.contains("command_buffer.bind_descriptor_sets(self.get_layout(),&sets,0);"));
+
+        let tokens = Mock(&method).to_token_stream().to_string().replace(' ', "");
+        assert!(
+            tokens.contains(
+                "self.record_bind(\"bind_all\",0u32);self.record_bind(\"bind_all\",1u32);"
+            )
+        );
+
+        // Sets after a gap can not be bound with a single call
+        let frag = Shader::new(
+            ShaderType::Fragment,
+            "frag".into(),
+            vec![],
+            vec![Uniform::new(
+                Param::new("color".into(), ParamType::Vec4),
+                2,
+                0,
+                0,
+            )],
+            vec![],
+        );
+        let pipeline = Pipeline::builder().name("Main").shader(frag).build();
+        assert!(pipeline.get_bind_all_method().is_none());
+    }
+
+    #[test]
+    fn set_structs() {
+        let frag = Shader::new(
+            ShaderType::Fragment,
+            "frag".into(),
+            vec![],
+            vec![
+                Uniform::new(Param::new("color".into(), ParamType::Vec4), 2, 0, 0),
+                Uniform::new(Param::new("tint".into(), ParamType::Vec4), 2, 1, 0),
+                Uniform::new(
+                    Param::new("albedo".into(), ParamType::SampledImage),
+                    2,
+                    2,
+                    0,
+                ),
+            ],
+            vec![],
+        );
+        let pipeline = Pipeline::builder()
+            .name("Main")
+            .shader(frag)
+            .buffer_offsets(vec!["tint".into()])
+            .build();
+
+        // Buffers of the same type are told apart by the names of the fields
+        let tokens = pipeline.get_uniform_items().to_string().replace(' ', "");
+        assert!(tokens.contains(
+            "#[derive(Clone,Copy)]pubstructPipelineMainSet2<'a>{pubcolor:&'aRenderBuffer,\
+            pubtint:&'aRenderBuffer,pubtint_offset:vk::DeviceSize,pubalbedo:&'aRenderTexture,}"
+        ));
+
+        let methods = pipeline.get_bind_methods();
+        let tokens = methods[0].to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(
+            "pubfnbind_set2_args(&self,command_buffer:&CommandBuffer,descriptors:&mutDescriptors,\
+            key:DescriptorKey,args:PipelineMainSet2<'_>,)->vk::DescriptorSet{self.bind_set2(\
+            command_buffer,descriptors,key,args.color,args.tint,args.tint_offset,args.albedo,)}"
+        ));
+
+        let tokens = Mock(&methods[0])
+            .to_token_stream()
+            .to_string()
+            .replace(' ', "");
+        assert!(tokens.contains("pubfnbind_set2_args("));
+    }
+
+    #[test]
+    fn push_descriptors() {
+        let code = vec![0x03, 0x02, 0x23, 0x07];
+        let mut vert = Shader::new(
+            ShaderType::Vertex,
+            "vert".into(),
+            vec![],
+            vec![
+                Uniform::new(Param::new("view_proj".into(), ParamType::Mat4), 0, 0, 0),
+                Uniform::new(Param::new("model".into(), ParamType::Mat4), 1, 0, 0),
+            ],
+            vec![],
+        );
+        vert.code = Some(code.clone());
+        let mut frag = Shader::new(
+            ShaderType::Fragment,
+            "frag".into(),
+            vec![],
+            vec![Uniform::new(
+                Param::new("albedo".into(), ParamType::SampledImage),
+                1,
+                1,
+                0,
+            )],
+            vec![],
+        );
+        frag.code = Some(code);
+
+        let pipeline = Pipeline::builder()
+            .name("Main")
+            .shader(vert.clone())
+            .shader(frag)
+            .push_set(Some(1))
+            .build();
+        let set_layouts = pipeline.get_set_layouts();
+        assert!(!set_layouts[0].push);
+        assert!(set_layouts[1].push);
+        assert_eq!(
+            get_set_layout_flags(&set_layouts)
+                .to_string()
+                .replace(' ', ""),
+            "vec![vk::DescriptorSetLayoutCreateFlags::empty(),vk::DescriptorSetLayoutCreateFlags::PUSH_DESCRIPTOR_KHR,]"
+        );
+        // Pushed descriptors are not allocated from pools
+        assert_eq!(pipeline.get_pool_sizes(), [(DescriptorType::Uniform, 1)]);
+
+        let methods = pipeline.get_bind_methods();
+        let tokens = methods[0].to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains("pubfnbind_view_proj(&self,command_buffer:&CommandBuffer,"));
+
+        let tokens = methods[1].to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(
+            "pubfnpush_model_and_albedo(&self,command_buffer:vk::CommandBuffer,\
+            push_descriptor:&ash::khr::push_descriptor::Device,model:&RenderBuffer,albedo:&RenderTexture,)"
+        ));
+        assert!(tokens.contains(
+            "push_descriptor.cmd_push_descriptor_set(command_buffer,vk::PipelineBindPoint::GRAPHICS,self.get_layout(),1u32,&["
+        ));
+        assert!(tokens.contains("vk::WriteDescriptorSet::default().dst_binding(0u32)"));
+        assert!(!tokens.contains("dst_set"));
+        assert!(!tokens.contains("get_or_create"));
+        assert!(!tokens.contains("derive_key"));
+
+        let tokens = Mock(&methods[1])
+            .to_token_stream()
+            .to_string()
+            .replace(' ', "");
+        assert!(tokens.contains("let_=(command_buffer,push_descriptor,model,albedo,layout);"));
+
+        let tokens = pipeline.to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(
+            ".zip(Self::get_reflected_set_layout_flags()).map(|((bindings,binding_flags),flags)|"
+        ));
+
+        // Dynamic uniforms can not be pushed
+        let result = std::panic::catch_unwind(|| {
+            Pipeline::builder()
+                .name("Main")
+                .shader(vert)
+                .dynamic(vec!["model".into()])
+                .push_set(Some(1))
+                .build()
+        });
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn frames_in_flight() {
+        let mut frag = Shader::new(
+            ShaderType::Fragment,
+            "frag".into(),
+            vec![],
+            vec![Uniform::new(
+                Param::new("albedo".into(), ParamType::SampledImage),
+                0,
+                0,
+                0,
+            )],
+            vec![],
+        );
+        frag.code = Some(vec![0x03, 0x02, 0x23, 0x07]);
+
+        let pipeline = Pipeline::builder()
+            .name("Main")
+            .shader(frag)
+            .frames_in_flight(Some(2))
+            .build();
+        let methods = pipeline.get_bind_methods();
+        let tokens = methods[0].to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(
+            "pubfnbind_albedo(&self,command_buffer:&CommandBuffer,descriptors:&mutDescriptors,\
+            key:DescriptorKey,frame_index:usize,albedo:&RenderTexture,)"
+        ));
+        assert!(tokens.contains(
+            "self.bind_set0_with_layout(command_buffer,descriptors,key,frame_index,albedo,"
+        ));
+        assert!(tokens.contains(
+            "assert!(frame_index<2usize,\"Frameindex{}isoutofthe2framesinflight\",frame_index);\
+            letkey=Self::derive_key(key,frame_index);"
+        ));
+        // The set of the frame is the one written
+        assert!(tokens.contains("descriptors.get_or_create(key,set_layouts)"));
+        assert!(tokens.contains(".dst_set(sets[0])"));
+
+        let tokens = Mock(&methods[0])
+            .to_token_stream()
+            .to_string()
+            .replace(' ', "");
+        assert!(tokens.contains("let_=(command_buffer,descriptors,key,frame_index,albedo,);"));
+    }
+
+    #[test]
+    fn scalar_and_vector_push() {
+        let push = PushMethod::new("exposure".into(), ParamType::Float, ShaderType::Fragment);
+        let push_tokens = push.to_token_stream().to_string().replace(' ', "");
+        assert!(push_tokens.contains("letbytes=exposure.as_bytes();"));
+        assert!(push_tokens.contains(
+            "debug_assert_eq!(bytes.len(),4usize,\"`exposure`ispushedas4bytes,buttheargumenthas{}bytes\",bytes.len());"
+        ));
+        assert!(push_tokens.contains("&bytes[0..4usize]"));
+        assert_eq!(ParamType::Float.to_token_stream().to_string(), "f32");
+        assert_eq!(ParamType::Bool.to_token_stream().to_string(), "u32");
+
+        let push = PushMethod::new("wireframe".into(), ParamType::Bool, ShaderType::Fragment);
+        let push_tokens = push.to_token_stream().to_string().replace(' ', "");
+        assert!(
+            push_tokens
+                .contains("pubfnpush_wireframe(&self,command_buffer:&CommandBuffer,wireframe:u32)")
+        );
+        assert!(push_tokens.contains("letbytes=wireframe.to_ne_bytes();"));
+        assert!(push_tokens.contains("&bytes[0..4usize]"));
+        assert!(push_tokens.contains("`0`forfalseor`1`fortrue"));
+
+        let push = PushMethod::new("resolution".into(), ParamType::IVec2, ShaderType::Fragment);
+        let push_tokens = push.to_token_stream().to_string().replace(' ', "");
+        assert!(push_tokens.contains("&bytes[0..8usize]"));
+        assert_eq!(ParamType::UVec4.to_token_stream().to_string(), "UVec4");
+
+        let push = PushMethod::new("tint".into(), ParamType::Half4, ShaderType::Fragment);
+        let push_tokens = push.to_token_stream().to_string().replace(' ', "");
+        assert!(push_tokens.contains("&bytes[0..8usize]"));
+        let range = PushRange::new(ParamType::Half3, ShaderType::Fragment);
+        let range_tokens = range.to_token_stream().to_string().replace(' ', "");
+        assert!(range_tokens.contains(".size(8usizeasu32)"));
+        let ty = ParamType::Half2
+            .to_token_stream()
+            .to_string()
+            .replace(' ', "");
+        assert_eq!(ty, "[u16;2]");
+    }
+
+    #[test]
+    fn padded_matrices() {
+        let range = PushRange::new(ParamType::Mat3x4, ShaderType::Vertex);
+        let tokens = range.to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(".size(48usizeasu32)"));
+        assert_eq!(ParamType::Mat2.get_size(), 32);
+        assert_eq!(ParamType::Mat4x3.get_size(), 64);
+
+        let ty = ParamType::Mat3x4
+            .to_token_stream()
+            .to_string()
+            .replace(' ', "");
+        assert_eq!(ty, "[[f32;4];3]");
+    }
+
+    #[test]
+    fn push_constant_arrays() {
+        let code = r#"
+            [vk::push_constant] float4 colors[4];
+
+            [shader("fragment")]
+            float4 main(uint index) : SV_Target {
+                return colors[index];
+            }
+        "#;
+
+        let slang = Slang::new();
+        let pipeline = Pipeline::builder()
+            .name("Palette")
+            .shader(Shader::from(slang.from_source("frag", code)))
+            .build();
+        let shader = &pipeline.shaders[0];
+        assert_eq!(shader.constants[0].ty, ParamType::Vec4);
+        assert_eq!(shader.constants[0].count, 4);
+
+        let ranges = pipeline.get_push_ranges();
+        assert_eq!(ranges[0].get_size(), 64);
+        let range_tokens = ranges[0].to_token_stream().to_string().replace(' ', "");
+        assert!(range_tokens.contains(".size(64usizeasu32)"));
+
+        let methods = pipeline.get_push_methods();
+        let push_tokens = methods[0].to_token_stream().to_string().replace(' ', "");
+        assert!(push_tokens.contains(
+            "pubfnpush_colors<B:AsBytes>(&self,command_buffer:&CommandBuffer,colors:&B)"
+        ));
+        assert!(push_tokens.contains("debug_assert_eq!(bytes.len(),64usize,"));
+        assert!(!push_tokens.contains("from_raw_parts"));
+        assert!(push_tokens.contains("&bytes[0..64usize]"));
+
+        // Scalars are not padded to 16 bytes as in uniform buffers
+        let mut method = PushMethod::new("weights".into(), ParamType::Float, ShaderType::Fragment);
+        method.count = 3;
+        let push_tokens = method.to_token_stream().to_string().replace(' ', "");
+        assert!(push_tokens.contains("debug_assert_eq!(bytes.len(),12usize,"));
+        assert!(push_tokens.contains("&bytes[0..12usize]"));
+    }
+
+    #[test]
+    fn overlapping_push_constants() {
+        let mut time = Param::new("time".into(), ParamType::Float);
+        time.offset = 64;
+        let vert = Shader::new(
+            ShaderType::Vertex,
+            "vert".into(),
+            vec![],
+            vec![],
+            vec![
+                Param::new("transform".into(), ParamType::Mat4),
+                time.clone(),
+            ],
+        );
+        let frag = Shader::new(
+            ShaderType::Fragment,
+            "frag".into(),
+            vec![],
+            vec![],
+            vec![time.clone()],
+        );
+        let pipeline = Pipeline::builder()
+            .name("Main")
+            .shader(vert.clone())
+            .shader(frag)
+            .build();
+
+        // A constant of both stages is pushed once, with a single range
+        let ranges = pipeline.get_push_ranges();
+        assert_eq!(ranges.len(), 2);
+        assert_eq!(ranges[1].stages, [ShaderType::Vertex, ShaderType::Fragment]);
+        let tokens = ranges[1].to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(
+            ".offset(64usizeasu32).stage_flags(vk::ShaderStageFlags::from_raw(\
+            vk::ShaderStageFlags::VERTEX.as_raw()|vk::ShaderStageFlags::FRAGMENT.as_raw()))"
+        ));
+
+        // Each push names the stages of its own range, at its offset
+        let methods = pipeline.get_push_methods();
+        assert_eq!(methods.len(), 2);
+        let tokens = methods[0].to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(
+            "command_buffer.push_constants(self,vk::ShaderStageFlags::VERTEX,0u32,&bytes[0..64usize]);"
+        ));
+        let tokens = methods[1].to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(
+            "command_buffer.push_constants(self,vk::ShaderStageFlags::from_raw(\
+            vk::ShaderStageFlags::VERTEX.as_raw()|vk::ShaderStageFlags::FRAGMENT.as_raw()),\
+            64u32,&bytes[0..4usize]);"
+        ));
+        let docs = methods[0].get_docs().to_string();
+        assert!(docs.contains("Pushes `transform` to the Vertex stage, 64 bytes at offset 0"));
+        let docs = methods[1].get_docs().to_string();
+        assert!(
+            docs.contains("Pushes `time` to the Vertex and Fragment stages, 4 bytes at offset 64")
+        );
+
+        let build = |frag: Shader| {
+            let vert = vert.clone();
+            std::panic::catch_unwind(move || {
+                Pipeline::builder()
+                    .name("Main")
+                    .shader(vert)
+                    .shader(frag)
+                    .build()
+                    .get_push_methods()
+            })
+        };
+
+        let frag = Shader::new(
+            ShaderType::Fragment,
+            "frag".into(),
+            vec![],
+            vec![],
+            vec![Param::new("time".into(), ParamType::Uint)],
+        );
+        let message = *build(frag).unwrap_err().downcast::<String>().unwrap();
+        assert!(message.ends_with(
+            "Push constant `time` of the Vertex stage has a different type in the Fragment stage"
+        ));
+
+        let mut moved_time = time.clone();
+        moved_time.offset = 80;
+        let frag = Shader::new(
+            ShaderType::Fragment,
+            "frag".into(),
+            vec![],
+            vec![],
+            vec![moved_time],
+        );
+        let message = *build(frag).unwrap_err().downcast::<String>().unwrap();
+        assert!(message.ends_with(
+            "Push constant `time` of the Vertex stage is at offset 80 in the Fragment stage"
+        ));
+
+        // Different constants reading the same bytes would overwrite each other
+        let frag = Shader::new(
+            ShaderType::Fragment,
+            "frag".into(),
+            vec![],
+            vec![],
+            vec![Param::new("tint".into(), ParamType::Vec4)],
+        );
+        let message = *build(frag).unwrap_err().downcast::<String>().unwrap();
+        assert!(message.contains(
+            "Push constant `transform` of the Vertex stage overlaps `tint` of the Fragment stage at bytes 0..16"
+        ));
+    }
+
+    #[test]
+    fn distinct_push_structs() {
+        let mut vertex_push = Param::new("vertex_push".into(), ParamType::Struct(64));
+        vertex_push.fields = vec![StructField {
+            name: "transform".into(),
+            ty: ParamType::Mat4,
+            count: 1,
+            offset: 0,
+            stride: 64,
+        }];
+        let vert = Shader::new(
+            ShaderType::Vertex,
+            "vert".into(),
+            vec![],
+            vec![],
+            vec![vertex_push],
+        );
+        let mut fragment_push = Param::new("fragment_push".into(), ParamType::Struct(32));
+        fragment_push.offset = 64;
+        fragment_push.fields = vec![
+            StructField {
+                name: "color".into(),
+                ty: ParamType::Vec4,
+                count: 1,
+                offset: 0,
+                stride: 16,
+            },
+            StructField {
+                name: "exposure".into(),
+                ty: ParamType::Float,
+                count: 1,
+                offset: 16,
+                stride: 4,
+            },
+        ];
+        let frag = Shader::new(
+            ShaderType::Fragment,
+            "frag".into(),
+            vec![],
+            vec![],
+            vec![fragment_push],
+        );
+        let pipeline = Pipeline::builder()
+            .name("Main")
+            .shader(vert)
+            .shader(frag)
+            .build();
+
+        let ident = Ident::new("PipelineMain", Span::call_site());
+        let (_, fns) = pipeline.get_set_layout_binding_items(&ident);
+        let tokens = fns.to_string().replace(' ', "");
+        assert!(tokens.contains("pubconstPUSH_CONSTANT_SIZE:usize=96usize;"));
+        assert!(tokens.contains(
+            "vk::PushConstantRange::default().offset(0usizeasu32)\
+            .stage_flags(vk::ShaderStageFlags::VERTEX).size(64usizeasu32),\
+            vk::PushConstantRange::default().offset(64usizeasu32)\
+            .stage_flags(vk::ShaderStageFlags::FRAGMENT).size(32usizeasu32),"
+        ));
+        assert!(tokens.contains(
+            "PipelineMainPushInfo{name:\"fragment_push\",offset:64usize,size:32usize,\
+            stage_flags:vk::ShaderStageFlags::FRAGMENT,}"
+        ));
+
+        // Each struct is pushed to its own stage at its own offset
+        let methods = pipeline.get_push_methods();
+        let tokens = methods[0].to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(
+            "command_buffer.push_constants(self,vk::ShaderStageFlags::VERTEX,0u32,&bytes[0..64usize]);"
+        ));
+        let tokens = methods[1].to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(
+            "pubfnpush_fragment_push(&self,command_buffer:&CommandBuffer,\
+            fragment_push:&PipelineMainPushFragmentPush)"
+        ));
+        assert!(tokens.contains(
+            "command_buffer.push_constants(self,vk::ShaderStageFlags::FRAGMENT,64u32,&bytes[0..32usize]);"
+        ));
+        // Fields are pushed at their offset in the block, past the start of the struct
+        assert!(tokens.contains(
+            "pubfnpush_fragment_push_exposure(&self,command_buffer:&CommandBuffer,exposure:&f32)"
+        ));
+        assert!(tokens.contains(
+            "command_buffer.push_constants(self,vk::ShaderStageFlags::FRAGMENT,80u32,bytes);"
+        ));
+        let docs = methods[1].get_docs().to_string();
+        assert!(
+            docs.contains("Pushes `fragment_push` to the Fragment stage, 32 bytes at offset 64")
+        );
+
+        let tokens = Mock(&methods[1])
+            .to_token_stream()
+            .to_string()
+            .replace(' ', "");
+        assert!(tokens.contains(
+            "self.record_push(\"push_fragment_push\",vk::ShaderStageFlags::FRAGMENT,64u32,&bytes[0..32usize]);"
+        ));
+        assert!(tokens.contains(
+            "self.record_push(\"push_fragment_push_exposure\",vk::ShaderStageFlags::FRAGMENT,80u32,bytes);"
+        ));
+
+        // The mirror of a struct starts at its own offset
+        let tokens = pipeline.get_push_structs().to_string().replace(' ', "");
+        assert!(tokens.contains(
+            "pubstructPipelineMainPushFragmentPush{pubcolor:[f32;4],pubexposure:f32,_pad2:[u8;12usize],}"
+        ));
+        assert!(tokens.contains(
+            "const_:()=assert!(std::mem::size_of::<PipelineMainPushFragmentPush>()==32usize);"
+        ));
+    }
+
+    #[test]
+    fn stage_push_structs() {
+        let vert_code = r#"
+            struct VertexPush {
+                float4x4 transform;
+            };
+
+            [vk::push_constant] VertexPush vertex_push;
+
+            [shader("vertex")]
+            float4 main(float4 position) : SV_Position {
+                return mul(vertex_push.transform, position);
+            }
+        "#;
+        let frag_code = r#"
+            struct FragmentPush {
+                [[vk::offset(64)]] float4 color;
+                float exposure;
+            };
+
+            [vk::push_constant] FragmentPush fragment_push;
+
+            [shader("fragment")]
+            float4 main() : SV_Target {
+                return fragment_push.color * fragment_push.exposure;
+            }
+        "#;
+
+        let slang = Slang::new();
+        let pipeline = Pipeline::builder()
+            .name("Main")
+            .shader(Shader::from(slang.from_source("vert", vert_code)))
+            .shader(Shader::from(slang.from_source("frag", frag_code)))
+            .build();
+
+        // The fragment struct is read from its first field, after the vertex struct
+        let ranges = pipeline.get_push_ranges();
+        assert_eq!(ranges.len(), 2);
+        assert_eq!((ranges[0].offset, ranges[0].get_size()), (0, 64));
+        assert_eq!((ranges[1].offset, ranges[1].get_size()), (64, 32));
+
+        let ident = Ident::new("PipelineMain", Span::call_site());
+        let (_, fns) = pipeline.get_set_layout_binding_items(&ident);
+        let tokens = fns.to_string().replace(' ', "");
+        assert!(tokens.contains("pubconstPUSH_CONSTANT_SIZE:usize=96usize;"));
+
+        let methods = pipeline.get_push_methods();
+        let tokens = methods[1].to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(
+            "command_buffer.push_constants(self,vk::ShaderStageFlags::FRAGMENT,64u32,&bytes[0..32usize]);"
+        ));
+        assert!(tokens.contains(
+            "command_buffer.push_constants(self,vk::ShaderStageFlags::FRAGMENT,80u32,bytes);"
+        ));
+
+        // Its mirror starts at its first field
+        let tokens = pipeline.get_push_structs().to_string().replace(' ', "");
+        assert!(tokens.contains(
+            "pubstructPipelineMainPushFragmentPush{pubcolor:[f32;4],pubexposure:f32,_pad2:[u8;12usize],}"
+        ));
+    }
+
+    #[test]
+    fn compute_pipeline() {
+        let code = r#"
+            [vk::binding(0, 0)]
+            Sampler2D source;
+
+            [shader("compute")]
+            [numthreads(8, 8, 1)]
+            void main(uint3 id : SV_DispatchThreadID) {
+                source.SampleLevel(float2(id.xy), 0.0);
+            }
+        "#;
+
+        let slang = Slang::new();
+        let compute = slang.from_source("test", code);
+        let spirv = compute.get_spirv();
+        let mut pipeline = Pipeline::builder().name("Blur").compute(compute).build();
+        assert!(pipeline.is_compute());
+        assert_eq!(pipeline.shaders[0].local_size, [8, 8, 1]);
+        pipeline.shaders[0].code = Some(spirv);
+
+        let tokens = pipeline.to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains("vk::ComputePipelineCreateInfo"));
+        assert!(tokens.contains("pubconstLOCAL_SIZE:[u32;3]=[8u32,8u32,1u32]"));
+        assert!(
+            tokens.contains("pubfndispatch(&self,command_buffer:&CommandBuffer,x:u32,y:u32,z:u32)")
+        );
+        assert!(tokens.contains("self.device.cmd_dispatch(command_buffer.command_buffer,x,y,z);"));
+        assert!(!tokens.contains("VertexInput"));
+
+        // Sets are bound at the compute bind point on the handle of a `CommandBuffer`
+        assert!(tokens.contains("pubfnbind_set0(&self,command_buffer:&CommandBuffer,"));
+        assert!(tokens.contains(
+            "self.device.cmd_bind_descriptor_sets(command_buffer.command_buffer,\
+            vk::PipelineBindPoint::COMPUTE,"
+        ));
+        assert!(!tokens.contains("vk::CommandBuffer"));
+
+        // Both the pipeline and its handle implement `Pipeline`, without vertex input
+        assert_eq!(
+            tokens.matches("implPipelineforPipelineBlurHandle{").count(),
+            2
+        );
+        assert_eq!(tokens.matches("implPipelineforPipelineBlur{").count(), 2);
+        assert!(tokens.contains("fnget_vertex_size(&self)->usize{0}"));
+
+        // The mock is created by `new` with the same parameters and records dispatches
+        assert!(tokens.contains(
+            "pubfnnew(device:&std::sync::Arc<ash::Device>)->Self{Self::from_mock(Some(device.clone()))}"
+        ));
+        assert!(tokens.contains("Dispatch{x:u32,y:u32,z:u32,},"));
+        assert!(tokens.contains("let_=command_buffer;self.record_dispatch(x,y,z);"));
+    }
+
+    #[test]
+    fn pipeline_options() {
+        let code = vec![0x03, 0x02, 0x23, 0x07];
+        let mut vert = Shader::new(ShaderType::Vertex, "vert".into(), vec![], vec![], vec![]);
+        vert.code = Some(code.clone());
+        let mut frag = Shader::new(ShaderType::Fragment, "frag".into(), vec![], vec![], vec![]);
+        frag.code = Some(code);
+
+        let options = PipelineOptions {
+            blend: Blend::Additive,
+            cull: Cull::Back,
+            topology: Some(Topology::PointList),
+            depth_write: Some(false),
+            samples: Some(4),
+            alpha_to_coverage: true,
+            sample_shading: Some(0.25),
+            polygon_mode: PolygonMode::Line,
+            line_width: Some(2.0),
+        };
+        let pipeline = Pipeline::builder()
+            .name("Particles")
+            .shader(vert.clone())
+            .shader(frag.clone())
+            .options(options)
+            .build();
+        let tokens = pipeline.to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(".src_color_blend_factor(vk::BlendFactor::ONE)"));
+        assert!(!tokens.contains("V::get_color_blend()"));
+        assert!(tokens.contains(".cull_mode(vk::CullModeFlags::BACK)"));
+        assert!(tokens.contains(".topology(vk::PrimitiveTopology::POINT_LIST)"));
+        assert!(tokens.contains("V::get_depth_state().depth_write_enable(false)"));
+        assert!(tokens.contains(".rasterization_samples(vk::SampleCountFlags::TYPE_4)"));
+        assert!(tokens.contains(".sample_shading_enable(true).min_sample_shading(0.25f32)"));
+        assert!(tokens.contains(".alpha_to_coverage_enable(true)"));
+        assert!(tokens.contains("\"sampleRateShading\""));
+        assert!(tokens.contains(".line_width(2f32)"));
+        assert!(tokens.contains("vk::PolygonMode::LINE,"));
+        assert!(tokens.contains("\"fillModeNonSolid\""));
+        assert!(tokens.contains("\"wideLines\""));
+
+        let pipeline = Pipeline::builder()
+            .name("Particles")
+            .shader(vert)
+            .shader(frag)
+            .build();
+        let tokens = pipeline.to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains("V::get_color_blend()"));
+        assert!(tokens.contains(".cull_mode(vk::CullModeFlags::NONE)"));
+        assert!(tokens.contains(".topology(V::get_topology())"));
+        assert!(tokens.contains("letdepth_state=V::get_depth_state();"));
+        assert!(tokens.contains(".rasterization_samples(vk::SampleCountFlags::TYPE_1)"));
+        assert!(tokens.contains(".sample_shading_enable(false)"));
+        assert!(tokens.contains(".alpha_to_coverage_enable(false)"));
+        assert!(tokens.contains(".line_width(1f32)"));
+        assert!(tokens.contains("vk::PolygonMode::FILL,"));
+        assert!(!tokens.contains("fillModeNonSolid"));
+    }
+
+    #[test]
+    fn subpass_option() {
+        let code = vec![0x03, 0x02, 0x23, 0x07];
+        let mut vert = Shader::new(ShaderType::Vertex, "vert".into(), vec![], vec![], vec![]);
+        vert.code = Some(code.clone());
+        let mut frag = Shader::new(ShaderType::Fragment, "frag".into(), vec![], vec![], vec![]);
+        frag.code = Some(code);
+
+        // The subpass of the vertex input by default
+        let pipeline = Pipeline::builder()
+            .name("Lighting")
+            .shader(vert.clone())
+            .shader(frag.clone())
+            .build();
+        let tokens = pipeline.to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(".subpass(V::get_subpass())"));
+        assert!(tokens.contains("subpass:V::get_subpass(),"));
+
+        let pipeline = Pipeline::builder()
+            .name("Lighting")
+            .shader(vert)
+            .shader(frag)
+            .subpass(Some(1))
+            .build();
+        let tokens = pipeline.to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(".subpass(1u32)"));
+        assert!(tokens.contains("subpass:1u32,"));
+        assert!(!tokens.contains("V::get_subpass()"));
+        assert!(tokens.contains("pubfnget_subpass(&self)->u32{self.subpass}"));
+    }
+
+    #[test]
+    fn rasterizer_discard() {
+        let mut vert = Shader::new(ShaderType::Vertex, "cull".into(), vec![], vec![], vec![]);
+        vert.code = Some(vec![0x03, 0x02, 0x23, 0x07]);
+        let pipeline = Pipeline::builder()
+            .name("Cull")
+            .rasterizer_discard(true)
+            .shader(vert)
+            .build();
+        let tokens = pipeline.to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(".rasterizer_discard_enable(true)"));
+        assert!(!tokens.contains("color_blend_state"));
+        assert!(!tokens.contains("vk::ShaderStageFlags::FRAGMENT"));
+        assert!(!tokens.contains("frag_module"));
+    }
+
+    #[test]
+    fn viewport_state() {
+        let mut vert = Shader::new(ShaderType::Vertex, "cull".into(), vec![], vec![], vec![]);
+        vert.code = Some(vec![0x03, 0x02, 0x23, 0x07]);
+
+        // Viewport and scissor are dynamic, set by a method of the handle
+        let pipeline = Pipeline::builder()
+            .name("Cull")
+            .rasterizer_discard(true)
+            .shader(vert.clone())
+            .build();
+        let tokens = pipeline.to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains("letextent=vk::Extent2D::default().width(1).height(1);"));
+        assert!(
+            tokens.contains("letstates=[vk::DynamicState::VIEWPORT,vk::DynamicState::SCISSOR];")
+        );
+        assert!(tokens.contains(
+            "pubfnset_viewport(&self,command_buffer:&CommandBuffer,extent:vk::Extent2D)"
+        ));
+        assert!(tokens.contains("letcommand_buffer=command_buffer.command_buffer;"));
+        assert!(tokens.contains("self.device.cmd_set_scissor(command_buffer,0,&[scissor]);"));
+        assert!(tokens.contains("pass:&Pass,)->Self"));
+
+        // A static viewport is baked from the extent given to the constructors
+        let pipeline = Pipeline::builder()
+            .name("Cull")
+            .rasterizer_discard(true)
+            .static_viewport(true)
+            .shader(vert)
+            .build();
+        let tokens = pipeline.to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains("letstates:[vk::DynamicState;0]=[];"));
+        assert!(!tokens.contains("set_viewport"));
+        assert!(tokens.contains("extent:vk::Extent2D,}"));
+        assert!(tokens.contains("pubfnget_extent(&self)->vk::Extent2D{self.extent}"));
+        assert!(tokens.contains("pass:&Pass,extent:vk::Extent2D,)->Self"));
+        assert!(tokens.contains("vk::PolygonMode::FILL,extent,)"));
+        assert!(tokens.contains("letscissors=[vk::Rect2D::default().extent(extent)];"));
+
+        // The mock keeps the extent as well
+        assert!(tokens.contains("Self::from_mock::<V>(Some(pass.device.clone()),extent,)"));
+        assert!(tokens.contains("calls:Default::default(),extent,};"));
+        assert_eq!(
+            tokens
+                .matches("pubfnget_extent(&self)->vk::Extent2D{self.extent}")
+                .count(),
+            2
+        );
+    }
+
+    #[test]
+    fn mock_constructors() {
+        let code = vec![0x03, 0x02, 0x23, 0x07];
+        let mut vert = Shader::new(ShaderType::Vertex, "vert".into(), vec![], vec![], vec![]);
+        vert.code = Some(code.clone());
+        let mut frag = Shader::new(ShaderType::Fragment, "frag".into(), vec![], vec![], vec![]);
+        frag.code = Some(code);
+
+        let pipeline = Pipeline::builder()
+            .name("Main")
+            .shader(vert)
+            .shader(frag)
+            .build();
+        let tokens = pipeline.to_token_stream().to_string().replace(' ', "");
+
+        // Each constructor is generated with the same signature for the pipeline and its mock
+        for signature in [
+            "pubfnnew<V:VertexInput>(#[cfg(target_os=\"android\")]android_app:&AndroidApp,pass:&Pass,)->Self",
+            "pubfnnew_cached<V:VertexInput>(#[cfg(target_os=\"android\")]android_app:&AndroidApp,pass:&Pass,cache:&PipelineMainLayoutCache,)->Self",
+            "pubfnnew_with_layout<V:VertexInput>(#[cfg(target_os=\"android\")]android_app:&AndroidApp,pass:&Pass,layout:vk::PipelineLayout,",
+            "pubfnnew_checked<V:VertexInput>(#[cfg(target_os=\"android\")]android_app:&AndroidApp,pass:&Pass,enabled:&vk::PhysicalDeviceFeatures,)->Result<Self,PipelineMainError>",
+        ] {
+            assert_eq!(tokens.matches(signature).count(), 2, "{}", signature);
+        }
+        assert!(tokens.contains("let_=android_app;let_=(cache,);Self::from_mock::<V>("));
+        assert!(
+            tokens.contains("pubfnnew_mock<V:VertexInput>()->Self{Self::from_mock::<V>(None,)}")
+        );
+
+        // Both the mock and its handle implement `Pipeline`
+        assert_eq!(
+            tokens.matches("implPipelineforPipelineMainHandle{").count(),
+            2
+        );
+        assert_eq!(tokens.matches("implPipelineforPipelineMain{").count(), 2);
+        assert!(
+            tokens.contains("fnget_layout(&self)->vk::PipelineLayout{vk::PipelineLayout::null()}")
+        );
+        assert!(tokens.contains("vertex_size:std::mem::size_of::<V>(),"));
+    }
+
+    #[test]
+    fn vertex_type() {
+        let code = vec![0x03, 0x02, 0x23, 0x07];
+        let mut vert = Shader::new(ShaderType::Vertex, "vert".into(), vec![], vec![], vec![]);
+        vert.code = Some(code.clone());
+        let mut frag = Shader::new(ShaderType::Fragment, "frag".into(), vec![], vec![], vec![]);
+        frag.code = Some(code);
+
+        let pipeline = Pipeline::builder()
+            .name("Skinned")
+            .vertex(Some("crate::gfx::SkinnedVertex".into()))
+            .shader(vert.clone())
+            .shader(frag.clone())
+            .build();
+        let tokens = pipeline.to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(
+            "pubfnnew(#[cfg(target_os=\"android\")]android_app:&AndroidApp,pass:&Pass,)->Self"
+        ));
+        assert!(tokens.contains("Self::from_layout::<crate::gfx::SkinnedVertex>("));
+        assert!(tokens.contains("Ok(Self::new(#[cfg"));
+        assert!(
+            tokens.contains(
+                "Self::from_mock::<crate::gfx::SkinnedVertex>(Some(pass.device.clone()),)"
+            )
+        );
+        assert!(!tokens.contains("pubfnnew<V:VertexInput>"));
+
+        let pipeline = Pipeline::builder()
+            .name("Skinned")
+            .shader(vert)
+            .shader(frag)
+            .build();
+        let tokens = pipeline.to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains("pubfnnew<V:VertexInput>("));
+        assert!(tokens.contains("Self::from_layout::<V>("));
+    }
+
+    #[test]
+    fn dependency_tracking() {
+        let manifest_dir = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+        let mut frag = Shader::new(ShaderType::Fragment, "frag".into(), vec![], vec![], vec![]);
+        frag.dependencies = vec![
+            manifest_dir.join("src/lib.rs"),
+            manifest_dir.join("src/parse.rs"),
+        ];
+        let mut vert = Shader::new(ShaderType::Vertex, "vert".into(), vec![], vec![], vec![]);
+        vert.dependencies = vec![manifest_dir.join("src/lib.rs")];
+
+        let pipeline = Pipeline {
+            name: "Main".into(),
+            shaders: vec![vert, frag],
+            ..Default::default()
+        };
+        let tokens = pipeline.get_dependency_items().to_string();
+        for file in ["lib.rs", "parse.rs"] {
+            let path = get_absolute_path(&manifest_dir.join("src").join(file));
+            assert_eq!(
+                tokens
+                    .matches(&format!("include_bytes ! ({:?})", path))
+                    .count(),
+                1
+            );
+        }
+
+        let pipeline = Pipeline::default();
+        assert!(pipeline.get_dependency_items().is_empty());
+    }
+
+    #[test]
+    fn spec_constants() {
+        let code = vec![0x03, 0x02, 0x23, 0x07];
+        let mut vert = Shader::new(ShaderType::Vertex, "vert".into(), vec![], vec![], vec![]);
+        vert.code = Some(code.clone());
+        vert.spec_constants = vec![SpecConstant::new("skinned".into(), 0, ScalarType::Bool)];
+        let mut frag = Shader::new(ShaderType::Fragment, "frag".into(), vec![], vec![], vec![]);
+        frag.code = Some(code.clone());
+        frag.spec_constants = vec![
+            SpecConstant::new("light_count".into(), 2, ScalarType::Uint),
+            SpecConstant::new("skinned".into(), 0, ScalarType::Bool),
+        ];
+
+        let pipeline = Pipeline {
+            name: "Main".into(),
+            shaders: vec![vert.clone(), frag.clone()],
+            ..Default::default()
+        };
+        let tokens = pipeline.to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains("#[repr(C)]"));
+        assert!(tokens.contains(
+            "pubstructPipelineMainSpecConstants{pubskinned:vk::Bool32,publight_count:u32,}"
+        ));
+        assert!(tokens.contains("pubfnnew_with_constants<V:VertexInput>("));
+        assert!(tokens.contains("offset_of!(PipelineMainSpecConstants,light_count)"));
+        assert_eq!(tokens.matches(".constant_id(0u32)").count(), 2);
+        assert_eq!(tokens.matches(".specialization_info(info)").count(), 2);
+
+        vert.spec_constants.clear();
+        frag.spec_constants.clear();
+        let pipeline = Pipeline {
+            name: "Main".into(),
+            shaders: vec![vert, frag],
+            ..Default::default()
+        };
+        let tokens = pipeline.to_token_stream().to_string();
+        assert!(!tokens.contains("SpecConstants"));
+        assert!(!tokens.contains("new_with_constants"));
+
+        let mut frag = Shader::new(ShaderType::Fragment, "frag".into(), vec![], vec![], vec![]);
+        frag.spec_constants = vec![SpecConstant::new("count".into(), 0, ScalarType::Int)];
+        let mut vert = Shader::new(ShaderType::Vertex, "vert".into(), vec![], vec![], vec![]);
+        vert.spec_constants = vec![SpecConstant::new("skinned".into(), 0, ScalarType::Bool)];
+        let pipeline = Pipeline {
+            name: "Main".into(),
+            shaders: vec![vert, frag],
+            ..Default::default()
+        };
+        let result = std::panic::catch_unwind(|| pipeline.get_spec_constants());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn embedded_spirv() {
+        let code = [0x03, 0x02, 0x23, 0x07, 0x00, 0x00, 0x01, 0x00];
+        let tokens = get_embedded_spirv(&code).to_string().replace(' ', "");
+        assert!(tokens.contains("#[repr(C,align(4))]"));
+        assert!(tokens.contains("AlignedSpirv([u8;8usize])"));
+        assert!(tokens.contains("&SPIRV.0"));
+
+        let result = std::panic::catch_unwind(|| get_embedded_spirv(&code[..6]));
+        assert!(result.is_err());
+        let result = std::panic::catch_unwind(|| get_embedded_spirv(&[]));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn ray_tracing_pipeline() {
+        let scene = Param::new("scene".into(), ParamType::AccelerationStructure);
+        let stages = [
+            (
+                ShaderType::RayGeneration,
+                vec![Uniform::new(scene, 0, 0, 0)],
+            ),
+            (ShaderType::Miss, vec![]),
+            (ShaderType::ClosestHit, vec![]),
+        ];
+        let mut builder = Pipeline::builder().name("PathTrace");
+        for (ty, uniforms) in stages {
+            let mut shader = Shader::new(ty, "rt".into(), vec![], uniforms, vec![]);
+            shader.code = Some(vec![0x03, 0x02, 0x23, 0x07]);
+            builder = builder.shader(shader);
+        }
+        let pipeline = builder.build();
+        assert!(pipeline.is_ray_tracing());
+
+        let tokens = pipeline.to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains("vk::RayTracingPipelineCreateInfoKHR"));
+        assert!(tokens.contains("vk::RayTracingShaderGroupTypeKHR::TRIANGLES_HIT_GROUP"));
+        assert!(tokens.contains("vk::DescriptorType::ACCELERATION_STRUCTURE_KHR"));
+        assert!(tokens.contains(".push_next(&mut"));
+        assert!(tokens.contains("vk::PipelineBindPoint::RAY_TRACING_KHR"));
+        assert!(tokens.contains("pubfnget_group_handles("));
+        assert!(!tokens.contains("VertexInput"));
+
+        // The mock returns zeroed group handles
+        assert!(tokens.contains("let_=ray_tracing;Self::from_mock(Some(device.clone()))"));
+        assert!(tokens.contains("vec![0;(PipelinePathTrace::GROUP_COUNT*handle_size)asusize]"));
+        assert!(!tokens.contains("Dispatch{"));
+    }
+
+    #[test]
+    fn reserved_param_names() {
+        for &name in RESERVED {
+            let param = Param::new(name.into(), ParamType::Vec4);
+            let method = BindMethod {
+                uniforms: vec![Uniform::new(param, 0, 0, 0)],
+                ..Default::default()
+            };
+            let method_tokens = method.to_token_stream().to_string().replace(' ', "");
+            assert!(method_tokens.contains(&format!("pubfnbind_{}(", name)));
+            assert!(method_tokens.contains(&format!("{}_u:&RenderBuffer", name)));
+            assert!(method_tokens.contains(&format!(".buffer({}_u.buffer)", name)));
+
+            let push = PushMethod::new(name.into(), ParamType::Vec4, ShaderType::Vertex);
+            let push_tokens = push.to_token_stream().to_string().replace(' ', "");
+            assert!(push_tokens.contains(&format!("pubfnpush_{}<", name)));
+            assert!(push_tokens.contains(&format!("{}_u:&B", name)));
+        }
+    }
+
+    #[test]
+    fn sanitized_names() {
+        let uniforms = ["type", "2d_lut", "lut-3d"]
+            .into_iter()
+            .enumerate()
+            .map(|(binding, name)| {
+                Uniform::new(
+                    Param::new(name.into(), ParamType::Vec4),
+                    0,
+                    binding as u32,
+                    0,
+                )
+            })
+            .collect::<Vec<_>>();
+        let method = BindMethod {
+            pipeline: "PipelineMain".into(),
+            uniforms: uniforms.clone(),
+            ..Default::default()
+        };
+        let tokens = method.to_token_stream().to_string().replace(' ', "");
+        assert!(
+            tokens.contains("type_u:&RenderBuffer,_2d_lut:&RenderBuffer,lut_3d:&RenderBuffer,")
+        );
+        assert!(tokens.contains("pubfnbind_type_and__2d_lut_and_lut_3d("));
+        assert!(tokens.contains("PipelineMainUniform::_2dLut,PipelineMainUniform::Lut3d,"));
+        assert_eq!(get_const_ident("SET", "2d_lut"), "SET_2D_LUT");
+
+        let push = PushMethod::new("2d-scale".into(), ParamType::Vec4, ShaderType::Vertex);
+        let tokens = push.to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains("pubfnpush__2d_scale<"));
+
+        // Reflection keeps the names of the shaders
+        let mut frag = Shader::new(
+            ShaderType::Fragment,
+            "frag".into(),
+            vec![],
+            uniforms,
+            vec![],
+        );
+        frag.code = Some(vec![0x03, 0x02, 0x23, 0x07]);
+        let pipeline = Pipeline::builder().name("Main").shader(frag).build();
+        let ident = Ident::new("PipelineMain", Span::call_site());
+        let (_, fns) = pipeline.get_set_layout_binding_items(&ident);
+        let tokens = fns.to_string().replace(' ', "");
+        assert!(tokens.contains("name:\"lut-3d\""));
+
+        let frag = Shader::new(
+            ShaderType::Fragment,
+            "frag".into(),
+            vec![],
+            vec![
+                Uniform::new(Param::new("lut-3d".into(), ParamType::Vec4), 0, 0, 0),
+                Uniform::new(Param::new("lut_3d".into(), ParamType::Vec4), 0, 1, 0),
+            ],
+            vec![],
+        );
+        let pipeline = Pipeline::builder().name("Main").shader(frag).build();
+        let result = std::panic::catch_unwind(move || pipeline.to_token_stream());
+        let message = *result.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.contains("Uniforms `lut-3d` and `lut_3d` are both named `lut_3d`"));
+    }
+
+    #[test]
+    fn sanitized_fields() {
+        // Fields and variants are valid identifiers for keywords and leading digits
+        let names = ["type", "self", "2d_lut"];
+        let mut constants = Param::new("constants".into(), ParamType::Struct(12));
+        constants.fields = names
+            .iter()
+            .enumerate()
+            .map(|(index, name)| StructField {
+                name: name.to_string(),
+                ty: ParamType::Float,
+                count: 1,
+                offset: index * 4,
+                stride: 4,
+            })
+            .collect();
+        let mut vert = Shader::new(
+            ShaderType::Vertex,
+            "vert".into(),
+            vec![],
+            vec![],
+            vec![constants],
+        );
+        vert.code = Some(vec![0x03, 0x02, 0x23, 0x07]);
+        vert.spec_constants = names
+            .iter()
+            .enumerate()
+            .map(|(id, name)| SpecConstant::new(name.to_string(), id as u32, ScalarType::Uint))
+            .collect();
+        let pipeline = Pipeline::builder().name("Main").shader(vert).build();
+
+        let tokens = pipeline.get_push_structs().to_string().replace(' ', "");
+        assert!(tokens.contains("pubr#type:f32,pubself_u:f32,pub_2d_lut:f32,"));
+        assert!(tokens.contains("pubfnnew(r#type:f32,self_u:f32,_2d_lut:f32)->Self"));
+
+        let tokens = pipeline.to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(
+            "pubstructPipelineMainSpecConstants{pubr#type:u32,pubself_u:u32,pub_2d_lut:u32,}"
+        ));
+        assert!(tokens.contains("offset_of!(PipelineMainSpecConstants,r#type)"));
+        assert!(tokens.contains("offset_of!(PipelineMainSpecConstants,self_u)"));
+        assert!(tokens.contains("offset_of!(PipelineMainSpecConstants,_2d_lut)"));
+
+        assert_eq!(get_variant_ident("type"), "Type");
+        assert_eq!(get_variant_ident("self"), "SelfU");
+        assert_eq!(get_variant_ident("2d_lut"), "_2dLut");
+    }
+
+    #[test]
+    fn long_former_names() {
+        let uniforms = ["camera", "lights", "shadow_cascades", "environment"]
+            .iter()
+            .enumerate()
+            .map(|(binding, name)| {
+                Uniform::new(
+                    Param::new(name.to_string(), ParamType::Mat4),
+                    0,
+                    binding as u32,
+                    0,
+                )
+            })
+            .collect::<Vec<_>>();
+        let method = BindMethod {
+            pipeline: "PipelineMain".into(),
+            uniforms: uniforms[..2].to_vec(),
+            ..Default::default()
+        };
+        let tokens = method.to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains("#[doc(alias=\"bind_camera_and_lights\")]"));
+        assert!(!tokens.contains("doc(alias=\"camera\")"));
+
+        // `bind_camera_and_lights_and_shadow_cascades_and_environment` is too long to be
+        // an alias, so `bind_set0` is found by the names of its uniforms instead
+        let method = BindMethod { uniforms, ..method };
+        let tokens = method.to_token_stream().to_string().replace(' ', "");
+        assert!(
+            !tokens.contains(
+                "doc(alias=\"bind_camera_and_lights_and_shadow_cascades_and_environment\")"
+            )
+        );
+        assert!(tokens.contains(
+            "#[doc(alias=\"camera\")]#[doc(alias=\"lights\")]\
+            #[doc(alias=\"shadow_cascades\")]#[doc(alias=\"environment\")]"
+        ));
+        assert!(tokens.contains("pubfnbind_set0("));
+        assert!(!tokens.contains("doc(hidden)"));
+        assert!(
+            tokens.contains("pubfnbind_camera_and_lights_and_shadow_cascades_and_environment(")
+        );
+    }
+
+    #[test]
+    fn method_collisions() {
+        let vert = Shader::new(
+            ShaderType::Vertex,
+            "vert".into(),
+            vec![],
+            vec![
+                Uniform::new(Param::new("model".into(), ParamType::Mat4), 0, 0, 0),
+                Uniform::new(Param::new("set0".into(), ParamType::Mat4), 1, 0, 0),
+            ],
+            vec![],
+        );
+        let pipeline = Pipeline::builder().name("Main").shader(vert).build();
+
+        // The former name of set 1 is the name of the method of set 0
+        let methods = pipeline.get_checked_bind_methods();
+        assert!(!methods[0].former_names_collide);
+        assert!(methods[1].former_names_collide);
+        let tokens = methods[1].to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains("pubfnbind_set1("));
+        assert!(!tokens.contains("pubfnbind_set0("));
+
+        let frag = Shader::new(
+            ShaderType::Fragment,
+            "frag".into(),
+            vec![],
+            vec![Uniform::new(
+                Param::new("color".into(), ParamType::Vec4),
+                2,
+                0,
+                0,
+            )],
+            vec![Param::new("set2".into(), ParamType::Vec4)],
+        );
+        let pipeline = Pipeline::builder()
+            .name("Main")
+            .shader(frag)
+            .push_set(Some(2))
+            .build();
+        let result = std::panic::catch_unwind(move || pipeline.get_checked_bind_methods());
+        let message = *result.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.contains(
+            "Methods generated for set 2 and push constant `set2` are both named `push_set2`"
+        ));
+
+        // Uniforms reused by different sets can not be told apart
+        let vert = Shader::new(
+            ShaderType::Vertex,
+            "vert".into(),
+            vec![],
+            vec![Uniform::new(
+                Param::new("params".into(), ParamType::Vec4),
+                0,
+                0,
+                0,
+            )],
+            vec![],
+        );
+        let frag = Shader::new(
+            ShaderType::Fragment,
+            "frag".into(),
+            vec![],
+            vec![Uniform::new(
+                Param::new("params".into(), ParamType::Vec4),
+                1,
+                0,
+                0,
+            )],
+            vec![],
+        );
+        let pipeline = Pipeline::builder()
+            .name("Main")
+            .shader(vert)
+            .shader(frag)
+            .build();
+        let result = std::panic::catch_unwind(move || pipeline.to_token_stream());
+        let message = *result.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.contains(
+            "Uniforms at set 0 binding 0 and at set 1 binding 0 are both named `params`"
+        ));
+    }
+
+    #[test]
+    fn push_constant_struct() {
+        let mut constants = Param::new("constants".into(), ParamType::Struct(80));
+        constants.fields = vec![
+            StructField {
+                name: "pretransform".into(),
+                ty: ParamType::Mat4,
+                count: 1,
+                offset: 0,
+                stride: 64,
+            },
+            StructField {
+                name: "scale".into(),
+                ty: ParamType::Float,
+                count: 1,
+                offset: 64,
+                stride: 4,
+            },
+        ];
+        let vert = Shader::new(
+            ShaderType::Vertex,
+            "vert".into(),
+            vec![],
+            vec![],
+            vec![constants],
+        );
+        let pipeline = Pipeline::builder().name("Main").shader(vert).build();
+
+        // The struct mirrors the reflected fields, padded up to the reflected size
+        let tokens = pipeline.get_push_structs().to_string().replace(' ', "");
+        assert!(tokens.contains(
+            "pubstructPipelineMainPushConstants{pubpretransform:[[f32;4];4],pubscale:f32,_pad2:[u8;12usize],}"
+        ));
+        assert!(tokens.contains("pubfnnew(pretransform:[[f32;4];4],scale:f32)->Self"));
+        assert!(
+            tokens.contains("assert!(std::mem::size_of::<PipelineMainPushConstants>()==80usize)")
+        );
+
+        let methods = pipeline.get_push_methods();
+        let tokens = methods[0].to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains("constants:&PipelineMainPushConstants"));
+        assert!(tokens.contains("std::mem::size_of::<PipelineMainPushConstants>()"));
+        let docs = methods[0].get_docs().to_string();
+        assert!(docs.contains("`constants` is pushed as a [`PipelineMainPushConstants`]"));
+    }
+
+    #[test]
+    fn push_constant_fields() {
+        let mut constants = Param::new("constants".into(), ParamType::Struct(64));
+        constants.fields = vec![
+            StructField {
+                name: "offsets".into(),
+                ty: ParamType::Vec4,
+                count: 3,
+                offset: 0,
+                stride: 16,
+            },
+            StructField {
+                name: "color".into(),
+                ty: ParamType::Vec4,
+                count: 1,
+                offset: 48,
+                stride: 16,
+            },
+        ];
+        let frag = Shader::new(
+            ShaderType::Fragment,
+            "frag".into(),
+            vec![],
+            vec![],
+            vec![constants],
+        );
+        let pipeline = Pipeline::builder().name("Main").shader(frag).build();
+
+        // Each field is pushed at its own offset, while the range covers the whole block
+        let ranges = pipeline.get_push_ranges();
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(ranges[0].get_size(), 64);
+
+        let methods = pipeline.get_push_methods();
+        let tokens = methods[0].to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains("pubfnpush_constants(&self,command_buffer:&CommandBuffer,constants:&PipelineMainPushConstants)"));
+        assert!(tokens.contains(
+            "pubfnpush_constants_offsets(&self,command_buffer:&CommandBuffer,offsets:&[[f32;4];3usize])"
+        ));
+        assert!(tokens.contains(
+            "pubfnpush_constants_color(&self,command_buffer:&CommandBuffer,color:&[f32;4])"
+        ));
+        assert!(tokens.contains(
+            "command_buffer.push_constants(self,vk::ShaderStageFlags::FRAGMENT,48u32,bytes);"
+        ));
+        assert!(tokens.contains("coloras*const[f32;4]as*constu8,16usize"));
+
+        let tokens = Mock(&methods[0])
+            .to_token_stream()
+            .to_string()
+            .replace(' ', "");
+        assert!(tokens.contains(
+            "self.record_push(\"push_constants_color\",vk::ShaderStageFlags::FRAGMENT,48u32,bytes);"
+        ));
+    }
+
+    #[test]
+    fn push_constant_array_field() {
+        let code = r#"
+            struct PushConstants {
+                float4 color;
+                float weights[4];
+                float exposure;
+            };
+
+            [vk::push_constant] PushConstants constants;
+
+            [shader("fragment")]
+            float4 main() : SV_Target {
+                return constants.color * constants.weights[3] * constants.exposure;
+            }
+        "#;
+
+        let slang = Slang::new();
+        let pipeline = Pipeline::builder()
+            .name("Main")
+            .shader(Shader::from(slang.from_source("frag", code)))
+            .build();
+
+        // Elements of a pushed array keep their std430 stride of 4 bytes
+        let tokens = pipeline.get_push_structs().to_string().replace(' ', "");
+        assert!(tokens.contains(
+            "pubstructPipelineMainPushConstants{pubcolor:[f32;4],pubweights:[f32;4usize],\
+            pubexposure:f32,_pad3:[u8;12usize],}"
+        ));
+        assert!(tokens.contains(
+            "const_:()=assert!(std::mem::size_of::<PipelineMainPushConstants>()==48usize);"
+        ));
+
+        // The array is pushed alone as its 16 bytes, without reaching the next field
+        let methods = pipeline.get_push_methods();
+        let tokens = methods[0].to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(
+            "pubfnpush_constants_weights(&self,command_buffer:&CommandBuffer,weights:&[f32;4usize])"
+        ));
+        assert!(tokens.contains("weightsas*const[f32;4usize]as*constu8,16usize"));
+        assert!(tokens.contains(
+            "command_buffer.push_constants(self,vk::ShaderStageFlags::FRAGMENT,16u32,bytes);"
+        ));
+        assert!(tokens.contains("exposureas*constf32as*constu8,4usize"));
+        assert!(tokens.contains(
+            "command_buffer.push_constants(self,vk::ShaderStageFlags::FRAGMENT,32u32,bytes);"
+        ));
+        assert!(tokens.contains("alone,16bytesatoffset16ofthepushconstantblock"));
+    }
+
+    #[test]
+    fn shared_push_constant_struct() {
+        let mut constants = Param::new("constants".into(), ParamType::Struct(80));
+        constants.fields = vec![
+            StructField {
+                name: "pretransform".into(),
+                ty: ParamType::Mat4,
+                count: 1,
+                offset: 0,
+                stride: 64,
+            },
+            StructField {
+                name: "color".into(),
+                ty: ParamType::Vec4,
+                count: 1,
+                offset: 64,
+                stride: 16,
+            },
+        ];
+        let mut vert = Shader::new(
+            ShaderType::Vertex,
+            "vert".into(),
+            vec![],
+            vec![],
+            vec![constants.clone()],
+        );
+        let mut frag = Shader::new(
+            ShaderType::Fragment,
+            "frag".into(),
+            vec![],
+            vec![],
+            vec![constants],
+        );
+        vert.code = Some(vec![0x03, 0x02, 0x23, 0x07]);
+        frag.code = Some(vec![0x03, 0x02, 0x23, 0x07]);
+        let pipeline = Pipeline::builder()
+            .name("Main")
+            .shader(vert)
+            .shader(frag)
+            .build();
+
+        // The same struct in both stages is pushed once, to a single range of both stages
+        let stages = "vk::ShaderStageFlags::from_raw(vk::ShaderStageFlags::VERTEX.as_raw()\
+            |vk::ShaderStageFlags::FRAGMENT.as_raw())";
+        let ranges = pipeline.get_push_ranges();
+        assert_eq!(ranges.len(), 1);
+        let tokens = ranges[0].to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(&format!(".stage_flags({})", stages)));
+
+        let methods = pipeline.get_push_methods();
+        assert_eq!(methods.len(), 1);
+        let tokens = methods[0].to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(&format!(
+            "command_buffer.push_constants(self,{},0u32,&bytes[0..80usize]);",
+            stages
+        )));
+
+        // Once for the pipeline and once for its mock
+        let tokens = pipeline.to_token_stream().to_string().replace(' ', "");
+        assert_eq!(tokens.matches("pubfnpush_constants(").count(), 2);
+        assert_eq!(
+            tokens.matches("pubstructPipelineMainPushConstants").count(),
+            1
+        );
+    }
+
+    #[test]
+    fn mat3_push_constant() {
+        let code = r#"
+            [vk::push_constant] float3x3 normal_matrix;
+
+            [shader("vertex")]
+            float4 main(float3 normal) : SV_Position {
+                return float4(mul(normal_matrix, normal), 1.0);
+            }
+        "#;
+
+        let slang = Slang::new();
+        let pipeline = Pipeline::builder()
+            .name("Normals")
+            .shader(Shader::from(slang.from_source("vert", code)))
+            .build();
+        assert_eq!(pipeline.shaders[0].constants[0].ty, ParamType::Mat3);
+
+        // Rows are padded to 16 bytes, so the range covers all of the third one
+        let ranges = pipeline.get_push_ranges();
+        assert_eq!(ranges[0].get_size(), 48);
+        let tokens = ranges[0].to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(".size(48usizeasu32)"));
+
+        let methods = pipeline.get_push_methods();
+        let tokens = methods[0].to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains("letpacked=normal_matrix.as_bytes();"));
+        assert!(
+            tokens
+                .contains("bytes[row*16..row*16+12].copy_from_slice(&packed[row*12..row*12+12]);")
+        );
+        assert!(tokens.contains("&bytes[0..48usize]"));
     }
 }
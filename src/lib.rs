@@ -6,6 +6,8 @@ extern crate proc_macro;
 
 use proc_macro::*;
 
+mod args;
+use args::*;
 mod model;
 use model::*;
 mod parse;
@@ -14,24 +16,491 @@ use parse::*;
 mod codegen;
 use codegen::*;
 
+mod sidecar;
+use sidecar::*;
+
 #[proc_macro]
 /// Takes as input:
 /// - the name of the pipeline
-/// - a search path
 /// - a path to a vertex shader
-/// - a path to a fragment shader
+/// - a path to a fragment shader, which can be omitted when the
+///   vertex shader path points to a module with both entry points
+///
+/// which can also be given by name as `name: Main, vert: "..", frag: ".."`, where
+/// `frag = ["lit.frag.slang", ..]` generates a pipeline per fragment shader, like `PipelineMainLit`, and
+/// - optional `key = value` options:
+///   - `optional = [name, ..]`: texture uniforms which can be bound to `None`
+///   - `max_gap = 4`: unused set or binding indices tolerated before warning
+///   - `deny_gaps = true`: report numbering gaps as errors instead of warnings
+///   - `precompiled = true`: shader paths are `.spv` files with a `.json` reflection sidecar
+///   - `export = "dir"`: write a `.spv` file and its `.json` sidecar for each shader to a directory
+///     relative to the crate, or to `OUT_DIR` with `export = true`, embedding their SPIR-V
+///   - `embed = true`: embed the SPIR-V compiled at expansion time instead of compiling at runtime
+///   - `blend = dual_source_coverage` or `additive`: blend preset replacing `VertexInput::get_color_blend()`
+///   - `debug_wireframe = true`: also create a line-mode pipeline toggled by `set_wireframe`
+///   - `subpass = 1`: subpass index instead of `VertexInput::get_subpass()`
+///   - `rasterizer_discard = true`: only the vertex stage, with no fragment shader nor color blending
+///   - `static_viewport = true`: viewport and scissor baked from an `extent` given to the constructors,
+///     instead of dynamic state set by `set_viewport`
+///   - `vertex = crate::gfx::SkinnedVertex`: vertex type of non-generic constructors like `new(&pass)`
+///   - `bindless_count = 4096`: descriptor count of unbounded arrays like `Texture2D textures[]`
+///   - `immutable_samplers = [name, ..]`: textures whose samplers are baked in the set layouts,
+///     provided to the constructors and bound by their image views
+///   - `dynamic = [name, ..]`: uniform buffers bound with a dynamic offset, taken after them
+///     by the bind methods, which then record on a raw `vk::CommandBuffer`
+///   - `buffer_offsets = [name, ..]`: buffers written from a `vk::DeviceSize` offset taken after them
+///     by the bind methods, for binding slices of a larger buffer
+///   - `image_layouts = [name = DEPTH_STENCIL_READ_ONLY_OPTIMAL, ..]`: images written in the given
+///     `vk::ImageLayout` instead of the one given to the bind methods, `SHADER_READ_ONLY_OPTIMAL` by default
+///   - `push_set = 2`: set written with `VK_KHR_push_descriptor` by a `push_set2` method
+///     instead of a bind method allocating it from `Descriptors`
+///   - `external_sets = [0, ..]`: sets bound by the engine, which are part of the pipeline layout
+///     but get no bind methods
+///   - `frames_in_flight = 3`: bind methods take a `frame_index: usize`, selecting a set per frame
+///   - `set_traits = [BindsCameraSet = 0, ..]`: traits with a `bind_set` method binding the set,
+///     implemented by the pipeline. A single identifier also defines the trait, while a path like
+///     `crate::BindsCameraSet` implements one defined by another pipeline with the same set layout
+///   - `struct_name = "MeshPso"`: name of the generated struct instead of `PipelineMesh`,
+///     while `get_name()` still returns `PipelineMesh`
+///   - `search_path = ["dir", ..]`: directories of imported Slang modules, relative to the crate
+///   - `vert_entry = vs_main`, `frag_entry = fs_main`: entry points instead of `main`,
+///     which can also follow the shader paths as `"path:entry"`
+///   - `defines = ["NAME", "NAME=value", ..]`: preprocessor macros, embedding the compiled SPIR-V
+///   - `profile = "sm_6_0"`: Slang profile of the target instead of `sm_6_5`
+///   - `spirv = "1.3"`: SPIR-V version of the target
+///   - `vulkan = "1.0"`: Vulkan version of the devices, selecting the profile and SPIR-V version
+///     of the target, and embedding the SPIR-V so that unsupported features are compile errors
+///   - `debug = true`, `optimize = none`: debug information and optimization level (`none`, `default`,
+///     or `high` as by default) of the compiled shaders, embedding their SPIR-V
+/// - a trailing block of fixed-function state, as `{ blend: additive, cull: back, topology: point_list, depth_write: false }`,
+///   multisampling as `{ samples: 4, alpha_to_coverage: true, sample_shading: 0.5 }`,
+///   and rasterization as `{ polygon_mode: line, line_width: 2.0 }`
 pub fn pipewriter(input: TokenStream) -> TokenStream {
-    let input_string = input.to_string().replace(['\"', ' '], "").replace(',', " ");
-    let args: Vec<&str> = input_string.split_whitespace().collect();
-    assert_eq!(args.len(), 3);
-    let name = args[0];
-    let vert_path = args[1];
-    let frag_path = args[2];
-
-    let slang = Slang::new();
-    let vert = slang.from_path(&vert_path);
-    let frag = slang.from_path(&frag_path);
-
-    let pipeline = Pipeline::builder().name(name).vert(vert).frag(frag).build();
-    codegen(pipeline).into()
+    let args = syn::parse_macro_input!(input as Args);
+    expand(args).into()
+}
+
+#[proc_macro]
+/// Takes the same arguments as `pipewriter!`, except that `vert` and `frag`
+/// are Slang sources instead of paths, e.g. `pipewriter_inline!(Blit, vert = r#".."#, frag = r#".."#)`.
+/// The compiled SPIR-V is embedded, so no shader file is needed at runtime.
+pub fn pipewriter_inline(input: TokenStream) -> TokenStream {
+    let InlineArgs(args) = syn::parse_macro_input!(input as InlineArgs);
+    expand(args).into()
+}
+
+#[proc_macro]
+/// Takes a directory relative to the crate, e.g. `pipewriter_dir!("shaders/materials")`,
+/// and generates a pipeline for each `<name>.vert.slang` and `<name>.frag.slang` pair in it,
+/// named after `<name>` in camel case, like `PipelineToonShaded` for `toon_shaded`.
+/// The directory can be followed by the options of `pipewriter!`, which apply to every pipeline.
+pub fn pipewriter_dir(input: TokenStream) -> TokenStream {
+    let DirArgs(pipelines) = syn::parse_macro_input!(input as DirArgs);
+    pipelines
+        .into_iter()
+        .enumerate()
+        .map(|(index, mut args)| {
+            if index > 0 {
+                args.set_traits = refer_set_traits(&args.set_traits);
+            }
+            expand(args)
+        })
+        .collect::<proc_macro2::TokenStream>()
+        .into()
+}
+
+#[proc_macro]
+/// Takes a name and a path to a Slang module with a compute entry point,
+/// optionally followed by `:entry`, e.g. `pipewriter_compute!(Blur, "shaders/blur.slang")`,
+/// and the `optional`, `max_gap`, `deny_gaps`, `search_path`, `defines`, `profile`, `spirv`, `vulkan`, `debug`, `optimize`,
+/// `dynamic`, `buffer_offsets`, `image_layouts`, `push_set`, `external_sets`, `frames_in_flight`, `set_traits`,
+/// and `struct_name` options.
+/// The compiled SPIR-V is embedded, and descriptor sets are bound at the compute bind point.
+pub fn pipewriter_compute(input: TokenStream) -> TokenStream {
+    let ComputeArgs(args) = syn::parse_macro_input!(input as ComputeArgs);
+
+    let slang = match new_slang(&args) {
+        Ok(slang) => slang,
+        Err(error) => return error.into(),
+    };
+    let entry = args.vert_entry.as_deref().unwrap_or("main");
+    let compute = slang.entry_from_path(&args.vert, entry);
+    let spirv = compute.get_spirv();
+
+    let mut pipeline = Pipeline::builder()
+        .name(args.name)
+        .optional(args.optional)
+        .struct_name(args.struct_name)
+        .bindless_count(args.bindless_count)
+        .dynamic(args.dynamic)
+        .buffer_offsets(args.buffer_offsets)
+        .image_layouts(args.image_layouts)
+        .external_sets(args.external_sets)
+        .push_set(args.push_set)
+        .frames_in_flight(args.frames_in_flight)
+        .set_traits(args.set_traits)
+        .compute(compute)
+        .build();
+    if !pipeline.is_compute() {
+        panic!(
+            "{}:{}: Entry point `{}` of `{}` is not a compute shader",
+            file!(),
+            line!(),
+            entry,
+            args.vert.display()
+        );
+    }
+    pipeline.shaders[0].code = Some(spirv);
+
+    check_gaps(pipeline, args.max_gap, args.deny_gaps).into()
+}
+
+#[proc_macro]
+/// Takes a name, a `raygen` shader path, and arrays of `miss` and `hit` shader paths,
+/// each optionally followed by `:entry`, e.g.
+/// `pipewriter_rt!(PathTrace, raygen = "rt.rgen.slang", miss = ["rt.rmiss.slang"], hit = ["rt.rchit.slang"])`,
+/// and the same options as `pipewriter_compute!`. The compiled SPIR-V is embedded, and
+/// descriptor sets are bound at the ray tracing bind point.
+pub fn pipewriter_rt(input: TokenStream) -> TokenStream {
+    let RayTracingArgs { args, miss, hit } = syn::parse_macro_input!(input as RayTracingArgs);
+
+    let slang = match new_slang(&args) {
+        Ok(slang) => slang,
+        Err(error) => return error.into(),
+    };
+
+    let raygen = (args.vert.clone(), args.vert_entry.clone());
+    let shaders = std::iter::once((raygen, ShaderType::RayGeneration))
+        .chain(miss.into_iter().map(|miss| (miss, ShaderType::Miss)))
+        .chain(hit.into_iter().map(|hit| (hit, ShaderType::ClosestHit)));
+
+    let mut builder = Pipeline::builder()
+        .name(args.name)
+        .optional(args.optional)
+        .struct_name(args.struct_name)
+        .bindless_count(args.bindless_count)
+        .dynamic(args.dynamic)
+        .buffer_offsets(args.buffer_offsets)
+        .image_layouts(args.image_layouts)
+        .external_sets(args.external_sets)
+        .push_set(args.push_set)
+        .frames_in_flight(args.frames_in_flight)
+        .set_traits(args.set_traits);
+    for ((path, entry), ty) in shaders {
+        let entry = entry.as_deref().unwrap_or("main");
+        let reflection = slang.entry_from_path(&path, entry);
+        let spirv = reflection.get_spirv();
+        let mut shader = Shader::from(reflection);
+        if shader.ty != ty {
+            panic!(
+                "{}:{}: Entry point `{}` of `{}` is not a {:?} shader",
+                file!(),
+                line!(),
+                entry,
+                path.display(),
+                ty
+            );
+        }
+        shader.code = Some(spirv);
+        builder = builder.shader(shader);
+    }
+
+    check_gaps(builder.build(), args.max_gap, args.deny_gaps).into()
+}
+
+fn expand(args: Args) -> proc_macro2::TokenStream {
+    // Each fragment shader generates a pipeline with the same vertex shader,
+    // while a rasterizer discard pipeline has none
+    let (vert, frags) = if args.precompiled {
+        let vert = load_shader(&args.vert);
+        let frags = match &args.frag {
+            Some(frag) => vec![(args.name.clone(), Some(load_shader(frag)))],
+            None if args.rasterizer_discard => vec![(args.name.clone(), None)],
+            None => args
+                .variants
+                .iter()
+                .map(|variant| {
+                    let name = format!("{}{}", args.name, variant.name);
+                    (name, Some(load_shader(&variant.path)))
+                })
+                .collect(),
+        };
+        (vert, frags)
+    } else {
+        let slang = match new_slang(&args) {
+            Ok(slang) => slang,
+            Err(error) => return error,
+        };
+
+        // Inline sources, variants with defines, lone vertex shaders, and shaders
+        // compiled with other options or for another target can not be compiled
+        // from a path at runtime.
+        // Shaders for a Vulkan version are compiled now, so that Slang reports
+        // what the version can not express as a compile error. Exported shaders
+        // are embedded as well, so that loading them back expands to the same code.
+        let embed = args.embed
+            || args.export.is_some()
+            || args.sources.is_some()
+            || !args.defines.is_empty()
+            || args.rasterizer_discard
+            || args.debug
+            || args.optimize.is_some()
+            || args.profile.is_some()
+            || args.spirv.is_some()
+            || args.vulkan.is_some();
+        let to_shader = |reflection: ShaderReflection| {
+            let spirv = embed.then(|| reflection.get_spirv());
+            let mut shader = Shader::from(reflection);
+            if let Some(spirv) = spirv {
+                if let Some(dir) = &args.export {
+                    export_shader(&mut shader, &spirv, dir);
+                }
+                shader.code = Some(spirv);
+            }
+            shader
+        };
+
+        let vert_entry = args.vert_entry.as_deref().unwrap_or("main");
+        let frag_entry = args.frag_entry.as_deref().unwrap_or("main");
+        if args.rasterizer_discard {
+            let vert = to_shader(slang.entry_from_path(&args.vert, vert_entry));
+            (vert, vec![(args.name.clone(), None)])
+        } else if !args.variants.is_empty() {
+            // The vertex shader is reflected once for all the variants
+            let vert = to_shader(slang.entry_from_path(&args.vert, vert_entry));
+            let frags = args
+                .variants
+                .iter()
+                .map(|variant| {
+                    let name = format!("{}{}", args.name, variant.name);
+                    let entry = variant.entry.as_deref().unwrap_or("main");
+                    let frag = to_shader(slang.entry_from_path(&variant.path, entry));
+                    (name, Some(frag))
+                })
+                .collect();
+            (vert, frags)
+        } else {
+            let (vert, frag) = match (
+                &args.sources,
+                &args.frag,
+                &args.vert_entry,
+                &args.frag_entry,
+            ) {
+                (Some((vert_source, frag_source)), ..) => (
+                    slang.entry_from_source(&args.vert, vert_source, vert_entry),
+                    slang.entry_from_source(args.frag.as_ref().unwrap(), frag_source, frag_entry),
+                ),
+                (None, None, None, None) => slang.stages_from_path(&args.vert),
+                (None, frag, ..) => (
+                    slang.entry_from_path(&args.vert, vert_entry),
+                    slang.entry_from_path(frag.as_ref().unwrap_or(&args.vert), frag_entry),
+                ),
+            };
+            (
+                to_shader(vert),
+                vec![(args.name.clone(), Some(to_shader(frag)))],
+            )
+        }
+    };
+
+    let mut tokens = proc_macro2::TokenStream::new();
+    for (index, (name, frag)) in frags.into_iter().enumerate() {
+        let set_traits = match index {
+            0 => args.set_traits.clone(),
+            _ => refer_set_traits(&args.set_traits),
+        };
+        let mut builder = Pipeline::builder()
+            .name(name)
+            .optional(args.optional.clone())
+            .debug_wireframe(args.debug_wireframe)
+            .options(args.options)
+            .subpass(args.subpass)
+            .rasterizer_discard(args.rasterizer_discard)
+            .static_viewport(args.static_viewport)
+            .struct_name(args.struct_name.clone())
+            .vertex(args.vertex.clone())
+            .bindless_count(args.bindless_count)
+            .immutable_samplers(args.immutable_samplers.clone())
+            .dynamic(args.dynamic.clone())
+            .buffer_offsets(args.buffer_offsets.clone())
+            .image_layouts(args.image_layouts.clone())
+            .external_sets(args.external_sets.clone())
+            .push_set(args.push_set)
+            .frames_in_flight(args.frames_in_flight)
+            .set_traits(set_traits)
+            .shader(vert.clone());
+        if let Some(frag) = frag {
+            builder = builder.shader(frag);
+        }
+        let pipeline = builder.build();
+        tokens.extend(check_gaps(pipeline, args.max_gap, args.deny_gaps));
+    }
+    tokens
+}
+
+/// Refers to the set traits defined by the first of several pipelines generated
+/// by the same invocation, instead of defining them again
+fn refer_set_traits(set_traits: &[(String, u32)]) -> Vec<(String, u32)> {
+    set_traits
+        .iter()
+        .map(|(name, set)| match name.contains("::") {
+            true => (name.clone(), *set),
+            false => (format!("self::{}", name), *set),
+        })
+        .collect()
+}
+
+/// Creates a Slang session for the target of the invocation, reporting
+/// an unknown profile or SPIR-V version as a compile error
+fn new_slang(args: &Args) -> Result<Slang, proc_macro2::TokenStream> {
+    let profile = args.profile.as_deref();
+    let spirv = args.spirv.as_deref();
+    Slang::with_options(
+        &args.search_paths,
+        &args.defines,
+        profile.or(args.vulkan.map(|vulkan| vulkan.get_profile())),
+        spirv.or(args.vulkan.map(|vulkan| vulkan.get_spirv())),
+        args.optimize.unwrap_or_default(),
+        args.debug,
+    )
+    .map_err(|error| syn::Error::new(proc_macro2::Span::call_site(), error).to_compile_error())
+}
+
+/// Generates the pipeline, reporting numbering gaps as warnings or as errors,
+/// and push constants larger than any device supports as warnings
+fn check_gaps(pipeline: Pipeline, max_gap: u32, deny_gaps: bool) -> proc_macro2::TokenStream {
+    let gaps = pipeline.get_numbering_gaps(max_gap);
+    if deny_gaps && !gaps.is_empty() {
+        let span = proc_macro2::Span::call_site();
+        return gaps
+            .iter()
+            .map(|gap| syn::Error::new(span, gap))
+            .reduce(|mut error, other| {
+                error.combine(other);
+                error
+            })
+            .unwrap()
+            .to_compile_error();
+    }
+
+    let push_size_warnings = pipeline.get_push_size_warnings();
+    let mut tokens = codegen(pipeline);
+    tokens.extend(codegen_warnings(&gaps));
+    tokens.extend(codegen_warnings(&push_size_warnings));
+    tokens
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn expand_inline() {
+        let input = r###"
+            Blit,
+            vert = r#"
+                [shader("vertex")]
+                float4 main(float2 pos) : SV_Position {
+                    return float4(pos, 0.0, 1.0);
+                }
+            "#,
+            frag = r#"
+                [vk::binding(0, 0)]
+                Sampler2D source;
+
+                [shader("fragment")]
+                float4 main(float2 uv) : SV_Target {
+                    return source.Sample(uv);
+                }
+            "#
+        "###;
+
+        let InlineArgs(args) = syn::parse_str(input).unwrap();
+        let tokens = expand(args);
+        let file: syn::File = syn::parse2(tokens.clone()).unwrap();
+        assert!(!file.items.is_empty());
+
+        let tokens = tokens.to_string().replace(' ', "");
+        assert!(tokens.contains("pubstructPipelineBlit"));
+        // Bind methods are named after their set, with their former names deprecated
+        assert!(tokens.contains("#[doc(alias=\"bind_source\")]pubfnbind_set0("));
+        assert!(tokens.contains(
+            "#[deprecated(note=\"Use`bind_set0`,namedaftertheset,whichdoesnotchangewithitsuniforms\")]\
+            pubfnbind_source("
+        ));
+        assert!(tokens.contains("#[repr(C,align(4))]structAlignedSpirv"));
+        assert!(!tokens.contains("create_shaders"));
+    }
+
+    #[test]
+    fn expand_struct_name() {
+        let input = r###"
+            Blit,
+            vert = r#"
+                [shader("vertex")]
+                float4 main(float2 pos) : SV_Position {
+                    return float4(pos, 0.0, 1.0);
+                }
+            "#,
+            frag = r#"
+                [vk::binding(0, 0)]
+                Sampler2D source;
+
+                [shader("fragment")]
+                float4 main(float2 uv) : SV_Target {
+                    return source.Sample(uv);
+                }
+            "#
+        "###;
+
+        let InlineArgs(args) = syn::parse_str(input).unwrap();
+        let tokens = expand(args).to_string().replace(' ', "");
+        assert!(tokens.contains("pubstructPipelineBlit{"));
+        assert!(tokens.contains("pubenumPipelineBlitUniform"));
+
+        let input = format!("{}, struct_name = \"BlitPso\"", input);
+        let InlineArgs(args) = syn::parse_str(&input).unwrap();
+        let tokens = expand(args).to_string().replace(' ', "");
+        assert!(tokens.contains("pubstructBlitPso{"));
+        assert!(tokens.contains("pubstructBlitPsoHandle{"));
+        assert!(tokens.contains("pubenumBlitPsoUniform"));
+        assert!(tokens.contains("String::from(\"PipelineBlit\")"));
+        assert!(!tokens.contains("pubstructPipelineBlit"));
+    }
+
+    #[test]
+    fn expand_target() {
+        let input = r###"
+            Blit,
+            vert = r#"
+                [shader("vertex")]
+                float4 main(float2 pos) : SV_Position {
+                    return float4(pos, 0.0, 1.0);
+                }
+            "#,
+            frag = r#"
+                [shader("fragment")]
+                float4 main() : SV_Target {
+                    return float4(1.0);
+                }
+            "#
+        "###;
+
+        // An unknown target is a compile error rather than a panic
+        let args = format!("{}, profile = \"sm_0_0\"", input);
+        let InlineArgs(args) = syn::parse_str(&args).unwrap();
+        let tokens = expand(args).to_string();
+        assert!(tokens.contains("compile_error"));
+        assert!(tokens.contains("Unknown Slang profile `sm_0_0`"));
+
+        // SPIR-V versions are checked when parsing, then against the ones Slang knows
+        let args = format!("{}, spirv = 0.1", input);
+        let error = syn::parse_str::<InlineArgs>(&args).err().unwrap();
+        assert!(error.to_string().contains("Expected a SPIR-V version"));
+        let error =
+            Slang::with_options(&[], &[], None, Some("1.9"), Optimization::default(), false)
+                .err()
+                .unwrap();
+        assert_eq!(error, "Unknown SPIR-V version `1.9`");
+    }
 }
@@ -4,34 +4,231 @@
 
 use std::path::PathBuf;
 
+use serde::{Deserialize, Serialize};
+
 use crate::ShaderReflection;
 
+/// Smallest `maxPushConstantsSize` allowed by the Vulkan spec, so the one of any device
+pub const MIN_MAX_PUSH_CONSTANTS_SIZE: usize = 128;
+
 #[derive(Default)]
-pub struct PipelineBuilder<'a> {
+pub struct PipelineBuilder {
     name: String,
-    shaders: Vec<ShaderReflection<'a>>,
+    shaders: Vec<Shader>,
+    optional: Vec<String>,
+    debug_wireframe: bool,
+    options: PipelineOptions,
+    subpass: Option<u32>,
+    rasterizer_discard: bool,
+    static_viewport: bool,
+    struct_name: Option<String>,
+    vertex: Option<String>,
+    bindless_count: Option<u32>,
+    immutable_samplers: Vec<String>,
+    dynamic: Vec<String>,
+    buffer_offsets: Vec<String>,
+    push_set: Option<u32>,
+    frames_in_flight: Option<u32>,
+    set_traits: Vec<(String, u32)>,
+    image_layouts: Vec<(String, String)>,
+    external_sets: Vec<u32>,
 }
 
-impl<'a> PipelineBuilder<'a> {
+impl PipelineBuilder {
     pub fn name<S: Into<String>>(mut self, name: S) -> Self {
         self.name = name.into();
         self
     }
 
-    pub fn vert(mut self, vert: ShaderReflection<'a>) -> Self {
-        self.shaders.push(vert);
+    pub fn compute(mut self, compute: ShaderReflection) -> Self {
+        self.shaders.push(Shader::from(compute));
+        self
+    }
+
+    /// Adds a shader which has already been reflected, e.g. loaded from a sidecar
+    pub fn shader(mut self, shader: Shader) -> Self {
+        self.shaders.push(shader);
+        self
+    }
+
+    /// Texture uniforms which can be left unbound in favor of a default texture
+    pub fn optional(mut self, optional: Vec<String>) -> Self {
+        self.optional = optional;
+        self
+    }
+
+    /// Also creates a line-mode variant of the pipeline which can be toggled at runtime
+    pub fn debug_wireframe(mut self, debug_wireframe: bool) -> Self {
+        self.debug_wireframe = debug_wireframe;
+        self
+    }
+
+    /// Fixed-function state overriding the one provided by the vertex input
+    pub fn options(mut self, options: PipelineOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Subpass index replacing `VertexInput::get_subpass()`
+    pub fn subpass(mut self, subpass: Option<u32>) -> Self {
+        self.subpass = subpass;
+        self
+    }
+
+    /// Discards primitives after the vertex stage, so that the pipeline
+    /// has neither a fragment shader nor color blend state
+    pub fn rasterizer_discard(mut self, rasterizer_discard: bool) -> Self {
+        self.rasterizer_discard = rasterizer_discard;
+        self
+    }
+
+    /// Bakes the viewport and scissor from an extent given to the constructors,
+    /// instead of making them dynamic state set by `set_viewport`
+    pub fn static_viewport(mut self, static_viewport: bool) -> Self {
+        self.static_viewport = static_viewport;
+        self
+    }
+
+    /// Name of the generated struct replacing `Pipeline{name}`
+    pub fn struct_name(mut self, struct_name: Option<String>) -> Self {
+        self.struct_name = struct_name;
+        self
+    }
+
+    /// Path of the vertex type which replaces the generic parameter of the constructors
+    pub fn vertex(mut self, vertex: Option<String>) -> Self {
+        self.vertex = vertex;
+        self
+    }
+
+    /// Number of descriptors of unbounded arrays, e.g. `Texture2D textures[]`
+    pub fn bindless_count(mut self, bindless_count: Option<u32>) -> Self {
+        self.bindless_count = bindless_count;
         self
     }
 
-    pub fn frag(mut self, frag: ShaderReflection<'a>) -> Self {
-        self.shaders.push(frag);
+    /// Texture uniforms whose samplers are baked in the set layouts
+    pub fn immutable_samplers(mut self, immutable_samplers: Vec<String>) -> Self {
+        self.immutable_samplers = immutable_samplers;
+        self
+    }
+
+    /// Uniform buffers bound with a dynamic offset into a larger buffer
+    pub fn dynamic(mut self, dynamic: Vec<String>) -> Self {
+        self.dynamic = dynamic;
+        self
+    }
+
+    /// Image uniforms written in a fixed layout, as names and `vk::ImageLayout` constants
+    pub fn image_layouts(mut self, image_layouts: Vec<(String, String)>) -> Self {
+        self.image_layouts = image_layouts;
+        self
+    }
+
+    /// Uniform and storage buffers bound from an offset into a larger buffer
+    pub fn buffer_offsets(mut self, buffer_offsets: Vec<String>) -> Self {
+        self.buffer_offsets = buffer_offsets;
+        self
+    }
+
+    /// Set whose descriptors are pushed on the command buffer instead of being
+    /// allocated, for sets changing at every draw
+    pub fn push_set(mut self, push_set: Option<u32>) -> Self {
+        self.push_set = push_set;
+        self
+    }
+
+    /// Sets bound by the engine rather than by the pipeline, which get no bind methods
+    pub fn external_sets(mut self, external_sets: Vec<u32>) -> Self {
+        self.external_sets = external_sets;
+        self
+    }
+
+    /// Number of frames in flight, each binding sets of its own
+    pub fn frames_in_flight(mut self, frames_in_flight: Option<u32>) -> Self {
+        self.frames_in_flight = frames_in_flight;
+        self
+    }
+
+    /// Traits binding a set, as paths and set indices, implemented by the pipeline
+    /// so that pipelines sharing a set layout can be bound by generic code
+    pub fn set_traits(mut self, set_traits: Vec<(String, u32)>) -> Self {
+        self.set_traits = set_traits;
         self
     }
 
     pub fn build(self) -> Pipeline {
         assert!(!self.name.is_empty());
         assert!(!self.shaders.is_empty());
-        Pipeline::new(self.name, self.shaders)
+
+        if self.options.blend == Blend::DualSourceCoverage {
+            let outputs = self
+                .shaders
+                .iter()
+                .filter(|shader| shader.ty == ShaderType::Fragment)
+                .map(|shader| shader.outputs.len())
+                .sum::<usize>();
+            if outputs != 2 {
+                panic!(
+                    "{}:{}: Dual source blending needs a fragment shader with two outputs, found {}",
+                    file!(),
+                    line!(),
+                    outputs
+                );
+            }
+        }
+
+        if self.rasterizer_discard
+            && self
+                .shaders
+                .iter()
+                .any(|shader| shader.ty != ShaderType::Vertex)
+        {
+            panic!(
+                "{}:{}: Rasterizer discard pipelines need a single vertex shader",
+                file!(),
+                line!()
+            );
+        }
+
+        let mut pipeline = Pipeline {
+            name: self.name,
+            shaders: self.shaders,
+            debug_wireframe: self.debug_wireframe,
+            options: self.options,
+            subpass: self.subpass,
+            rasterizer_discard: self.rasterizer_discard,
+            static_viewport: self.static_viewport,
+            struct_name: self.struct_name,
+            vertex: self.vertex,
+            push_set: None,
+            frames_in_flight: self.frames_in_flight,
+            set_traits: self.set_traits,
+            external_sets: Vec::new(),
+        };
+        for name in &self.optional {
+            pipeline.set_optional(name);
+        }
+        for name in &self.immutable_samplers {
+            pipeline.set_immutable_sampler(name);
+        }
+        for name in &self.dynamic {
+            pipeline.set_dynamic(name);
+        }
+        for name in &self.buffer_offsets {
+            pipeline.set_buffer_offset(name);
+        }
+        for (name, layout) in &self.image_layouts {
+            pipeline.set_image_layout(name, layout);
+        }
+        pipeline.set_bindless_count(self.bindless_count);
+        if let Some(push_set) = self.push_set {
+            pipeline.set_push_set(push_set);
+        }
+        for set in self.external_sets {
+            pipeline.set_external_set(set);
+        }
+        pipeline
     }
 }
 
@@ -39,83 +236,713 @@ impl<'a> PipelineBuilder<'a> {
 pub struct Pipeline {
     pub name: String,
     pub shaders: Vec<Shader>,
+    pub debug_wireframe: bool,
+    pub options: PipelineOptions,
+    pub subpass: Option<u32>,
+    pub rasterizer_discard: bool,
+    /// Viewport and scissor are baked from an extent given to the constructors
+    pub static_viewport: bool,
+    pub struct_name: Option<String>,
+    pub vertex: Option<String>,
+    /// Set written with push descriptors instead of allocated sets
+    pub push_set: Option<u32>,
+    /// Bind methods take the index of the frame, which selects a set of its own
+    pub frames_in_flight: Option<u32>,
+    /// Traits binding a set, as paths and set indices. A trait named by a single
+    /// identifier is also defined by the pipeline, while a longer path refers to a
+    /// trait defined by another pipeline
+    pub set_traits: Vec<(String, u32)>,
+    /// Sets bound by the engine, which are part of the pipeline layout but get no bind methods
+    pub external_sets: Vec<u32>,
 }
 
 impl Pipeline {
-    pub fn builder<'a>() -> PipelineBuilder<'a> {
+    pub fn builder() -> PipelineBuilder {
         PipelineBuilder::default()
     }
 
-    pub fn get_set_layouts(&self) -> Vec<SetLayout> {
-        let mut ret = Vec::new();
+    /// Logical name of the pipeline returned by `get_name()`
+    pub fn get_pipeline_name(&self) -> String {
+        format!("Pipeline{}", self.name)
+    }
+
+    /// Name of the generated struct, which prefixes its handle, error, and uniform types
+    pub fn get_struct_name(&self) -> String {
+        self.struct_name
+            .clone()
+            .unwrap_or_else(|| self.get_pipeline_name())
+    }
+
+    /// Whether this is a compute pipeline made of a single compute shader
+    pub fn is_compute(&self) -> bool {
+        self.shaders
+            .first()
+            .is_some_and(|shader| shader.ty == ShaderType::Compute)
+    }
 
-        let vert = &self.shaders[0];
-        let frag = &self.shaders[1];
+    /// Whether this is a ray tracing pipeline, starting with a ray generation shader
+    pub fn is_ray_tracing(&self) -> bool {
+        self.shaders
+            .first()
+            .is_some_and(|shader| shader.ty == ShaderType::RayGeneration)
+    }
 
-        if vert.uniforms.is_empty() && frag.uniforms.is_empty() {
-            return ret;
+    pub fn get_bind_point(&self) -> BindPoint {
+        if self.is_compute() {
+            BindPoint::Compute
+        } else if self.is_ray_tracing() {
+            BindPoint::RayTracing
+        } else {
+            BindPoint::Graphics
         }
+    }
 
-        // Find the number of descriptor looking into both shaders
-        let descriptor_count = vert.get_descriptor_max().max(frag.get_descriptor_max()) + 1;
-        for set in 0..descriptor_count {
-            ret.push(SetLayout::new(self.get_set_layout_bindings(set)));
+    /// Number of descriptor sets looking into all shaders
+    fn get_descriptor_count(&self) -> u32 {
+        if self.shaders.iter().all(|shader| shader.uniforms.is_empty()) {
+            return 0;
         }
 
-        ret
+        self.shaders
+            .iter()
+            .map(|shader| shader.get_descriptor_max())
+            .max()
+            .unwrap_or_default()
+            + 1
     }
 
-    pub fn get_set_layout_bindings(&self, set: u32) -> Vec<SetLayoutBinding> {
+    /// Layouts of all sets up to the last one used, where sets skipped by the
+    /// shaders have no bindings so that the indices of the others are kept
+    pub fn get_set_layouts(&self) -> Vec<SetLayout> {
         let mut ret = Vec::new();
-        let vert = &self.shaders[0];
-        let frag = &self.shaders[1];
-        ret.extend(vert.get_set_layout_bindings(set));
-        ret.extend(frag.get_set_layout_bindings(set));
+
+        let descriptor_count = self.get_descriptor_count();
+        for set in 0..descriptor_count {
+            let mut set_layout = SetLayout::new(self.get_set_layout_bindings(set));
+            set_layout.push = self.push_set == Some(set);
+            ret.push(set_layout);
+        }
+
         ret
     }
 
-    pub fn get_bind_methods(&self) -> Vec<BindMethod> {
-        let mut ret = Vec::new();
+    /// Descriptor counts of each type across the layouts of all sets,
+    /// for sizing pools allocating one set of each layout
+    pub fn get_pool_sizes(&self) -> Vec<(DescriptorType, u32)> {
+        let mut ret: Vec<(DescriptorType, u32)> = Vec::new();
+        for (set, set_layout) in self.get_set_layouts().into_iter().enumerate() {
+            // Pushed descriptors are not allocated from pools, and external sets
+            // are allocated by the engine
+            if set_layout.push || self.external_sets.contains(&(set as u32)) {
+                continue;
+            }
+            for binding in set_layout.bindings {
+                match ret
+                    .iter_mut()
+                    .find(|(ty, _)| *ty == binding.descriptor_type)
+                {
+                    Some((_, count)) => *count += binding.count,
+                    None => ret.push((binding.descriptor_type, binding.count)),
+                }
+            }
+        }
+        ret
+    }
 
-        let vert = &self.shaders[0];
-        let frag = &self.shaders[1];
+    /// Bindings of a set, where a binding used by several shaders is declared
+    /// once with the stages of all of them
+    pub fn get_set_layout_bindings(&self, set: u32) -> Vec<SetLayoutBinding> {
+        self.check_binding_conflicts(set);
 
-        if vert.uniforms.is_empty() && frag.uniforms.is_empty() {
-            return ret;
+        let mut ret: Vec<SetLayoutBinding> = Vec::new();
+        for shader in &self.shaders {
+            for binding in shader.get_set_layout_bindings(set) {
+                let Some(other) = ret
+                    .iter_mut()
+                    .find(|other| other.binding == binding.binding)
+                else {
+                    ret.push(binding);
+                    continue;
+                };
+                for stage in binding.stages {
+                    if !other.stages.contains(&stage) {
+                        other.stages.push(stage);
+                    }
+                }
+            }
         }
+        ret.sort_by_key(|binding| binding.binding);
+        ret
+    }
 
-        // Find the number of descriptor looking into both shaders
-        let descriptor_count = vert.get_descriptor_max().max(frag.get_descriptor_max()) + 1;
-        ret.resize(descriptor_count as usize, BindMethod::default());
+    /// Uniforms of different shaders can share a binding only when they are
+    /// the same resource, otherwise the layout would misbind one of them
+    fn check_binding_conflicts(&self, set: u32) {
+        let uniforms: Vec<(&Shader, &Uniform)> = self
+            .shaders
+            .iter()
+            .flat_map(|shader| shader.uniforms.iter().map(move |uniform| (shader, uniform)))
+            .filter(|(_, uniform)| uniform.set == set)
+            .collect();
+
+        for (i, (shader, uniform)) in uniforms.iter().enumerate() {
+            for (other_shader, other) in &uniforms[i + 1..] {
+                if other.binding != uniform.binding
+                    || (other.get_descriptor_type() == uniform.get_descriptor_type()
+                        && other.array_count == uniform.array_count
+                        && other.get_buffer_size() == uniform.get_buffer_size())
+                {
+                    continue;
+                }
+                panic!(
+                    "{}:{}: `{}` in {} and `{}` in {} conflict at set {}, binding {}: {:?}[{}] and {:?}[{}]",
+                    file!(),
+                    line!(),
+                    uniform.param.name,
+                    shader.path.display(),
+                    other.param.name,
+                    other_shader.path.display(),
+                    set,
+                    uniform.binding,
+                    uniform.param.ty,
+                    uniform.array_count,
+                    other.param.ty,
+                    other.array_count
+                );
+            }
+        }
+    }
 
-        vert.get_bind_methods(&mut ret);
-        frag.get_bind_methods(&mut ret);
+    /// Uniforms of all shaders, once per name
+    pub fn get_uniforms(&self) -> Vec<&Uniform> {
+        let mut ret: Vec<&Uniform> = Vec::new();
+        for shader in &self.shaders {
+            for uniform in &shader.uniforms {
+                if !ret
+                    .iter()
+                    .any(|other| other.param.name == uniform.param.name)
+                {
+                    ret.push(uniform);
+                }
+            }
+        }
         ret
     }
 
-    pub fn get_push_ranges(&self) -> Vec<PushRange> {
+    pub fn get_bind_methods(&self) -> Vec<BindMethod> {
         let mut ret = Vec::new();
-        for param in &self.shaders[0].constants {
-            ret.push(PushRange::new(param.ty, ShaderType::Vertex));
+
+        let descriptor_count = self.get_descriptor_count();
+        let method = BindMethod {
+            pipeline: self.get_struct_name(),
+            bind_point: self.get_bind_point(),
+            frames_in_flight: self.frames_in_flight,
+            ..Default::default()
+        };
+        ret.resize(descriptor_count as usize, method);
+
+        for shader in &self.shaders {
+            shader.get_bind_methods(&mut ret);
         }
-        for param in &self.shaders[1].constants {
-            ret.push(PushRange::new(param.ty, ShaderType::Fragment));
+        if let Some(push_set) = self.push_set {
+            ret[push_set as usize].push = true;
         }
+        // Sets skipped by the shaders have nothing to bind, and external sets are bound by the engine
+        ret.retain(|method| {
+            !method.uniforms.is_empty() && !self.external_sets.contains(&method.uniforms[0].set)
+        });
         ret
     }
 
+    /// Returns a method binding all the sets in a single call, when they are
+    /// contiguous from set 0 and none of them is written with push descriptors
+    pub fn get_bind_all_method(&self) -> Option<BindAllMethod> {
+        let methods = self.get_bind_methods();
+        let contiguous = methods
+            .iter()
+            .enumerate()
+            .all(|(index, method)| method.uniforms[0].set as usize == index && !method.push);
+        if methods.is_empty() || !contiguous {
+            return None;
+        }
+        Some(BindAllMethod { methods })
+    }
+
+    /// Push constant ranges, one per constant with the stages reading it
+    pub fn get_push_ranges(&self) -> Vec<PushRange> {
+        self.get_push_methods()
+            .into_iter()
+            .map(|method| PushRange {
+                ty: method.ty,
+                count: method.count,
+                stages: method.stages,
+                offset: method.offset,
+            })
+            .collect()
+    }
+
+    /// Methods pushing each constant, where a constant declared with the same name
+    /// and type by several shaders is pushed once to all of their stages
     pub fn get_push_methods(&self) -> Vec<PushMethod> {
-        let mut ret = Vec::new();
+        let mut ret: Vec<PushMethod> = Vec::new();
 
         for shader in &self.shaders {
             for param in &shader.constants {
-                ret.push(PushMethod::new(param.name.clone(), param.ty, shader.ty));
+                if let Some(method) = ret.iter_mut().find(|method| method.name == param.name) {
+                    if method.ty != param.ty
+                        || method.count != param.count
+                        || method.fields != param.fields
+                    {
+                        panic!(
+                            "{}:{}: Push constant `{}` of the {:?} stage has a different type in the {:?} stage",
+                            file!(),
+                            line!(),
+                            param.name,
+                            method.stages[0],
+                            shader.ty
+                        );
+                    }
+                    if method.offset != param.offset {
+                        panic!(
+                            "{}:{}: Push constant `{}` of the {:?} stage is at offset {} in the {:?} stage",
+                            file!(),
+                            line!(),
+                            param.name,
+                            method.stages[0],
+                            param.offset,
+                            shader.ty
+                        );
+                    }
+                    if !method.stages.contains(&shader.ty) {
+                        method.stages.push(shader.ty);
+                    }
+                    continue;
+                }
+                let mut method = PushMethod::new(param.name.clone(), param.ty, shader.ty);
+                method.count = param.count;
+                method.pipeline = self.get_struct_name();
+                method.fields = param.fields.clone();
+                method.offset = param.offset;
+                ret.push(method);
+            }
+        }
+
+        // Different constants read from the same bytes would overwrite each other when pushed
+        for (i, method) in ret.iter().enumerate() {
+            for other in &ret[i + 1..] {
+                if method.offset < other.get_end() && other.offset < method.get_end() {
+                    panic!(
+                        "{}:{}: Push constant `{}` of the {:?} stage overlaps `{}` of the {:?} stage at bytes {}..{}, move the first field of one past the other with `[[vk::offset(N)]]` or share a single constant",
+                        file!(),
+                        line!(),
+                        method.name,
+                        method.stages[0],
+                        other.name,
+                        other.stages[0],
+                        method.offset.max(other.offset),
+                        method.get_end().min(other.get_end())
+                    );
+                }
+            }
+        }
+
+        ret
+    }
+
+    /// Reports push constants ending past the `maxPushConstantsSize` guaranteed by every device
+    pub fn get_push_size_warnings(&self) -> Vec<String> {
+        let oversized = self
+            .get_push_methods()
+            .into_iter()
+            .filter(|method| method.get_end() > MIN_MAX_PUSH_CONSTANTS_SIZE)
+            .map(|method| match method.offset {
+                0 => format!("`{}` ({} bytes)", method.name, method.get_size()),
+                offset => format!(
+                    "`{}` ({} bytes at offset {})",
+                    method.name,
+                    method.get_size(),
+                    offset
+                ),
+            })
+            .collect::<Vec<String>>();
+        if oversized.is_empty() {
+            return Vec::new();
+        }
+        vec![format!(
+            "Pipeline `{}` pushes more than the {} bytes of push constants guaranteed by Vulkan, check `maxPushConstantsSize` of the device: {}",
+            self.name,
+            MIN_MAX_PUSH_CONSTANTS_SIZE,
+            oversized.join(", ")
+        )]
+    }
+
+    /// Reports sets, and bindings within a set, whose index leaves more than
+    /// `max_gap` unused indices after the previous one
+    pub fn get_numbering_gaps(&self, max_gap: u32) -> Vec<String> {
+        let mut ret = Vec::new();
+
+        let mut uniforms: Vec<&Uniform> = self
+            .shaders
+            .iter()
+            .flat_map(|shader| shader.uniforms.iter())
+            .collect();
+        uniforms.sort_by_key(|uniform| (uniform.set, uniform.binding));
+
+        let list = |uniforms: &[&Uniform]| {
+            uniforms
+                .iter()
+                .map(|uniform| {
+                    format!(
+                        "`{}` (set {}, binding {})",
+                        uniform.param.name, uniform.set, uniform.binding
+                    )
+                })
+                .collect::<Vec<String>>()
+                .join(", ")
+        };
+
+        let mut sets: Vec<u32> = uniforms.iter().map(|uniform| uniform.set).collect();
+        sets.dedup();
+
+        let mut next_set = 0;
+        for &set in &sets {
+            if set - next_set > max_gap {
+                ret.push(format!(
+                    "Pipeline `{}` leaves {} unused set layouts before set {}: {}",
+                    self.name,
+                    set - next_set,
+                    set,
+                    list(&uniforms)
+                ));
+            }
+            next_set = set + 1;
+
+            let set_uniforms: Vec<&Uniform> = uniforms
+                .iter()
+                .filter(|uniform| uniform.set == set)
+                .copied()
+                .collect();
+
+            let mut next_binding = 0;
+            for uniform in &set_uniforms {
+                if uniform.binding > next_binding + max_gap {
+                    ret.push(format!(
+                        "Pipeline `{}` leaves {} unused bindings before binding {} of set {}: {}",
+                        self.name,
+                        uniform.binding - next_binding,
+                        uniform.binding,
+                        set,
+                        list(&set_uniforms)
+                    ));
+                }
+                next_binding = next_binding.max(uniform.binding + 1);
+            }
+        }
+
+        ret
+    }
+
+    /// Marks the texture uniform with this name as optional in every shader using it
+    pub fn set_optional(&mut self, name: &str) {
+        let mut found = false;
+        for shader in &mut self.shaders {
+            for uniform in &mut shader.uniforms {
+                if uniform.param.name == name {
+                    if VkrType::from(uniform.param.ty) != VkrType::Texture || uniform.bindless {
+                        panic!(
+                            "{}:{}: Optional uniform `{}` is not a texture",
+                            file!(),
+                            line!(),
+                            name
+                        );
+                    }
+                    uniform.optional = true;
+                    found = true;
+                }
+            }
+        }
+        if !found {
+            panic!(
+                "{}:{}: Failed to find optional uniform `{}`",
+                file!(),
+                line!(),
+                name
+            );
+        }
+    }
+
+    /// Bakes the sampler of the texture uniform with this name in the set layouts,
+    /// so that only its image view is bound
+    pub fn set_immutable_sampler(&mut self, name: &str) {
+        let mut found = false;
+        for shader in &mut self.shaders {
+            for uniform in &mut shader.uniforms {
+                if uniform.param.name == name {
+                    if uniform.param.ty != ParamType::SampledImage
+                        || uniform.array_count != 1
+                        || uniform.bindless
+                    {
+                        panic!(
+                            "{}:{}: Immutable sampler `{}` is not a single texture with a sampler",
+                            file!(),
+                            line!(),
+                            name
+                        );
+                    }
+                    if uniform.optional {
+                        panic!(
+                            "{}:{}: Immutable sampler `{}` can not be optional",
+                            file!(),
+                            line!(),
+                            name
+                        );
+                    }
+                    uniform.immutable = true;
+                    found = true;
+                }
+            }
+        }
+        if !found {
+            panic!(
+                "{}:{}: Failed to find immutable sampler `{}`",
+                file!(),
+                line!(),
+                name
+            );
+        }
+    }
+
+    /// Binds the uniform buffer with this name with a dynamic offset, so that
+    /// the same set can point to different elements of a larger buffer
+    pub fn set_dynamic(&mut self, name: &str) {
+        let mut found = false;
+        for shader in &mut self.shaders {
+            for uniform in &mut shader.uniforms {
+                if uniform.param.name == name {
+                    if DescriptorType::from(uniform.param.ty) != DescriptorType::Uniform
+                        || uniform.array_count != 1
+                        || uniform.bindless
+                    {
+                        panic!(
+                            "{}:{}: Dynamic uniform `{}` is not a single uniform buffer",
+                            file!(),
+                            line!(),
+                            name
+                        );
+                    }
+                    uniform.dynamic = true;
+                    found = true;
+                }
+            }
+        }
+        if !found {
+            panic!(
+                "{}:{}: Failed to find dynamic uniform `{}`",
+                file!(),
+                line!(),
+                name
+            );
+        }
+    }
+
+    /// Writes the image uniform with this name in the given layout, whatever the
+    /// layout given to its bind method, e.g. for a depth texture sampled while
+    /// still attached read-only
+    pub fn set_image_layout(&mut self, name: &str, layout: &str) {
+        let mut found = false;
+        for shader in &mut self.shaders {
+            for uniform in &mut shader.uniforms {
+                if uniform.param.name == name {
+                    if VkrType::from(uniform.param.ty) != VkrType::Texture {
+                        panic!(
+                            "{}:{}: Image layout uniform `{}` is not an image",
+                            file!(),
+                            line!(),
+                            name
+                        );
+                    }
+                    uniform.image_layout = Some(layout.to_string());
+                    found = true;
+                }
+            }
+        }
+        if !found {
+            panic!(
+                "{}:{}: Failed to find image layout uniform `{}`",
+                file!(),
+                line!(),
+                name
+            );
+        }
+    }
+
+    /// Binds the buffer uniform with this name from an offset, so that it can be
+    /// a slice of a larger buffer
+    pub fn set_buffer_offset(&mut self, name: &str) {
+        let mut found = false;
+        for shader in &mut self.shaders {
+            for uniform in &mut shader.uniforms {
+                if uniform.param.name == name {
+                    let buffer = matches!(
+                        DescriptorType::from(uniform.param.ty),
+                        DescriptorType::Uniform | DescriptorType::StorageBuffer
+                    );
+                    if !buffer || uniform.array_count != 1 || uniform.bindless || uniform.dynamic {
+                        panic!(
+                            "{}:{}: Buffer offset uniform `{}` is not a single buffer without a dynamic offset",
+                            file!(),
+                            line!(),
+                            name
+                        );
+                    }
+                    uniform.buffer_offset = true;
+                    found = true;
+                }
+            }
+        }
+        if !found {
+            panic!(
+                "{}:{}: Failed to find buffer offset uniform `{}`",
+                file!(),
+                line!(),
+                name
+            );
+        }
+    }
+
+    /// Pushes the descriptors of this set on the command buffer, which rules out
+    /// unbounded arrays updated after binding and dynamic uniform buffers
+    pub fn set_push_set(&mut self, set: u32) {
+        let uniforms = self
+            .get_uniforms()
+            .into_iter()
+            .filter(|uniform| uniform.set == set)
+            .collect::<Vec<_>>();
+        if uniforms.is_empty() {
+            panic!("{}:{}: Push set {} has no uniforms", file!(), line!(), set);
+        }
+        if let Some(uniform) = uniforms
+            .iter()
+            .find(|uniform| uniform.bindless || uniform.dynamic)
+        {
+            panic!(
+                "{}:{}: Push set {} can not contain the unbounded array or dynamic uniform `{}`",
+                file!(),
+                line!(),
+                set,
+                uniform.param.name
+            );
+        }
+        self.push_set = Some(set);
+    }
+
+    /// Leaves the binding of this set to the engine, for example a set of frame data
+    /// shared by all pipelines, so its layout is created but it gets no bind methods
+    pub fn set_external_set(&mut self, set: u32) {
+        if !self.get_uniforms().iter().any(|uniform| uniform.set == set) {
+            panic!(
+                "{}:{}: External set {} has no uniforms",
+                file!(),
+                line!(),
+                set
+            );
+        }
+        if self.push_set == Some(set) {
+            panic!(
+                "{}:{}: External set {} can not be a push set",
+                file!(),
+                line!(),
+                set
+            );
+        }
+        self.external_sets.push(set);
+    }
+
+    /// Texture uniforms whose samplers are baked in the set layouts, once per name
+    pub fn get_immutable_samplers(&self) -> Vec<&Uniform> {
+        self.get_uniforms()
+            .into_iter()
+            .filter(|uniform| uniform.immutable)
+            .collect()
+    }
+
+    /// Sizes unbounded arrays, which can not be created without a count
+    fn set_bindless_count(&mut self, bindless_count: Option<u32>) {
+        for shader in &mut self.shaders {
+            for uniform in shader
+                .uniforms
+                .iter_mut()
+                .filter(|uniform| uniform.bindless)
+            {
+                uniform.array_count = bindless_count.unwrap_or_else(|| {
+                    panic!(
+                        "{}:{}: `{}` is an unbounded array, which needs a `bindless_count`",
+                        file!(),
+                        line!(),
+                        uniform.param.name
+                    )
+                });
             }
         }
+    }
+
+    /// Device features needed by the fixed-function state of this pipeline
+    pub fn get_required_features(&self) -> Vec<Feature> {
+        let mut ret = Vec::new();
+        if self.options.blend == Blend::DualSourceCoverage {
+            ret.push(Feature::DualSrcBlend);
+        }
+        if self.debug_wireframe || self.options.polygon_mode != PolygonMode::Fill {
+            ret.push(Feature::FillModeNonSolid);
+        }
+        if self.options.line_width.is_some_and(|width| width != 1.0) {
+            ret.push(Feature::WideLines);
+        }
+        if self.options.sample_shading.is_some() {
+            ret.push(Feature::SampleRateShading);
+        }
+        ret
+    }
 
+    /// Specialization constants of all the shaders sorted by ID,
+    /// where shaders declaring the same ID share the constant
+    pub fn get_spec_constants(&self) -> Vec<SpecConstant> {
+        let mut ret: Vec<SpecConstant> = Vec::new();
+        for constant in self
+            .shaders
+            .iter()
+            .flat_map(|shader| &shader.spec_constants)
+        {
+            match ret.iter().find(|other| other.id == constant.id) {
+                Some(other) if other != constant => panic!(
+                    "{}:{}: Specialization constant {} is declared as both `{}` and `{}`",
+                    file!(),
+                    line!(),
+                    constant.id,
+                    other.name,
+                    constant.name
+                ),
+                Some(_) => (),
+                None => ret.push(constant.clone()),
+            }
+        }
+        ret.sort_by_key(|constant| constant.id);
         ret
     }
 
+    /// Whether the fragment shader writes `SV_Depth`
+    pub fn writes_depth(&self) -> bool {
+        self.shaders.iter().any(|shader| shader.writes_depth)
+    }
+
+    /// Whether any bind method falls back to a default texture
+    pub fn has_optional(&self) -> bool {
+        self.shaders
+            .iter()
+            .any(|shader| shader.uniforms.iter().any(|uniform| uniform.optional))
+    }
+
+    #[allow(unused)]
     pub fn new<S: Into<String>>(name: S, reflections: Vec<ShaderReflection>) -> Self {
         let mut shaders = Vec::new();
         assert!(!reflections.is_empty());
@@ -127,6 +954,7 @@ impl Pipeline {
         Pipeline {
             name: name.into(),
             shaders,
+            ..Default::default()
         }
     }
 }
@@ -144,12 +972,19 @@ impl<'a> From<ShaderReflection<'a>> for Shader {
         let mut params = Vec::default();
         let mut uniforms = Vec::default();
         let mut constants = Vec::default();
+        let mut spec_constants = Vec::default();
+        let mut entry_uniforms = Vec::<String>::new();
 
         let parameter_count = entry_point.get_parameter_count();
         for i in 0..parameter_count {
             let var_layout = entry_point
                 .get_parameter_by_index(i)
                 .expect("Failed to get parameter by index");
+            // System values like `SV_VertexID` are not vertex attributes
+            if Shader::is_system_value(&var_layout) {
+                continue;
+            }
+
             let var = var_layout.get_variable().unwrap();
             let name = var.get_name();
             let ty = var.get_type();
@@ -165,14 +1000,22 @@ impl<'a> From<ShaderReflection<'a>> for Shader {
                     params.push(param);
                 }
                 slang::ParameterCategory::PushConstantBuffer => {
-                    let param = Param::new(name.into(), param_type);
-                    constants.push(param);
+                    ParamType::check_matrices(name, ty);
+                    constants.push(Param::new_constant(name.into(), &var_layout));
+                }
+                // Uniform parameters of an entry point have no binding, as Slang
+                // gathers them in the push constant block of the entry point
+                slang::ParameterCategory::Uniform => {
+                    entry_uniforms.push(name.to_string());
+                    ParamType::check_matrices(name, ty);
+                    constants.push(Param::new_constant(name.into(), &var_layout));
                 }
-                slang::ParameterCategory::Uniform | slang::ParameterCategory::Subpass => {
+                slang::ParameterCategory::Subpass => {
                     let binding = var_layout.get_binding_index();
                     let set = var_layout.get_binding_space();
                     let param = Param::new(name.into(), param_type);
-                    let uniform = Uniform::new(param, set, binding, 0);
+                    let mut uniform = Uniform::new(param, set, binding, 0);
+                    uniform.fields = StructField::from_type(ty);
                     uniforms.push(uniform)
                 }
                 _ => panic!(
@@ -184,6 +1027,17 @@ impl<'a> From<ShaderReflection<'a>> for Shader {
             }
         }
 
+        // A single uniform parameter is supported, several ones are gathered in a struct
+        if entry_uniforms.len() > 1 {
+            panic!(
+                "{}:{}: uniform parameters `{}` share the push constant block of `{}`, gather them in a struct",
+                file!(),
+                line!(),
+                entry_uniforms.join("`, `"),
+                reflection.entry
+            );
+        }
+
         let parameter_count = reflection.get_parameter_count();
         for i in 0..parameter_count {
             let var_layout = reflection
@@ -195,20 +1049,46 @@ impl<'a> From<ShaderReflection<'a>> for Shader {
             let type_layout = var_layout.get_type_layout().unwrap();
             let category = type_layout.get_parameter_category();
 
+            // The offset of a specialization constant is its constant ID
+            if category == slang::ParameterCategory::SpecializationConstant {
+                let id = var_layout.get_offset(category) as u32;
+                let ty = ScalarType::from_type(ty);
+                spec_constants.push(SpecConstant::new(name.into(), id, ty));
+                continue;
+            }
+
+            // The resources of a parameter block go to the descriptor set of the block
+            if type_layout.get_kind() == slang::TypeKind::ParameterBlock {
+                uniforms.extend(Uniform::from_parameter_block(&var_layout));
+                continue;
+            }
+
+            // An array of resources takes one binding with a descriptor per element
+            let (ty, array_count) = match category {
+                slang::ParameterCategory::DescriptorTableSlot => Uniform::get_array_element(ty),
+                _ => (ty, 1),
+            };
+
+            // Matrices of doubles or halves are rejected rather than sized as float ones
+            ParamType::check_matrices(name, ty);
+
             // Guess param type for the moment
             let mut param_type = ParamType::from_type(ty);
 
             match category {
                 slang::ParameterCategory::PushConstantBuffer
                 | slang::ParameterCategory::Uniform => {
-                    let param = Param::new(name.into(), param_type);
-                    constants.push(param)
+                    constants.push(Param::new_constant(name.into(), &var_layout))
                 }
                 slang::ParameterCategory::DescriptorTableSlot => {
                     let binding = var_layout.get_binding_index();
                     let set = var_layout.get_binding_space();
                     let param = Param::new(name.into(), param_type);
-                    let uniform = Uniform::new(param, set, binding, 0);
+                    let mut uniform = Uniform::new(param, set, binding, 0);
+                    uniform.array_count = array_count;
+                    uniform.bindless = array_count == 0;
+                    uniform.shape = TextureShape::from_type(ty);
+                    uniform.fields = StructField::from_type(ty);
                     uniforms.push(uniform)
                 }
                 slang::ParameterCategory::Mixed => {
@@ -252,7 +1132,10 @@ impl<'a> From<ShaderReflection<'a>> for Shader {
         // Remove samplers from uniforms and store them in another vector
         let mut samplers = Vec::new();
         uniforms.retain(|uniform| {
-            if uniform.param.ty == ParamType::SampledImage {
+            if matches!(
+                uniform.param.ty,
+                ParamType::SampledImage | ParamType::Sampler
+            ) {
                 samplers.push(uniform.clone());
                 false
             } else {
@@ -260,6 +1143,9 @@ impl<'a> From<ShaderReflection<'a>> for Shader {
             }
         });
 
+        // A comparison sampler sharing the binding of a texture is combined with it
+        samplers.sort_by_key(|sampler| sampler.param.ty == ParamType::Sampler);
+
         for sampler in samplers {
             if uniforms
                 .iter()
@@ -274,18 +1160,60 @@ impl<'a> From<ShaderReflection<'a>> for Shader {
         }
 
         uniforms.sort_by_key(|uniform| uniform.binding);
-        Shader::new(ty, reflection.path.clone(), params, uniforms, constants)
+        let mut shader = Shader::new(ty, reflection.path.clone(), params, uniforms, constants);
+        shader.entry = reflection.entry.clone();
+        shader.dependencies = reflection.get_dependencies();
+        shader.spec_constants = spec_constants;
+
+        if ty == ShaderType::Fragment
+            && let Some(result) = entry_point.get_result_var_layout()
+        {
+            shader.outputs = Shader::get_outputs(&result);
+            shader.writes_depth = Shader::get_writes_depth(&result);
+        }
+
+        if ty == ShaderType::Compute {
+            let [x, y, z] = entry_point.get_compute_thread_group_size();
+            shader.local_size = [x as u32, y as u32, z as u32];
+        }
+
+        shader
     }
 }
 
-#[derive(Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct Shader {
     pub ty: ShaderType,
     /// This is needed for embedding shader input code with include_str!()
     pub path: PathBuf,
+    /// Name of the entry point function, as a module may contain several of them
+    #[serde(default = "Shader::get_default_entry")]
+    pub entry: String,
+    /// Varying inputs of the entry point, without system values like `SV_VertexID`
     pub params: Vec<Param>,
     pub uniforms: Vec<Uniform>,
     pub constants: Vec<Param>,
+    /// SPIR-V compiled at expansion time or precompiled, embedded when the runtime
+    /// could not compile the same variant from `path`
+    #[serde(skip)]
+    pub code: Option<Vec<u8>>,
+    /// Color outputs of a fragment shader
+    #[serde(default)]
+    pub outputs: Vec<Param>,
+    /// A fragment shader writing `SV_Depth` needs depth writes enabled
+    #[serde(default)]
+    pub writes_depth: bool,
+    /// Workgroup size of a compute shader
+    #[serde(default)]
+    pub local_size: [u32; 3],
+    /// Specialization constants, which keep their default values unless
+    /// the pipeline is created with other ones
+    #[serde(default)]
+    pub spec_constants: Vec<SpecConstant>,
+    /// Files of the module and of the modules it imports, which are
+    /// tracked so that editing any of them rebuilds the invoking crate
+    #[serde(default)]
+    pub dependencies: Vec<PathBuf>,
 }
 
 impl Shader {
@@ -299,20 +1227,87 @@ impl Shader {
         Self {
             ty,
             path,
+            entry: Shader::get_default_entry(),
             params,
             uniforms,
             constants,
+            code: None,
+            outputs: Vec::new(),
+            writes_depth: false,
+            local_size: [0; 3],
+            spec_constants: Vec::new(),
+            dependencies: Vec::new(),
         }
     }
 
-    pub fn get_set_layout_bindings(&self, set: u32) -> Vec<SetLayoutBinding> {
-        let mut ret = Vec::new();
-        for uniform in &self.uniforms {
-            if uniform.set == set {
-                ret.push(uniform.get_set_layout_binding(self.ty));
-            }
-        }
-        ret
+    fn get_default_entry() -> String {
+        String::from("main")
+    }
+
+    /// Collects the `SV_Target` outputs of the result of a fragment entry point
+    fn get_outputs(result: &slang::VariableLayoutReflection) -> Vec<Param> {
+        let is_target = |var_layout: &slang::VariableLayoutReflection| {
+            var_layout
+                .get_semantic_name()
+                .is_some_and(|semantic| semantic.to_uppercase().starts_with("SV_TARGET"))
+        };
+
+        let type_layout = result.get_type_layout().unwrap();
+        if type_layout.get_kind() != slang::TypeKind::Struct {
+            if !is_target(result) {
+                return Vec::new();
+            }
+            let ty = ParamType::from_type(type_layout.get_type().unwrap());
+            return vec![Param::new("output".into(), ty)];
+        }
+
+        let mut ret = Vec::new();
+        for i in 0..type_layout.get_field_count() {
+            let field = type_layout.get_field_by_index(i).unwrap();
+            if is_target(&field) {
+                let var = field.get_variable().unwrap();
+                ret.push(Param::new(
+                    var.get_name().into(),
+                    ParamType::from_type(var.get_type()),
+                ));
+            }
+        }
+        ret
+    }
+
+    /// Whether the result of a fragment entry point has an `SV_Depth` semantic,
+    /// including its conservative variants
+    fn get_writes_depth(result: &slang::VariableLayoutReflection) -> bool {
+        let is_depth = |var_layout: &slang::VariableLayoutReflection| {
+            var_layout
+                .get_semantic_name()
+                .is_some_and(|semantic| semantic.to_uppercase().starts_with("SV_DEPTH"))
+        };
+
+        let type_layout = result.get_type_layout().unwrap();
+        if type_layout.get_kind() != slang::TypeKind::Struct {
+            return is_depth(result);
+        }
+
+        (0..type_layout.get_field_count())
+            .any(|i| is_depth(&type_layout.get_field_by_index(i).unwrap()))
+    }
+
+    /// Whether an entry point parameter has a system-value semantic, e.g. `SV_InstanceID`
+    fn is_system_value(var_layout: &slang::VariableLayoutReflection) -> bool {
+        var_layout
+            .get_semantic_name()
+            .is_some_and(|semantic| semantic.to_uppercase().starts_with("SV_"))
+    }
+
+    pub fn get_set_layout_bindings(&self, set: u32) -> Vec<SetLayoutBinding> {
+        let mut ret = Vec::new();
+        for uniform in &self.uniforms {
+            if uniform.set == set {
+                ret.push(uniform.get_set_layout_binding(self.ty));
+            }
+        }
+        ret
     }
 
     pub fn get_descriptor_max(&self) -> u32 {
@@ -325,16 +1320,27 @@ impl Shader {
 
     pub fn get_bind_methods(&self, methods: &mut [BindMethod]) {
         for uniform in &self.uniforms {
-            methods[uniform.set as usize].uniforms.push(uniform.clone());
+            let uniforms = &mut methods[uniform.set as usize].uniforms;
+            // A uniform used by several shaders is bound once
+            if !uniforms
+                .iter()
+                .any(|other| other.binding == uniform.binding)
+            {
+                uniforms.push(uniform.clone());
+            }
         }
     }
 }
 
-#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub enum ShaderType {
     #[default]
     Vertex,
     Fragment,
+    Compute,
+    RayGeneration,
+    Miss,
+    ClosestHit,
 }
 
 impl From<slang::Stage> for ShaderType {
@@ -342,31 +1348,312 @@ impl From<slang::Stage> for ShaderType {
         match stage {
             slang::Stage::Vertex => ShaderType::Vertex,
             slang::Stage::Fragment => ShaderType::Fragment,
+            slang::Stage::Compute => ShaderType::Compute,
+            slang::Stage::RayGeneration => ShaderType::RayGeneration,
+            slang::Stage::Miss => ShaderType::Miss,
+            slang::Stage::ClosestHit => ShaderType::ClosestHit,
             _ => panic!("{}:{}: Unimplemented stage {:?}", file!(), line!(), stage),
         }
     }
 }
 
+/// Color blend state of the pipeline
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Blend {
+    /// Provided by `VertexInput::get_color_blend()`
+    #[default]
+    Vertex,
+    /// Blends the first output weighted by the second one per channel,
+    /// as needed by subpixel coverage e.g. for font rendering
+    DualSourceCoverage,
+    /// Adds the output to the attachment, e.g. for particles
+    Additive,
+}
+
+impl Blend {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "vertex" => Some(Self::Vertex),
+            "dual_source_coverage" => Some(Self::DualSourceCoverage),
+            "additive" => Some(Self::Additive),
+            _ => None,
+        }
+    }
+}
+
+/// Fixed-function state given by the options block of the macro,
+/// where `None` keeps the state provided by the vertex input
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PipelineOptions {
+    /// Color blend state, the one of the vertex input by default
+    pub blend: Blend,
+    pub cull: Cull,
+    pub topology: Option<Topology>,
+    pub depth_write: Option<bool>,
+    /// Rasterization samples, a power of two instead of a single sample
+    pub samples: Option<u32>,
+    pub alpha_to_coverage: bool,
+    /// Minimum fraction of samples shaded individually, enabling sample shading
+    pub sample_shading: Option<f32>,
+    /// Rasterization mode of the polygons
+    pub polygon_mode: PolygonMode,
+    /// Width of rasterized lines, one by default
+    pub line_width: Option<f32>,
+}
+
+/// How the rasterizer fills polygons, where lines and points need the `fillModeNonSolid` feature
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum PolygonMode {
+    #[default]
+    Fill,
+    Line,
+    Point,
+}
+
+impl PolygonMode {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "fill" => Some(Self::Fill),
+            "line" => Some(Self::Line),
+            "point" => Some(Self::Point),
+            _ => None,
+        }
+    }
+}
+
+/// Faces culled by the rasterizer
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Cull {
+    #[default]
+    None,
+    Front,
+    Back,
+    FrontAndBack,
+}
+
+impl Cull {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(Self::None),
+            "front" => Some(Self::Front),
+            "back" => Some(Self::Back),
+            "front_and_back" => Some(Self::FrontAndBack),
+            _ => None,
+        }
+    }
+}
+
+/// Primitive topology replacing `VertexInput::get_topology()`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Topology {
+    PointList,
+    LineList,
+    LineStrip,
+    TriangleList,
+    TriangleStrip,
+    TriangleFan,
+}
+
+impl Topology {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "point_list" => Some(Self::PointList),
+            "line_list" => Some(Self::LineList),
+            "line_strip" => Some(Self::LineStrip),
+            "triangle_list" => Some(Self::TriangleList),
+            "triangle_strip" => Some(Self::TriangleStrip),
+            "triangle_fan" => Some(Self::TriangleFan),
+            _ => None,
+        }
+    }
+}
+
+/// Device features which must be enabled to create a pipeline
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Feature {
+    DualSrcBlend,
+    FillModeNonSolid,
+    SampleRateShading,
+    WideLines,
+}
+
+impl Feature {
+    /// Name of the feature as in the Vulkan specification
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Feature::DualSrcBlend => "dualSrcBlend",
+            Feature::FillModeNonSolid => "fillModeNonSolid",
+            Feature::SampleRateShading => "sampleRateShading",
+            Feature::WideLines => "wideLines",
+        }
+    }
+
+    /// Name of the field of `vk::PhysicalDeviceFeatures`
+    pub fn get_field(&self) -> &'static str {
+        match self {
+            Feature::DualSrcBlend => "dual_src_blend",
+            Feature::FillModeNonSolid => "fill_mode_non_solid",
+            Feature::SampleRateShading => "sample_rate_shading",
+            Feature::WideLines => "wide_lines",
+        }
+    }
+}
+
+/// A specialization constant, declared with `[vk::constant_id(id)]`
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SpecConstant {
+    pub name: String,
+    pub id: u32,
+    pub ty: ScalarType,
+}
+
+impl SpecConstant {
+    pub fn new(name: String, id: u32, ty: ScalarType) -> Self {
+        Self { name, id, ty }
+    }
+}
+
+/// Type of a specialization constant, each taking 4 bytes
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ScalarType {
+    Bool,
+    Int,
+    Uint,
+    Float,
+}
+
+impl ScalarType {
+    fn from_type(ty: slang::ReflectionType) -> Self {
+        match ty.get_scalar_type() {
+            slang::ScalarType::Bool => Self::Bool,
+            slang::ScalarType::Int32 => Self::Int,
+            slang::ScalarType::Uint32 => Self::Uint,
+            slang::ScalarType::Float32 => Self::Float,
+            scalar_type => panic!(
+                "{}:{}: unsupported scalar type {:?}",
+                file!(),
+                line!(),
+                scalar_type
+            ),
+        }
+    }
+}
+
 /// A shader parameter can be any input/output parameter: a vertex attribute,
 /// a uniform, a sampler, and so on.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Param {
     pub name: String,
     ty: ParamType,
+    /// Number of elements of a push constant array, one otherwise
+    #[serde(default = "Param::get_default_count")]
+    pub count: u32,
+    /// Fields of a push constant struct, mirrored by a generated Rust struct
+    #[serde(default)]
+    pub fields: Vec<StructField>,
+    /// Byte offset of a push constant in the push constant block, as reflected,
+    /// which is the one of the first field of a struct
+    #[serde(default)]
+    pub offset: usize,
 }
 
 impl Param {
     pub fn new(name: String, ty: ParamType) -> Self {
-        Self { name, ty }
+        Self {
+            name,
+            ty,
+            count: 1,
+            fields: Vec::new(),
+            offset: 0,
+        }
+    }
+
+    fn get_default_count() -> u32 {
+        1
+    }
+
+    /// A push constant at its reflected offset, where an array of scalars or vectors
+    /// keeps its element type, and structs take the std430 layout reflected by Slang,
+    /// spanning from their first field to their end
+    fn new_constant(name: String, var_layout: &slang::VariableLayoutReflection) -> Self {
+        let ty = var_layout.get_variable().unwrap().get_type();
+        let mut param = match ParamType::get_push_array(ty) {
+            Some((element_type, count)) => {
+                let mut param = Self::new(name, element_type);
+                param.count = count;
+                param
+            }
+            None => {
+                let type_layout = Self::get_push_layout(var_layout);
+                let size = type_layout.get_size(slang::ParameterCategory::Uniform);
+                let ty = match ParamType::from_type(ty) {
+                    ParamType::Struct(_) => ParamType::Struct(size),
+                    ty => ty,
+                };
+                let mut param = Self::new(name, ty);
+                param.fields = StructField::from_push_layout(&param.name, type_layout);
+
+                // A struct whose first field is placed past the constants of other stages,
+                // e.g. with `[[vk::offset(64)]]`, is read from there, so it starts there
+                let start = param.fields.first().map_or(0, |field| field.offset);
+                if start > 0 {
+                    for field in &mut param.fields {
+                        field.offset -= start;
+                    }
+                    param.ty = ParamType::Struct(size - start);
+                    param.offset = start;
+                }
+                param
+            }
+        };
+        param.offset += var_layout.get_offset(slang::ParameterCategory::Uniform);
+        param
+    }
+
+    /// Layout of the contents of a push constant, out of the constant buffer wrapping them
+    fn get_push_layout(
+        var_layout: &slang::VariableLayoutReflection,
+    ) -> &slang::TypeLayoutReflection {
+        let type_layout = var_layout.get_type_layout().unwrap();
+        match type_layout.get_kind() {
+            slang::TypeKind::ConstantBuffer => type_layout.get_element_type_layout().unwrap(),
+            _ => type_layout,
+        }
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Uniform {
     pub param: Param,
     pub set: u32,
-    binding: u32,
+    pub binding: u32,
     input_attachment_index: u32,
+    /// Number of descriptors in this binding, greater than one for arrays
+    array_count: u32,
+    /// Dimensionality of a texture, checked against the views bound to it
+    #[serde(default)]
+    pub shape: Option<TextureShape>,
+    /// Fields of a constant buffer struct, mirrored by a generated Rust struct
+    #[serde(default)]
+    pub fields: Vec<StructField>,
+    /// Unbounded array, whose count is the `bindless_count` of the pipeline
+    #[serde(default)]
+    pub bindless: bool,
+    /// Optional textures fall back to a default texture when not provided
+    #[serde(skip)]
+    pub optional: bool,
+    /// Textures whose sampler is baked in the set layout, bound by their view
+    #[serde(skip)]
+    pub immutable: bool,
+    /// Uniform buffers bound with a dynamic offset
+    #[serde(skip)]
+    pub dynamic: bool,
+    /// Buffers written from an offset given to the bind method
+    #[serde(skip)]
+    pub buffer_offset: bool,
+    /// Layout of an image written regardless of the layout given to the bind method
+    #[serde(skip)]
+    pub image_layout: Option<String>,
 }
 
 impl Uniform {
@@ -376,52 +1663,367 @@ impl Uniform {
             set,
             binding,
             input_attachment_index,
+            array_count: 1,
+            shape: None,
+            fields: Vec::new(),
+            bindless: false,
+            optional: false,
+            immutable: false,
+            dynamic: false,
+            buffer_offset: false,
+            image_layout: None,
+        }
+    }
+
+    /// Returns the resources of a `ParameterBlock`, all in the descriptor set of the block
+    fn from_parameter_block(var_layout: &slang::VariableLayoutReflection) -> Vec<Self> {
+        let var = var_layout.get_variable().unwrap();
+        let name = var.get_name();
+        let set = var_layout.get_offset(slang::ParameterCategory::SubElementRegisterSpace) as u32;
+        let element_layout = var_layout
+            .get_type_layout()
+            .unwrap()
+            .get_element_var_layout()
+            .unwrap();
+        let base_binding = element_layout.get_offset(slang::ParameterCategory::DescriptorTableSlot);
+        let struct_layout = element_layout.get_type_layout().unwrap();
+
+        // Ordinary data would need an implicit constant buffer
+        if struct_layout.get_size(slang::ParameterCategory::Uniform) > 0 {
+            panic!(
+                "{}:{}: Parameter block `{}` has fields outside of resources, move them into a `ConstantBuffer`",
+                file!(),
+                line!(),
+                name
+            );
+        }
+
+        let mut uniforms = Vec::new();
+        for i in 0..struct_layout.get_field_count() {
+            let field_layout = struct_layout.get_field_by_index(i).unwrap();
+            let field = field_layout.get_variable().unwrap();
+            let (ty, array_count) = Uniform::get_array_element(field.get_type());
+
+            let binding = base_binding
+                + field_layout.get_offset(slang::ParameterCategory::DescriptorTableSlot);
+            let param = Param::new(field.get_name().into(), ParamType::from_type(ty));
+            let mut uniform = Uniform::new(param, set, binding as u32, 0);
+            uniform.array_count = array_count;
+            uniform.bindless = array_count == 0;
+            uniform.shape = TextureShape::from_type(ty);
+            uniform.fields = StructField::from_type(ty);
+            uniforms.push(uniform);
+        }
+        uniforms
+    }
+
+    /// Returns the element type and the element count of an array type, which is
+    /// zero for an unbounded array, or the type itself with a count of one
+    fn get_array_element(ty: slang::ReflectionType) -> (slang::ReflectionType, u32) {
+        match ty.get_kind() {
+            slang::TypeKind::Array => {
+                let count = ty.get_element_count();
+                (ty.get_element_type().unwrap(), count as u32)
+            }
+            _ => (ty, 1),
+        }
+    }
+
+    pub fn get_descriptor_type(&self) -> DescriptorType {
+        if self.dynamic {
+            return DescriptorType::UniformDynamic;
+        }
+        self.param.ty.into()
+    }
+
+    pub fn get_array_count(&self) -> u32 {
+        self.array_count
+    }
+
+    /// Index of a subpass input in the input attachments of its subpass
+    pub fn get_input_attachment_index(&self) -> u32 {
+        self.input_attachment_index
+    }
+
+    /// Size of a constant buffer, or of an element of a storage buffer
+    pub fn get_buffer_size(&self) -> Option<usize> {
+        match self.param.ty {
+            ParamType::StorageBuffer(size) => Some(size),
+            ParamType::Struct(0) => None,
+            ty if DescriptorType::from(ty) == DescriptorType::Uniform => Some(ty.get_size()),
+            _ => None,
+        }
+    }
+
+    /// Size of a constant buffer whose struct fields are known
+    pub fn get_struct_size(&self) -> Option<usize> {
+        match self.param.ty {
+            ParamType::Struct(size) if !self.fields.is_empty() => Some(size),
+            _ => None,
         }
     }
 
     pub fn get_set_layout_binding(&self, stage: ShaderType) -> SetLayoutBinding {
         SetLayoutBinding {
-            stage,
-            descriptor_type: self.param.ty.into(),
+            stages: vec![stage],
+            descriptor_type: self.get_descriptor_type(),
             binding: self.binding,
+            count: self.array_count,
+            bindless: self.bindless,
+            immutable: self.immutable,
         }
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+/// A field of a struct at its std140 offset in a constant buffer,
+/// or at its std430 offset in a push constant
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StructField {
+    pub name: String,
+    /// Type of the field, or of its elements for an array
+    pub ty: ParamType,
+    /// Number of elements, one for a field which is not an array
+    pub count: u32,
+    pub offset: usize,
+    /// Bytes from an element of an array to the next one, or size of a field which is not
+    #[serde(default)]
+    pub stride: usize,
+}
+
+impl StructField {
+    /// Returns the fields of a constant buffer of a struct, or none for other types
+    fn from_type(ty: slang::ReflectionType) -> Vec<Self> {
+        if ty.get_kind() != slang::TypeKind::ConstantBuffer {
+            return Vec::new();
+        }
+        Self::from_struct(ty.get_element_type().unwrap())
+    }
+
+    /// Returns the fields of a push constant struct at their std430 offsets reflected
+    /// by Slang, or none for other types. Array elements are mirrored with their stride,
+    /// so every field must end before the next one and the struct must hold them all
+    fn from_push_layout(name: &str, type_layout: &slang::TypeLayoutReflection) -> Vec<Self> {
+        if type_layout.get_kind() != slang::TypeKind::Struct {
+            return Vec::new();
+        }
+
+        let size = type_layout.get_size(slang::ParameterCategory::Uniform);
+        let mut fields: Vec<Self> = (0..type_layout.get_field_count())
+            .map(|i| {
+                let field_layout = type_layout.get_field_by_index(i).unwrap();
+                let field = field_layout.get_variable().unwrap();
+                let mut element_layout = field_layout.get_type_layout().unwrap();
+                let field_size = element_layout.get_size(slang::ParameterCategory::Uniform);
+
+                // Arrays of arrays are flattened, as their elements are evenly spaced
+                let mut ty = field.get_type();
+                let mut count = 1;
+                while ty.get_kind() == slang::TypeKind::Array {
+                    count *= ty.get_element_count() as u32;
+                    ty = ty.get_element_type().unwrap();
+                    element_layout = element_layout.get_element_type_layout().unwrap();
+                }
+                let element_size = element_layout.get_size(slang::ParameterCategory::Uniform);
+                // The last element of an array is not padded to the stride
+                let stride = match count {
+                    1 => field_size,
+                    count => (field_size - element_size) / (count as usize - 1),
+                };
+                let ty = match ParamType::from_type(ty) {
+                    ParamType::Struct(_) => ParamType::Struct(element_size),
+                    ty => ty,
+                };
+                Self {
+                    name: field.get_name().into(),
+                    ty,
+                    count,
+                    offset: field_layout.get_offset(slang::ParameterCategory::Uniform),
+                    stride,
+                }
+            })
+            .collect();
+        fields.sort_by_key(|field| field.offset);
+
+        let ends = fields
+            .iter()
+            .skip(1)
+            .map(|field| field.offset)
+            .chain([size]);
+        for (field, end) in fields.iter().zip(ends) {
+            if field.offset + field.stride * field.count as usize > end {
+                panic!(
+                    "{}:{}: Field `{}` of push constant `{}` takes {} bytes at offset {}, past byte {} where Slang lays out what follows it",
+                    file!(),
+                    line!(),
+                    field.name,
+                    name,
+                    field.stride * field.count as usize,
+                    field.offset,
+                    end
+                );
+            }
+        }
+        fields
+    }
+
+    fn from_struct(ty: slang::ReflectionType) -> Vec<Self> {
+        if ty.get_kind() != slang::TypeKind::Struct {
+            return Vec::new();
+        }
+
+        let (_, offsets) = ParamType::get_struct_layout(ty);
+        (0..ty.get_field_count())
+            .filter_map(|i| ty.get_field_by_index(i))
+            .zip(offsets)
+            .map(|(field, offset)| {
+                // Arrays of arrays are flattened, as their elements are padded to 16 bytes anyway
+                let mut ty = field.get_type();
+                let mut count = 1;
+                while ty.get_kind() == slang::TypeKind::Array {
+                    count *= ty.get_element_count() as u32;
+                    ty = ty.get_element_type().unwrap();
+                }
+                let stride = match count {
+                    1 => ParamType::get_type_size(ty),
+                    _ => align_to(ParamType::get_type_size(ty), 16),
+                };
+                Self {
+                    name: field.get_name().into(),
+                    ty: ParamType::from_type(ty),
+                    count,
+                    offset,
+                    stride,
+                }
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum ParamType {
     Vec2,
     Vec3,
     Vec4,
+    IVec2,
+    IVec3,
+    IVec4,
+    UVec2,
+    UVec3,
+    UVec4,
+    Mat2,
     Mat3,
     Mat4,
+    /// Row-major matrix of 3 rows and 4 columns, e.g. a skinning palette entry
+    Mat3x4,
+    /// Row-major matrix of 4 rows and 3 columns
+    Mat4x3,
+    Float,
+    Int,
+    Uint,
+    Bool,
+    /// Half-precision float, e.g. `half` or `float16_t`
+    Half,
+    Half2,
+    Half3,
+    Half4,
+    /// Double-precision float, e.g. `double`
+    Double,
+    DVec2,
+    DVec3,
+    DVec4,
     SampledImage,
     Image,
+    /// Image written by the shader, e.g. `RWTexture2D`
+    StorageImage,
+    /// Standalone comparison sampler, e.g. `SamplerComparisonState`
     Sampler,
+    AccelerationStructure,
+    /// Structured or byte-address buffer, read-only or read-write, with its element stride
+    StorageBuffer(usize),
+    /// Typed buffer read through a buffer view, e.g. `Buffer<float4>`
+    UniformTexelBuffer,
+    /// Typed buffer written through a buffer view, e.g. `RWBuffer<uint>`
+    StorageTexelBuffer,
     Struct(usize),
 }
 
+/// Rounds `offset` up to a multiple of `align`
+fn align_to(offset: usize, align: usize) -> usize {
+    offset.div_ceil(align) * align
+}
+
 impl ParamType {
+    /// Whether a type is or contains a matrix of other scalars than 32-bit floats,
+    /// which would be sized as the `f32` matrix of the same shape
+    fn has_non_float_matrix(ty: slang::ReflectionType) -> bool {
+        match ty.get_kind() {
+            slang::TypeKind::Matrix => ty.get_scalar_type() != slang::ScalarType::Float32,
+            slang::TypeKind::Array | slang::TypeKind::ConstantBuffer => ty
+                .get_element_type()
+                .is_some_and(Self::has_non_float_matrix),
+            slang::TypeKind::Struct => (0..ty.get_field_count())
+                .filter_map(|i| ty.get_field_by_index(i))
+                .any(|field| Self::has_non_float_matrix(field.get_type())),
+            _ => false,
+        }
+    }
+
+    /// Rejects the matrices of other scalars than 32-bit floats in the type of a variable
+    fn check_matrices(name: &str, ty: slang::ReflectionType) {
+        if Self::has_non_float_matrix(ty) {
+            panic!(
+                "{}:{}: `{}` uses a matrix of other scalars than `float`, which is not supported in uniforms and push constants",
+                file!(),
+                line!(),
+                name
+            );
+        }
+    }
+
     fn get_type_size(ty: slang::ReflectionType) -> usize {
+        Self::get_type_layout(ty).0
+    }
+
+    /// Width in bytes of the scalars of a scalar, vector, or matrix type
+    fn get_scalar_size(ty: slang::ReflectionType) -> usize {
+        match ty.get_scalar_type() {
+            slang::ScalarType::Int8 | slang::ScalarType::Uint8 => 1,
+            slang::ScalarType::Float16 | slang::ScalarType::Int16 | slang::ScalarType::Uint16 => 2,
+            slang::ScalarType::Float64 | slang::ScalarType::Int64 | slang::ScalarType::Uint64 => 8,
+            _ => 4,
+        }
+    }
+
+    /// Size and alignment of a vector of `count` scalars of `scalar_size` bytes,
+    /// where three-element vectors are aligned as four-element ones
+    fn get_vector_layout(scalar_size: usize, count: usize) -> (usize, usize) {
+        match count {
+            1 => (scalar_size, scalar_size),
+            2 => (2 * scalar_size, 2 * scalar_size),
+            3 => (3 * scalar_size, 4 * scalar_size),
+            4 => (4 * scalar_size, 4 * scalar_size),
+            _ => panic!("{}:{}: unsupported vector[{}]", file!(), line!(), count),
+        }
+    }
+
+    /// Size and alignment of a type in the std140 layout of uniform buffers,
+    /// where arrays, matrix rows, and structs are aligned to at least 16 bytes
+    fn get_type_layout(ty: slang::ReflectionType) -> (usize, usize) {
         let kind = ty.get_kind();
         let element_count = ty.get_element_count();
         let column_count = ty.get_column_count();
         let row_count = ty.get_row_count();
+        let scalar_size = Self::get_scalar_size(ty);
         match kind {
-            slang::TypeKind::Vector => match element_count {
-                2 => 2 * 4,
-                3 => 3 * 4,
-                4 => 4 * 4,
-                _ => panic!(
-                    "{}:{}: unsupported vector[{}]",
-                    file!(),
-                    line!(),
-                    element_count
-                ),
-            },
+            slang::TypeKind::Scalar => (scalar_size, scalar_size),
+            slang::TypeKind::Vector => Self::get_vector_layout(scalar_size, element_count),
+            // Row-major rows are padded to 16 bytes, or to the alignment of wider rows
             slang::TypeKind::Matrix => match (row_count, column_count) {
-                (3, 3) => 3 * 3 * 4,
-                (4, 4) => 4 * 4 * 4,
+                (2, 2) | (3, 3) | (4, 4) | (3, 4) | (4, 3) => {
+                    let (size, align) = Self::get_vector_layout(scalar_size, column_count as usize);
+                    let align = align.max(16);
+                    (row_count as usize * align_to(size, align), align)
+                }
                 _ => panic!(
                     "{}:{}: unsupported matrix[{}][{}]",
                     file!(),
@@ -430,34 +2032,118 @@ impl ParamType {
                     column_count
                 ),
             },
+            slang::TypeKind::Array => {
+                let element_type = ty.get_element_type().unwrap();
+                let (size, align) = Self::get_type_layout(element_type);
+                let align = align.max(16);
+                (align_to(size, align) * element_count, align)
+            }
             slang::TypeKind::ConstantBuffer => {
                 let element_type = ty.get_element_type().unwrap();
-                Self::get_type_size(element_type)
+                Self::get_type_layout(element_type)
             }
             slang::TypeKind::Struct => {
-                let mut size = 0;
-                for i in 0..ty.get_field_count() {
-                    if let Some(field) = ty.get_field_by_index(i) {
-                        let field_ty = field.get_type();
-                        size += ParamType::get_type_size(field_ty);
-                    }
-                }
-                size
+                let (size, align, _) = Self::get_aligned_struct_layout(ty);
+                (size, align)
             }
             _ => panic!("{}:{}: unsupported slang type {:?}", file!(), line!(), kind),
         }
     }
 
+    /// Size of a struct in the std140 layout and the offsets of its fields
+    pub fn get_struct_layout(ty: slang::ReflectionType) -> (usize, Vec<usize>) {
+        let (size, _, offsets) = Self::get_aligned_struct_layout(ty);
+        (size, offsets)
+    }
+
+    /// Size, alignment, and field offsets of a struct, which is aligned to
+    /// its widest field and to at least 16 bytes
+    fn get_aligned_struct_layout(ty: slang::ReflectionType) -> (usize, usize, Vec<usize>) {
+        let mut offset = 0;
+        let mut struct_align = 16;
+        let mut offsets = Vec::new();
+        for i in 0..ty.get_field_count() {
+            if let Some(field) = ty.get_field_by_index(i) {
+                let (size, align) = Self::get_type_layout(field.get_type());
+                offset = align_to(offset, align);
+                offsets.push(offset);
+                offset += size;
+                struct_align = struct_align.max(align);
+            }
+        }
+        (align_to(offset, struct_align), struct_align, offsets)
+    }
+
+    /// Returns the element type and the element count of an array of scalars or
+    /// vectors, whose std430 stride is the size of its elements
+    fn get_push_array(ty: slang::ReflectionType) -> Option<(Self, u32)> {
+        if ty.get_kind() != slang::TypeKind::Array {
+            return None;
+        }
+        let element_type = ty.get_element_type()?;
+        match element_type.get_kind() {
+            slang::TypeKind::Scalar | slang::TypeKind::Vector => {
+                Some((Self::from_type(element_type), ty.get_element_count() as u32))
+            }
+            _ => None,
+        }
+    }
+
     fn from_type(ty: slang::ReflectionType) -> Self {
         let kind = ty.get_kind();
         let element_count = ty.get_element_count();
         let column_count = ty.get_column_count();
         let row_count = ty.get_row_count();
         match kind {
-            slang::TypeKind::Vector => match element_count {
-                2 => Self::Vec2,
-                3 => Self::Vec3,
-                4 => Self::Vec4,
+            slang::TypeKind::Scalar if ty.get_scalar_type() == slang::ScalarType::Float16 => {
+                Self::Half
+            }
+            slang::TypeKind::Vector if ty.get_scalar_type() == slang::ScalarType::Float16 => {
+                match element_count {
+                    2 => Self::Half2,
+                    3 => Self::Half3,
+                    4 => Self::Half4,
+                    _ => panic!(
+                        "{}:{}: unsupported half vector[{}]",
+                        file!(),
+                        line!(),
+                        element_count
+                    ),
+                }
+            }
+            slang::TypeKind::Scalar if ty.get_scalar_type() == slang::ScalarType::Float64 => {
+                Self::Double
+            }
+            slang::TypeKind::Vector if ty.get_scalar_type() == slang::ScalarType::Float64 => {
+                match element_count {
+                    2 => Self::DVec2,
+                    3 => Self::DVec3,
+                    4 => Self::DVec4,
+                    _ => panic!(
+                        "{}:{}: unsupported double vector[{}]",
+                        file!(),
+                        line!(),
+                        element_count
+                    ),
+                }
+            }
+            slang::TypeKind::Scalar => match ScalarType::from_type(ty) {
+                ScalarType::Float => Self::Float,
+                ScalarType::Int => Self::Int,
+                ScalarType::Uint => Self::Uint,
+                ScalarType::Bool => Self::Bool,
+            },
+            // Booleans take 4 bytes, as unsigned integers
+            slang::TypeKind::Vector => match (ScalarType::from_type(ty), element_count) {
+                (ScalarType::Float, 2) => Self::Vec2,
+                (ScalarType::Float, 3) => Self::Vec3,
+                (ScalarType::Float, 4) => Self::Vec4,
+                (ScalarType::Int, 2) => Self::IVec2,
+                (ScalarType::Int, 3) => Self::IVec3,
+                (ScalarType::Int, 4) => Self::IVec4,
+                (ScalarType::Uint | ScalarType::Bool, 2) => Self::UVec2,
+                (ScalarType::Uint | ScalarType::Bool, 3) => Self::UVec3,
+                (ScalarType::Uint | ScalarType::Bool, 4) => Self::UVec4,
                 _ => panic!(
                     "{}:{}: unsupported vector[{}]",
                     file!(),
@@ -466,8 +2152,11 @@ impl ParamType {
                 ),
             },
             slang::TypeKind::Matrix => match (row_count, column_count) {
+                (2, 2) => Self::Mat2,
                 (3, 3) => Self::Mat3,
                 (4, 4) => Self::Mat4,
+                (3, 4) => Self::Mat3x4,
+                (4, 3) => Self::Mat4x3,
                 _ => panic!(
                     "{}:{}: unsupported matrix[{}][{}]",
                     file!(),
@@ -480,12 +2169,33 @@ impl ParamType {
                 let element_type = ty.get_element_type().unwrap();
                 Self::from_type(element_type)
             }
-            slang::TypeKind::Resource => Self::SampledImage,
-            slang::TypeKind::Struct => {
-                let size = Self::get_type_size(ty);
-                // Align size to 16 bytes
-                let size = (size + 15) / 16 * 16;
-                Self::Struct(size)
+            slang::TypeKind::Resource => match ty.get_resource_shape() {
+                slang::ResourceShape::SlangAccelerationStructure => Self::AccelerationStructure,
+                slang::ResourceShape::SlangStructuredBuffer => {
+                    let element_type = ty.get_resource_result_type().unwrap();
+                    Self::StorageBuffer(Self::get_type_size(element_type))
+                }
+                // Byte-address buffers are accessed as 32-bit words
+                slang::ResourceShape::SlangByteAddressBuffer => Self::StorageBuffer(4),
+                // Typed buffers are accessed through a buffer view of their format
+                slang::ResourceShape::SlangTextureBuffer
+                    if ty.get_resource_access() == slang::ResourceAccess::ReadWrite =>
+                {
+                    Self::StorageTexelBuffer
+                }
+                slang::ResourceShape::SlangTextureBuffer => Self::UniformTexelBuffer,
+                _ if ty.get_resource_access() == slang::ResourceAccess::ReadWrite => {
+                    Self::StorageImage
+                }
+                _ => Self::SampledImage,
+            },
+            // Arrays outside of descriptors are plain bytes, e.g. a push constant `float4x4 m[2]`
+            slang::TypeKind::Struct | slang::TypeKind::Array => {
+                Self::Struct(Self::get_type_size(ty))
+            }
+            // Comparison samplers for shadow maps are bound on their own
+            slang::TypeKind::SamplerState if ty.get_name() == "SamplerComparisonState" => {
+                Self::Sampler
             }
             slang::TypeKind::SamplerState => Self::SampledImage,
             _ => panic!("{}:{}: unsupported slang type {:?}", file!(), line!(), kind),
@@ -494,11 +2204,24 @@ impl ParamType {
 
     pub fn get_size(&self) -> usize {
         match self {
-            ParamType::Vec2 => std::mem::size_of::<f32>() * 2,
-            ParamType::Vec3 => std::mem::size_of::<f32>() * 4, // simd
-            ParamType::Vec4 => std::mem::size_of::<f32>() * 4,
-            ParamType::Mat3 => std::mem::size_of::<f32>() * 9,
+            ParamType::Vec2 | ParamType::IVec2 | ParamType::UVec2 => std::mem::size_of::<f32>() * 2,
+            ParamType::Vec3 | ParamType::IVec3 | ParamType::UVec3 => std::mem::size_of::<f32>() * 4, // simd
+            ParamType::Vec4 | ParamType::IVec4 | ParamType::UVec4 => std::mem::size_of::<f32>() * 4,
+            // Row-major rows are padded to 16 bytes on the GPU
+            ParamType::Mat3 => std::mem::size_of::<f32>() * 4 * 3,
+            ParamType::Mat2 => std::mem::size_of::<f32>() * 4 * 2,
+            ParamType::Mat3x4 => std::mem::size_of::<f32>() * 4 * 3,
+            ParamType::Mat4x3 => std::mem::size_of::<f32>() * 4 * 4,
             ParamType::Mat4 => std::mem::size_of::<f32>() * 16,
+            ParamType::Float | ParamType::Int | ParamType::Uint | ParamType::Bool => 4,
+            ParamType::Half => std::mem::size_of::<u16>(),
+            ParamType::Half2 => std::mem::size_of::<u16>() * 2,
+            ParamType::Half3 => std::mem::size_of::<u16>() * 4, // aligned as half4
+            ParamType::Half4 => std::mem::size_of::<u16>() * 4,
+            ParamType::Double => std::mem::size_of::<f64>(),
+            ParamType::DVec2 => std::mem::size_of::<f64>() * 2,
+            ParamType::DVec3 => std::mem::size_of::<f64>() * 4, // aligned as double4
+            ParamType::DVec4 => std::mem::size_of::<f64>() * 4,
             ParamType::Struct(size) => {
                 if *size == 0 {
                     panic!("{}:{}: Struct size is not known", file!(), line!());
@@ -510,11 +2233,19 @@ impl ParamType {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum DescriptorType {
     Uniform,
+    /// Uniform buffer whose offset is given when binding its set
+    UniformDynamic,
     CombinedSampler,
     InputAttachment,
+    AccelerationStructure,
+    StorageBuffer,
+    StorageImage,
+    Sampler,
+    UniformTexelBuffer,
+    StorageTexelBuffer,
 }
 
 impl From<ParamType> for DescriptorType {
@@ -522,6 +2253,12 @@ impl From<ParamType> for DescriptorType {
         match param {
             ParamType::SampledImage => DescriptorType::CombinedSampler,
             ParamType::Image => DescriptorType::InputAttachment,
+            ParamType::AccelerationStructure => DescriptorType::AccelerationStructure,
+            ParamType::StorageBuffer(_) => DescriptorType::StorageBuffer,
+            ParamType::StorageImage => DescriptorType::StorageImage,
+            ParamType::Sampler => DescriptorType::Sampler,
+            ParamType::UniformTexelBuffer => DescriptorType::UniformTexelBuffer,
+            ParamType::StorageTexelBuffer => DescriptorType::StorageTexelBuffer,
             _ => DescriptorType::Uniform,
         }
     }
@@ -530,57 +2267,208 @@ impl From<ParamType> for DescriptorType {
 #[derive(Default)]
 pub struct SetLayout {
     pub bindings: Vec<SetLayoutBinding>,
+    /// Created for push descriptors, so no set is allocated with it
+    pub push: bool,
 }
 
 impl SetLayout {
     pub fn new(bindings: Vec<SetLayoutBinding>) -> SetLayout {
-        Self { bindings }
+        Self {
+            bindings,
+            push: false,
+        }
+    }
+
+    /// Stable structural hash of the set layout, equal for the set layouts of any
+    /// pipeline created from the same bindings and flags, or `None` when immutable
+    /// samplers provided at runtime are baked in it
+    pub fn get_hash(&self) -> Option<u64> {
+        if self.bindings.iter().any(|binding| binding.immutable) {
+            return None;
+        }
+        let mut words = vec![self.push as u32];
+        for binding in &self.bindings {
+            let stages = binding
+                .stages
+                .iter()
+                .fold(0u32, |stages, stage| stages | 1 << *stage as u32);
+            words.extend([
+                binding.binding,
+                binding.descriptor_type as u32,
+                binding.count,
+                stages,
+                binding.bindless as u32,
+            ]);
+        }
+        // FNV-1a does not depend on the compiler, unlike the hasher of the standard library
+        let hash = words
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .fold(0xcbf29ce484222325u64, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+            });
+        Some(hash)
     }
 }
 
 pub struct SetLayoutBinding {
-    pub stage: ShaderType,
+    /// Stages of the shaders using the binding
+    pub stages: Vec<ShaderType>,
     pub descriptor_type: DescriptorType,
     pub binding: u32,
+    pub count: u32,
+    /// Partially bound and updated after binding
+    pub bindless: bool,
+    /// Created with an immutable sampler, provided when creating the set layouts
+    pub immutable: bool,
 }
 
 #[derive(Clone, Default, Debug)]
 pub struct BindMethod {
+    /// Name of the pipeline struct, whose uniform enum is mixed into descriptor keys
+    pub pipeline: String,
+    /// Sets of compute and ray tracing pipelines are bound on a raw command buffer
+    pub bind_point: BindPoint,
     pub uniforms: Vec<Uniform>,
+    /// The set is written with push descriptors instead of being allocated
+    pub push: bool,
+    /// Sets are allocated once per frame in flight, selected by the frame index
+    pub frames_in_flight: Option<u32>,
+    /// The former names of the methods, joining the names of the uniforms,
+    /// collide with other generated methods, so they are left out
+    pub former_names_collide: bool,
 }
 
 impl BindMethod {
+    /// Whether any uniform of this set is bound with a dynamic offset
+    pub fn has_dynamic(&self) -> bool {
+        self.uniforms.iter().any(|uniform| uniform.dynamic)
+    }
+
+    /// Whether any uniform of this set is written with an image info
+    pub fn has_textures(&self) -> bool {
+        self.uniforms
+            .iter()
+            .any(|uniform| VkrType::from(uniform.param.ty) == VkrType::Texture)
+    }
+
     pub fn get_method_params(&self) -> Vec<MethodParam> {
         let mut ret = Vec::new();
         for uniform in &self.uniforms {
             ret.push(MethodParam {
                 name: uniform.param.name.clone(),
                 ty: uniform.param.ty,
+                optional: uniform.optional,
+                count: uniform.array_count,
+                bindless: uniform.bindless,
+                immutable: uniform.immutable,
+                dynamic: uniform.dynamic,
+                buffer_offset: uniform.buffer_offset,
+                raw: false,
             })
         }
         ret
     }
 
+    /// Parameters of the variant of the bind method taking raw Vulkan handles
+    pub fn get_raw_method_params(&self) -> Vec<MethodParam> {
+        let mut ret = self.get_method_params();
+        for param in &mut ret {
+            param.raw = true;
+        }
+        ret
+    }
+
     pub fn get_write_sets(&self) -> Vec<WriteSet> {
         let mut ret = Vec::new();
         for uniform in &self.uniforms {
             ret.push(WriteSet {
                 binding: uniform.binding,
-                descriptor_type: uniform.param.ty.into(),
+                descriptor_type: uniform.get_descriptor_type(),
                 info: WriteSetInfo {
                     name: uniform.param.name.clone(),
                     ty: uniform.param.ty,
+                    optional: uniform.optional,
+                    count: uniform.array_count,
+                    bindless: uniform.bindless,
+                    immutable: uniform.immutable,
+                    buffer_offset: uniform.buffer_offset,
+                    raw: false,
+                    image_layout: uniform.image_layout.clone(),
                 },
+                push: self.push,
+                index: 0,
             })
         }
         ret
     }
+
+    /// Writes of the variant of the bind method taking raw Vulkan handles
+    pub fn get_raw_write_sets(&self) -> Vec<WriteSet> {
+        let mut ret = self.get_write_sets();
+        for write_set in &mut ret {
+            write_set.info.raw = true;
+        }
+        ret
+    }
+}
+
+/// Method binding every set of the pipeline at once
+#[derive(Clone, Debug)]
+pub struct BindAllMethod {
+    /// Bind methods of the sets, from set 0 onwards
+    pub methods: Vec<BindMethod>,
+}
+
+impl BindAllMethod {
+    /// Whether any set is bound with dynamic offsets
+    pub fn has_dynamic(&self) -> bool {
+        self.methods.iter().any(|method| method.has_dynamic())
+    }
+
+    /// Whether any set is written with an image info
+    pub fn has_textures(&self) -> bool {
+        self.methods.iter().any(|method| method.has_textures())
+    }
+
+    /// Parameters of every set, ordered by set and then by binding
+    pub fn get_method_params(&self) -> Vec<MethodParam> {
+        self.methods
+            .iter()
+            .flat_map(|method| method.get_method_params())
+            .collect()
+    }
+
+    /// Writes of every set, each one into the set at its index
+    pub fn get_write_sets(&self) -> Vec<WriteSet> {
+        let mut ret = Vec::new();
+        for (index, method) in self.methods.iter().enumerate() {
+            for mut write_set in method.get_write_sets() {
+                write_set.index = index;
+                ret.push(write_set);
+            }
+        }
+        ret
+    }
 }
 
 #[derive(Clone, Debug)]
 pub struct MethodParam {
     pub name: String,
     pub ty: ParamType,
+    pub optional: bool,
+    /// Arrays are taken as references to fixed-size arrays of resources
+    pub count: u32,
+    /// Unbounded arrays are taken as slices, with the index of their first element
+    pub bindless: bool,
+    /// Textures with an immutable sampler are taken as image views
+    pub immutable: bool,
+    /// Dynamic uniform buffers are followed by their offset
+    pub dynamic: bool,
+    /// Buffers bound from an offset are followed by it
+    pub buffer_offset: bool,
+    /// Resources are taken as the raw Vulkan handles they wrap
+    pub raw: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -588,6 +2476,10 @@ pub struct WriteSet {
     pub binding: u32,
     pub descriptor_type: DescriptorType,
     pub info: WriteSetInfo,
+    /// Pushed descriptors are written without a destination set
+    pub push: bool,
+    /// Index of the destination among the sets written together
+    pub index: usize,
 }
 
 /// The info associated to the `WriteDescriptorSet` changes according to the
@@ -596,45 +2488,159 @@ pub struct WriteSet {
 pub struct WriteSetInfo {
     pub name: String,
     pub ty: ParamType,
+    pub optional: bool,
+    /// Number of infos to write, one per array element
+    pub count: u32,
+    /// Unbounded arrays write an info per element of a slice
+    pub bindless: bool,
+    /// Textures with an immutable sampler write only their image view
+    pub immutable: bool,
+    /// Buffers are written from the offset following them
+    pub buffer_offset: bool,
+    /// Resources are read from raw Vulkan handles
+    pub raw: bool,
+    /// Images are written in this layout instead of the one given to the bind method
+    pub image_layout: Option<String>,
 }
 
 /// Push constant range for constructing the pipeline layout
 #[derive(Clone, Debug)]
 pub struct PushRange {
     pub ty: ParamType,
-    pub stage: ShaderType,
+    /// Number of elements of an array, one otherwise
+    pub count: u32,
+    /// Stages of the shaders reading the constant
+    pub stages: Vec<ShaderType>,
+    /// Byte offset of the constant in the push constant block
+    pub offset: usize,
 }
 
 impl PushRange {
     pub fn new(ty: ParamType, stage: ShaderType) -> Self {
-        Self { ty, stage }
+        Self {
+            ty,
+            count: 1,
+            stages: vec![stage],
+            offset: 0,
+        }
+    }
+
+    /// Size of the range, where array elements are laid out with their std430 stride
+    pub fn get_size(&self) -> usize {
+        self.ty.get_size() * self.count as usize
     }
 }
 
 /// Methods for pushing constants
 #[derive(Clone, Debug)]
 pub struct PushMethod {
+    /// Name of the pipeline struct, which prefixes the struct mirroring the constant
+    pub pipeline: String,
     pub name: String,
     pub ty: ParamType,
-    pub stage: ShaderType,
+    /// Arrays are pushed from references to fixed-size arrays of their elements
+    pub count: u32,
+    /// Stages of the shaders reading the constant
+    pub stages: Vec<ShaderType>,
+    /// Fields of a struct constant, pushed from a generated Rust struct
+    pub fields: Vec<StructField>,
+    /// Byte offset of the constant in the push constant block
+    pub offset: usize,
 }
 
 impl PushMethod {
     pub fn new(name: String, ty: ParamType, stage: ShaderType) -> Self {
-        Self { name, ty, stage }
+        Self {
+            pipeline: String::new(),
+            name,
+            ty,
+            count: 1,
+            stages: vec![stage],
+            fields: Vec::new(),
+            offset: 0,
+        }
     }
-}
 
-#[derive(Copy, Clone)]
+    /// Number of bytes pushed, where array elements are laid out with their std430 stride
+    pub fn get_size(&self) -> usize {
+        self.ty.get_size() * self.count as usize
+    }
+
+    /// Offset of the byte following the constant in the push constant block
+    pub fn get_end(&self) -> usize {
+        self.offset + self.get_size()
+    }
+}
+
+/// Point where the descriptor sets of a pipeline are bound
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum BindPoint {
+    #[default]
+    Graphics,
+    Compute,
+    RayTracing,
+}
+
+/// Dimensionality of a texture, matching the type of its image view
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TextureShape {
+    Texture1D,
+    Texture1DArray,
+    Texture2D,
+    Texture2DArray,
+    Texture3D,
+    Cube,
+    CubeArray,
+}
+
+impl TextureShape {
+    /// Returns the shape of a texture type, or `None` for other types
+    fn from_type(ty: slang::ReflectionType) -> Option<Self> {
+        if ty.get_kind() != slang::TypeKind::Resource {
+            return None;
+        }
+        match ty.get_resource_shape() {
+            slang::ResourceShape::SlangTexture1d => Some(Self::Texture1D),
+            slang::ResourceShape::SlangTexture1dArray => Some(Self::Texture1DArray),
+            slang::ResourceShape::SlangTexture2d => Some(Self::Texture2D),
+            slang::ResourceShape::SlangTexture2dArray => Some(Self::Texture2DArray),
+            slang::ResourceShape::SlangTexture3d => Some(Self::Texture3D),
+            slang::ResourceShape::SlangTextureCube => Some(Self::Cube),
+            slang::ResourceShape::SlangTextureCubeArray => Some(Self::CubeArray),
+            _ => None,
+        }
+    }
+
+    pub fn get_name(&self) -> &'static str {
+        match self {
+            Self::Texture1D => "1D",
+            Self::Texture1DArray => "1D array",
+            Self::Texture2D => "2D",
+            Self::Texture2DArray => "2D array",
+            Self::Texture3D => "3D",
+            Self::Cube => "cube",
+            Self::CubeArray => "cube array",
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq)]
 pub enum VkrType {
     Buffer,
     Texture,
+    AccelerationStructure,
+    Sampler,
+    /// Texel buffers are bound through a view of the buffer
+    BufferView,
 }
 
 impl From<ParamType> for VkrType {
     fn from(ty: ParamType) -> Self {
         match ty {
-            ParamType::SampledImage | ParamType::Image => Self::Texture,
+            ParamType::SampledImage | ParamType::Image | ParamType::StorageImage => Self::Texture,
+            ParamType::AccelerationStructure => Self::AccelerationStructure,
+            ParamType::Sampler => Self::Sampler,
+            ParamType::UniformTexelBuffer | ParamType::StorageTexelBuffer => Self::BufferView,
             _ => Self::Buffer,
         }
     }
@@ -670,7 +2676,10 @@ mod test {
         "#;
         let slang = Slang::new();
         let vert = slang.from_source("test", code);
-        let pipeline = Pipeline::builder().name("Shader").vert(vert).build();
+        let pipeline = Pipeline::builder()
+            .name("Shader")
+            .shader(Shader::from(vert))
+            .build();
         assert_eq!(pipeline.name, "Shader");
         Ok(())
     }
@@ -686,7 +2695,10 @@ mod test {
 
         let slang = Slang::new();
         let vert = slang.from_source("test", code);
-        let pipeline = Pipeline::builder().name("Shader").vert(vert).build();
+        let pipeline = Pipeline::builder()
+            .name("Shader")
+            .shader(Shader::from(vert))
+            .build();
         assert_eq!(pipeline.name, "Shader");
 
         assert!(!pipeline.shaders.is_empty());
@@ -703,6 +2715,37 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn parse_system_values() -> Result<(), Box<dyn Error>> {
+        let code = r#"
+            [vk::binding(0, 0)]
+            ConstantBuffer<float4x4> view_proj;
+
+            [shader("vertex")]
+            float4 main(
+                float3 pos,
+                uint vertex_id : SV_VertexID,
+                float2 uv,
+                uint instance_id : SV_InstanceID,
+            ) : SV_Position {
+                float offset = float(vertex_id + instance_id);
+                return mul(view_proj, float4(pos + offset, uv.x));
+            }
+        "#;
+
+        let slang = Slang::new();
+        let vert = slang.from_source("test", code);
+        let shader = Shader::from(vert);
+        let names = shader
+            .params
+            .iter()
+            .map(|param| param.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["pos", "uv"]);
+
+        Ok(())
+    }
+
     #[test]
     fn parse_uniforms() -> Result<(), Box<dyn Error>> {
         let code = r#"
@@ -724,7 +2767,10 @@ mod test {
 
         let slang = Slang::new();
         let vert = slang.from_source("test", code);
-        let pipeline = Pipeline::builder().name("Shader").vert(vert).build();
+        let pipeline = Pipeline::builder()
+            .name("Shader")
+            .shader(Shader::from(vert))
+            .build();
         assert_eq!(pipeline.name, "Shader");
 
         assert!(!pipeline.shaders.is_empty());
@@ -767,7 +2813,10 @@ mod test {
 
         let slang = Slang::new();
         let vert = slang.from_source("test", code);
-        let pipeline = Pipeline::builder().name("Shader").vert(vert).build();
+        let pipeline = Pipeline::builder()
+            .name("Shader")
+            .shader(Shader::from(vert))
+            .build();
         assert_eq!(pipeline.name, "Shader");
 
         assert!(!pipeline.shaders.is_empty());
@@ -793,7 +2842,10 @@ mod test {
 
         let slang = Slang::new();
         let vert = slang.from_source("test", code);
-        let pipeline = Pipeline::builder().name("Shader").vert(vert).build();
+        let pipeline = Pipeline::builder()
+            .name("Shader")
+            .shader(Shader::from(vert))
+            .build();
         assert_eq!(pipeline.name, "Shader");
 
         assert!(!pipeline.shaders.is_empty());
@@ -804,6 +2856,16 @@ mod test {
         assert_eq!(shader.uniforms[0].set, 2);
         assert_eq!(shader.uniforms[0].binding, 3);
 
+        // Input attachments are written without a sampler, in the layout of the bind method
+        use quote::ToTokens;
+        let methods = pipeline.get_bind_methods();
+        let tokens = methods[0].to_token_stream().to_string().replace(' ', "");
+        assert!(tokens.contains(
+            "[vk::DescriptorImageInfo::default().image_layout(layout).image_view(scene_color.view)]"
+        ));
+        assert!(!tokens.contains(".sampler("));
+        assert!(tokens.contains("pubfnbind_scene_color_with_layout("));
+
         Ok(())
     }
 
@@ -819,7 +2881,10 @@ mod test {
 
         let slang = Slang::new();
         let vert = slang.from_source("test", code);
-        let pipeline = Pipeline::builder().name("Shader").vert(vert).build();
+        let pipeline = Pipeline::builder()
+            .name("Shader")
+            .shader(Shader::from(vert))
+            .build();
         assert_eq!(pipeline.name, "Shader");
 
         assert!(!pipeline.shaders.is_empty());
@@ -829,6 +2894,32 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn parse_scalar_constants() -> Result<(), Box<dyn Error>> {
+        let code = r#"
+            [vk::push_constant] float exposure;
+            [shader("fragment")]
+            float4 main() : SV_Target {
+                return float4(exposure);
+            }
+        "#;
+
+        let slang = Slang::new();
+        let vert = slang.from_source("test", code);
+        let pipeline = Pipeline::builder()
+            .name("Shader")
+            .shader(Shader::from(vert))
+            .build();
+        assert_eq!(pipeline.name, "Shader");
+
+        assert!(!pipeline.shaders.is_empty());
+        let shader = &pipeline.shaders[0];
+        assert_eq!(shader.constants[0].ty, ParamType::Float);
+        assert_eq!(shader.constants[0].ty.get_size(), 4);
+
+        Ok(())
+    }
+
     #[test]
     fn parse_complex_constants() -> Result<(), Box<dyn Error>> {
         let code = r#"
@@ -844,12 +2935,949 @@ mod test {
 
         let slang = Slang::new();
         let vert = slang.from_source("test", code);
-        let pipeline = Pipeline::builder().name("Shader").vert(vert).build();
+        let pipeline = Pipeline::builder()
+            .name("Shader")
+            .shader(Shader::from(vert))
+            .build();
         assert_eq!(pipeline.name, "Shader");
 
         assert!(!pipeline.shaders.is_empty());
         let shader = &pipeline.shaders[0];
         assert_eq!(shader.constants[0].ty, ParamType::Struct(16));
+        assert_eq!(
+            shader.constants[0].fields,
+            vec![StructField {
+                name: "color".into(),
+                ty: ParamType::Vec4,
+                count: 1,
+                offset: 0,
+                stride: 16,
+            }]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn push_constant_matrix_array() -> Result<(), Box<dyn Error>> {
+        let code = r#"
+            struct LightParams {
+                float3 direction;
+                float intensity;
+                float4 color;
+            };
+
+            struct PushConstants {
+                float4x4 m[2];
+                LightParams light;
+            };
+
+            [vk::push_constant] ConstantBuffer<PushConstants> constants;
+
+            [shader("vertex")]
+            float4 main(float4 position) : SV_Position {
+                return mul(constants.m[1], position) * constants.light.intensity;
+            }
+        "#;
+
+        let slang = Slang::new();
+        let vert = slang.from_source("test", code);
+        let var_layout = vert.get_parameter_by_index(0).unwrap();
+        let size = var_layout
+            .get_type_layout()
+            .unwrap()
+            .get_element_type_layout()
+            .unwrap()
+            .get_size(slang::ParameterCategory::Uniform);
+        assert_eq!(size, 160);
+
+        let shader = Shader::from(vert);
+        assert_eq!(shader.constants[0].ty, ParamType::Struct(size));
+
+        Ok(())
+    }
+
+    #[test]
+    fn push_constant_std430() -> Result<(), Box<dyn Error>> {
+        let code = r#"
+            struct PushConstants {
+                float weights[4];
+                float3 tint;
+                float exposure;
+            };
+
+            [vk::push_constant] PushConstants constants;
+
+            [shader("fragment")]
+            float4 main() : SV_Target {
+                return float4(constants.tint * constants.weights[3], constants.exposure);
+            }
+        "#;
+
+        let slang = Slang::new();
+        let frag = slang.from_source("test", code);
+        let var_layout = frag.get_parameter_by_index(0).unwrap();
+        let struct_layout = Param::get_push_layout(&var_layout);
+        let size = struct_layout.get_size(slang::ParameterCategory::Uniform);
+        assert_eq!(size, 32);
+
+        // Array elements are not padded to 16 bytes as in constant buffers
+        let shader = Shader::from(frag);
+        let constants = &shader.constants[0];
+        assert_eq!(constants.ty, ParamType::Struct(size));
+        let fields = constants
+            .fields
+            .iter()
+            .map(|field| (field.ty, field.count, field.offset, field.stride))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            vec![
+                (ParamType::Float, 4, 0, 4),
+                (ParamType::Vec3, 1, 16, 12),
+                (ParamType::Float, 1, 28, 4),
+            ]
+        );
+        for (i, field) in constants.fields.iter().enumerate() {
+            let field_layout = struct_layout.get_field_by_index(i as u32).unwrap();
+            assert_eq!(
+                field.offset,
+                field_layout.get_offset(slang::ParameterCategory::Uniform)
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn nested_struct_uniform() -> Result<(), Box<dyn Error>> {
+        let code = r#"
+            struct Inner {
+                float2 a;
+                float b;
+            };
+
+            struct Middle {
+                Inner inner;
+                float3 c;
+                Inner pair[2];
+            };
+
+            struct Outer {
+                float x;
+                Middle middle;
+                float y[2][2];
+            };
+
+            [vk::binding(0, 0)] ConstantBuffer<Outer> outer;
+
+            [shader("fragment")]
+            float4 main() : SV_Target {
+                return float4(outer.middle.pair[1].b + outer.x + outer.y[1][0]);
+            }
+        "#;
+
+        let slang = Slang::new();
+        let frag = slang.from_source("test", code);
+        let var_layout = frag.get_parameter_by_index(0).unwrap();
+        let size = var_layout
+            .get_type_layout()
+            .unwrap()
+            .get_element_type_layout()
+            .unwrap()
+            .get_size(slang::ParameterCategory::Uniform);
+        assert_eq!(size, 144);
+
+        let shader = Shader::from(frag);
+        let outer = &shader.uniforms[0];
+        assert_eq!(outer.param.ty, ParamType::Struct(size));
+        let fields = outer
+            .fields
+            .iter()
+            .map(|field| (field.ty, field.count, field.offset))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            vec![
+                (ParamType::Float, 1, 0),
+                (ParamType::Struct(64), 1, 16),
+                (ParamType::Float, 4, 80),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_optional_texture() -> Result<(), Box<dyn Error>> {
+        let code = r#"
+            [vk::binding(0)]
+            Sampler2D albedo;
+
+            [vk::binding(1)]
+            Sampler2D emissive;
+
+            [shader("fragment")]
+            float4 main(float2 uv) : SV_Target {
+                return albedo.Sample(uv) + emissive.Sample(uv);
+            }
+        "#;
+
+        let slang = Slang::new();
+        let frag = slang.from_source("test", code);
+        let pipeline = Pipeline::builder()
+            .name("Shader")
+            .shader(Shader::from(frag))
+            .optional(vec!["emissive".into()])
+            .build();
+        assert!(pipeline.has_optional());
+
+        let shader = &pipeline.shaders[0];
+        assert_eq!(shader.uniforms[0].param.name, "albedo");
+        assert!(!shader.uniforms[0].optional);
+        assert_eq!(shader.uniforms[1].param.name, "emissive");
+        assert!(shader.uniforms[1].optional);
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_constant_buffer_array() -> Result<(), Box<dyn Error>> {
+        let code = r#"
+            struct CascadeData {
+                float4x4 view_proj;
+                float4 split;
+            };
+
+            [vk::binding(0, 0)]
+            ConstantBuffer<CascadeData> cascades[4];
+
+            [shader("fragment")]
+            float4 main() : SV_Target {
+                return cascades[3].split;
+            }
+        "#;
+
+        let slang = Slang::new();
+        let frag = slang.from_source("test", code);
+        let pipeline = Pipeline::builder()
+            .name("Shader")
+            .shader(Shader::from(frag))
+            .build();
+
+        let shader = &pipeline.shaders[0];
+        assert_eq!(shader.uniforms.len(), 1);
+        assert_eq!(shader.uniforms[0].param.ty, ParamType::Struct(80));
+        assert_eq!(shader.uniforms[0].array_count, 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn std140_layout() -> Result<(), Box<dyn Error>> {
+        let code = r#"
+            struct Packed {
+                float3 a;
+                float b;
+                float2 c;
+            };
+
+            struct Padded {
+                float a;
+                float3 b;
+                float2 c;
+                float d;
+            };
+
+            struct Nested {
+                float2 a;
+                Packed b;
+                float c;
+            };
+
+            struct Arrays {
+                float a[3];
+                float3x3 b;
+                uint c;
+            };
+
+            [vk::binding(0, 0)] ConstantBuffer<Packed> packed;
+            [vk::binding(1, 0)] ConstantBuffer<Padded> padded;
+            [vk::binding(2, 0)] ConstantBuffer<Nested> nested;
+            [vk::binding(3, 0)] ConstantBuffer<Arrays> arrays;
+
+            [shader("fragment")]
+            float4 main() : SV_Target {
+                return float4(packed.b + padded.d + nested.c + arrays.a[2] + float(arrays.c));
+            }
+        "#;
+
+        let slang = Slang::new();
+        let frag = slang.from_source("test", code);
+        for i in 0..frag.get_parameter_count() {
+            let var_layout = frag.get_parameter_by_index(i).unwrap();
+            let element_layout = var_layout
+                .get_type_layout()
+                .unwrap()
+                .get_element_type_layout()
+                .unwrap();
+            let element_type = var_layout
+                .get_variable()
+                .unwrap()
+                .get_type()
+                .get_element_type();
+            let (size, offsets) = ParamType::get_struct_layout(element_type.unwrap());
+            assert_eq!(
+                size,
+                element_layout.get_size(slang::ParameterCategory::Uniform)
+            );
+            for (j, offset) in offsets.into_iter().enumerate() {
+                let field = element_layout.get_field_by_index(j as u32).unwrap();
+                assert_eq!(offset, field.get_offset(slang::ParameterCategory::Uniform));
+            }
+        }
+
+        let (size, offsets) = ParamType::get_struct_layout(
+            frag.get_parameter_by_index(1)
+                .unwrap()
+                .get_variable()
+                .unwrap()
+                .get_type()
+                .get_element_type()
+                .unwrap(),
+        );
+        assert_eq!(offsets, vec![0, 16, 32, 40]);
+        assert_eq!(size, 48);
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_struct_fields() {
+        let code = r#"
+            struct Material {
+                float3 base;
+                float roughness;
+                float2 tint;
+                float weights[2];
+                uint flags;
+            };
+
+            [vk::binding(0, 0)]
+            ConstantBuffer<Material> material;
+
+            [vk::binding(1, 0)]
+            ConstantBuffer<float4> color;
+
+            [shader("fragment")]
+            float4 main() : SV_Target {
+                return color * material.roughness;
+            }
+        "#;
+
+        let slang = Slang::new();
+        let frag = slang.from_source("test", code);
+        let shader = Shader::from(frag);
+
+        let material = &shader.uniforms[0];
+        assert_eq!(material.get_struct_size(), Some(80));
+        let fields = material
+            .fields
+            .iter()
+            .map(|field| (field.name.as_str(), field.ty, field.count, field.offset))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields,
+            vec![
+                ("base", ParamType::Vec3, 1, 0),
+                ("roughness", ParamType::Float, 1, 12),
+                ("tint", ParamType::Vec2, 1, 16),
+                ("weights", ParamType::Float, 2, 32),
+                ("flags", ParamType::Uint, 1, 64),
+            ]
+        );
+
+        assert!(shader.uniforms[1].fields.is_empty());
+        assert_eq!(shader.uniforms[1].get_struct_size(), None);
+    }
+
+    #[test]
+    fn parse_parameter_block() {
+        let code = r#"
+            struct Camera {
+                float4x4 view_proj;
+            };
+
+            struct Scene {
+                ConstantBuffer<Camera> camera;
+                Sampler2D albedo;
+                Sampler2D normal;
+            };
+
+            [vk::binding(0, 0)]
+            ConstantBuffer<float4> tint;
+
+            ParameterBlock<Scene> scene;
+
+            [shader("fragment")]
+            float4 main(float2 uv) : SV_Target {
+                return tint * scene.albedo.Sample(uv) * scene.normal.Sample(uv)
+                    * scene.camera.view_proj[0][0];
+            }
+        "#;
+
+        let slang = Slang::new();
+        let frag = slang.from_source("test", code);
+        let pipeline = Pipeline::builder()
+            .name("Scene")
+            .shader(Shader::from(frag))
+            .build();
+
+        let shader = &pipeline.shaders[0];
+        let block = shader
+            .uniforms
+            .iter()
+            .filter(|uniform| uniform.param.name != "tint")
+            .map(|uniform| (uniform.param.name.as_str(), uniform.set, uniform.binding))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            block,
+            vec![("camera", 1, 0), ("albedo", 1, 1), ("normal", 1, 2)]
+        );
+        let camera = shader
+            .uniforms
+            .iter()
+            .find(|uniform| uniform.param.name == "camera");
+        assert_eq!(camera.unwrap().param.ty, ParamType::Struct(64));
+        assert_eq!(shader.get_set_layout_bindings(1).len(), 3);
+
+        // The block gets a bind method of its own
+        let methods = pipeline.get_bind_methods();
+        assert_eq!(methods.len(), 2);
+        assert_eq!(methods[1].uniforms.len(), 3);
+    }
+
+    #[test]
+    fn mixed_scalar_widths() -> Result<(), Box<dyn Error>> {
+        let code = r#"
+            struct Bounds {
+                float scale;
+                half2 uv;
+                double radius;
+                double3 origin;
+                float4 color;
+            };
+
+            [vk::push_constant] double2 offset;
+
+            [vk::binding(0, 0)]
+            ConstantBuffer<Bounds> bounds;
+
+            [shader("fragment")]
+            float4 main() : SV_Target {
+                float2 uv = float2(bounds.uv);
+                return bounds.color * bounds.scale * uv.x
+                    * float(bounds.radius + bounds.origin.x + offset.y);
+            }
+        "#;
+
+        let slang = Slang::new();
+        let frag = slang.from_source("test", code);
+        let var_layout = frag.get_parameter_by_index(1).unwrap();
+        let element_layout = var_layout
+            .get_type_layout()
+            .unwrap()
+            .get_element_type_layout()
+            .unwrap();
+        let element_type = var_layout
+            .get_variable()
+            .unwrap()
+            .get_type()
+            .get_element_type()
+            .unwrap();
+        let (size, offsets) = ParamType::get_struct_layout(element_type);
+        assert_eq!(offsets, vec![0, 4, 8, 32, 64]);
+        assert_eq!(size, 96);
+        assert_eq!(
+            size,
+            element_layout.get_size(slang::ParameterCategory::Uniform)
+        );
+        for (i, offset) in offsets.into_iter().enumerate() {
+            let field = element_layout.get_field_by_index(i as u32).unwrap();
+            assert_eq!(offset, field.get_offset(slang::ParameterCategory::Uniform));
+        }
+
+        let shader = Shader::from(frag);
+        assert_eq!(shader.constants[0].ty, ParamType::DVec2);
+        assert_eq!(shader.constants[0].ty.get_size(), 16);
+        assert_eq!(shader.uniforms[0].param.ty, ParamType::Struct(96));
+        let field_types = shader.uniforms[0]
+            .fields
+            .iter()
+            .map(|field| field.ty)
+            .collect::<Vec<ParamType>>();
+        assert_eq!(
+            field_types,
+            vec![
+                ParamType::Float,
+                ParamType::Half2,
+                ParamType::Double,
+                ParamType::DVec3,
+                ParamType::Vec4
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn reject_non_float_matrices() {
+        let code = r#"
+            [vk::push_constant] double4x4 transform;
+
+            [shader("vertex")]
+            float4 main(float4 position) : SV_Position {
+                return float4(mul(transform, double4(position)));
+            }
+        "#;
+
+        let slang = Slang::new();
+        let vert = slang.from_source("test", code);
+        let result = std::panic::catch_unwind(|| Shader::from(vert));
+        let message = *result.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.contains("`transform` uses a matrix of other scalars than `float`"));
+
+        let code = r#"
+            struct Material {
+                float4 color;
+                half3x3 rotation;
+            };
+
+            [vk::binding(0, 0)]
+            ConstantBuffer<Material> material;
+
+            [shader("fragment")]
+            float4 main() : SV_Target {
+                return material.color * float(material.rotation[0][0]);
+            }
+        "#;
+
+        let frag = slang.from_source("test", code);
+        let result = std::panic::catch_unwind(|| Shader::from(frag));
+        let message = *result.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.contains("`material` uses a matrix of other scalars than `float`"));
+    }
+
+    #[test]
+    fn parse_half_vectors() -> Result<(), Box<dyn Error>> {
+        let code = r#"
+            struct Material {
+                half4 tint;
+                half roughness;
+            };
+
+            [vk::push_constant] half4 color;
+
+            [vk::binding(0, 0)]
+            ConstantBuffer<Material> material;
+
+            [shader("fragment")]
+            float4 main() : SV_Target {
+                return float4(color * material.tint * material.roughness);
+            }
+        "#;
+
+        let slang = Slang::new();
+        let frag = slang.from_source("test", code);
+        let pipeline = Pipeline::builder()
+            .name("Shader")
+            .shader(Shader::from(frag))
+            .build();
+
+        let shader = &pipeline.shaders[0];
+        assert_eq!(shader.constants[0].ty, ParamType::Half4);
+        assert_eq!(shader.constants[0].ty.get_size(), 8);
+        assert_eq!(shader.uniforms[0].param.ty, ParamType::Struct(16));
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_integer_vectors() -> Result<(), Box<dyn Error>> {
+        let code = r#"
+            [vk::push_constant] int2 resolution;
+
+            [vk::binding(0, 0)]
+            ConstantBuffer<uint4> flags;
+
+            [shader("fragment")]
+            float4 main() : SV_Target {
+                return float4(float2(resolution), float2(flags.xy));
+            }
+        "#;
+
+        let slang = Slang::new();
+        let frag = slang.from_source("test", code);
+        let pipeline = Pipeline::builder()
+            .name("Shader")
+            .shader(Shader::from(frag))
+            .build();
+
+        let shader = &pipeline.shaders[0];
+        assert_eq!(shader.constants[0].ty, ParamType::IVec2);
+        assert_eq!(shader.constants[0].ty.get_size(), 8);
+        assert_eq!(shader.uniforms[0].param.ty, ParamType::UVec4);
+        assert_eq!(shader.uniforms[0].param.ty.get_size(), 16);
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_booleans() -> Result<(), Box<dyn Error>> {
+        let code = r#"
+            [vk::push_constant] bool wireframe;
+
+            [vk::binding(0, 0)]
+            ConstantBuffer<bool> enable_fog;
+
+            [shader("fragment")]
+            float4 main() : SV_Target {
+                return float4(wireframe ? 1.0 : 0.0, enable_fog ? 1.0 : 0.0, 0.0, 1.0);
+            }
+        "#;
+
+        let slang = Slang::new();
+        let frag = slang.from_source("test", code);
+        let pipeline = Pipeline::builder()
+            .name("Shader")
+            .shader(Shader::from(frag))
+            .build();
+
+        let shader = &pipeline.shaders[0];
+        assert_eq!(shader.constants[0].ty, ParamType::Bool);
+        assert_eq!(shader.constants[0].ty.get_size(), 4);
+        assert_eq!(shader.uniforms[0].param.ty, ParamType::Bool);
+        assert_eq!(shader.uniforms[0].param.ty.get_size(), 4);
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_entry_point_uniforms() -> Result<(), Box<dyn Error>> {
+        let code = r#"
+            [vk::binding(0, 0)]
+            ConstantBuffer<float4> tint;
+
+            [shader("vertex")]
+            float4 main(float3 pos, uniform float4x4 mvp) : SV_Position {
+                return mul(mvp, float4(pos, 1.0)) * tint;
+            }
+        "#;
+
+        let slang = Slang::new();
+        let vert = slang.from_source("test", code);
+        let pipeline = Pipeline::builder()
+            .name("Shader")
+            .shader(Shader::from(vert))
+            .build();
+
+        // The entry point uniform does not collide with the binding of `tint`
+        let shader = &pipeline.shaders[0];
+        assert_eq!(shader.constants.len(), 1);
+        assert_eq!(shader.constants[0].name, "mvp");
+        assert_eq!(shader.constants[0].ty, ParamType::Mat4);
+        assert_eq!(shader.uniforms.len(), 1);
+        assert_eq!(shader.uniforms[0].param.name, "tint");
+        assert_eq!(pipeline.get_push_methods()[0].name, "mvp");
+
+        let code = r#"
+            [shader("vertex")]
+            float4 main(float3 pos, uniform float4x4 mvp, uniform float4 tint) : SV_Position {
+                return mul(mvp, float4(pos, 1.0)) * tint;
+            }
+        "#;
+        let vert = slang.from_source("test", code);
+        let result = std::panic::catch_unwind(|| Shader::from(vert));
+        let message = *result.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.contains("`mvp`, `tint` share the push constant block of `main`"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn conflicting_bindings() -> Result<(), Box<dyn Error>> {
+        let vert_code = r#"
+            [vk::binding(0, 0)]
+            ConstantBuffer<float4x4> view_proj;
+
+            [shader("vertex")]
+            float4 main(float3 pos) : SV_Position {
+                return mul(view_proj, float4(pos, 1.0));
+            }
+        "#;
+        let frag_code = r#"
+            [vk::binding(0, 0)]
+            Sampler2D albedo;
+
+            [shader("fragment")]
+            float4 main(float2 uv) : SV_Target {
+                return albedo.Sample(uv);
+            }
+        "#;
+
+        let slang = Slang::new();
+        let vert = slang.from_source("conflict.vert.slang", vert_code);
+        let frag = slang.from_source("conflict.frag.slang", frag_code);
+        let pipeline = Pipeline::builder()
+            .name("Shader")
+            .shader(Shader::from(vert))
+            .shader(Shader::from(frag))
+            .build();
+
+        let result = std::panic::catch_unwind(|| pipeline.get_set_layouts());
+        let message = *result.unwrap_err().downcast::<String>().unwrap();
+        assert!(message.contains(
+            "`view_proj` in conflict.vert.slang and `albedo` in conflict.frag.slang conflict at set 0, binding 0"
+        ));
+
+        // The same resource in both shaders is not a conflict
+        let frag_code = r#"
+            [vk::binding(0, 0)]
+            ConstantBuffer<float4x4> view_proj;
+
+            [shader("fragment")]
+            float4 main(float2 uv) : SV_Target {
+                return view_proj[0];
+            }
+        "#;
+        let vert = slang.from_source("conflict.vert.slang", vert_code);
+        let frag = slang.from_source("shared.frag.slang", frag_code);
+        let pipeline = Pipeline::builder()
+            .name("Shader")
+            .shader(Shader::from(vert))
+            .shader(Shader::from(frag))
+            .build();
+        assert_eq!(pipeline.get_set_layout_bindings(0).len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_matrices() -> Result<(), Box<dyn Error>> {
+        let code = r#"
+            [vk::push_constant] float2x2 rotation;
+
+            [vk::binding(0, 0)]
+            ConstantBuffer<float3x4> bone;
+
+            [shader("vertex")]
+            float4 main(float3 pos) : SV_Position {
+                return float4(mul(rotation, pos.xy), mul(bone, float4(pos, 1.0)).z, 1.0);
+            }
+        "#;
+
+        let slang = Slang::new();
+        let vert = slang.from_source("test", code);
+        let pipeline = Pipeline::builder()
+            .name("Shader")
+            .shader(Shader::from(vert))
+            .build();
+
+        let shader = &pipeline.shaders[0];
+        assert_eq!(shader.constants[0].ty, ParamType::Mat2);
+        assert_eq!(shader.uniforms[0].param.ty, ParamType::Mat3x4);
+        assert_eq!(shader.uniforms[0].param.ty.get_size(), 48);
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_storage_buffers() -> Result<(), Box<dyn Error>> {
+        let code = r#"
+            struct InstanceData {
+                float4x4 model;
+                float4 color;
+            };
+
+            [vk::binding(0, 0)]
+            StructuredBuffer<InstanceData> instances;
+
+            [vk::binding(1, 0)]
+            RWByteAddressBuffer words;
+
+            [shader("vertex")]
+            float4 main(float3 pos, uint id : SV_InstanceID) : SV_Position {
+                words.Store(0, id);
+                return mul(instances[id].model, float4(pos, 1.0));
+            }
+        "#;
+
+        let slang = Slang::new();
+        let vert = slang.from_source("test", code);
+        let pipeline = Pipeline::builder()
+            .name("Shader")
+            .shader(Shader::from(vert))
+            .build();
+
+        let shader = &pipeline.shaders[0];
+        assert_eq!(shader.uniforms[0].param.ty, ParamType::StorageBuffer(80));
+        assert_eq!(shader.uniforms[1].param.ty, ParamType::StorageBuffer(4));
+
+        Ok(())
+    }
+
+    #[test]
+    fn oversized_push_constants() -> Result<(), Box<dyn Error>> {
+        let code = r#"
+            struct PushConstants {
+                float4x4 transforms[4];
+            };
+            [vk::push_constant] PushConstants constants;
+            [shader("vertex")]
+            float4 main(float4 pos) : SV_Position {
+                return mul(constants.transforms[3], pos);
+            }
+        "#;
+
+        let slang = Slang::new();
+        let vert = slang.from_source("test", code);
+        let pipeline = Pipeline::builder()
+            .name("Shader")
+            .shader(Shader::from(vert))
+            .build();
+
+        let warnings = pipeline.get_push_size_warnings();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("more than the 128 bytes of push constants"));
+        assert!(warnings[0].contains("`constants` (256 bytes)"));
+
+        let code = r#"
+            [vk::push_constant] float4x4 transform;
+            [shader("vertex")]
+            float4 main(float4 pos) : SV_Position {
+                return mul(transform, pos);
+            }
+        "#;
+        let vert = slang.from_source("test", code);
+        let pipeline = Pipeline::builder()
+            .name("Shader")
+            .shader(Shader::from(vert))
+            .build();
+        assert!(pipeline.get_push_size_warnings().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn numbering_gaps() -> Result<(), Box<dyn Error>> {
+        let code = r#"
+            [vk::binding(0, 0)]
+            ConstantBuffer<float4x4> model;
+
+            [vk::binding(0, 3)]
+            ConstantBuffer<float4x4> view_proj;
+
+            [vk::binding(40, 3)]
+            ConstantBuffer<float4> color;
+
+            [shader("vertex")]
+            float4 main(float3 pos) : SV_Position {
+                return color * mul(view_proj, mul(model, float4(pos, 1.0)));
+            }
+        "#;
+
+        let slang = Slang::new();
+        let vert = slang.from_source("test", code);
+        let pipeline = Pipeline::builder()
+            .name("Shader")
+            .shader(Shader::from(vert))
+            .build();
+
+        assert!(pipeline.get_numbering_gaps(64).is_empty());
+
+        let gaps = pipeline.get_numbering_gaps(1);
+        assert_eq!(gaps.len(), 2);
+        assert!(gaps[0].contains("2 unused set layouts before set 3"));
+        assert!(gaps[0].contains("`view_proj` (set 3, binding 0)"));
+        assert!(gaps[1].contains("39 unused bindings before binding 40 of set 3"));
+        assert!(gaps[1].contains("`color` (set 3, binding 40)"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_depth_output() -> Result<(), Box<dyn Error>> {
+        let code = r#"
+            struct Output {
+                float4 color : SV_Target;
+                float depth : SV_Depth;
+            };
+
+            [shader("fragment")]
+            Output main(float4 pos : SV_Position) {
+                Output output;
+                output.color = float4(1.0);
+                output.depth = pos.z * 0.5;
+                return output;
+            }
+        "#;
+
+        let slang = Slang::new();
+        let frag = slang.from_source("test", code);
+        let shader = Shader::from(frag);
+        assert!(shader.writes_depth);
+        assert_eq!(shader.outputs.len(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn parse_dual_source_outputs() -> Result<(), Box<dyn Error>> {
+        let code = r#"
+            struct Output {
+                [[vk::location(0)]]
+                float4 color : SV_Target0;
+                [[vk::location(0), vk::index(1)]]
+                float4 coverage : SV_Target1;
+            };
+
+            [shader("fragment")]
+            Output main(float2 uv) {
+                Output output;
+                output.color = float4(1.0);
+                output.coverage = float4(uv, 0.0, 1.0);
+                return output;
+            }
+        "#;
+
+        let slang = Slang::new();
+        let frag = slang.from_source("test", code);
+        let pipeline = Pipeline::builder()
+            .name("Shader")
+            .shader(Shader::from(frag))
+            .options(PipelineOptions {
+                blend: Blend::DualSourceCoverage,
+                ..Default::default()
+            })
+            .build();
+
+        let shader = &pipeline.shaders[0];
+        assert_eq!(shader.outputs.len(), 2);
+        assert_eq!(shader.outputs[0].name, "color");
+        assert_eq!(shader.outputs[1].name, "coverage");
+        assert_eq!(
+            pipeline.get_required_features(),
+            vec![Feature::DualSrcBlend]
+        );
+
+        let pipeline = Pipeline {
+            debug_wireframe: true,
+            ..pipeline
+        };
+        assert_eq!(
+            pipeline.get_required_features(),
+            vec![Feature::DualSrcBlend, Feature::FillModeNonSolid]
+        );
 
         Ok(())
     }
@@ -873,7 +3901,10 @@ mod test {
 
         let slang = Slang::new();
         let vert = slang.from_source("test", code);
-        let pipeline = Pipeline::builder().name("Shader").vert(vert).build();
+        let pipeline = Pipeline::builder()
+            .name("Shader")
+            .shader(Shader::from(vert))
+            .build();
         assert_eq!(pipeline.name, "Shader");
 
         assert!(!pipeline.shaders.is_empty());
@@ -890,4 +3921,45 @@ mod test {
 
         Ok(())
     }
+
+    #[test]
+    fn parse_defines() -> Result<(), Box<dyn Error>> {
+        let code = r#"
+            #ifdef FOO
+            [vk::binding(0, 0)]
+            ConstantBuffer<float4x4> model;
+            #endif
+
+            [shader("vertex")]
+            float4 main(float3 pos) : SV_Position {
+            #ifdef FOO
+                return mul(model, float4(pos, 1.0));
+            #else
+                return float4(pos, 1.0);
+            #endif
+            }
+        "#;
+
+        // Reflection runs on the defined variant
+        let defines = [(String::from("FOO"), String::from("1"))];
+        let slang = Slang::with_options(
+            &[],
+            &defines,
+            None,
+            None,
+            crate::parse::Optimization::default(),
+            false,
+        )?;
+        let shader = Shader::from(slang.from_source("test", code));
+        assert_eq!(shader.uniforms.len(), 1);
+        assert_eq!(shader.uniforms[0].param.name, "model");
+        assert_eq!(shader.uniforms[0].set, 0);
+        assert_eq!(shader.uniforms[0].binding, 0);
+
+        let slang = Slang::new();
+        let shader = Shader::from(slang.from_source("test", code));
+        assert!(shader.uniforms.is_empty());
+
+        Ok(())
+    }
 }
@@ -7,40 +7,214 @@ use std::path::PathBuf;
 
 use slang::Downcast;
 
+/// Optimization level of the Slang session
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Optimization {
+    None,
+    Default,
+    #[default]
+    High,
+}
+
+impl Optimization {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(Self::None),
+            "default" => Some(Self::Default),
+            "high" => Some(Self::High),
+            _ => None,
+        }
+    }
+}
+
+impl From<Optimization> for slang::OptimizationLevel {
+    fn from(optimization: Optimization) -> Self {
+        match optimization {
+            Optimization::None => slang::OptimizationLevel::None,
+            Optimization::Default => slang::OptimizationLevel::Default,
+            Optimization::High => slang::OptimizationLevel::High,
+        }
+    }
+}
+
+/// Vulkan version of the devices running the shaders, selecting
+/// the profile and the SPIR-V version of the target
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Vulkan {
+    V1_0,
+    V1_1,
+    V1_2,
+    V1_3,
+}
+
+impl Vulkan {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "1.0" => Some(Self::V1_0),
+            "1.1" => Some(Self::V1_1),
+            "1.2" => Some(Self::V1_2),
+            "1.3" => Some(Self::V1_3),
+            _ => None,
+        }
+    }
+
+    /// Shader model 6.5 needs SPIR-V features missing before Vulkan 1.2
+    pub fn get_profile(&self) -> &'static str {
+        match self {
+            Self::V1_0 | Self::V1_1 => "glsl_450",
+            Self::V1_2 | Self::V1_3 => "sm_6_5",
+        }
+    }
+
+    /// Highest SPIR-V version supported by the core of this Vulkan version
+    pub fn get_spirv(&self) -> &'static str {
+        match self {
+            Self::V1_0 => "1.0",
+            Self::V1_1 => "1.3",
+            Self::V1_2 => "1.5",
+            Self::V1_3 => "1.6",
+        }
+    }
+}
+
 pub struct Slang {
     session: slang::Session,
     _global_session: slang::GlobalSession,
 }
 
 impl Slang {
+    #[allow(unused)]
     pub fn new() -> Slang {
+        Slang::with_options(&[], &[], None, None, Optimization::default(), false).unwrap()
+    }
+
+    /// Creates a session where `import` also looks for modules in `search_paths`,
+    /// and where each `(name, value)` of `defines` is a preprocessor macro.
+    /// The target uses `profile` instead of `sm_6_5` and the `spirv` version as `1.N`,
+    /// failing when Slang does not know them. Shaders are compiled at the `optimization`
+    /// level, and with debug information when `debug` is set.
+    pub fn with_options(
+        search_paths: &[PathBuf],
+        defines: &[(String, String)],
+        profile: Option<&str>,
+        spirv: Option<&str>,
+        optimization: Optimization,
+        debug: bool,
+    ) -> Result<Slang, String> {
         let global_session = slang::GlobalSession::new().unwrap();
 
+        let profile_name = profile.unwrap_or("sm_6_5");
+        let profile = global_session.find_profile(profile_name);
+        if profile == slang::ProfileID::UNKNOWN {
+            return Err(format!("Unknown Slang profile `{}`", profile_name));
+        }
+
         // All compiler options are available through this builder.
-        let session_options = slang::CompilerOptions::default()
-            .optimization(slang::OptimizationLevel::High)
+        let mut session_options = slang::CompilerOptions::default()
+            .optimization(optimization.into())
             .matrix_layout_row(true);
+        if debug {
+            session_options = session_options.debug_information(slang::DebugInfoLevel::Standard);
+        }
+        for (name, value) in defines {
+            session_options = session_options.macro_define(name, value);
+        }
+        if let Some(spirv) = spirv {
+            let name = format!("spirv_{}", spirv.replace('.', "_"));
+            let capability = global_session.find_capability(&name);
+            if capability == slang::CapabilityID::UNKNOWN {
+                return Err(format!("Unknown SPIR-V version `{}`", spirv));
+            }
+            session_options = session_options.capability(capability);
+        }
 
         let targets = [slang::TargetDesc::default()
             .format(slang::CompileTarget::Spirv)
-            .profile(global_session.find_profile("sm_6_5"))];
+            .profile(profile)];
+
+        let search_paths = search_paths
+            .iter()
+            .map(|path| {
+                std::ffi::CString::new(path.to_string_lossy().as_bytes())
+                    .expect("Failed to convert search path")
+            })
+            .collect::<Vec<_>>();
+        let search_path_ptrs = search_paths
+            .iter()
+            .map(|path| path.as_ptr())
+            .collect::<Vec<_>>();
 
         let session_desc = slang::SessionDesc::default()
             .targets(&targets)
+            .search_paths(&search_path_ptrs)
             .options(&session_options);
 
         let session = global_session.create_session(&session_desc).unwrap();
 
-        Slang {
+        Ok(Slang {
             session,
             _global_session: global_session,
-        }
+        })
     }
 
+    #[allow(unused)]
     pub fn from_path<'a, P: Into<PathBuf>>(&'a self, path: P) -> ShaderReflection<'a> {
         ShaderReflection::from_path(self, path)
     }
 
+    /// Reflects the entry point named `entry` instead of `main`
+    pub fn entry_from_path<'a, P: Into<PathBuf>>(
+        &'a self,
+        path: P,
+        entry: &str,
+    ) -> ShaderReflection<'a> {
+        ShaderReflection::from_path_entry(self, path, entry)
+    }
+
+    /// Reflects the vertex and the fragment entry points of the same module
+    pub fn stages_from_path<'a, P: Into<PathBuf>>(
+        &'a self,
+        path: P,
+    ) -> (ShaderReflection<'a>, ShaderReflection<'a>) {
+        let path = path.into();
+        let mut vert = None;
+        let mut frag = None;
+
+        for reflection in self.entries_from_path(&path) {
+            let stage = reflection
+                .get_entry_point_by_index(0)
+                .expect("Failed to get entry point")
+                .get_stage();
+            match stage {
+                slang::Stage::Vertex => vert = Some(reflection),
+                slang::Stage::Fragment => frag = Some(reflection),
+                _ => (),
+            }
+        }
+
+        match (vert, frag) {
+            (Some(vert), Some(frag)) => (vert, frag),
+            (None, _) => panic!(
+                "{}:{}: Failed to find a vertex entry point in `{}`",
+                file!(),
+                line!(),
+                path.display()
+            ),
+            (_, None) => panic!(
+                "{}:{}: Failed to find a fragment entry point in `{}`",
+                file!(),
+                line!(),
+                path.display()
+            ),
+        }
+    }
+
+    /// Reflects every entry point of a module, e.g. a vertex and a fragment
+    /// entry point living in the same file
+    pub fn entries_from_path<'a, P: Into<PathBuf>>(&'a self, path: P) -> Vec<ShaderReflection<'a>> {
+        ShaderReflection::from_path_entries(self, path)
+    }
+
     #[allow(unused)]
     pub fn from_source<'a, P: Into<PathBuf>, S: Into<String>>(
         &'a self,
@@ -49,13 +223,25 @@ impl Slang {
     ) -> ShaderReflection<'a> {
         ShaderReflection::from_source(self, path, source)
     }
+
+    /// Reflects the entry point named `entry` of a source instead of `main`
+    pub fn entry_from_source<'a, P: Into<PathBuf>, S: Into<String>>(
+        &'a self,
+        path: P,
+        source: S,
+        entry: &str,
+    ) -> ShaderReflection<'a> {
+        ShaderReflection::from_source_entry(self, path, source, entry)
+    }
 }
 
 pub struct ShaderReflection<'a> {
     pub path: PathBuf,
+    /// Name of the entry point function
+    pub entry: String,
     reflection: slang::ShaderReflection,
-    _program: slang::ComponentType,
-    _module: slang::Module,
+    program: slang::ComponentType,
+    module: slang::Module,
     _phantom: PhantomData<&'a i32>,
 }
 
@@ -68,11 +254,47 @@ impl<'a> std::ops::Deref for ShaderReflection<'a> {
 }
 
 impl<'a> ShaderReflection<'a> {
+    /// Returns the files the module was loaded from, including the modules it imports
+    pub fn get_dependencies(&self) -> Vec<PathBuf> {
+        (0..self.module.dependency_file_count())
+            .map(|i| PathBuf::from(self.module.dependency_file_path(i)))
+            .filter(|path| path.is_file())
+            .collect()
+    }
+
+    /// Returns the SPIR-V code of the entry point of this shader, panicking with
+    /// the diagnostics of Slang when the target can not express the shader
+    pub fn get_spirv(&self) -> Vec<u8> {
+        let code = self
+            .program
+            .link()
+            .and_then(|linked| linked.get_entry_point_code(0, 0))
+            .unwrap_or_else(|err| {
+                panic!(
+                    "{}:{}: Failed to compile `{}` for the target: {}",
+                    file!(),
+                    line!(),
+                    self.path.display(),
+                    err
+                )
+            });
+        code.as_slice().to_vec()
+    }
+
     #[allow(unused)]
     pub fn from_source<P: Into<PathBuf>, S: Into<String>>(
         slang: &'a Slang,
         path: P,
         source: S,
+    ) -> ShaderReflection<'a> {
+        Self::from_source_entry(slang, path, source, "main")
+    }
+
+    pub fn from_source_entry<P: Into<PathBuf>, S: Into<String>>(
+        slang: &'a Slang,
+        path: P,
+        source: S,
+        entry_name: &str,
     ) -> ShaderReflection<'a> {
         let path = path.into();
         let name = path.to_string_lossy();
@@ -83,29 +305,58 @@ impl<'a> ShaderReflection<'a> {
             .load_module_from_source_string(&name, &name, &source)
             .unwrap();
 
-        let entry = module
-            .find_entry_point_by_name("main")
-            .expect("Failed to find `main` entry point");
+        let Some(entry) = module.find_entry_point_by_name(entry_name) else {
+            panic!(
+                "{}:{}: Failed to find `{}` entry point in `{}`",
+                file!(),
+                line!(),
+                entry_name,
+                path.display()
+            );
+        };
 
-        let program = slang
+        Self::from_entry_point(slang, path, module, entry)
+    }
+
+    #[allow(unused)]
+    pub fn from_path<P: Into<PathBuf>>(slang: &'a Slang, file_path: P) -> ShaderReflection<'a> {
+        Self::from_path_entry(slang, file_path, "main")
+    }
+
+    pub fn from_path_entry<P: Into<PathBuf>>(
+        slang: &'a Slang,
+        file_path: P,
+        entry_name: &str,
+    ) -> ShaderReflection<'a> {
+        let shader_path = file_path.into();
+
+        let module = slang
             .session
-            .create_composite_component_type(&[module.downcast().clone(), entry.downcast().clone()])
-            .expect("Failed to create program");
+            .load_module(&shader_path.to_string_lossy())
+            .unwrap();
 
-        let reflection = program
-            .get_layout()
-            .expect("Failed to get shader reflection");
+        let Some(entry) = module.find_entry_point_by_name(entry_name) else {
+            let entry_names = Self::from_path_entries(slang, &shader_path)
+                .into_iter()
+                .map(|reflection| format!("`{}`", reflection.entry))
+                .collect::<Vec<String>>();
+            panic!(
+                "{}:{}: Failed to find `{}` entry point in `{}`, found {}",
+                file!(),
+                line!(),
+                entry_name,
+                shader_path.display(),
+                entry_names.join(", ")
+            );
+        };
 
-        Self {
-            path,
-            reflection,
-            _module: module,
-            _program: program,
-            _phantom: PhantomData::default(),
-        }
+        Self::from_entry_point(slang, shader_path, module, entry)
     }
 
-    pub fn from_path<P: Into<PathBuf>>(slang: &'a Slang, file_path: P) -> ShaderReflection<'a> {
+    pub fn from_path_entries<P: Into<PathBuf>>(
+        slang: &'a Slang,
+        file_path: P,
+    ) -> Vec<ShaderReflection<'a>> {
         let shader_path = file_path.into();
 
         let module = slang
@@ -113,10 +364,22 @@ impl<'a> ShaderReflection<'a> {
             .load_module(&shader_path.to_string_lossy())
             .unwrap();
 
-        let entry = module
-            .find_entry_point_by_name("main")
-            .expect("Failed to find `main` entry point");
+        (0..module.entry_point_count())
+            .map(|i| {
+                let entry = module
+                    .entry_point_by_index(i)
+                    .expect("Failed to get entry point by index");
+                Self::from_entry_point(slang, shader_path.clone(), module.clone(), entry)
+            })
+            .collect()
+    }
 
+    fn from_entry_point(
+        slang: &'a Slang,
+        path: PathBuf,
+        module: slang::Module,
+        entry: slang::EntryPoint,
+    ) -> ShaderReflection<'a> {
         let program = slang
             .session
             .create_composite_component_type(&[module.downcast().clone(), entry.downcast().clone()])
@@ -126,11 +389,18 @@ impl<'a> ShaderReflection<'a> {
             .get_layout()
             .expect("Failed to get shader reflection");
 
+        let entry = reflection
+            .get_entry_point_by_index(0)
+            .expect("Failed to get entry point")
+            .get_name()
+            .to_string();
+
         Self {
-            path: shader_path,
+            path,
+            entry,
             reflection,
-            _module: module,
-            _program: program,
+            module,
+            program,
             _phantom: PhantomData::default(),
         }
     }
@@ -146,4 +416,90 @@ mod test {
         let reflection = slang.from_path("shaders/simple.vert.slang");
         assert_eq!(reflection.get_entry_point_count(), 1);
     }
+
+    #[test]
+    fn imported_dependencies() {
+        let dir = std::env::temp_dir().join(format!("rayca-pipe-import-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("common.slang"),
+            "public float4 get_color() { return float4(1.0); }",
+        )
+        .unwrap();
+        let path = dir.join("main.frag.slang");
+        std::fs::write(
+            &path,
+            r#"
+            import common;
+
+            [shader("fragment")]
+            float4 main() : SV_Target {
+                return get_color();
+            }
+            "#,
+        )
+        .unwrap();
+
+        let slang =
+            Slang::with_options(&[], &[], None, None, Optimization::default(), false).unwrap();
+        let reflection = slang.from_path(&path);
+        let dependencies = reflection.get_dependencies();
+        assert!(
+            dependencies
+                .iter()
+                .any(|dependency| dependency.ends_with("common.slang"))
+        );
+        assert!(
+            dependencies
+                .iter()
+                .any(|dependency| dependency.ends_with("main.frag.slang"))
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn search_path_imports() {
+        let dir = std::env::temp_dir().join(format!("rayca-pipe-search-{}", std::process::id()));
+        let include = dir.join("include");
+        std::fs::create_dir_all(&include).unwrap();
+        std::fs::write(
+            include.join("lights.slang"),
+            "public float4 get_light() { return float4(1.0); }",
+        )
+        .unwrap();
+        let path = dir.join("main.frag.slang");
+        std::fs::write(
+            &path,
+            r#"
+            import lights;
+
+            [shader("fragment")]
+            float4 main() : SV_Target {
+                return get_light();
+            }
+            "#,
+        )
+        .unwrap();
+
+        // The module is only found next to the shader through the search path
+        let slang = Slang::with_options(
+            &[include.clone()],
+            &[],
+            None,
+            None,
+            Optimization::default(),
+            false,
+        )
+        .unwrap();
+        let reflection = slang.from_path(&path);
+        assert!(
+            reflection
+                .get_dependencies()
+                .iter()
+                .any(|dependency| dependency.ends_with("include/lights.slang"))
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
 }
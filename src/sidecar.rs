@@ -0,0 +1,166 @@
+// Copyright © 2021-2025
+// Author: Antonio Caggiano <info@antoniocaggiano.eu>
+// SPDX-License-Identifier: MIT
+
+//! A shader can be precompiled to a `.spv` file accompanied by a `.json`
+//! sidecar with its reflection, so that slang is not needed at expansion time.
+
+use std::path::{Path, PathBuf};
+
+use crate::model::*;
+
+/// Path of the SPIR-V file exported to `dir` for a shader source, named after it
+pub fn get_spirv_path(dir: &Path, path: &Path) -> PathBuf {
+    dir.join(path.file_name().unwrap_or_default())
+        .with_extension("spv")
+}
+
+/// Path of the reflection sidecar of a SPIR-V file
+pub fn get_sidecar_path(spirv_path: &Path) -> PathBuf {
+    spirv_path.with_extension("json")
+}
+
+pub fn to_sidecar(shader: &Shader) -> String {
+    serde_json::to_string_pretty(shader).expect("Failed to serialize shader reflection")
+}
+
+pub fn from_sidecar(sidecar: &str) -> Shader {
+    serde_json::from_str(sidecar).expect("Failed to deserialize shader reflection")
+}
+
+/// Writes the SPIR-V and the reflection sidecar of a shader to `dir`, which are then
+/// tracked as dependencies of the shader, as they are by a shader loaded from them
+pub fn export_shader(shader: &mut Shader, spirv: &[u8], dir: &Path) {
+    if let Err(err) = std::fs::create_dir_all(dir) {
+        panic!(
+            "{}:{}: Failed to create `{}`: {}",
+            file!(),
+            line!(),
+            dir.display(),
+            err
+        );
+    }
+
+    let spirv_path = get_spirv_path(dir, &shader.path);
+    if let Err(err) = std::fs::write(&spirv_path, spirv) {
+        panic!(
+            "{}:{}: Failed to write `{}`: {}",
+            file!(),
+            line!(),
+            spirv_path.display(),
+            err
+        );
+    }
+
+    let sidecar_path = get_sidecar_path(&spirv_path);
+    if let Err(err) = std::fs::write(&sidecar_path, to_sidecar(shader)) {
+        panic!(
+            "{}:{}: Failed to write `{}`: {}",
+            file!(),
+            line!(),
+            sidecar_path.display(),
+            err
+        );
+    }
+
+    shader.dependencies.push(spirv_path);
+    shader.dependencies.push(sidecar_path);
+}
+
+/// Loads a SPIR-V file to embed and its reflection sidecar. The shader sources
+/// recorded in the sidecar are still tracked when they exist
+pub fn load_shader<P: Into<PathBuf>>(spirv_path: P) -> Shader {
+    let spirv_path = spirv_path.into();
+    let code = match std::fs::read(&spirv_path) {
+        Ok(code) => code,
+        Err(err) => panic!(
+            "{}:{}: Failed to read `{}`: {}",
+            file!(),
+            line!(),
+            spirv_path.display(),
+            err
+        ),
+    };
+
+    let sidecar_path = get_sidecar_path(&spirv_path);
+    let sidecar = match std::fs::read_to_string(&sidecar_path) {
+        Ok(sidecar) => sidecar,
+        Err(err) => panic!(
+            "{}:{}: Failed to read `{}`: {}",
+            file!(),
+            line!(),
+            sidecar_path.display(),
+            err
+        ),
+    };
+
+    let mut shader = from_sidecar(&sidecar);
+    shader.code = Some(code);
+    shader.dependencies.retain(|path| path.is_file());
+    shader.dependencies.push(spirv_path);
+    shader.dependencies.push(sidecar_path);
+    shader
+}
+
+#[cfg(test)]
+mod test {
+    use crate::args::Args;
+
+    #[test]
+    fn round_trip() {
+        let dir = std::env::temp_dir().join(format!("rayca-pipe-sidecar-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let vert = dir.join("main.vert.slang");
+        std::fs::write(
+            &vert,
+            r#"
+            [vk::push_constant]
+            float4x4 pretransform;
+
+            [vk::binding(0, 0)]
+            ConstantBuffer<float4x4> model;
+
+            [shader("vertex")]
+            float4 main(float3 pos) : SV_Position {
+                return mul(pretransform, mul(model, float4(pos, 1.0)));
+            }
+            "#,
+        )
+        .unwrap();
+        let frag = dir.join("main.frag.slang");
+        std::fs::write(
+            &frag,
+            r#"
+            [vk::binding(1, 0)]
+            Sampler2D albedo;
+
+            [shader("fragment")]
+            float4 main(float2 uv) : SV_Target {
+                return albedo.Sample(uv);
+            }
+            "#,
+        )
+        .unwrap();
+
+        let spv = dir.join("spv");
+        let input = format!("Main, {:?}, {:?}, export = {:?}", vert, frag, spv);
+        let live = crate::expand(syn::parse_str::<Args>(&input).unwrap()).to_string();
+        assert!(spv.join("main.vert.spv").is_file());
+        assert!(spv.join("main.frag.json").is_file());
+
+        let input = format!(
+            "Main, {:?}, {:?}, precompiled = true",
+            spv.join("main.vert.spv"),
+            spv.join("main.frag.spv")
+        );
+        let precompiled = crate::expand(syn::parse_str::<Args>(&input).unwrap()).to_string();
+        assert_eq!(live, precompiled);
+
+        // Both embed the same SPIR-V, with nothing left to compile at runtime
+        let tokens = live.replace(' ', "");
+        assert!(tokens.contains("structAlignedSpirv"));
+        assert!(!tokens.contains("create_shaders"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}